@@ -1,11 +1,11 @@
-use gpu_tracer::{NvidiaSmiMonitor, GpuReadings};
+use gpu_tracer::NvidiaSmiMonitor;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("GPU Tracer Demo - nvidia-smi Integration");
     println!("==========================================");
     
     // Initialize nvidia-smi monitor
-    let monitor = NvidiaSmiMonitor::new();
+    let mut monitor = NvidiaSmiMonitor::new();
     
     if !monitor.is_available() {
         println!("❌ nvidia-smi is not available on this system");
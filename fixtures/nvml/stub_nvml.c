@@ -0,0 +1,279 @@
+/* Stub of the few NVML functions the tracer uses: two A100s, the first one encoding video, the
+ * second one split into two MIG instances with a process on each. */
+#include <stdio.h>
+#include <string.h>
+
+typedef int nvmlReturn_t;
+#define NVML_SUCCESS 0
+#define NVML_ERROR_INVALID_ARGUMENT 2
+#define NVML_ERROR_NOT_SUPPORTED 3
+#define NVML_ERROR_NOT_FOUND 6
+#define NVML_ERROR_INSUFFICIENT_SIZE 7
+
+typedef struct { int index; unsigned int gpuInstanceId; /* 0: the whole GPU */ } device_t;
+typedef device_t *nvmlDevice_t;
+typedef struct { unsigned long long total, free, used; } nvmlMemory_t;
+typedef struct { unsigned int gpu, memory; } nvmlUtilization_t;
+typedef struct {
+    unsigned int pid;
+    unsigned long long usedGpuMemory;
+    unsigned int gpuInstanceId, computeInstanceId;
+} nvmlProcessInfo_t;
+typedef struct {
+    unsigned int pid;
+    unsigned long long timeStamp;
+    unsigned int smUtil, memUtil, encUtil, decUtil;
+} nvmlProcessUtilizationSample_t;
+
+typedef struct {
+    unsigned int sessionId, pid, vgpuInstance, codecType, hResolution, vResolution, averageFps, averageLatency;
+} nvmlEncoderSessionInfo_t;
+
+static device_t devices[2] = {{0, 0}, {1, 0}};
+static device_t migDevices[2] = {{1, 1}, {1, 2}};
+static const unsigned long long MiB = 1024ULL * 1024ULL;
+
+nvmlReturn_t nvmlInit_v2(void) { return NVML_SUCCESS; }
+nvmlReturn_t nvmlShutdown(void) { return NVML_SUCCESS; }
+const char *nvmlErrorString(nvmlReturn_t ret) { return ret == NVML_SUCCESS ? "Success" : "Stub error"; }
+
+nvmlReturn_t nvmlSystemGetDriverVersion(char *version, unsigned int length) {
+    snprintf(version, length, "550.54.15");
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlSystemGetProcessName(unsigned int pid, char *name, unsigned int length) {
+    (void)pid;
+    snprintf(name, length, "python3");
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetCount_v2(unsigned int *count) {
+    *count = 2;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetHandleByIndex_v2(unsigned int index, nvmlDevice_t *device) {
+    if (index > 1) return NVML_ERROR_INVALID_ARGUMENT;
+    *device = &devices[index];
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetName(nvmlDevice_t device, char *name, unsigned int length) {
+    snprintf(name, length, device->gpuInstanceId ? "NVIDIA A100-SXM4-80GB MIG 3g.40gb" : "NVIDIA A100-SXM4-80GB");
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetUUID(nvmlDevice_t device, char *uuid, unsigned int length) {
+    if (device->gpuInstanceId)
+        snprintf(uuid, length, "MIG-00000000-0000-0000-0000-00000000000%u", device->gpuInstanceId);
+    else
+        snprintf(uuid, length, "GPU-00000000-0000-0000-0000-00000000000%d", device->index);
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetMemoryInfo(nvmlDevice_t device, nvmlMemory_t *memory) {
+    if (device->gpuInstanceId) {
+        memory->total = 40192 * MiB;
+        memory->used = device->gpuInstanceId == 1 ? 20480 * MiB : 512 * MiB;
+        memory->free = memory->total - memory->used;
+        return NVML_SUCCESS;
+    }
+    memory->total = 81920 * MiB;
+    memory->used = device->index ? 20480 * MiB : 0;
+    memory->free = memory->total - memory->used;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetUtilizationRates(nvmlDevice_t device, nvmlUtilization_t *utilization) {
+    utilization->gpu = device->index ? 97 : 0;
+    utilization->memory = device->index ? 41 : 0;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetTemperature(nvmlDevice_t device, unsigned int sensor, unsigned int *temp) {
+    if (sensor != 0) return NVML_ERROR_NOT_SUPPORTED;
+    *temp = device->index ? 67 : 31;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetPowerUsage(nvmlDevice_t device, unsigned int *milliwatts) {
+    *milliwatts = device->index ? 312500 : 61000;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetEnforcedPowerLimit(nvmlDevice_t device, unsigned int *milliwatts) {
+    (void)device;
+    *milliwatts = 400000;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetFanSpeed(nvmlDevice_t device, unsigned int *speed) {
+    (void)device;
+    (void)speed;
+    return NVML_ERROR_NOT_SUPPORTED;
+}
+
+nvmlReturn_t nvmlDeviceGetClockInfo(nvmlDevice_t device, unsigned int type, unsigned int *clock) {
+    *clock = type == 2 ? 1593 : (device->index ? 1410 : 210);
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetMaxClockInfo(nvmlDevice_t device, unsigned int type, unsigned int *clock) {
+    (void)device;
+    *clock = type == 2 ? 1593 : 1410;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetCurrentClocksEventReasons(nvmlDevice_t device, unsigned long long *reasons) {
+    *reasons = device->index ? 0x4 /* sw power cap */ : 0x1 /* idle */;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetTotalEccErrors(nvmlDevice_t device, unsigned int errorType, unsigned int counterType,
+                                         unsigned long long *eccCounts) {
+    /* corrected errors since the GPU was made, none since the driver was loaded */
+    *eccCounts = device->index && errorType == 0 && counterType == 1 ? 12 : 0;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetRemappedRows(nvmlDevice_t device, unsigned int *corrRows, unsigned int *uncRows,
+                                       unsigned int *isPending, unsigned int *failureOccurred) {
+    *corrRows = device->index ? 3 : 0;
+    *uncRows = 0;
+    *isPending = device->index ? 1 : 0;
+    *failureOccurred = 0;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetComputeRunningProcesses_v3(nvmlDevice_t device, unsigned int *count,
+                                                      nvmlProcessInfo_t *infos) {
+    nvmlProcessInfo_t running[2] = {{4242, 20480 * MiB, 1, 0}, {4243, 512 * MiB, 2, 0}};
+    unsigned int n = device->index ? 2 : 0;
+    if (*count < n) {
+        *count = n;
+        return NVML_ERROR_INSUFFICIENT_SIZE;
+    }
+    memcpy(infos, running, n * sizeof(nvmlProcessInfo_t));
+    *count = n;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetProcessUtilization(nvmlDevice_t device, nvmlProcessUtilizationSample_t *samples,
+                                             unsigned int *count, unsigned long long lastSeen) {
+    /* two samples of 4242, the newer one counts; 4243 was not sampled */
+    nvmlProcessUtilizationSample_t recorded[2] = {{4242, 1000, 90, 30, 0, 0}, {4242, 2000, 95, 35, 0, 12}};
+    unsigned int n = device->index && lastSeen < 2000 ? 2 : 0;
+    if (n == 0) {
+        *count = 0;
+        return NVML_ERROR_NOT_SUPPORTED;
+    }
+    if (samples == NULL || *count < n) {
+        *count = n;
+        return NVML_ERROR_INSUFFICIENT_SIZE;
+    }
+    memcpy(samples, recorded, n * sizeof(nvmlProcessUtilizationSample_t));
+    *count = n;
+    return NVML_SUCCESS;
+}
+
+/* the second GPU sits in a slot with half the lanes */
+nvmlReturn_t nvmlDeviceGetCurrPcieLinkGeneration(nvmlDevice_t device, unsigned int *gen) {
+    (void)device;
+    *gen = 4;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetMaxPcieLinkGeneration(nvmlDevice_t device, unsigned int *gen) {
+    (void)device;
+    *gen = 4;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetCurrPcieLinkWidth(nvmlDevice_t device, unsigned int *width) {
+    *width = device->index ? 8 : 16;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetMaxPcieLinkWidth(nvmlDevice_t device, unsigned int *width) {
+    (void)device;
+    *width = 16;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetPcieThroughput(nvmlDevice_t device, unsigned int counter, unsigned int *value) {
+    /* KB/s, sent and received */
+    *value = device->index ? (counter == 0 ? 1500000 : 24000) : 0;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetMigMode(nvmlDevice_t device, unsigned int *currentMode, unsigned int *pendingMode) {
+    *currentMode = *pendingMode = device->index ? 1 : 0;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetMaxMigDeviceCount(nvmlDevice_t device, unsigned int *count) {
+    *count = device->index ? 7 : 0;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetMigDeviceHandleByIndex(nvmlDevice_t device, unsigned int index, nvmlDevice_t *migDevice) {
+    /* two of seven slots taken */
+    if (!device->index || index >= 2) return NVML_ERROR_NOT_FOUND;
+    *migDevice = &migDevices[index];
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetGpuInstanceId(nvmlDevice_t device, unsigned int *id) {
+    if (!device->gpuInstanceId) return NVML_ERROR_NOT_SUPPORTED;
+    *id = device->gpuInstanceId;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetComputeInstanceId(nvmlDevice_t device, unsigned int *id) {
+    if (!device->gpuInstanceId) return NVML_ERROR_NOT_SUPPORTED;
+    *id = 0;
+    return NVML_SUCCESS;
+}
+
+/* the first GPU encodes two streams; no JPEG or OFA calls, like drivers before 12.x */
+nvmlReturn_t nvmlDeviceGetEncoderUtilization(nvmlDevice_t device, unsigned int *utilization,
+                                             unsigned int *samplingPeriodUs) {
+    *utilization = device->index ? 0 : 35;
+    *samplingPeriodUs = 167000;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetDecoderUtilization(nvmlDevice_t device, unsigned int *utilization,
+                                             unsigned int *samplingPeriodUs) {
+    *utilization = device->index ? 0 : 60;
+    *samplingPeriodUs = 167000;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetEncoderStats(nvmlDevice_t device, unsigned int *sessionCount, unsigned int *averageFps,
+                                       unsigned int *averageLatency) {
+    *sessionCount = device->index ? 0 : 2;
+    *averageFps = device->index ? 0 : 29;
+    *averageLatency = device->index ? 0 : 1180;
+    return NVML_SUCCESS;
+}
+
+nvmlReturn_t nvmlDeviceGetEncoderSessions(nvmlDevice_t device, unsigned int *sessionCount,
+                                          nvmlEncoderSessionInfo_t *sessionInfos) {
+    /* HEVC 4K and H.264 1080p of the same process */
+    nvmlEncoderSessionInfo_t running[2] = {{1, 5150, 0, 1, 3840, 2160, 24, 1660},
+                                           {2, 5150, 0, 0, 1920, 1080, 34, 700}};
+    unsigned int n = device->index ? 0 : 2;
+    if (*sessionCount == 0) {
+        *sessionCount = n;
+        return NVML_SUCCESS;
+    }
+    if (*sessionCount < n) {
+        *sessionCount = n;
+        return NVML_ERROR_INSUFFICIENT_SIZE;
+    }
+    memcpy(sessionInfos, running, n * sizeof(nvmlEncoderSessionInfo_t));
+    *sessionCount = n;
+    return NVML_SUCCESS;
+}
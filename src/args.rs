@@ -1,4 +1,22 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+
+/// Tool the GPU readings come from
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GpuBackendKind {
+    /// First one that works
+    Auto,
+    /// NVIDIA GPUs through libnvidia-ml, falling back to nvidia-smi where it is missing
+    #[cfg(feature = "nvml")]
+    Nvml,
+    /// NVIDIA GPUs
+    NvidiaSmi,
+    /// AMD GPUs
+    RocmSmi,
+    /// Intel GPUs (Arc, Flex, integrated)
+    IntelGpuTop,
+    /// Linux sysfs and DRM fdinfo, no vendor tools needed (amdgpu, i915, xe)
+    Sysfs,
+}
 
 #[derive(Parser, Debug)]
 #[clap(version)]
@@ -8,10 +26,52 @@ pub struct Args {
     #[clap(value_parser)]
     pub application: Option<String>,
 
+    /// Working directory of the application (default: current directory).
+    #[clap(long, value_parser)]
+    pub cwd: Option<String>,
+
+    /// Extra environment variable for the application, can be repeated: --env KEY=VAL
+    #[clap(long = "env", value_name = "KEY=VAL", value_parser = crate::launcher::parse_env_var)]
+    pub env: Vec<(String, String)>,
+
+    /// File for application stdout (default: <app>.out), relative to the current directory, not --cwd.
+    #[clap(long, value_parser)]
+    pub stdout: Option<String>,
+
+    /// File for application stderr (default: <app>.err), relative to the current directory, not --cwd.
+    #[clap(long, value_parser)]
+    pub stderr: Option<String>,
+
+    /// Do not capture application stdout/stderr.
+    #[clap(long, action)]
+    pub no_capture: bool,
+
+    /// Seconds to wait after forwarding SIGINT/SIGTERM to the application before sending SIGKILL.
+    #[clap(long, default_value_t = 5)]
+    pub kill_timeout: u64,
+
+    /// Extract application metric from its output with named capture, can be repeated:
+    /// --metric-regex 'it/s=(?P<throughput>[0-9.]+)'
+    /// Needs captured output of a spawned application, so not with --no-capture, --pid or --cgroup.
+    #[clap(long, value_parser, conflicts_with_all = ["no_capture", "pid", "cgroup"])]
+    pub metric_regex: Vec<String>,
+
+    /// JSON file with run summary (GPU/CPU statistics, application metrics and their efficiency)
+    #[clap(long, value_parser)]
+    pub summary: Option<String>,
+
     /// PID of external process.
     #[clap(short, long, value_parser)]
     pub pid: Option<i32>,
 
+    /// Trace all processes of a cgroup v2 group (e.g. "system.slice/docker-<id>.scope") instead of a PID.
+    #[clap(long, value_parser)]
+    pub cgroup: Option<String>,
+
+    /// Root of the cgroup v2 hierarchy.
+    #[clap(long, default_value = "/sys/fs/cgroup")]
+    pub cgroup_root: String,
+
     /// Switch off UI - csv style output
     #[clap(short, long, action)]
     pub noui: bool,
@@ -25,6 +85,51 @@ pub struct Args {
     #[clap(default_value_t = 1000)]
     pub refresh: u64,
 
+    /// Tool asking the GPUs: auto tries nvidia-smi, rocm-smi, intel_gpu_top, then sysfs.
+    #[clap(long, value_enum, default_value_t = GpuBackendKind::Auto)]
+    pub gpu_backend: GpuBackendKind,
+
+    /// Root of sysfs, for the sysfs GPU backend and where GPUs are attached.
+    #[clap(long, default_value = crate::trace::datastreams::DEFAULT_SYSFS_ROOT)]
+    pub sysfs_root: String,
+
+    /// Root of procfs, for per-process GPU usage of the sysfs GPU backend and where processes may run.
+    #[clap(long, default_value = crate::trace::datastreams::DEFAULT_PROCFS_ROOT)]
+    pub procfs_root: String,
+
+    /// Simulate that many GPUs instead of asking nvidia-smi - for demos and machines without GPUs.
+    #[clap(long, value_name = "N")]
+    pub simulate_gpus: Option<u32>,
+
+    /// Seed of the GPU simulator - same seed, same readings.
+    #[clap(long, default_value_t = crate::trace::datastreams::DEFAULT_SIMULATOR_SEED)]
+    pub simulate_seed: u64,
+
+    /// Save every nvidia-smi/rocm-smi invocation (args, stdout, stderr, exit code, timing) into this directory - attach it to bug reports.
+    #[clap(long, value_name = "DIR")]
+    pub capture_raw: Option<String>,
+
+    /// Replay a --capture-raw directory in place of the real GPU tool.
+    #[clap(long, value_name = "DIR", conflicts_with_all = ["capture_raw", "simulate_gpus"])]
+    pub replay_raw: Option<String>,
+
+    /// Milliseconds a single nvidia-smi/rocm-smi call may take before it is killed and the GPU backend marked degraded.
+    #[clap(long, default_value_t = 3000)]
+    pub gpu_timeout: u64,
+
+    /// Seconds between full `nvidia-smi -q -x` reports (ECC, retired pages, MIG, accounting), 0 for none.
+    #[clap(long, value_name = "SECONDS", default_value_t = 30)]
+    pub report_interval: u64,
+
+    /// Ask nvidia-smi for accounted processes every sample, so GPU processes of the traced tree that exit between
+    /// samples are still recorded. Needs accounting mode on (`nvidia-smi -am 1`); NVML does not report them.
+    #[clap(long, action)]
+    pub gpu_accounting: bool,
+
+    /// Minutes of readings kept at full resolution, older ones are kept as 10s/1min min/avg/max rollups.
+    #[clap(long, default_value_t = 10)]
+    pub history_minutes: u64,
+
     /// CSV output file
     #[clap(short, long)]
     pub output: Option<String>,
@@ -34,7 +139,8 @@ pub struct Args {
     pub log: String,
 
     /// GPU-specific options
-    /// Select specific GPU(s) to monitor (comma-separated indices, e.g., "0,1,2")
+    /// Select specific GPU(s) to monitor (comma-separated indices or MIG device UUIDs,
+    /// e.g., "0,1,2" or "MIG-c6d4f1e2-..."). Spawned application gets matching CUDA_VISIBLE_DEVICES.
     #[clap(long, value_parser)]
     pub gpu_indices: Option<String>,
 
@@ -96,6 +202,25 @@ impl Args {
         }
     }
 
+    /// GPU indices and MIG device UUIDs from the command line argument, in the order given
+    pub fn get_gpu_devices(&self) -> Vec<String> {
+        self.gpu_indices
+            .iter()
+            .flat_map(|devices| devices.split(','))
+            .map(str::trim)
+            .filter(|s| s.parse::<u32>().is_ok() || s.starts_with("MIG-"))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// MIG device UUIDs from the command line argument
+    pub fn get_mig_uuids(&self) -> Vec<String> {
+        self.get_gpu_devices()
+            .into_iter()
+            .filter(|d| d.starts_with("MIG-"))
+            .collect()
+    }
+
     /// Parse metrics filter from the command line argument
     pub fn get_metrics_filter(&self) -> Vec<String> {
         if let Some(metrics_str) = &self.metrics {
@@ -1,4 +1,5 @@
 use std::io;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Our custom result type. Because the standard one just wasn't quirky enough.
@@ -24,6 +25,72 @@ pub enum TraceError {
     /// "All that glitters is not gold." - Shakespeare, while dealing with data corruption.
     #[error("{0}")]
     IoError(String),
+
+    /// GPU errors. When the most expensive part of the machine stops talking to us.
+    /// "Houston, we have a problem." - Jim Lovell, whose GPU fell off the bus.
+    #[error(transparent)]
+    Gpu(#[from] GpuError),
+}
+
+/// Everything that can go wrong while asking GPUs how they are.
+/// Tells "not installed" from "hung" from "driver and library disagree", so callers can react to each.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum GpuError {
+    /// The tool (or library) is not there - no point in asking again.
+    #[error("{0} is not installed")]
+    NotInstalled(String),
+
+    /// The call did not finish in time and was killed - a GPU in a bad state, usually.
+    #[error("{command} did not finish within {timeout:?}, killed")]
+    Timeout { command: String, timeout: Duration },
+
+    /// The tool ran and failed.
+    #[error("{command} failed with exit code {code:?}: {stderr}")]
+    ExitStatus {
+        command: String,
+        code: Option<i32>,
+        stderr: String,
+    },
+
+    /// Kernel module and user space library are of different versions - typically after a driver upgrade without reboot.
+    #[error("driver/library version mismatch: {0}")]
+    DriverMismatch(String),
+
+    /// A value in the output is not what the field should hold.
+    #[error("cannot parse {field} from {value:?}")]
+    Parse { field: String, value: String },
+
+    /// The driver does not know the queried field.
+    #[error("field {0} is not supported by this driver")]
+    UnsupportedField(String),
+
+    /// Earlier calls failed, this one was not even tried.
+    #[error("backing off after failures, next try in {0:?}")]
+    BackingOff(Duration),
+
+    /// Running the tool failed for any other reason.
+    #[error("{0}")]
+    Io(String),
+}
+
+impl GpuError {
+    /// Errors retrying won't fix - someone has to install or reload something first.
+    pub fn is_permanent(&self) -> bool {
+        matches!(
+            self,
+            GpuError::NotInstalled(_) | GpuError::DriverMismatch(_) | GpuError::UnsupportedField(_)
+        )
+    }
+
+    /// What a human could do about it
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            GpuError::NotInstalled(_) => Some("install the GPU driver and its tools, or pick another --gpu-backend"),
+            GpuError::Timeout { .. } => Some("check dmesg for Xid errors, the GPU may have fallen off the bus"),
+            GpuError::DriverMismatch(_) => Some("reload the kernel module or reboot after the driver upgrade"),
+            _ => None,
+        }
+    }
 }
 
 /// Converting IO error to our error. Because it's important to have more layers of abstraction.
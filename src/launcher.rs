@@ -0,0 +1,455 @@
+use crate::args::Args;
+use crate::error::{Result, TraceError};
+use crate::trace::metrics::MetricExtractor;
+use crate::trace::output::{tee, OutputLog, OutputStream, SharedOutputLog, DEFAULT_OUTPUT_LINES};
+use std::env;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Signal numbers used for forwarding, so callers don't need libc.
+pub const SIGINT: i32 = libc::SIGINT;
+/// Polite termination request.
+pub const SIGTERM: i32 = libc::SIGTERM;
+/// Last resort.
+pub const SIGKILL: i32 = libc::SIGKILL;
+
+/// Where the output of the spawned application goes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Capture {
+    /// Written into file
+    File(PathBuf),
+    /// Thrown away
+    Null,
+}
+
+impl Capture {
+    /// Captured output goes through a pipe, so it can be shown live as well as written to file.
+    fn stdio(&self) -> Stdio {
+        match self {
+            Capture::File(_) => Stdio::piped(),
+            Capture::Null => Stdio::null(),
+        }
+    }
+
+    fn file(&self) -> Result<Option<File>> {
+        match self {
+            Capture::File(path) => Ok(Some(File::create(path)?)),
+            Capture::Null => Ok(None),
+        }
+    }
+}
+
+/// Everything needed to run the traced application exactly like our scripts would.
+#[derive(Debug, Clone)]
+pub struct LaunchSpec {
+    /// Resolved executable
+    pub program: PathBuf,
+    /// Short name of the application, used for default output files
+    pub name: String,
+    /// Arguments passed to the program
+    pub args: Vec<String>,
+    /// Working directory of the child
+    pub cwd: PathBuf,
+    /// Additional environment variables
+    pub env: Vec<(String, String)>,
+    /// Stdout capture, relative paths are relative to the tracer's directory, not `cwd`
+    pub stdout: Capture,
+    /// Stderr capture, relative paths are relative to the tracer's directory, not `cwd`
+    pub stderr: Capture,
+    /// Rules extracting application metrics from captured output
+    pub metrics: Option<MetricExtractor>,
+}
+
+impl LaunchSpec {
+    /// Build launch specification from command line, `None` if there is no application to run.
+    pub fn from_args(args: &Args) -> Result<Option<Self>> {
+        let Some(application) = &args.application else {
+            return Ok(None);
+        };
+
+        let mut words = shell_words::split(application).map_err(|e| {
+            TraceError::AppNotFound(format!("Cannot parse application \"{}\": {}", application, e))
+        })?;
+        if words.is_empty() {
+            return Err(TraceError::AppNotFound("Empty application.".to_string()));
+        }
+        let program = words.remove(0);
+        words.extend(args.args.iter().cloned());
+
+        let cwd = match &args.cwd {
+            Some(dir) => PathBuf::from(dir),
+            None => env::current_dir()?,
+        };
+        let resolved = find_executable(&program, &cwd)?;
+        let name = resolved
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or(program);
+
+        let mut env = args.env.clone();
+        if let Some(devices) = cuda_visible_devices(args) {
+            // CUDA numbers devices fastest first by default, nvidia-smi by PCI bus - make them agree
+            let order = "PCI_BUS_ID".to_string();
+            for (key, value) in [("CUDA_VISIBLE_DEVICES", devices), ("CUDA_DEVICE_ORDER", order)] {
+                if !env.iter().any(|(k, _)| k == key) {
+                    env.push((key.to_string(), value));
+                }
+            }
+        }
+
+        let capture = |path: &Option<String>, ext: &str| {
+            if args.no_capture {
+                Capture::Null
+            } else {
+                Capture::File(
+                    path.as_ref()
+                        .map(PathBuf::from)
+                        .unwrap_or_else(|| PathBuf::from(format!("{}.{}", name, ext))),
+                )
+            }
+        };
+
+        Ok(Some(Self {
+            stdout: capture(&args.stdout, "out"),
+            stderr: capture(&args.stderr, "err"),
+            program: resolved,
+            name: name.clone(),
+            args: words,
+            cwd,
+            env,
+            metrics: if args.metric_regex.is_empty() {
+                None
+            } else {
+                Some(MetricExtractor::new(&args.metric_regex)?)
+            },
+        }))
+    }
+
+    /// Prepared command, without stdio set up.
+    /// On unix the child becomes leader of its own process group, so the whole tree can be signalled.
+    pub fn command(&self) -> Command {
+        let mut cmd = Command::new(&self.program);
+        cmd.current_dir(&self.cwd)
+            .args(&self.args)
+            .envs(self.env.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::null());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            cmd.process_group(0);
+        }
+        cmd
+    }
+
+    /// Spawn the application with outputs captured as requested.
+    /// Captured lines are written into files and kept in the output log for the viewer.
+    pub fn spawn(&self) -> Result<ChildProcess> {
+        let stdout_file = self.stdout.file()?;
+        let stderr_file = self.stderr.file()?;
+        let mut child = self
+            .command()
+            .stdout(self.stdout.stdio())
+            .stderr(self.stderr.stdio())
+            .spawn()
+            .map_err(|e| {
+                TraceError::AppNotFound(format!(
+                    "Failed to run {}: {}",
+                    self.program.display(),
+                    e
+                ))
+            })?;
+
+        let output = if stdout_file.is_some() || stderr_file.is_some() {
+            let log = OutputLog::shared(DEFAULT_OUTPUT_LINES, self.metrics.clone());
+            if let Some(out) = child.stdout.take() {
+                tee(out, stdout_file, OutputStream::Stdout, log.clone());
+            }
+            if let Some(err) = child.stderr.take() {
+                tee(err, stderr_file, OutputStream::Stderr, log.clone());
+            }
+            Some(log)
+        } else {
+            None
+        };
+
+        Ok(ChildProcess { child, output })
+    }
+}
+
+/// Spawned application, running in its own process group.
+pub struct ChildProcess {
+    child: Child,
+    output: Option<SharedOutputLog>,
+}
+
+impl ChildProcess {
+    /// PID of the application (and its process group ID)
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Live stdout/stderr of the application, unless capture is disabled
+    pub fn output_log(&self) -> Option<SharedOutputLog> {
+        self.output.clone()
+    }
+
+    /// Exit status, if the application already finished
+    pub fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        Ok(self.child.try_wait()?)
+    }
+
+    /// Send signal to the whole process group, so grandchildren get it too.
+    #[cfg(unix)]
+    pub fn signal(&mut self, signal: i32) -> Result<()> {
+        // SAFETY: killpg has no memory safety requirements, PID comes from our own child.
+        let res = unsafe { libc::killpg(self.child.id() as libc::pid_t, signal) };
+        if res == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error().into())
+        }
+    }
+
+    /// Without process groups only the child itself can be killed.
+    #[cfg(not(unix))]
+    pub fn signal(&mut self, _signal: i32) -> Result<()> {
+        Ok(self.child.kill()?)
+    }
+
+    /// Forward `signal` to the process group, then escalate to SIGKILL if it is still alive after `timeout`.
+    /// The group is always killed at the end, so nothing keeps holding GPU memory after we exit.
+    pub fn terminate(&mut self, signal: i32, timeout: Duration) -> Result<ExitStatus> {
+        if let Some(status) = self.try_wait()? {
+            // Leader is gone, but grandchildren may still be around.
+            let _ = self.signal(SIGKILL);
+            return Ok(status);
+        }
+
+        log::debug!("Forwarding signal {} to process group {}", signal, self.id());
+        let _ = self.signal(signal);
+
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if let Some(status) = self.try_wait()? {
+                let _ = self.signal(SIGKILL);
+                return Ok(status);
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        log::warn!(
+            "Application {} still running after {:?}, sending SIGKILL.",
+            self.id(),
+            timeout
+        );
+        let _ = self.signal(SIGKILL);
+        Ok(self.child.wait()?)
+    }
+}
+
+/// `CUDA_VISIBLE_DEVICES` derived from `--gpu-indices`, unless all GPUs are requested.
+/// MIG device UUIDs are passed as they are, CUDA takes them too.
+pub fn cuda_visible_devices(args: &Args) -> Option<String> {
+    let devices = args.get_gpu_devices();
+    if args.all_gpus || devices.is_empty() {
+        None
+    } else {
+        Some(devices.join(","))
+    }
+}
+
+/// Parse `KEY=VAL` pair for `--env`.
+pub fn parse_env_var(s: &str) -> std::result::Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((k, v)) if !k.is_empty() => Ok((k.to_string(), v.to_string())),
+        _ => Err(format!("invalid KEY=VAL: no `=` found in `{}`", s)),
+    }
+}
+
+/// Find executable like a shell would: paths with separator are relative to `cwd`,
+/// bare names are looked up in `PATH` and then in `cwd`.
+pub fn find_executable(program: &str, cwd: &Path) -> Result<PathBuf> {
+    let candidate = Path::new(program);
+    if program.contains(std::path::MAIN_SEPARATOR) {
+        let full = if candidate.is_absolute() {
+            candidate.to_path_buf()
+        } else {
+            cwd.join(candidate)
+        };
+        return if is_executable(&full) {
+            Ok(full)
+        } else {
+            Err(TraceError::AppNotFound(format!(
+                "Could not find application: {}.",
+                full.display()
+            )))
+        };
+    }
+
+    let path_var = env::var_os("PATH").unwrap_or_default();
+    env::split_paths(&path_var)
+        .map(|dir| dir.join(program))
+        .chain(std::iter::once(cwd.join(program)))
+        .find(|p| is_executable(p))
+        .ok_or_else(|| TraceError::AppNotFound(format!("Could not find application: {}.", program)))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_launch_spec_from_args() {
+        let args = Args::parse_from([
+            "tracer",
+            "sh -c 'echo \"hello world\"'",
+            "--cwd",
+            "/tmp",
+            "--env",
+            "A=1",
+            "--env",
+            "B=x=y",
+            "--gpu-indices",
+            "1,3",
+            "--stdout",
+            "run.log",
+            "--",
+            "extra",
+        ]);
+        let spec = LaunchSpec::from_args(&args).unwrap().unwrap();
+        assert_eq!(spec.name, "sh");
+        assert_eq!(spec.args, vec!["-c", "echo \"hello world\"", "extra"]);
+        assert_eq!(spec.cwd, PathBuf::from("/tmp"));
+        assert_eq!(
+            spec.env,
+            vec![
+                ("A".to_string(), "1".to_string()),
+                ("B".to_string(), "x=y".to_string()),
+                ("CUDA_VISIBLE_DEVICES".to_string(), "1,3".to_string()),
+                ("CUDA_DEVICE_ORDER".to_string(), "PCI_BUS_ID".to_string()),
+            ]
+        );
+        assert_eq!(spec.stdout, Capture::File(PathBuf::from("run.log")));
+        assert_eq!(spec.stderr, Capture::File(PathBuf::from("sh.err")));
+    }
+
+    #[test]
+    fn test_no_capture_and_explicit_cuda_devices() {
+        let args = Args::parse_from([
+            "tracer",
+            "sh",
+            "--no-capture",
+            "--gpu-indices",
+            "0",
+            "--env",
+            "CUDA_VISIBLE_DEVICES=2",
+            "--env",
+            "CUDA_DEVICE_ORDER=FASTEST_FIRST",
+        ]);
+        let spec = LaunchSpec::from_args(&args).unwrap().unwrap();
+        assert_eq!(spec.stdout, Capture::Null);
+        assert_eq!(spec.stderr, Capture::Null);
+        assert_eq!(
+            spec.env,
+            vec![
+                ("CUDA_VISIBLE_DEVICES".to_string(), "2".to_string()),
+                ("CUDA_DEVICE_ORDER".to_string(), "FASTEST_FIRST".to_string()),
+            ]
+        );
+        // metrics come from captured output only
+        assert!(Args::try_parse_from(["tracer", "sh", "--no-capture", "--metric-regex", "(?P<x>1)"]).is_err());
+        assert!(Args::try_parse_from(["tracer", "--pid", "1", "--metric-regex", "(?P<x>1)"]).is_err());
+    }
+
+    #[test]
+    fn test_mig_devices_are_visible_devices() {
+        let args = Args::parse_from([
+            "tracer",
+            "sh",
+            "--gpu-indices",
+            "MIG-c6d4f1e2-8a3b-5c9d-b1e7-2f6a9d0c4e18, 2,bogus",
+        ]);
+        assert_eq!(
+            cuda_visible_devices(&args).as_deref(),
+            Some("MIG-c6d4f1e2-8a3b-5c9d-b1e7-2f6a9d0c4e18,2")
+        );
+        assert_eq!(args.get_gpu_indices(), vec![2]);
+        assert_eq!(args.get_mig_uuids(), vec!["MIG-c6d4f1e2-8a3b-5c9d-b1e7-2f6a9d0c4e18"]);
+    }
+
+    #[test]
+    fn test_find_executable() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(find_executable("sh", dir.path()).is_ok());
+        assert!(find_executable("./not-there", dir.path()).is_err());
+        assert!(find_executable("definitely-not-a-command-42", dir.path()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_terminate_escalates_and_kills_group() {
+        let args = Args::parse_from([
+            "tracer",
+            "--no-capture",
+            "sh -c 'trap \"\" TERM; sleep 30 & echo $! > grandchild.pid; wait'",
+        ]);
+        let dir = tempfile::tempdir().unwrap();
+        let mut spec = LaunchSpec::from_args(&args).unwrap().unwrap();
+        spec.cwd = dir.path().to_path_buf();
+        let mut child = spec.spawn().unwrap();
+
+        let pid_file = dir.path().join("grandchild.pid");
+        let started = Instant::now();
+        while !pid_file.exists() && started.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(20));
+        }
+        thread::sleep(Duration::from_millis(50));
+        let grandchild: i32 = std::fs::read_to_string(&pid_file)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+
+        let begin = Instant::now();
+        let status = child
+            .terminate(SIGTERM, Duration::from_millis(300))
+            .unwrap();
+        assert!(!status.success());
+        assert!(begin.elapsed() >= Duration::from_millis(300));
+
+        thread::sleep(Duration::from_millis(100));
+        // SAFETY: signal 0 only checks whether the process exists.
+        let alive = unsafe { libc::kill(grandchild, 0) } == 0;
+        let zombie = std::fs::read_to_string(format!("/proc/{}/stat", grandchild))
+            .map(|s| s.contains(") Z"))
+            .unwrap_or(true);
+        assert!(!alive || zombie);
+    }
+
+    #[test]
+    fn test_parse_env_var() {
+        assert_eq!(
+            parse_env_var("KEY=VAL"),
+            Ok(("KEY".to_string(), "VAL".to_string()))
+        );
+        assert!(parse_env_var("KEY").is_err());
+        assert!(parse_env_var("=VAL").is_err());
+    }
+}
@@ -1,6 +1,7 @@
 pub mod trace;
 pub mod args;
 pub mod error;
+pub mod launcher;
 pub mod utils;
 
 // Re-export commonly used types
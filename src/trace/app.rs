@@ -1,34 +1,36 @@
-use std::sync::LazyLock;
-use sysinfo::Pid;
 use termion::event::Key;
 
 use crate::error::Result;
 use crate::trace::app_data_streams::AppDataStreams;
 use crate::trace::cmd::Cmd;
-use crate::trace::ui::tabs::Tabs;
-use crate::trace::datastreams::{NvidiaSmiMonitor, GpuReadings};
+use crate::trace::output::{OutputView, SharedOutputLog};
+use crate::trace::summary::SummaryBuilder;
+use std::collections::BTreeMap;
+use crate::trace::ui::tabs::{TabKind, Tabs};
+use crate::trace::datastreams::{GpuHealth, GpuReadings, Readings, Retention, Sample, Timestamp};
+use std::time::Duration;
 use ratatui::style::{Color, Style};
 use ratatui::text::{Line, Span}; // Changed Spans to Line
 
-/// Some info, really? What info?
-/// "Knowledge is power." - Francis Bacon, probably about the importance of comments.
-static INFO: LazyLock<String> = LazyLock::new(|| {
-    format!(
-        "Live tracing memory and CPU usage, version {}.",
-        env!("CARGO_PKG_VERSION")
-    )
-});
+/// Chart windows the user can zoom between with '+' and '-'.
+pub const ZOOM_LEVELS: [Duration; 9] = [
+    Duration::from_secs(30),
+    Duration::from_secs(60),
+    Duration::from_secs(5 * 60),
+    Duration::from_secs(15 * 60),
+    Duration::from_secs(60 * 60),
+    Duration::from_secs(3 * 60 * 60),
+    Duration::from_secs(6 * 60 * 60),
+    Duration::from_secs(12 * 60 * 60),
+    Duration::from_secs(24 * 60 * 60),
+];
 
 /// The App struct. Because every good project needs a struct. Or two. Or a hundred.
 /// "A place for everything and everything in its place." - Samuel Smiles, probably not talking about Rust structs.
 pub struct App<'a> {
-    #[allow(dead_code)]
-    pub pid: Pid, // Because why use a useful variable name when you can use something cryptic?
     pub selected_proc: usize, // Selected process, do we need to select it anyway?
     pub tabs: Tabs<'a>,       // Tabs, because who doesn't love tabs?
-    pub window: [f64; 2],     // Window: what are we watching?
-    pub cpu_panel_memory: Vec<(f64, f64)>, // The CPU's memory, or is it memory of CPU?
-    pub mem_panel_memory: Vec<(f64, f64)>, // Memory, I think.
+    pub window: Duration,     // Window: how far into the past are we watching?
     pub cpu_usage_str: String, // CPU usage in string
     pub mem_usage_str: String, // memory usage in string
     pub datastreams: AppDataStreams, // Because no one understands `Streams`
@@ -36,88 +38,111 @@ pub struct App<'a> {
     pub refresh: u64,         // refresh rate
     
     // GPU monitoring
-    pub gpu_monitor: NvidiaSmiMonitor, // nvidia-smi monitor
     pub gpu_readings: GpuReadings,     // Current GPU readings
     pub gpu_available: bool,           // Whether GPU monitoring is available
+    pub gpu_health: GpuHealth,         // Is the GPU backend answering?
+
+    // Application output
+    pub output_log: Option<SharedOutputLog>, // Live stdout/stderr of spawned application
+    pub output_view: OutputView,             // Scrolling and searching in it
+    pub sample: u64,                         // Number of samples taken so far
+    pub metrics: BTreeMap<String, f64>,      // Application metrics found since previous sample
+    pub summary: SummaryBuilder,             // Statistics of the whole run
+    pub selected_mig: Vec<String>,           // MIG devices given to the application
 }
 
 impl App<'_> {
     /// Creates a new App. With all the bells and whistles.
     /// Or maybe just some rusty gears.
     pub fn new(
-        history_len: usize,     //How long we should keep it?
-        interpolation_len: u16, //How long to interpolate, well no idea...
-        pid: Pid,               // Pid - it's like password for application
+        retention: Retention,   //How long we should keep it?
+        window: Duration,       //How much of it is on the charts
+        readings: Readings,     // First readings - something to show before samples come
         autoscale: bool,        // Autoscale because why not?
         refresh: u64,           // how often we should refresh?
+        gpu_available: bool,    // will samples have GPUs in them?
+        output_log: Option<SharedOutputLog>, // what the application says
     ) -> Result<Self> {
-        // Initialize GPU monitoring
-        let gpu_monitor = NvidiaSmiMonitor::new();
-        let gpu_available = gpu_monitor.is_available();
-        let gpu_readings = if gpu_available {
-            gpu_monitor.get_gpu_info().unwrap_or_default()
-        } else {
-            GpuReadings::new()
-        };
+        let mut titles = vec![Line::from(vec![
+            Span::styled("CPU/Memory", Style::default().fg(Color::LightYellow)),
+            Span::styled("   q-Quit +/-Zoom", Style::default().fg(Color::Yellow)),
+        ])];
+        let mut kinds = vec![TabKind::CpuMemory];
+
+        if gpu_available {
+            titles.push(Line::from(vec![
+                Span::styled("GPU Monitoring", Style::default().fg(Color::LightCyan)),
+                Span::styled("   q-Quit", Style::default().fg(Color::Yellow)),
+            ]));
+            kinds.push(TabKind::Gpu);
+            titles.push(Line::from(vec![
+                Span::styled("Topology", Style::default().fg(Color::LightBlue)),
+            ]));
+            kinds.push(TabKind::Topology);
+        }
+
+        if output_log.is_some() {
+            titles.push(Line::from(vec![
+                Span::styled("Output", Style::default().fg(Color::LightGreen)),
+                Span::styled("   /-Search", Style::default().fg(Color::Yellow)),
+            ]));
+            kinds.push(TabKind::Output);
+        }
 
         Ok(Self {
-            pid,
             selected_proc: 0,
             tabs: Tabs {
-                titles: {
-                    let mut titles = vec![
-                        Line::from(vec![
-                            Span::styled("CPU/Memory", Style::default().fg(Color::LightYellow)),
-                            Span::styled("   q-Quit", Style::default().fg(Color::Yellow)),
-                        ]),
-                    ];
-                    
-                    if gpu_available {
-                        titles.push(Line::from(vec![
-                            Span::styled("GPU Monitoring", Style::default().fg(Color::LightCyan)),
-                            Span::styled("   q-Quit", Style::default().fg(Color::Yellow)),
-                        ]));
-                    }
-                    
-                    titles
-                },
+                titles,
+                kinds,
                 selection: 0,
             },
-            window: [0.0, history_len as f64],
-            cpu_panel_memory: Vec::new(),
-            mem_panel_memory: Vec::new(),
+            window,
+            datastreams: AppDataStreams::new(&retention, readings),
             cpu_usage_str: String::new(),
             mem_usage_str: String::new(),
-            datastreams: AppDataStreams::new(history_len, interpolation_len, pid)?,
             autoscale,
             refresh,
-            gpu_monitor,
-            gpu_readings,
+            gpu_readings: GpuReadings::new(),
+            gpu_health: GpuHealth::Healthy,
             gpu_available,
+            output_log,
+            output_view: OutputView::default(),
+            sample: 0,
+            metrics: BTreeMap::new(),
+            summary: SummaryBuilder::new(),
+            selected_mig: Vec::new(),
         })
     }
 
     /// Input handler. Because someone has to handle the chaos.
     /// "The only thing that interferes with my learning is my education." - Albert Einstein, probably not about key handlers.
     pub fn input_handler(&mut self, input: Key) -> Option<Cmd> {
+        if self.tabs.current() == TabKind::Output && self.output_input_handler(input) {
+            return None; // Output viewer took it.
+        }
         match input {
             Key::Char('q') => {
                 // 'q' for Quit, because 'Q' is too mainstream.
                 return Some(Cmd::Quit);
             }
-            Key::Up => {
+            Key::Up if self.tabs.current() == TabKind::CpuMemory && self.selected_proc > 0 => {
                 // Up, for going down.
-                if self.tabs.selection == 0 && self.selected_proc > 0 {
-                    self.selected_proc -= 1
-                }
+                self.selected_proc -= 1
             }
-            Key::Down => {
+            Key::Down
+                if self.tabs.current() == TabKind::CpuMemory
+                    && self.selected_proc < self.datastreams.processes().processes.len() - 1 =>
+            {
                 // Down, for going up.
-                if self.tabs.selection == 0
-                    && self.selected_proc < self.datastreams.process_info.processes.len() - 1
-                {
-                    self.selected_proc += 1;
-                }
+                self.selected_proc += 1;
+            }
+            Key::Char('-') => {
+                // Zoom out - further into the past, coarser data.
+                self.zoom_out();
+            }
+            Key::Char('+') | Key::Char('=') => {
+                // Zoom in - back to the details.
+                self.zoom_in();
             }
             Key::Left => {
                 // Left, to go right.
@@ -132,62 +157,110 @@ impl App<'_> {
         None
     }
 
-    /// Update function. Updating data, like we're a real-time stock ticker. Except not.
-    /// "Progress is impossible without change, and those who cannot change their minds cannot change anything." - George Bernard Shaw, also not about updates.
-    pub fn update(&mut self) -> Result<()> {
-        self.datastreams.update()?;
-        
-        // Update GPU data if available
-        if self.gpu_available {
-            match self.gpu_monitor.get_gpu_info() {
-                Ok(new_readings) => {
-                    self.gpu_readings = new_readings;
-                    log::debug!("GPU Data Update - GPUs: {}, First GPU: {} - Memory: {}/{}MB, Utilization: {}% GPU, {}% Memory", 
-                        self.gpu_readings.gpus.len(),
-                        if !self.gpu_readings.gpus.is_empty() { &self.gpu_readings.gpus[0].name } else { "None" },
-                        if !self.gpu_readings.gpus.is_empty() { self.gpu_readings.gpus[0].memory.used } else { 0 },
-                        if !self.gpu_readings.gpus.is_empty() { self.gpu_readings.gpus[0].memory.total } else { 0 },
-                        if !self.gpu_readings.gpus.is_empty() { self.gpu_readings.gpus[0].utilization.gpu } else { 0 },
-                        if !self.gpu_readings.gpus.is_empty() { self.gpu_readings.gpus[0].utilization.memory } else { 0 }
-                    );
+    /// Keys of the output viewer: scrolling and searching. Returns true when key was used.
+    fn output_input_handler(&mut self, input: Key) -> bool {
+        let Some(log) = &self.output_log else {
+            return false;
+        };
+        let log = log.lock().unwrap();
+        let len = log.lines().len();
+        let view = &mut self.output_view;
+
+        if view.searching {
+            // Typing the pattern - every key belongs to it, even 'q'.
+            match input {
+                Key::Char('\n') => {
+                    view.searching = false;
+                    view.find(&log, true);
                 }
-                Err(e) => {
-                    log::error!("GPU data collection error: {}", e);
+                Key::Esc => {
+                    view.searching = false;
+                    view.search.clear();
                 }
+                Key::Backspace => {
+                    view.search.pop();
+                }
+                Key::Char(c) => view.search.push(c),
+                _ => {}
             }
+            return true;
         }
-        
-        //CPU History Parsing
-        {
-            self.cpu_panel_memory = self
-                .datastreams
-                .cpu_info
-                .cpu_usage_history
-                .iter()
-                .enumerate()
-                .map(|(i, u)| (i as f64, *u as f64))
-                .collect::<Vec<(f64, f64)>>(); // Collect it all!
 
+        match input {
+            Key::Up => view.scroll(-1, len),
+            Key::Down => view.scroll(1, len),
+            Key::PageUp => view.scroll(-10, len),
+            Key::PageDown => view.scroll(10, len),
+            Key::Home => view.selected = Some(0),
+            Key::End => view.selected = None,
+            Key::Char('/') => {
+                view.searching = true;
+                view.search.clear();
+            }
+            Key::Char('n') => view.find(&log, true),
+            Key::Char('N') => view.find(&log, false),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Applies a sample: streams, summary and all the strings we like to show.
+    /// "Data is not information, information is not knowledge." - Clifford Stoll, who never had to apply a sample.
+    pub fn apply(&mut self, sample: &Sample) {
+        self.datastreams.poll(sample);
+        self.summary.record(sample);
+        self.sample = sample.index;
+        self.metrics = sample.metrics.clone();
+        if let Some(gpu) = &sample.gpu {
+            self.gpu_readings = gpu.clone(); // on error we keep showing the last good ones
+        }
+        if let Some(health) = &sample.gpu_health {
+            self.gpu_health = health.clone();
+        }
+
+        //CPU History - charts query it by time themselves
+        {
             self.cpu_usage_str =
-                format!("Total CPU: ({:.2}%)", self.datastreams.cpu_info.cpu_usage);
+                format!("Total CPU: ({:.2}%)", self.datastreams.cpu().cpu_usage);
         }
-        //Memory History Parsing
+        //Memory History - same story
         {
-            self.mem_panel_memory = self
-                .datastreams
-                .mem_info
-                .memory_usage_history
-                .iter()
-                .enumerate()
-                .map(|(i, u)| (i as f64, *u))
-                .collect::<Vec<(f64, f64)>>(); // Collect it all again!
+            let memory = self.datastreams.memory();
             self.mem_usage_str = format!(
                 "Total memory ({:.2}%)",
-                100.0 * self.datastreams.mem_info.memory_usage as f64
-                    / self.datastreams.mem_info.total_memory as f64
+                100.0 * memory.memory_usage as f64 / memory.total_memory as f64
             );
+            if let Some(peak) = sample.cgroup.as_ref().and_then(|r| r.memory_peak) {
+                self.mem_usage_str
+                    .push_str(&format!(" peak {:.2} GB", peak as f64 / 1024.0 / 1024.0 / 1024.0));
+            }
         }
+    }
+
+    /// Next bigger chart window.
+    pub fn zoom_out(&mut self) {
+        if let Some(w) = ZOOM_LEVELS.iter().find(|w| **w > self.window) {
+            self.window = *w;
+        }
+    }
+
+    /// Next smaller chart window.
+    pub fn zoom_in(&mut self) {
+        if let Some(w) = ZOOM_LEVELS.iter().rev().find(|w| **w < self.window) {
+            self.window = *w;
+        }
+    }
+
+    /// Time range shown on the charts: `window` back from the latest reading.
+    /// "Time is an illusion. Lunchtime doubly so." - Douglas Adams, who never had to label an x axis.
+    pub fn chart_range(&self) -> (Timestamp, Timestamp) {
+        let to = self.datastreams.readings.get_time();
+        let window = chrono::Duration::from_std(self.window).unwrap_or(chrono::Duration::MAX);
+        (to.checked_sub_signed(window).unwrap_or(to), to)
+    }
 
-        Ok(())
+    /// X axis bounds of the charts, in seconds relative to the latest reading
+    pub fn chart_bounds(&self) -> [f64; 2] {
+        [-self.window.as_secs_f64(), 0.0]
     }
 }
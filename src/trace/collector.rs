@@ -0,0 +1,453 @@
+use chrono::Local;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, System as SysInfoSystem};
+use sysinfo::{PidExt, ProcessExt, SystemExt};
+
+use crate::error::Result;
+use crate::trace::datastreams::{
+    AffinityReader, Cgroup, CgroupReadings, GpuAccountedProcess, GpuBackend, GpuReadings, Readings,
+    Sample, DEFAULT_PROCFS_ROOT,
+};
+use crate::trace::event::Event;
+use crate::trace::output::SharedOutputLog;
+
+/// Collector - takes samples on its own schedule, so the UI never waits for sysinfo or nvidia-smi.
+/// "Slow and steady wins the race." - Aesop, who clearly never waited for nvidia-smi.
+pub struct Collector {
+    pid: Pid, // The process ID. Because everything needs an ID. Even your toaster.
+    sys_info_src: SysInfoSystem, //  SysInfoSystem, because we needed a source of truth. Or just more data.
+    readings: Readings, // The readings. Because we're reading. Or something.
+    cgroup: Option<Cgroup>, // cgroup v2 group - when set, it replaces sysinfo summing.
+    cgroup_readings: Option<CgroupReadings>, // Last cgroup reading, if any.
+    traced_pids: Vec<u32>, // All PIDs we are tracing: process tree or cgroup members.
+    gpu_backend: Option<Box<dyn GpuBackend>>, // GPUs, when there are any to ask.
+    output_log: Option<SharedOutputLog>, // Application output - for its metrics.
+    affinity: AffinityReader, // Where GPUs sit and where their processes may run.
+    seen_pids: BTreeSet<u32>, // Every PID that was ever traced - ours, even once it is gone.
+    foreign_pids: BTreeSet<u32>, // Accounted PIDs found outside our process group - somebody else's.
+    group: Option<u32>, // Process group led by the traced process - a launched application has one.
+    accounted: BTreeSet<(u32, u32)>, // GPU and PID of the exited processes already recorded.
+    samples: u64, // How many samples we took. Counting is hard.
+}
+
+impl Collector {
+    /// Creates a collector for process `pid` (or whole `cgroup`), optionally with GPUs and application output.
+    /// Where GPUs are attached and where processes may run is read under `sysfs_root` and `procfs_root`.
+    pub fn new(
+        pid: Pid,
+        cgroup: Option<Cgroup>,
+        gpu_backend: Option<Box<dyn GpuBackend>>,
+        output_log: Option<SharedOutputLog>,
+        sysfs_root: impl AsRef<Path>,
+        procfs_root: impl AsRef<Path>,
+    ) -> Self {
+        let mut sys = SysInfoSystem::new(); // Creating a system object. Because why not?
+        let readings = Readings::new(&mut sys, pid); // Reading some data. Can it read our minds?
+        let group = match cgroup {
+            Some(_) => None,
+            None => process_group(pid.as_u32()).filter(|&group| group == pid.as_u32()),
+        };
+        Self {
+            pid,
+            sys_info_src: sys, // Here's our source. Unfiltered. Probably.
+            readings,
+            cgroup,
+            cgroup_readings: None,
+            traced_pids: vec![pid.as_u32()],
+            gpu_backend,
+            output_log,
+            affinity: AffinityReader::new(sysfs_root.as_ref(), procfs_root.as_ref()),
+            seen_pids: BTreeSet::new(),
+            foreign_pids: BTreeSet::new(),
+            group,
+            accounted: BTreeSet::new(),
+            samples: 0,
+        }
+    }
+
+    /// Latest process readings
+    pub fn readings(&self) -> &Readings {
+        &self.readings
+    }
+
+    /// Whether samples will carry GPU readings
+    pub fn gpu_available(&self) -> bool {
+        self.gpu_backend.is_some()
+    }
+
+    /// Takes one sample right now: process (or cgroup), GPUs and application metrics.
+    /// "The only true wisdom is in knowing you know nothing." - Socrates, probably while taking a sample.
+    pub fn collect(&mut self) -> Result<Sample> {
+        if let Some(cgroup) = &mut self.cgroup {
+            // Whole group at once - kernel already did the summing for us.
+            let r = cgroup.read()?;
+            self.readings.refresh(r.cpu_usage, r.memory_current);
+            self.traced_pids = r.pids.clone();
+            self.cgroup_readings = Some(r);
+        } else {
+            self.update_process_tree();
+        }
+        self.seen_pids.extend(&self.traced_pids);
+
+        let mut gpu = self.gpu_backend.as_mut().and_then(|m| match m.get_gpu_info() {
+            Ok(readings) => {
+                log::debug!(
+                    "GPU Data Update - GPUs: {}, total memory used: {}MB, average utilization: {:.1}%",
+                    readings.gpus.len(),
+                    readings.get_total_memory_used(),
+                    readings.get_average_gpu_utilization()
+                );
+                Some(readings)
+            }
+            Err(e) => {
+                // Backend logs when it degrades and recovers, no need to repeat it every sample.
+                log::debug!("GPU data collection error: {}", e);
+                None
+            }
+        });
+        let gpu_health = self.gpu_backend.as_ref().map(|m| m.health().clone());
+
+        // Which CPUs are close to which GPU, and where the traced GPU users may run
+        let mut affinity = Vec::new();
+        if let Some(readings) = &mut gpu {
+            self.affinity.fill_gpus(readings);
+            let mut pids: Vec<u32> = readings
+                .get_processes_for_pids(&self.traced_pids)
+                .iter()
+                .map(|p| p.pid)
+                .collect();
+            pids.sort_unstable();
+            pids.dedup();
+            affinity = self.affinity.read_processes(&pids);
+        }
+        let accounted = gpu.as_ref().map_or(Vec::new(), |readings| self.take_exited(readings));
+
+        let index = self.samples + 1;
+        let mut metrics = BTreeMap::new();
+        if let Some(log) = &self.output_log {
+            let gpu_memory_used = gpu.as_ref().map(|g| g.get_total_memory_used());
+            let mut log = log.lock().unwrap();
+            log.mark_sample(index, gpu_memory_used);
+            metrics = log.take_metrics();
+        }
+
+        self.samples = index;
+        Ok(Sample {
+            index,
+            time: Local::now(),
+            readings: self.readings.clone(),
+            cgroup: self.cgroup_readings.clone(),
+            traced_pids: self.traced_pids.clone(),
+            gpu,
+            gpu_health,
+            metrics,
+            affinity,
+            accounted,
+        })
+    }
+
+    /// Accounted GPU processes of the traced tree that exited, each once.
+    fn take_exited(&mut self, readings: &GpuReadings) -> Vec<GpuAccountedProcess> {
+        let mut exited = Vec::new();
+        for process in &readings.accounted_processes {
+            let gone = !process.is_running && !self.traced_pids.contains(&process.pid);
+            if gone && self.is_ours(process.pid) && self.accounted.insert((process.gpu_index, process.pid)) {
+                exited.push(process.clone());
+            }
+        }
+        exited
+    }
+
+    /// Whether accounted `pid` belongs to the traced application: seen in the tree at some sample, or still
+    /// around in its process group (an unreaped zombie too). One that is gone without a trace is not - it
+    /// may as well be somebody else's.
+    fn is_ours(&mut self, pid: u32) -> bool {
+        if self.seen_pids.contains(&pid) {
+            return true;
+        }
+        if self.foreign_pids.contains(&pid) {
+            return false;
+        }
+        match process_group(pid) {
+            Some(group) if Some(group) == self.group => {
+                self.seen_pids.insert(pid);
+                true
+            }
+            Some(_) => {
+                self.foreign_pids.insert(pid);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the collector to its own thread, sending a sample every `interval` until `stop` is set
+    /// or nobody listens anymore. Schedule is kept against the start time, so it does not drift;
+    /// samples that could not be taken in time (e.g. hung nvidia-smi) are skipped, not bunched up.
+    pub fn spawn(
+        mut self,
+        interval: Duration,
+        tx: Sender<Event>,
+        stop: Arc<AtomicBool>,
+    ) -> JoinHandle<()> {
+        thread::Builder::new()
+            .name("collector".to_string())
+            .spawn(move || {
+                let start = Instant::now();
+                let mut tick = 0;
+                while !stop.load(Ordering::SeqCst) {
+                    match self.collect() {
+                        Ok(sample) => {
+                            if tx.send(Event::Sample(Box::new(sample))).is_err() {
+                                break; // nobody is listening
+                            }
+                        }
+                        Err(e) => log::error!("Sample collection failed: {}", e),
+                    }
+
+                    let (next, skipped) = next_tick(tick, start.elapsed(), interval);
+                    if skipped > 0 {
+                        log::warn!("Collection took too long, skipped {} sample(s)", skipped);
+                    }
+                    tick = next;
+                    // Sleep in short naps, so stopping does not wait for a whole interval.
+                    while !stop.load(Ordering::SeqCst) {
+                        let elapsed = start.elapsed();
+                        let due = interval * tick;
+                        if elapsed >= due {
+                            break;
+                        }
+                        thread::sleep((due - elapsed).min(Duration::from_millis(100)));
+                    }
+                }
+            })
+            .expect("Cannot start collector thread")
+    }
+
+    /// Sums CPU and memory of traced process and its children with sysinfo.
+    fn update_process_tree(&mut self) {
+        // Refreshing all of them - children come and go, and we want to know about it.
+        self.sys_info_src.refresh_processes();
+
+        let Some(p) = self.sys_info_src.process(self.pid) else {
+            // Process is gone - nothing left to sum.
+            self.readings.refresh(0.0, 0);
+            self.traced_pids.clear();
+            return;
+        };
+
+        // We don't know what we're doing.
+        let mut cpu = p.cpu_usage(); // Are we sure it's CPU?
+        let mut mem = p.memory(); // And memory?
+
+        //  subs PIDs
+        let ps = self.sys_info_src.processes(); // Get all processes
+
+        // Here we are iterating over processes, because why not?
+        let mut subs: Vec<Pid> = vec![];
+        let mut traced = vec![self.pid.as_u32()];
+        for p in ps
+            .values()
+            .filter(|p| p.parent() == Some(self.pid)) // Filtering by parent - sounds familiar
+        {
+            subs.push(p.pid()); // Adding PIDs
+            traced.push(p.pid().as_u32());
+            cpu += p.cpu_usage(); // because more is better
+            mem += p.memory(); // and same here
+        }
+
+        // TODO: 2 levels of parents PIDs at the moment - need to fix it / check if needed more
+        // Because two levels is never enough. Or is it?
+        for p in ps
+            .values()
+            .filter(|p| subs.contains(&p.parent().unwrap_or_else(|| Pid::from(0)))) // Checking parent PIDs - what can go wrong?
+        {
+            traced.push(p.pid().as_u32());
+            cpu += p.cpu_usage(); // More cpu - more better!
+            mem += p.memory(); // Same with memory - better!
+        }
+
+        self.readings.refresh(cpu, mem); // Reading are refreshed!
+        self.traced_pids = traced;
+    }
+}
+
+/// Process group of `pid`, `None` once it is gone (zombies still have one)
+fn process_group(pid: u32) -> Option<u32> {
+    let stat = fs::read_to_string(Path::new(DEFAULT_PROCFS_ROOT).join(pid.to_string()).join("stat")).ok()?;
+    // comm may hold spaces and parentheses: state, ppid and pgrp follow the last ')'
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(2)?.parse().ok()
+}
+
+/// Next tick after `tick` that is still in the future at `elapsed`, and how many ticks were missed.
+fn next_tick(tick: u32, elapsed: Duration, interval: Duration) -> (u32, u32) {
+    let next = tick + 1;
+    if interval.is_zero() || interval * next > elapsed {
+        return (next, 0);
+    }
+    let due = (elapsed.as_nanos() / interval.as_nanos()) as u32 + 1;
+    (due, due - next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::GpuError;
+    use crate::trace::datastreams::{GpuHealth, DEFAULT_SYSFS_ROOT};
+    use crate::trace::metrics::MetricExtractor;
+    use crate::trace::output::{OutputLog, OutputStream};
+    use std::sync::Mutex;
+
+    /// Hands out whatever readings the test puts in
+    struct FakeGpu(Arc<Mutex<GpuReadings>>, GpuHealth);
+
+    impl GpuBackend for FakeGpu {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn get_gpu_info(&mut self) -> std::result::Result<GpuReadings, GpuError> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+
+        fn health(&self) -> &GpuHealth {
+            &self.1
+        }
+    }
+
+    #[test]
+    fn test_next_tick_skips_missed() {
+        let interval = Duration::from_millis(100);
+        assert_eq!(next_tick(0, Duration::from_millis(30), interval), (1, 0));
+        // slow collection still aims at the original schedule
+        assert_eq!(next_tick(1, Duration::from_millis(190), interval), (2, 0));
+        // hung for 3.5 intervals - skip what is gone
+        assert_eq!(next_tick(1, Duration::from_millis(450), interval), (5, 3));
+    }
+
+    #[test]
+    fn test_exited_accounted_processes_of_the_tree_once() {
+        let pid = Pid::from(std::process::id() as i32);
+        let mut collector = Collector::new(pid, None, None, None, DEFAULT_SYSFS_ROOT, DEFAULT_PROCFS_ROOT);
+        collector.traced_pids = vec![4242, 4250];
+        collector.seen_pids.extend([4242, 4250, 4251]);
+        let accounted = |pid, is_running| GpuAccountedProcess { pid, is_running, ..Default::default() };
+        let mut readings = GpuReadings::new();
+        readings.accounted_processes = vec![
+            accounted(4242, true),   // still at it
+            accounted(4250, false),  // done with the GPU, not exited yet
+            accounted(4251, false),  // ours, gone
+            accounted(9999, false),  // somebody else's
+        ];
+        let pids = |processes: Vec<GpuAccountedProcess>| processes.iter().map(|p| p.pid).collect::<Vec<_>>();
+        assert_eq!(pids(collector.take_exited(&readings)), vec![4251]);
+        assert!(collector.take_exited(&readings).is_empty());
+
+        collector.traced_pids = vec![4242];
+        assert_eq!(pids(collector.take_exited(&readings)), vec![4250]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_process_forked_and_gone_between_samples() {
+        use std::io::Write;
+        use std::os::unix::process::CommandExt;
+        use std::process::{Command, Stdio};
+
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("deepest");
+        // every level waits in sleep, which reaps nobody - the deepest is left a zombie in the group
+        let fork = dir.path().join("fork.sh");
+        let script = concat!(
+            "if [ \"$1\" -gt 0 ]; then sh \"$0\" $(($1 - 1)) \"$2\" & exec sleep 10; fi\n",
+            "echo $$ > \"$2\"\n"
+        );
+        fs::write(&fork, script).unwrap();
+        // on the go, forks a GPU user below the two levels sampled, gone before the next sample
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(format!("read go; sh {} 2 {} & exec sleep 10", fork.display(), pid_file.display()))
+            .stdin(Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let gpu = Arc::new(Mutex::new(GpuReadings::new()));
+        let backend = Box::new(FakeGpu(gpu.clone(), GpuHealth::Healthy));
+        let pid = Pid::from(child.id() as i32);
+        let (sysfs, procfs) = (DEFAULT_SYSFS_ROOT, DEFAULT_PROCFS_ROOT);
+        let mut collector = Collector::new(pid, None, Some(backend), None, sysfs, procfs);
+        assert!(collector.collect().unwrap().accounted.is_empty());
+
+        child.stdin.take().unwrap().write_all(b"go\n").unwrap();
+        let state = |pid: u32| {
+            let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap_or_default();
+            stat.rsplit_once(')').and_then(|(_, fields)| fields.split_whitespace().next().map(str::to_string))
+        };
+        let begin = Instant::now();
+        let deepest: u32 = loop {
+            let pid = fs::read_to_string(&pid_file).ok().and_then(|s| s.trim().parse().ok());
+            if let Some(pid) = pid.filter(|&pid| state(pid).as_deref() == Some("Z")) {
+                break pid;
+            }
+            assert!(begin.elapsed() < Duration::from_secs(5), "deepest process did not finish");
+            thread::sleep(Duration::from_millis(10));
+        };
+        // reaped at once, nothing tells whose it was
+        let mut reaped = Command::new("true").spawn().unwrap();
+        reaped.wait().unwrap();
+        let accounted = |pid, is_running| GpuAccountedProcess { pid, is_running, ..Default::default() };
+        gpu.lock().unwrap().accounted_processes = vec![
+            accounted(deepest, false),            // never sampled, a zombie of our group
+            accounted(reaped.id(), false),        // never sampled and gone
+            accounted(std::process::id(), false), // still around, in another process group
+        ];
+        let sample = collector.collect().unwrap();
+        let pids: Vec<u32> = sample.accounted.iter().map(|p| p.pid).collect();
+        assert_eq!(pids, vec![deepest]);
+
+        // the whole group, sleepers included
+        unsafe { libc::kill(-(child.id() as i32), libc::SIGKILL) };
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn test_collect_and_spawn() {
+        let extractor = MetricExtractor::new(&[r"loss=(?P<loss>[0-9.]+)".to_string()]).unwrap();
+        let log = OutputLog::shared(10, Some(extractor));
+        let pid = Pid::from(std::process::id() as i32);
+        let (sysfs, procfs) = (DEFAULT_SYSFS_ROOT, DEFAULT_PROCFS_ROOT);
+        let mut collector = Collector::new(pid, None, None, Some(log.clone()), sysfs, procfs);
+
+        log.lock().unwrap().push(OutputStream::Stdout, "loss=0.5".to_string());
+        let sample = collector.collect().unwrap();
+        assert_eq!(sample.index, 1);
+        assert_eq!(sample.readings.get_pid(), std::process::id());
+        assert!(sample.gpu.is_none());
+        assert!(sample.gpu_health.is_none());
+        assert_eq!(sample.metrics.get("loss"), Some(&0.5));
+        assert_eq!(log.lock().unwrap().lines()[0].sample, 0);
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = collector.spawn(Duration::from_millis(10), tx, stop.clone());
+        let indices: Vec<u64> = rx
+            .iter()
+            .take(3)
+            .map(|e| match e {
+                Event::Sample(s) => s.index,
+                _ => 0,
+            })
+            .collect();
+        assert_eq!(indices, vec![2, 3, 4]);
+        stop.store(true, Ordering::SeqCst);
+        handle.join().unwrap();
+    }
+}
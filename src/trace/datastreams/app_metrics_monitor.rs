@@ -0,0 +1,39 @@
+use crate::trace::datastreams::data_stream::SysDataStream;
+use crate::trace::datastreams::retention::{History, Retention};
+use crate::trace::datastreams::sample::Sample;
+use std::any::Any;
+use std::collections::BTreeMap;
+
+/// Application metrics (from `--metric-regex`) over time, one history per metric.
+pub struct AppMetricsMonitor {
+    pub metrics: BTreeMap<String, History>,
+    retention: Retention,
+}
+
+impl AppMetricsMonitor {
+    pub fn new(retention: &Retention) -> Self {
+        Self {
+            metrics: BTreeMap::new(),
+            retention: retention.clone(),
+        }
+    }
+}
+
+impl SysDataStream for AppMetricsMonitor {
+    fn name(&self) -> &'static str {
+        "app_metrics"
+    }
+
+    fn poll(&mut self, sample: &Sample) {
+        for (name, value) in &sample.metrics {
+            self.metrics
+                .entry(name.clone())
+                .or_insert_with(|| History::new(&self.retention))
+                .push(sample.time, *value);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
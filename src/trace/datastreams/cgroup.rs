@@ -0,0 +1,175 @@
+use crate::error::{Result, TraceError};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Default mount point of the cgroup v2 unified hierarchy
+pub const DEFAULT_CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// Single reading of a cgroup v2 group
+#[derive(Debug, Clone, Default)]
+pub struct CgroupReadings {
+    /// Member PIDs from `cgroup.procs`
+    pub pids: Vec<u32>,
+    /// CPU usage in percent (100% = one core), computed from `cpu.stat` deltas
+    pub cpu_usage: f32,
+    /// Total CPU time consumed by the group in microseconds
+    pub cpu_usage_usec: u64,
+    /// Current memory usage in bytes (`memory.current`)
+    pub memory_current: u64,
+    /// Peak memory usage in bytes (`memory.peak`), not available on older kernels
+    pub memory_peak: Option<u64>,
+}
+
+/// cgroup v2 reader - replaces per-process sysinfo summing when tracing a container or job
+pub struct Cgroup {
+    /// Full path of the group directory (root + cgroup path)
+    dir: PathBuf,
+    /// Previous `usage_usec` with the time it was read, used for CPU percentage
+    last_cpu: Option<(u64, Instant)>,
+}
+
+impl Cgroup {
+    /// Create reader for `path` relative to `root` (e.g. `/sys/fs/cgroup`).
+    /// Paths already starting with `root` are used as they are.
+    pub fn new(root: impl AsRef<Path>, path: &str) -> Result<Self> {
+        let root = root.as_ref();
+        let dir = if Path::new(path).starts_with(root) {
+            PathBuf::from(path)
+        } else {
+            root.join(path.trim_start_matches('/'))
+        };
+
+        if !dir.join("cgroup.procs").is_file() {
+            return Err(TraceError::AppNotFound(format!(
+                "Could not find cgroup: {} (no cgroup.procs in {}).",
+                path,
+                dir.display()
+            )));
+        }
+
+        Ok(Self {
+            dir,
+            last_cpu: None,
+        })
+    }
+
+    /// Directory of the group
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Member PIDs of the group
+    pub fn pids(&self) -> Result<Vec<u32>> {
+        let procs = fs::read_to_string(self.dir.join("cgroup.procs"))?;
+        Ok(procs
+            .lines()
+            .filter_map(|l| l.trim().parse::<u32>().ok())
+            .collect())
+    }
+
+    /// Read PIDs, CPU and memory of the group
+    pub fn read(&mut self) -> Result<CgroupReadings> {
+        let pids = self.pids()?;
+        let cpu_usage_usec = self.read_cpu_usage_usec()?;
+        let memory_current = self.read_u64("memory.current")?;
+        let memory_peak = self.read_u64("memory.peak").ok();
+
+        let now = Instant::now();
+        let cpu_usage = match self.last_cpu {
+            Some((last_usec, last_time)) => {
+                let elapsed = now.duration_since(last_time).as_micros() as f64;
+                if elapsed > 0.0 {
+                    (cpu_usage_usec.saturating_sub(last_usec) as f64 / elapsed * 100.0) as f32
+                } else {
+                    0.0
+                }
+            }
+            None => 0.0,
+        };
+        self.last_cpu = Some((cpu_usage_usec, now));
+
+        Ok(CgroupReadings {
+            pids,
+            cpu_usage,
+            cpu_usage_usec,
+            memory_current,
+            memory_peak,
+        })
+    }
+
+    /// `usage_usec` line of `cpu.stat`
+    fn read_cpu_usage_usec(&self) -> Result<u64> {
+        let stat = fs::read_to_string(self.dir.join("cpu.stat"))?;
+        stat.lines()
+            .find_map(|l| {
+                let mut parts = l.split_whitespace();
+                match (parts.next(), parts.next()) {
+                    (Some("usage_usec"), Some(v)) => v.parse::<u64>().ok(),
+                    _ => None,
+                }
+            })
+            .ok_or_else(|| {
+                TraceError::Unknown(format!(
+                    "No usage_usec in {}",
+                    self.dir.join("cpu.stat").display()
+                ))
+            })
+    }
+
+    /// Single value file, e.g. `memory.current`
+    fn read_u64(&self, file: &str) -> Result<u64> {
+        let value = fs::read_to_string(self.dir.join(file))?;
+        value
+            .trim()
+            .parse::<u64>()
+            .map_err(|e| TraceError::Unknown(format!("Failed to parse {}: {}", file, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_cgroup(root: &Path, usage_usec: u64) -> PathBuf {
+        let dir = root.join("system.slice/docker-abc.scope");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("cgroup.procs"), "100\n200\n").unwrap();
+        fs::write(
+            dir.join("cpu.stat"),
+            format!("usage_usec {}\nuser_usec 10\nsystem_usec 5\n", usage_usec),
+        )
+        .unwrap();
+        fs::write(dir.join("memory.current"), "4096\n").unwrap();
+        fs::write(dir.join("memory.peak"), "8192\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_cgroup_readings() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = fake_cgroup(root.path(), 1_000);
+
+        let mut cgroup = Cgroup::new(root.path(), "/system.slice/docker-abc.scope").unwrap();
+        assert_eq!(cgroup.dir(), dir.as_path());
+
+        let first = cgroup.read().unwrap();
+        assert_eq!(first.pids, vec![100, 200]);
+        assert_eq!(first.cpu_usage_usec, 1_000);
+        assert_eq!(first.cpu_usage, 0.0);
+        assert_eq!(first.memory_current, 4096);
+        assert_eq!(first.memory_peak, Some(8192));
+
+        fs::write(dir.join("cpu.stat"), "usage_usec 500000000\n").unwrap();
+        fs::remove_file(dir.join("memory.peak")).unwrap();
+        let second = cgroup.read().unwrap();
+        assert!(second.cpu_usage > 0.0);
+        assert_eq!(second.memory_peak, None);
+    }
+
+    #[test]
+    fn test_cgroup_missing() {
+        let root = tempfile::tempdir().unwrap();
+        assert!(Cgroup::new(root.path(), "nope").is_err());
+    }
+}
@@ -1,37 +1,36 @@
 use crate::trace::datastreams::{
-    data_stream::{Readings, SysDataStream},
-    utils,
+    data_stream::SysDataStream,
+    retention::{History, Retention},
+    sample::Sample,
 };
+use std::any::Any;
 
 pub struct CPUMonitor {
     pub cpu_usage: f32,
-    pub cpu_usage_history: Vec<f32>,
-    max_history_len: usize,
-    interpolation_len: u16,
+    pub cpu_usage_history: History, // CPU usage in %, 100% per core
 }
 
-impl SysDataStream for CPUMonitor {
-    fn new(max_hist_len: usize, inter_len: u16) -> Self {
+impl CPUMonitor {
+    pub fn new(retention: &Retention) -> Self {
         Self {
             cpu_usage: 0.0,
-            cpu_usage_history: vec![0.0; max_hist_len],
-            max_history_len: max_hist_len,
-            interpolation_len: inter_len,
+            cpu_usage_history: History::new(retention),
         }
     }
+}
 
-    fn poll(&mut self, system_info: &Readings) {
-        self.cpu_usage = system_info.get_cpu();
+impl SysDataStream for CPUMonitor {
+    fn name(&self) -> &'static str {
+        "cpu"
+    }
 
-        while self.cpu_usage_history.len() >= self.max_history_len {
-            self.cpu_usage_history.remove(0);
-        }
-        let last_mem = match self.cpu_usage_history.last() {
-            Some(l) => *l,
-            None => 0.0,
-        };
-        self.cpu_usage_history.extend_from_slice(
-            utils::interpolate(last_mem, self.cpu_usage / 100.0, self.interpolation_len).as_slice(),
-        );
+    fn poll(&mut self, sample: &Sample) {
+        self.cpu_usage = sample.readings.get_cpu();
+        self.cpu_usage_history
+            .push(sample.time, self.cpu_usage as f64);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
     }
 }
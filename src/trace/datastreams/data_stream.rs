@@ -1,7 +1,12 @@
+use crate::trace::datastreams::sample::Sample;
+use crate::trace::datastreams::time_series::Timestamp;
+use chrono::Local;
+use std::any::Any;
 use sysinfo::{Pid, PidExt, ProcessExt, System, SystemExt};
 
 /// Cache structure between calls to system.refresh - to make sure all output is the same,
 /// and get proper CPU readings (they need some time between consecutive calls)
+#[derive(Debug, Clone)]
 pub struct Readings {
     pid: u32,
     process: String,
@@ -9,6 +14,7 @@ pub struct Readings {
     mem: u64,
     total: u64,
     cpus_no: usize,
+    time: Timestamp,
 }
 
 impl Readings {
@@ -23,6 +29,7 @@ impl Readings {
             mem: (process.memory() + process.virtual_memory()) / 1024,
             total: sys.total_memory() / 1024,
             cpus_no: sys.cpus().len(),
+            time: Local::now(),
         }
     }
 
@@ -50,14 +57,32 @@ impl Readings {
         self.cpus_no
     }
 
+    /// When the readings were taken
+    pub fn get_time(&self) -> Timestamp {
+        self.time
+    }
+
     /// Refreshing only data provided by traced process
     pub fn refresh(&mut self, cpu: f32, mem: u64) {
         self.cpu = cpu;
         self.mem = mem / 1024;
+        self.time = Local::now();
     }
 }
 
-pub trait SysDataStream {
-    fn new(max_hist_len: usize, interpolation_len: u16) -> Self;
-    fn poll(&mut self, system_info: &Readings);
+/// Anything that follows samples over time - CPU, memory, GPUs, application metrics...
+pub trait SysDataStream: Send {
+    /// Short name of the stream, e.g. "cpu" or "gpu_memory"
+    fn name(&self) -> &'static str;
+
+    /// GPU the stream follows, for per GPU streams
+    fn gpu_index(&self) -> Option<u32> {
+        None
+    }
+
+    /// Take from the sample whatever the stream needs
+    fn poll(&mut self, sample: &Sample);
+
+    /// Typed access through the registry
+    fn as_any(&self) -> &dyn Any;
 }
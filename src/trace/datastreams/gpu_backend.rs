@@ -0,0 +1,16 @@
+use crate::error::GpuError;
+use crate::trace::datastreams::gpu_data::GpuReadings;
+use crate::trace::datastreams::gpu_health::GpuHealth;
+
+/// Source of GPU readings: nvidia-smi, the simulator, ...
+/// Lives on the collector thread, so it has to be `Send`.
+pub trait GpuBackend: Send {
+    /// Short name, for logs and the UI
+    fn name(&self) -> &'static str;
+
+    /// Reads all GPUs and their processes
+    fn get_gpu_info(&mut self) -> Result<GpuReadings, GpuError>;
+
+    /// Healthy, or degraded since the first failure of an outage
+    fn health(&self) -> &GpuHealth;
+}
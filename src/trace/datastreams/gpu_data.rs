@@ -1,7 +1,8 @@
 use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Represents a single GPU and its metrics
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GpuInfo {
     /// GPU index (0-based)
     pub index: u32,
@@ -43,10 +44,64 @@ pub struct GpuInfo {
     pub compute_mode: String,
     /// Timestamp of the reading
     pub timestamp: String,
+    /// Pages retired after ECC errors (before Ampere, from the XML report)
+    #[serde(default)]
+    pub retired_pages: Option<GpuRetiredPages>,
+    /// Rows remapped after ECC errors (Ampere and later, from the XML report)
+    #[serde(default)]
+    pub remapped_rows: Option<GpuRemappedRows>,
+    /// Why clocks are held down right now
+    #[serde(default)]
+    pub throttle_reasons: Option<ThrottleReasons>,
+    /// MIG mode: Enabled, Disabled or N/A
+    #[serde(default)]
+    pub mig_mode: String,
+    /// MIG devices the GPU is partitioned into
+    #[serde(default)]
+    pub mig_devices: Vec<GpuMigDevice>,
+    /// NVLink links, empty without NVLink
+    #[serde(default)]
+    pub nvlinks: Vec<GpuNvLink>,
+    /// How the GPU reaches each of the other GPUs, empty when not known
+    #[serde(default)]
+    pub peers: Vec<GpuPeer>,
+    /// CPUs close to the GPU, empty when not known
+    #[serde(default)]
+    pub cpu_affinity: Vec<u32>,
+    /// NUMA node the GPU is attached to
+    #[serde(default)]
+    pub numa_node: Option<u32>,
+    /// NVENC sessions, frame rate and latency, all sessions together
+    #[serde(default)]
+    pub encoder_stats: Option<GpuEncoderStats>,
+    /// Seconds since the XML report that added ECC locations, retired pages, remapped rows or MIG
+    /// devices was read, `None` when nothing came from one
+    #[serde(default)]
+    pub report_age_secs: Option<u64>,
+}
+
+impl GpuInfo {
+    /// MB/s sent over all NVLink links - `None` without links, or on the first reading of the
+    /// counters throughput is computed from
+    pub fn nvlink_tx(&self) -> Option<f64> {
+        self.nvlink_total(|l| l.tx_throughput)
+    }
+
+    /// MB/s received over all NVLink links, `None` like `nvlink_tx`
+    pub fn nvlink_rx(&self) -> Option<f64> {
+        self.nvlink_total(|l| l.rx_throughput)
+    }
+
+    fn nvlink_total(&self, throughput: fn(&GpuNvLink) -> Option<f64>) -> Option<f64> {
+        if self.nvlinks.is_empty() {
+            return None;
+        }
+        self.nvlinks.iter().map(throughput).sum()
+    }
 }
 
 /// GPU memory information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GpuMemory {
     /// Total memory in MB
     pub total: u64,
@@ -65,7 +120,7 @@ pub struct GpuMemory {
 }
 
 /// GPU utilization metrics
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GpuUtilization {
     /// GPU utilization percentage
     pub gpu: u32,
@@ -79,10 +134,24 @@ pub struct GpuUtilization {
     pub jpeg: Option<u32>,
     /// OFA utilization percentage (if available)
     pub ofa: Option<u32>,
+    /// Busy percentage per engine, for backends naming their engines (e.g. "Render/3D", "Video")
+    #[serde(default)]
+    pub engines: BTreeMap<String, u32>,
+}
+
+/// Video encoder load of a GPU, over all its NVENC sessions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct GpuEncoderStats {
+    /// Active encoder sessions
+    pub session_count: u32,
+    /// Average frames per second of the sessions
+    pub average_fps: u32,
+    /// Average time to encode a frame in microseconds
+    pub average_latency: u32,
 }
 
 /// GPU temperature readings
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GpuTemperature {
     /// Core GPU temperature in Celsius
     pub gpu: Option<i32>,
@@ -93,7 +162,7 @@ pub struct GpuTemperature {
 }
 
 /// GPU power information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GpuPower {
     /// Current power draw in watts
     pub draw: Option<f32>,
@@ -116,7 +185,7 @@ pub struct GpuPower {
 }
 
 /// GPU clock speeds
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GpuClocks {
     /// Current graphics clock in MHz
     pub graphics: Option<u32>,
@@ -139,7 +208,7 @@ pub struct GpuClocks {
 }
 
 /// GPU ECC (Error Correction Code) information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GpuEcc {
     /// Current ECC mode
     pub mode_current: String,
@@ -156,7 +225,7 @@ pub struct GpuEcc {
 }
 
 /// ECC error counts
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GpuEccErrors {
     /// Device memory errors
     pub device_memory: u64,
@@ -178,8 +247,133 @@ pub struct GpuEccErrors {
     pub total: u64,
 }
 
+/// Memory pages retired after ECC errors
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GpuRetiredPages {
+    /// Retired after multiple single bit errors
+    pub single_bit: u64,
+    /// Retired after a double bit error
+    pub double_bit: u64,
+    /// Pages waiting for retirement on the next reboot
+    pub pending: bool,
+}
+
+/// DRAM rows remapped after ECC errors
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GpuRemappedRows {
+    /// Remapped after correctable errors
+    pub correctable: u64,
+    /// Remapped after uncorrectable errors
+    pub uncorrectable: u64,
+    /// A remapping waits for a GPU reset
+    pub pending: bool,
+    /// Remapping failed - the GPU needs servicing
+    pub failure: bool,
+}
+
+/// Reasons the clocks are held below their maximum, as a bitset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThrottleReasons(pub u64);
+
+impl ThrottleReasons {
+    pub const GPU_IDLE: u64 = 1 << 0;
+    pub const APPLICATIONS_CLOCKS_SETTING: u64 = 1 << 1;
+    pub const SW_POWER_CAP: u64 = 1 << 2;
+    pub const HW_SLOWDOWN: u64 = 1 << 3;
+    pub const SYNC_BOOST: u64 = 1 << 4;
+    pub const SW_THERMAL_SLOWDOWN: u64 = 1 << 5;
+    pub const HW_THERMAL_SLOWDOWN: u64 = 1 << 6;
+    pub const HW_POWER_BRAKE_SLOWDOWN: u64 = 1 << 7;
+    pub const DISPLAY_CLOCK_SETTING: u64 = 1 << 8;
+
+    /// Every reason with the name nvidia-smi gives it
+    pub const ALL: [(u64, &'static str); 9] = [
+        (Self::GPU_IDLE, "gpu_idle"),
+        (Self::APPLICATIONS_CLOCKS_SETTING, "applications_clocks_setting"),
+        (Self::SW_POWER_CAP, "sw_power_cap"),
+        (Self::HW_SLOWDOWN, "hw_slowdown"),
+        (Self::SYNC_BOOST, "sync_boost"),
+        (Self::SW_THERMAL_SLOWDOWN, "sw_thermal_slowdown"),
+        (Self::HW_THERMAL_SLOWDOWN, "hw_thermal_slowdown"),
+        (Self::HW_POWER_BRAKE_SLOWDOWN, "hw_power_brake_slowdown"),
+        (Self::DISPLAY_CLOCK_SETTING, "display_clocks_setting"),
+    ];
+
+    /// Bit of a reason named as nvidia-smi does
+    pub fn bit(name: &str) -> Option<u64> {
+        Self::ALL.iter().find(|(_, n)| *n == name).map(|(bit, _)| *bit)
+    }
+
+    pub fn contains(&self, bit: u64) -> bool {
+        self.0 & bit != 0
+    }
+
+    pub fn insert(&mut self, bit: u64) {
+        self.0 |= bit;
+    }
+
+    /// Names of the active reasons
+    pub fn names(&self) -> Vec<&'static str> {
+        Self::ALL
+            .iter()
+            .filter(|(bit, _)| self.contains(*bit))
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+/// One MIG device (GPU instance + compute instance) of a partitioned GPU
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GpuMigDevice {
+    /// MIG device index within the parent GPU
+    pub index: u32,
+    /// MIG device UUID (`MIG-...`), what `CUDA_VISIBLE_DEVICES` takes
+    #[serde(default)]
+    pub uuid: String,
+    /// Instance profile, e.g. `3g.20gb`
+    #[serde(default)]
+    pub profile: String,
+    /// GPU instance ID
+    pub gpu_instance_id: u32,
+    /// Compute instance ID
+    pub compute_instance_id: u32,
+    /// Streaming multiprocessors of the instance
+    pub multiprocessor_count: Option<u32>,
+    /// Total memory in MB
+    pub memory_total: u64,
+    /// Used memory in MB
+    pub memory_used: u64,
+}
+
+impl GpuMigDevice {
+    /// Whether `process` runs on this instance - its GPU is not checked
+    pub fn runs(&self, process: &GpuProcess) -> bool {
+        process.gpu_instance_id == Some(self.gpu_instance_id)
+            && process.compute_instance_id.is_none_or(|ci| ci == self.compute_instance_id)
+    }
+}
+
+/// Process recorded by accounting mode, also after it exited
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GpuAccountedProcess {
+    /// Process ID
+    pub pid: u32,
+    /// GPU index
+    pub gpu_index: u32,
+    /// Average GPU utilization percentage over the process lifetime
+    pub gpu_utilization: Option<u32>,
+    /// Average memory utilization percentage over the process lifetime
+    pub memory_utilization: Option<u32>,
+    /// Peak memory usage in MB
+    pub max_memory_usage: Option<u64>,
+    /// Run time in milliseconds
+    pub time_ms: Option<u64>,
+    /// Still running
+    pub is_running: bool,
+}
+
 /// PCIe information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GpuPcie {
     /// Current PCIe generation
     pub gen_current: Option<u32>,
@@ -195,10 +389,87 @@ pub struct GpuPcie {
     pub bus: Option<u32>,
     /// PCIe device
     pub device: Option<u32>,
+    /// Sent over PCIe in MB/s
+    #[serde(default)]
+    pub tx_throughput: Option<f64>,
+    /// Received over PCIe in MB/s
+    #[serde(default)]
+    pub rx_throughput: Option<f64>,
+}
+
+impl GpuPcie {
+    /// Whether the link runs below its maximum generation or width - a card in the wrong slot,
+    /// a riser, or power saving at idle
+    pub fn is_degraded(&self) -> bool {
+        let below = |current: Option<u32>, max: Option<u32>| matches!((current, max), (Some(c), Some(m)) if c < m);
+        below(self.gen_current, self.gen_max) || below(self.width_current, self.width_max)
+    }
+}
+
+/// One NVLink link of a GPU
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GpuNvLink {
+    /// Link number
+    pub link: u32,
+    /// Sent in MB/s
+    pub tx_throughput: Option<f64>,
+    /// Received in MB/s
+    pub rx_throughput: Option<f64>,
+    /// Error counters by name (replay, recovery, CRC, ...)
+    pub errors: BTreeMap<String, u64>,
+}
+
+impl GpuNvLink {
+    /// All errors of the link
+    pub fn error_count(&self) -> u64 {
+        self.errors.values().sum()
+    }
+}
+
+/// Path from a GPU to another one
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GpuPeer {
+    /// Index of the other GPU
+    pub gpu_index: u32,
+    /// As `nvidia-smi topo -m` names it: NV# (bonded NVLinks), PIX, PXB, PHB, NODE or SYS
+    pub connection: String,
+}
+
+impl GpuPeer {
+    /// Whether the GPUs talk over NVLink rather than PCIe
+    pub fn is_nvlink(&self) -> bool {
+        self.connection.starts_with("NV")
+    }
+
+    /// Whether the path crosses the interconnect between NUMA nodes - the slowest there is
+    pub fn crosses_numa(&self) -> bool {
+        self.connection == "SYS"
+    }
+}
+
+/// One NVENC session
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GpuEncoderSession {
+    /// GPU index
+    pub gpu_index: u32,
+    /// Session ID
+    pub session_id: u32,
+    /// Process encoding
+    pub pid: u32,
+    /// H.264, HEVC or AV1
+    pub codec: String,
+    /// Horizontal resolution in pixels
+    pub h_resolution: u32,
+    /// Vertical resolution in pixels
+    pub v_resolution: u32,
+    /// Average frames per second
+    pub average_fps: u32,
+    /// Average time to encode a frame in microseconds
+    pub average_latency: u32,
 }
 
 /// GPU process information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GpuProcess {
     /// Process ID
     pub pid: u32,
@@ -210,6 +481,21 @@ pub struct GpuProcess {
     pub used_memory: u64,
     /// GPU index
     pub gpu_index: u32,
+    /// GPU utilization percentage of this process (if available)
+    #[serde(default)]
+    pub utilization: Option<u32>,
+    /// GPU instance the process runs on, on a MIG partitioned GPU
+    #[serde(default)]
+    pub gpu_instance_id: Option<u32>,
+    /// Compute instance the process runs on, on a MIG partitioned GPU
+    #[serde(default)]
+    pub compute_instance_id: Option<u32>,
+    /// Encoder utilization percentage of this process (if available)
+    #[serde(default)]
+    pub encoder_utilization: Option<u32>,
+    /// Decoder utilization percentage of this process (if available)
+    #[serde(default)]
+    pub decoder_utilization: Option<u32>,
 }
 
 /// Collection of all GPU information
@@ -223,22 +509,12 @@ pub struct GpuReadings {
     pub gpu_count: u32,
     /// Timestamp of the reading
     pub timestamp: String,
-}
-
-impl Default for GpuEccErrors {
-    fn default() -> Self {
-        Self {
-            device_memory: 0,
-            dram: 0,
-            register_file: 0,
-            l1_cache: 0,
-            l2_cache: 0,
-            texture_memory: 0,
-            cbu: 0,
-            sram: 0,
-            total: 0,
-        }
-    }
+    /// Processes recorded by accounting mode
+    #[serde(default)]
+    pub accounted_processes: Vec<GpuAccountedProcess>,
+    /// NVENC sessions of all GPUs
+    #[serde(default)]
+    pub encoder_sessions: Vec<GpuEncoderSession>,
 }
 
 impl GpuReadings {
@@ -249,6 +525,8 @@ impl GpuReadings {
             processes: Vec::new(),
             gpu_count: 0,
             timestamp: chrono::Utc::now().format("%Y/%m/%d %H:%M:%S%.3f").to_string(),
+            accounted_processes: Vec::new(),
+            encoder_sessions: Vec::new(),
         }
     }
 
@@ -262,6 +540,21 @@ impl GpuReadings {
         self.gpus.iter().find(|gpu| gpu.uuid == uuid)
     }
 
+    /// Get the MIG device with the given UUID, and the GPU it is part of
+    pub fn get_mig_device_by_uuid(&self, uuid: &str) -> Option<(&GpuInfo, &GpuMigDevice)> {
+        self.gpus
+            .iter()
+            .find_map(|gpu| gpu.mig_devices.iter().find(|d| d.uuid == uuid).map(|d| (gpu, d)))
+    }
+
+    /// Get processes running on a MIG device of a GPU
+    pub fn get_mig_device_processes(&self, gpu_index: u32, device: &GpuMigDevice) -> Vec<&GpuProcess> {
+        self.processes
+            .iter()
+            .filter(|proc| proc.gpu_index == gpu_index && device.runs(proc))
+            .collect()
+    }
+
     /// Get processes for a specific GPU
     pub fn get_gpu_processes(&self, gpu_index: u32) -> Vec<&GpuProcess> {
         self.processes
@@ -270,6 +563,22 @@ impl GpuReadings {
             .collect()
     }
 
+    /// Get NVENC sessions of a process on a specific GPU
+    pub fn get_encoder_sessions(&self, gpu_index: u32, pid: u32) -> Vec<&GpuEncoderSession> {
+        self.encoder_sessions
+            .iter()
+            .filter(|session| session.gpu_index == gpu_index && session.pid == pid)
+            .collect()
+    }
+
+    /// Get processes belonging to any of the given PIDs (e.g. traced process tree or cgroup members)
+    pub fn get_processes_for_pids(&self, pids: &[u32]) -> Vec<&GpuProcess> {
+        self.processes
+            .iter()
+            .filter(|proc| pids.contains(&proc.pid))
+            .collect()
+    }
+
     /// Get total memory usage across all GPUs
     pub fn get_total_memory_used(&self) -> u64 {
         self.gpus.iter().map(|gpu| gpu.memory.used).sum()
@@ -0,0 +1,227 @@
+use crate::error::GpuError;
+use crate::trace::datastreams::time_series::Timestamp;
+use chrono::Local;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// First wait after a failed GPU query
+pub const BACKOFF_BASE: Duration = Duration::from_secs(1);
+/// Longest wait between GPU queries while the backend keeps failing
+pub const BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+/// State of the GPU backend
+#[derive(Debug, Clone, PartialEq)]
+pub enum GpuHealth {
+    /// Last query worked
+    Healthy,
+    /// Queries keep failing - since the first failure of the outage
+    Degraded {
+        since: Timestamp,
+        failures: u32,
+        last_error: GpuError,
+    },
+    /// Failed in a way retrying won't fix - not asked again
+    Stopped { since: Timestamp, error: GpuError },
+}
+
+impl GpuHealth {
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, GpuHealth::Healthy)
+    }
+
+    /// Error of the last failed query, while degraded
+    pub fn last_error(&self) -> Option<&GpuError> {
+        match self {
+            GpuHealth::Healthy => None,
+            GpuHealth::Degraded { last_error, .. } => Some(last_error),
+            GpuHealth::Stopped { error, .. } => Some(error),
+        }
+    }
+}
+
+impl fmt::Display for GpuHealth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GpuHealth::Healthy => write!(f, "GPU backend healthy"),
+            GpuHealth::Degraded {
+                since, last_error, ..
+            } => write!(
+                f,
+                "GPU backend degraded since {}, last error: {}",
+                since.format("%H:%M:%S"),
+                last_error
+            ),
+            GpuHealth::Stopped { since, error } => {
+                write!(f, "GPU backend stopped at {}: {}", since.format("%H:%M:%S"), error)
+            }
+        }
+    }
+}
+
+/// Health of the GPU backend plus exponential backoff between retries while it fails.
+#[derive(Debug, Clone)]
+pub struct GpuBackoff {
+    health: GpuHealth,
+    retry_at: Option<Instant>,
+}
+
+impl GpuBackoff {
+    pub fn new() -> Self {
+        Self {
+            health: GpuHealth::Healthy,
+            retry_at: None,
+        }
+    }
+
+    pub fn health(&self) -> &GpuHealth {
+        &self.health
+    }
+
+    /// Whether the backend may be asked again at `now`
+    pub fn ready(&self, now: Instant) -> bool {
+        !self.is_stopped() && self.retry_at.is_none_or(|at| now >= at)
+    }
+
+    /// Whether the backend gave up for good
+    pub fn is_stopped(&self) -> bool {
+        matches!(self.health, GpuHealth::Stopped { .. })
+    }
+
+    /// Fails right away while waiting for the next try - or forever, with the error that stopped it
+    pub fn check(&self, now: Instant) -> Result<(), GpuError> {
+        match &self.health {
+            GpuHealth::Stopped { error, .. } => Err(error.clone()),
+            _ if self.ready(now) => Ok(()),
+            _ => Err(GpuError::BackingOff(self.retry_in(now))),
+        }
+    }
+
+    /// Books the outcome of a query started at `now`, passing it on. Errors retrying won't fix
+    /// (not installed, driver mismatch) stop the backend instead of backing off.
+    pub fn record<T>(&mut self, now: Instant, result: Result<T, GpuError>) -> Result<T, GpuError> {
+        match &result {
+            Ok(_) => self.succeeded(),
+            Err(e) if e.is_permanent() => self.stop(e.clone()),
+            Err(e) => {
+                let delay = self.failed(now, e.clone());
+                log::debug!("GPU query failed, next try in {:?}", delay);
+            }
+        }
+        result
+    }
+
+    /// Query worked - back to healthy.
+    pub fn succeeded(&mut self) {
+        if let GpuHealth::Degraded {
+            since, failures, ..
+        } = &self.health
+        {
+            log::info!(
+                "GPU backend recovered after {} failed queries (degraded since {})",
+                failures,
+                since.format("%H:%M:%S")
+            );
+        }
+        self.health = GpuHealth::Healthy;
+        self.retry_at = None;
+    }
+
+    /// Query failed for good - no more tries.
+    pub fn stop(&mut self, error: GpuError) {
+        match error.hint() {
+            Some(hint) => log::error!("GPU backend stopped: {} - {}", error, hint),
+            None => log::error!("GPU backend stopped: {}", error),
+        }
+        self.health = GpuHealth::Stopped {
+            since: Local::now(),
+            error,
+        };
+        self.retry_at = None;
+    }
+
+    /// How long until the next try
+    pub fn retry_in(&self, now: Instant) -> Duration {
+        self.retry_at.map_or(Duration::ZERO, |at| at.saturating_duration_since(now))
+    }
+
+    /// Query failed at `now` - returns how long until the next try.
+    pub fn failed(&mut self, now: Instant, error: GpuError) -> Duration {
+        let (since, failures) = match &self.health {
+            GpuHealth::Healthy | GpuHealth::Stopped { .. } => {
+                log::warn!("GPU backend degraded: {}", error);
+                (Local::now(), 1)
+            }
+            GpuHealth::Degraded {
+                since, failures, ..
+            } => (*since, failures + 1),
+        };
+        let delay = BACKOFF_BASE
+            .saturating_mul(1 << (failures - 1).min(16))
+            .min(BACKOFF_MAX);
+        self.health = GpuHealth::Degraded {
+            since,
+            failures,
+            last_error: error,
+        };
+        self.retry_at = Some(now + delay);
+        delay
+    }
+}
+
+impl Default for GpuBackoff {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timeout() -> GpuError {
+        GpuError::Timeout {
+            command: "nvidia-smi".to_string(),
+            timeout: Duration::from_secs(3),
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_recovers() {
+        let mut backoff = GpuBackoff::new();
+        let now = Instant::now();
+        assert!(backoff.ready(now));
+
+        assert_eq!(backoff.failed(now, timeout()), Duration::from_secs(1));
+        assert!(!backoff.ready(now));
+        assert!(backoff.ready(now + Duration::from_secs(1)));
+        assert_eq!(backoff.failed(now, timeout()), Duration::from_secs(2));
+        assert_eq!(backoff.failed(now, GpuError::Io("hung".to_string())), Duration::from_secs(4));
+        for _ in 0..20 {
+            backoff.failed(now, GpuError::Io("hung".to_string()));
+        }
+        assert_eq!(backoff.failed(now, GpuError::Io("hung".to_string())), BACKOFF_MAX);
+        assert_eq!(backoff.health().last_error(), Some(&GpuError::Io("hung".to_string())));
+        assert_eq!(backoff.retry_in(now), BACKOFF_MAX);
+        assert!(backoff.health().to_string().starts_with("GPU backend degraded since "));
+
+        backoff.succeeded();
+        assert!(backoff.health().is_healthy());
+        assert!(backoff.ready(now));
+    }
+
+    #[test]
+    fn test_permanent_errors_stop_the_backend() {
+        let mut backoff = GpuBackoff::new();
+        let now = Instant::now();
+        let mismatch = GpuError::DriverMismatch("NVML 550 vs kernel 535".to_string());
+        let _ = backoff.record::<()>(now, Err(mismatch.clone()));
+        assert!(backoff.is_stopped());
+        assert_eq!(backoff.health().last_error(), Some(&mismatch));
+        assert!(backoff.health().to_string().starts_with("GPU backend stopped at "));
+
+        // not even after the longest backoff
+        let later = now + BACKOFF_MAX * 2;
+        assert!(!backoff.ready(later));
+        assert_eq!(backoff.check(later), Err(mismatch));
+    }
+}
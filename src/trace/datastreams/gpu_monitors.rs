@@ -1,57 +1,86 @@
-use crate::trace::datastreams::gpu_data::{GpuReadings, GpuInfo};
+use crate::trace::datastreams::gpu_data::{
+    GpuAccountedProcess, GpuEcc, GpuEncoderSession, GpuEncoderStats, GpuInfo, GpuNvLink, GpuPcie, GpuReadings,
+    GpuRemappedRows, GpuRetiredPages, GpuUtilization, ThrottleReasons,
+};
 use crate::trace::datastreams::data_stream::SysDataStream;
-use crate::trace::datastreams::utils;
+use crate::trace::datastreams::numa::{format_cpu_list, Placement, ProcessAffinity};
+use crate::trace::datastreams::retention::{History, Retention};
+use crate::trace::datastreams::sample::Sample;
+use crate::trace::datastreams::time_series::Timestamp;
+use std::any::Any;
+use std::collections::{BTreeSet, VecDeque};
+
+/// Throttle reason changes kept for the timeline - one entry per change, not per reading
+const MAX_THROTTLE_CHANGES: usize = 10_000;
+
+/// ECC alerts kept per GPU, the oldest go first
+const MAX_ECC_ALERTS: usize = 100;
+
+/// NUMA warnings kept, the oldest go first
+const MAX_NUMA_WARNINGS: usize = 100;
+
+/// Creates a stream following one GPU
+pub type GpuStreamFactory = fn(&Retention, u32) -> Box<dyn SysDataStream>;
+
+/// Streams every GPU gets when it first shows up. New per-GPU monitors only need to be listed here.
+pub const GPU_STREAMS: [GpuStreamFactory; 9] = [
+    |r, i| Box::new(GpuMemoryMonitor::new_for_gpu(r, i)),
+    |r, i| Box::new(GpuUtilizationMonitor::new_for_gpu(r, i)),
+    |r, i| Box::new(GpuTemperatureMonitor::new_for_gpu(r, i)),
+    |r, i| Box::new(GpuPowerMonitor::new_for_gpu(r, i)),
+    |r, i| Box::new(GpuClockMonitor::new_for_gpu(r, i)),
+    |r, i| Box::new(GpuInterconnectMonitor::new_for_gpu(r, i)),
+    |r, i| Box::new(GpuMediaMonitor::new_for_gpu(r, i)),
+    |_, i| Box::new(GpuEccMonitor::new_for_gpu(i)),
+    |_, i| Box::new(GpuProcessMonitor::new_for_gpu(i)),
+];
 
 /// GPU Memory Monitor - tracks GPU memory usage over time
 pub struct GpuMemoryMonitor {
-    pub memory_usage_history: Vec<f64>, // Memory usage percentage
-    pub memory_used_history: Vec<u64>,  // Absolute memory used in MB
+    pub memory_usage_history: History, // Memory usage percentage
+    pub memory_used_history: History,  // Absolute memory used in MB
     pub memory_total: u64,
     pub current_usage: f64,
     pub current_used: u64,
     pub current_free: u64,
-    max_history_len: usize,
-    interpolation_len: u16,
     gpu_index: u32,
 }
 
 impl SysDataStream for GpuMemoryMonitor {
-    fn new(max_hist_len: usize, inter_len: u16) -> Self {
-        Self {
-            memory_usage_history: vec![0.0; max_hist_len],
-            memory_used_history: vec![0; max_hist_len],
-            memory_total: 0,
-            current_usage: 0.0,
-            current_used: 0,
-            current_free: 0,
-            max_history_len: max_hist_len,
-            interpolation_len: inter_len,
-            gpu_index: 0,
+    fn name(&self) -> &'static str {
+        "gpu_memory"
+    }
+
+    fn gpu_index(&self) -> Option<u32> {
+        Some(self.gpu_index)
+    }
+
+    fn poll(&mut self, sample: &Sample) {
+        match sample.gpu.as_ref().and_then(|g| g.get_gpu(self.gpu_index)) {
+            Some(info) => self.poll_gpu(info, sample.time),
+            None => self.poll_gap(sample.time),
         }
     }
 
-    fn poll(&mut self, _system_info: &crate::trace::datastreams::data_stream::Readings) {
-        // This will be updated to work with GPU data
-        // For now, we'll implement a placeholder
+    fn as_any(&self) -> &dyn Any {
+        self
     }
 }
 
 impl GpuMemoryMonitor {
-    pub fn new_for_gpu(max_hist_len: usize, inter_len: u16, gpu_index: u32) -> Self {
+    pub fn new_for_gpu(retention: &Retention, gpu_index: u32) -> Self {
         Self {
-            memory_usage_history: vec![0.0; max_hist_len],
-            memory_used_history: vec![0; max_hist_len],
+            memory_usage_history: History::new(retention),
+            memory_used_history: History::new(retention),
             memory_total: 0,
             current_usage: 0.0,
             current_used: 0,
             current_free: 0,
-            max_history_len: max_hist_len,
-            interpolation_len: inter_len,
             gpu_index,
         }
     }
 
-    pub fn poll_gpu(&mut self, gpu_info: &GpuInfo) {
+    pub fn poll_gpu(&mut self, gpu_info: &GpuInfo, at: Timestamp) {
         self.memory_total = gpu_info.memory.total;
         self.current_used = gpu_info.memory.used;
         self.current_free = gpu_info.memory.free;
@@ -61,348 +90,709 @@ impl GpuMemoryMonitor {
             0.0
         };
 
-        // Update history
-        while self.memory_usage_history.len() >= self.max_history_len {
-            self.memory_usage_history.remove(0);
-        }
-        while self.memory_used_history.len() >= self.max_history_len {
-            self.memory_used_history.remove(0);
-        }
-
-        let last_usage = self.memory_usage_history.last().copied().unwrap_or(0.0);
-        let last_used = self.memory_used_history.last().copied().unwrap_or(0);
-
-        self.memory_usage_history.extend_from_slice(
-            utils::interpolate(
-                last_usage,
-                self.current_usage / 100.0,
-                self.interpolation_len,
-            )
-            .as_slice(),
-        );
+        self.memory_usage_history.push(at, self.current_usage);
+        self.memory_used_history.push(at, self.current_used as f64);
+    }
 
-        self.memory_used_history.extend_from_slice(
-            utils::interpolate(
-                last_used as f64,
-                self.current_used as f64,
-                self.interpolation_len,
-            )
-            .iter()
-            .map(|&x| x as u64)
-            .collect::<Vec<u64>>()
-            .as_slice(),
-        );
+    /// No reading from this GPU at `at` - recorded as a gap, not as zeros.
+    pub fn poll_gap(&mut self, at: Timestamp) {
+        self.memory_usage_history.push_gap(at);
+        self.memory_used_history.push_gap(at);
     }
 }
 
 /// GPU Utilization Monitor - tracks GPU utilization over time
 pub struct GpuUtilizationMonitor {
-    pub gpu_utilization_history: Vec<f32>,
-    pub memory_utilization_history: Vec<f32>,
+    pub gpu_utilization_history: History,
+    pub memory_utilization_history: History,
     pub current_gpu_util: u32,
     pub current_memory_util: u32,
-    max_history_len: usize,
-    interpolation_len: u16,
     gpu_index: u32,
 }
 
 impl SysDataStream for GpuUtilizationMonitor {
-    fn new(max_hist_len: usize, inter_len: u16) -> Self {
-        Self {
-            gpu_utilization_history: vec![0.0; max_hist_len],
-            memory_utilization_history: vec![0.0; max_hist_len],
-            current_gpu_util: 0,
-            current_memory_util: 0,
-            max_history_len: max_hist_len,
-            interpolation_len: inter_len,
-            gpu_index: 0,
+    fn name(&self) -> &'static str {
+        "gpu_utilization"
+    }
+
+    fn gpu_index(&self) -> Option<u32> {
+        Some(self.gpu_index)
+    }
+
+    fn poll(&mut self, sample: &Sample) {
+        match sample.gpu.as_ref().and_then(|g| g.get_gpu(self.gpu_index)) {
+            Some(info) => self.poll_gpu(info, sample.time),
+            None => self.poll_gap(sample.time),
         }
     }
 
-    fn poll(&mut self, _system_info: &crate::trace::datastreams::data_stream::Readings) {
-        // This will be updated to work with GPU data
+    fn as_any(&self) -> &dyn Any {
+        self
     }
 }
 
 impl GpuUtilizationMonitor {
-    pub fn new_for_gpu(max_hist_len: usize, inter_len: u16, gpu_index: u32) -> Self {
+    pub fn new_for_gpu(retention: &Retention, gpu_index: u32) -> Self {
         Self {
-            gpu_utilization_history: vec![0.0; max_hist_len],
-            memory_utilization_history: vec![0.0; max_hist_len],
+            gpu_utilization_history: History::new(retention),
+            memory_utilization_history: History::new(retention),
             current_gpu_util: 0,
             current_memory_util: 0,
-            max_history_len: max_hist_len,
-            interpolation_len: inter_len,
             gpu_index,
         }
     }
 
-    pub fn poll_gpu(&mut self, gpu_info: &GpuInfo) {
+    pub fn poll_gpu(&mut self, gpu_info: &GpuInfo, at: Timestamp) {
         self.current_gpu_util = gpu_info.utilization.gpu;
         self.current_memory_util = gpu_info.utilization.memory;
 
-        // Update GPU utilization history
-        while self.gpu_utilization_history.len() >= self.max_history_len {
-            self.gpu_utilization_history.remove(0);
-        }
-        let last_gpu_util = self.gpu_utilization_history.last().copied().unwrap_or(0.0);
-        self.gpu_utilization_history.extend_from_slice(
-            utils::interpolate(
-                last_gpu_util,
-                self.current_gpu_util as f32 / 100.0,
-                self.interpolation_len,
-            )
-            .as_slice(),
-        );
+        self.gpu_utilization_history
+            .push(at, self.current_gpu_util as f64);
+        self.memory_utilization_history
+            .push(at, self.current_memory_util as f64);
+    }
 
-        // Update memory utilization history
-        while self.memory_utilization_history.len() >= self.max_history_len {
-            self.memory_utilization_history.remove(0);
-        }
-        let last_mem_util = self.memory_utilization_history.last().copied().unwrap_or(0.0);
-        self.memory_utilization_history.extend_from_slice(
-            utils::interpolate(
-                last_mem_util,
-                self.current_memory_util as f32 / 100.0,
-                self.interpolation_len,
-            )
-            .as_slice(),
-        );
+    /// No reading at `at` - a gap in the histories.
+    pub fn poll_gap(&mut self, at: Timestamp) {
+        self.gpu_utilization_history.push_gap(at);
+        self.memory_utilization_history.push_gap(at);
     }
 }
 
 /// GPU Temperature Monitor - tracks GPU temperature over time
 pub struct GpuTemperatureMonitor {
-    pub temperature_history: Vec<f32>,
+    pub temperature_history: History,
     pub current_temperature: Option<i32>,
     pub max_temperature: Option<i32>,
     pub memory_temperature: Option<i32>,
-    max_history_len: usize,
-    interpolation_len: u16,
     gpu_index: u32,
 }
 
 impl SysDataStream for GpuTemperatureMonitor {
-    fn new(max_hist_len: usize, inter_len: u16) -> Self {
-        Self {
-            temperature_history: vec![0.0; max_hist_len],
-            current_temperature: None,
-            max_temperature: None,
-            memory_temperature: None,
-            max_history_len: max_hist_len,
-            interpolation_len: inter_len,
-            gpu_index: 0,
+    fn name(&self) -> &'static str {
+        "gpu_temperature"
+    }
+
+    fn gpu_index(&self) -> Option<u32> {
+        Some(self.gpu_index)
+    }
+
+    fn poll(&mut self, sample: &Sample) {
+        match sample.gpu.as_ref().and_then(|g| g.get_gpu(self.gpu_index)) {
+            Some(info) => self.poll_gpu(info, sample.time),
+            None => self.poll_gap(sample.time),
         }
     }
 
-    fn poll(&mut self, _system_info: &crate::trace::datastreams::data_stream::Readings) {
-        // This will be updated to work with GPU data
+    fn as_any(&self) -> &dyn Any {
+        self
     }
 }
 
 impl GpuTemperatureMonitor {
-    pub fn new_for_gpu(max_hist_len: usize, inter_len: u16, gpu_index: u32) -> Self {
+    pub fn new_for_gpu(retention: &Retention, gpu_index: u32) -> Self {
         Self {
-            temperature_history: vec![0.0; max_hist_len],
+            temperature_history: History::new(retention),
             current_temperature: None,
             max_temperature: None,
             memory_temperature: None,
-            max_history_len: max_hist_len,
-            interpolation_len: inter_len,
             gpu_index,
         }
     }
 
-    pub fn poll_gpu(&mut self, gpu_info: &GpuInfo) {
+    pub fn poll_gpu(&mut self, gpu_info: &GpuInfo, at: Timestamp) {
         self.current_temperature = gpu_info.temperature.gpu;
         self.max_temperature = gpu_info.temperature.gpu_tlimit;
         self.memory_temperature = gpu_info.temperature.memory;
 
         if let Some(temp) = self.current_temperature {
-            while self.temperature_history.len() >= self.max_history_len {
-                self.temperature_history.remove(0);
-            }
-            let last_temp = self.temperature_history.last().copied().unwrap_or(temp as f32);
-            self.temperature_history.extend_from_slice(
-                utils::interpolate(
-                    last_temp,
-                    temp as f32,
-                    self.interpolation_len,
-                )
-                .as_slice(),
-            );
+            self.temperature_history.push(at, temp as f64);
         }
     }
+
+    /// No reading at `at` - a gap in the histories.
+    pub fn poll_gap(&mut self, at: Timestamp) {
+        self.temperature_history.push_gap(at);
+    }
 }
 
 /// GPU Power Monitor - tracks GPU power consumption over time
 pub struct GpuPowerMonitor {
-    pub power_draw_history: Vec<f32>,
+    pub power_draw_history: History,
     pub current_power_draw: Option<f32>,
     pub power_limit: Option<f32>,
     pub power_management: String,
-    max_history_len: usize,
-    interpolation_len: u16,
     gpu_index: u32,
 }
 
 impl SysDataStream for GpuPowerMonitor {
-    fn new(max_hist_len: usize, inter_len: u16) -> Self {
-        Self {
-            power_draw_history: vec![0.0; max_hist_len],
-            current_power_draw: None,
-            power_limit: None,
-            power_management: String::new(),
-            max_history_len: max_hist_len,
-            interpolation_len: inter_len,
-            gpu_index: 0,
+    fn name(&self) -> &'static str {
+        "gpu_power"
+    }
+
+    fn gpu_index(&self) -> Option<u32> {
+        Some(self.gpu_index)
+    }
+
+    fn poll(&mut self, sample: &Sample) {
+        match sample.gpu.as_ref().and_then(|g| g.get_gpu(self.gpu_index)) {
+            Some(info) => self.poll_gpu(info, sample.time),
+            None => self.poll_gap(sample.time),
         }
     }
 
-    fn poll(&mut self, _system_info: &crate::trace::datastreams::data_stream::Readings) {
-        // This will be updated to work with GPU data
+    fn as_any(&self) -> &dyn Any {
+        self
     }
 }
 
 impl GpuPowerMonitor {
-    pub fn new_for_gpu(max_hist_len: usize, inter_len: u16, gpu_index: u32) -> Self {
+    pub fn new_for_gpu(retention: &Retention, gpu_index: u32) -> Self {
         Self {
-            power_draw_history: vec![0.0; max_hist_len],
+            power_draw_history: History::new(retention),
             current_power_draw: None,
             power_limit: None,
             power_management: String::new(),
-            max_history_len: max_hist_len,
-            interpolation_len: inter_len,
             gpu_index,
         }
     }
 
-    pub fn poll_gpu(&mut self, gpu_info: &GpuInfo) {
+    pub fn poll_gpu(&mut self, gpu_info: &GpuInfo, at: Timestamp) {
         self.current_power_draw = gpu_info.power.draw;
         self.power_limit = gpu_info.power.limit;
         self.power_management = gpu_info.power.management.clone();
 
         if let Some(power) = self.current_power_draw {
-            while self.power_draw_history.len() >= self.max_history_len {
-                self.power_draw_history.remove(0);
-            }
-            let last_power = self.power_draw_history.last().copied().unwrap_or(power);
-            self.power_draw_history.extend_from_slice(
-                utils::interpolate(
-                    last_power,
-                    power,
-                    self.interpolation_len,
-                )
-                .as_slice(),
-            );
+            self.power_draw_history.push(at, power as f64);
         }
     }
+
+    /// No reading at `at` - a gap in the histories.
+    pub fn poll_gap(&mut self, at: Timestamp) {
+        self.power_draw_history.push_gap(at);
+    }
 }
 
-/// GPU Clock Monitor - tracks GPU clock speeds over time
+/// GPU Clock Monitor - tracks GPU clock speeds and why they are held back over time
 pub struct GpuClockMonitor {
-    pub graphics_clock_history: Vec<f32>,
-    pub memory_clock_history: Vec<f32>,
+    pub graphics_clock_history: History,
+    pub sm_clock_history: History,
+    pub memory_clock_history: History,
     pub current_graphics_clock: Option<u32>,
+    pub current_sm_clock: Option<u32>,
     pub current_memory_clock: Option<u32>,
     pub max_graphics_clock: Option<u32>,
+    pub max_sm_clock: Option<u32>,
     pub max_memory_clock: Option<u32>,
-    max_history_len: usize,
-    interpolation_len: u16,
+    pub throttle_reasons: Option<ThrottleReasons>,
+    /// Active throttle reasons since each change, `None` while unknown
+    pub throttle_timeline: VecDeque<(Timestamp, Option<ThrottleReasons>)>,
     gpu_index: u32,
 }
 
 impl SysDataStream for GpuClockMonitor {
-    fn new(max_hist_len: usize, inter_len: u16) -> Self {
-        Self {
-            graphics_clock_history: vec![0.0; max_hist_len],
-            memory_clock_history: vec![0.0; max_hist_len],
-            current_graphics_clock: None,
-            current_memory_clock: None,
-            max_graphics_clock: None,
-            max_memory_clock: None,
-            max_history_len: max_hist_len,
-            interpolation_len: inter_len,
-            gpu_index: 0,
+    fn name(&self) -> &'static str {
+        "gpu_clocks"
+    }
+
+    fn gpu_index(&self) -> Option<u32> {
+        Some(self.gpu_index)
+    }
+
+    fn poll(&mut self, sample: &Sample) {
+        match sample.gpu.as_ref().and_then(|g| g.get_gpu(self.gpu_index)) {
+            Some(info) => self.poll_gpu(info, sample.time),
+            None => self.poll_gap(sample.time),
         }
     }
 
-    fn poll(&mut self, _system_info: &crate::trace::datastreams::data_stream::Readings) {
-        // This will be updated to work with GPU data
+    fn as_any(&self) -> &dyn Any {
+        self
     }
 }
 
 impl GpuClockMonitor {
-    pub fn new_for_gpu(max_hist_len: usize, inter_len: u16, gpu_index: u32) -> Self {
+    pub fn new_for_gpu(retention: &Retention, gpu_index: u32) -> Self {
         Self {
-            graphics_clock_history: vec![0.0; max_hist_len],
-            memory_clock_history: vec![0.0; max_hist_len],
+            graphics_clock_history: History::new(retention),
+            sm_clock_history: History::new(retention),
+            memory_clock_history: History::new(retention),
             current_graphics_clock: None,
+            current_sm_clock: None,
             current_memory_clock: None,
             max_graphics_clock: None,
+            max_sm_clock: None,
             max_memory_clock: None,
-            max_history_len: max_hist_len,
-            interpolation_len: inter_len,
+            throttle_reasons: None,
+            throttle_timeline: VecDeque::new(),
             gpu_index,
         }
     }
 
-    pub fn poll_gpu(&mut self, gpu_info: &GpuInfo) {
+    pub fn poll_gpu(&mut self, gpu_info: &GpuInfo, at: Timestamp) {
         self.current_graphics_clock = gpu_info.clocks.graphics;
+        self.current_sm_clock = gpu_info.clocks.sm;
         self.current_memory_clock = gpu_info.clocks.memory;
         self.max_graphics_clock = gpu_info.clocks.max_graphics;
+        self.max_sm_clock = gpu_info.clocks.max_sm;
         self.max_memory_clock = gpu_info.clocks.max_memory;
 
-        // Update graphics clock history
         if let Some(clock) = self.current_graphics_clock {
-            while self.graphics_clock_history.len() >= self.max_history_len {
-                self.graphics_clock_history.remove(0);
-            }
-            let last_clock = self.graphics_clock_history.last().copied().unwrap_or(clock as f32);
-            self.graphics_clock_history.extend_from_slice(
-                utils::interpolate(
-                    last_clock,
-                    clock as f32,
-                    self.interpolation_len,
-                )
-                .as_slice(),
-            );
-        }
-
-        // Update memory clock history
+            self.graphics_clock_history.push(at, clock as f64);
+        }
+        if let Some(clock) = self.current_sm_clock {
+            self.sm_clock_history.push(at, clock as f64);
+        }
         if let Some(clock) = self.current_memory_clock {
-            while self.memory_clock_history.len() >= self.max_history_len {
-                self.memory_clock_history.remove(0);
+            self.memory_clock_history.push(at, clock as f64);
+        }
+        self.record_throttle(at, gpu_info.throttle_reasons);
+    }
+
+    /// No reading at `at` - a gap in the histories.
+    pub fn poll_gap(&mut self, at: Timestamp) {
+        self.graphics_clock_history.push_gap(at);
+        self.sm_clock_history.push_gap(at);
+        self.memory_clock_history.push_gap(at);
+        self.record_throttle(at, None);
+    }
+
+    /// Active throttle reasons at `at`, `None` where they are not known
+    pub fn throttle_at(&self, at: Timestamp) -> Option<ThrottleReasons> {
+        let after = self.throttle_timeline.partition_point(|(since, _)| *since <= at);
+        after.checked_sub(1).and_then(|i| self.throttle_timeline[i].1)
+    }
+
+    fn record_throttle(&mut self, at: Timestamp, reasons: Option<ThrottleReasons>) {
+        self.throttle_reasons = reasons;
+        if self.throttle_timeline.back().map(|(_, r)| *r) != Some(reasons) {
+            self.throttle_timeline.push_back((at, reasons));
+            if self.throttle_timeline.len() > MAX_THROTTLE_CHANGES {
+                self.throttle_timeline.pop_front();
+            }
+        }
+    }
+}
+
+/// GPU Interconnect Monitor - tracks PCIe and NVLink throughput over time
+pub struct GpuInterconnectMonitor {
+    pub pcie_tx_history: History, // MB/s
+    pub pcie_rx_history: History,
+    pub nvlink_tx_history: History, // MB/s, all links together
+    pub nvlink_rx_history: History,
+    pub pcie: GpuPcie,
+    pub nvlinks: Vec<GpuNvLink>,
+    pub nvlink_tx: Option<f64>,
+    pub nvlink_rx: Option<f64>,
+    gpu_index: u32,
+}
+
+impl SysDataStream for GpuInterconnectMonitor {
+    fn name(&self) -> &'static str {
+        "gpu_interconnect"
+    }
+
+    fn gpu_index(&self) -> Option<u32> {
+        Some(self.gpu_index)
+    }
+
+    fn poll(&mut self, sample: &Sample) {
+        match sample.gpu.as_ref().and_then(|g| g.get_gpu(self.gpu_index)) {
+            Some(info) => self.poll_gpu(info, sample.time),
+            None => self.poll_gap(sample.time),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl GpuInterconnectMonitor {
+    pub fn new_for_gpu(retention: &Retention, gpu_index: u32) -> Self {
+        Self {
+            pcie_tx_history: History::new(retention),
+            pcie_rx_history: History::new(retention),
+            nvlink_tx_history: History::new(retention),
+            nvlink_rx_history: History::new(retention),
+            pcie: GpuPcie::default(),
+            nvlinks: Vec::new(),
+            nvlink_tx: None,
+            nvlink_rx: None,
+            gpu_index,
+        }
+    }
+
+    pub fn poll_gpu(&mut self, gpu_info: &GpuInfo, at: Timestamp) {
+        self.pcie = gpu_info.pcie.clone();
+        self.nvlinks = gpu_info.nvlinks.clone();
+        self.nvlink_tx = gpu_info.nvlink_tx();
+        self.nvlink_rx = gpu_info.nvlink_rx();
+
+        if let Some(tx) = self.pcie.tx_throughput {
+            self.pcie_tx_history.push(at, tx);
+        }
+        if let Some(rx) = self.pcie.rx_throughput {
+            self.pcie_rx_history.push(at, rx);
+        }
+        if let Some(tx) = self.nvlink_tx {
+            self.nvlink_tx_history.push(at, tx);
+        }
+        if let Some(rx) = self.nvlink_rx {
+            self.nvlink_rx_history.push(at, rx);
+        }
+    }
+
+    /// No reading at `at` - a gap in the histories.
+    pub fn poll_gap(&mut self, at: Timestamp) {
+        self.pcie_tx_history.push_gap(at);
+        self.pcie_rx_history.push_gap(at);
+        self.nvlink_tx_history.push_gap(at);
+        self.nvlink_rx_history.push_gap(at);
+    }
+
+    /// NVLink errors of all links
+    pub fn nvlink_errors(&self) -> u64 {
+        self.nvlinks.iter().map(GpuNvLink::error_count).sum()
+    }
+}
+
+/// GPU Media Monitor - tracks video encoder, decoder, JPEG and optical flow engines over time
+pub struct GpuMediaMonitor {
+    pub encoder_history: History, // percent
+    pub decoder_history: History,
+    pub jpeg_history: History,
+    pub ofa_history: History,
+    pub utilization: GpuUtilization,
+    pub encoder_stats: Option<GpuEncoderStats>,
+    pub sessions: Vec<GpuEncoderSession>,
+    gpu_index: u32,
+}
+
+impl SysDataStream for GpuMediaMonitor {
+    fn name(&self) -> &'static str {
+        "gpu_media"
+    }
+
+    fn gpu_index(&self) -> Option<u32> {
+        Some(self.gpu_index)
+    }
+
+    fn poll(&mut self, sample: &Sample) {
+        match sample.gpu.as_ref() {
+            Some(readings) => match readings.get_gpu(self.gpu_index) {
+                Some(info) => self.poll_gpu(info, &readings.encoder_sessions, sample.time),
+                None => self.poll_gap(sample.time),
+            },
+            None => self.poll_gap(sample.time),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl GpuMediaMonitor {
+    pub fn new_for_gpu(retention: &Retention, gpu_index: u32) -> Self {
+        Self {
+            encoder_history: History::new(retention),
+            decoder_history: History::new(retention),
+            jpeg_history: History::new(retention),
+            ofa_history: History::new(retention),
+            utilization: GpuUtilization::default(),
+            encoder_stats: None,
+            sessions: Vec::new(),
+            gpu_index,
+        }
+    }
+
+    /// Takes the engines of `gpu_info`, and those of `sessions` running on it
+    pub fn poll_gpu(&mut self, gpu_info: &GpuInfo, sessions: &[GpuEncoderSession], at: Timestamp) {
+        self.utilization = gpu_info.utilization.clone();
+        self.encoder_stats = gpu_info.encoder_stats;
+        self.sessions = sessions
+            .iter()
+            .filter(|s| s.gpu_index == self.gpu_index)
+            .cloned()
+            .collect();
+
+        let engines = [
+            (&mut self.encoder_history, self.utilization.encoder),
+            (&mut self.decoder_history, self.utilization.decoder),
+            (&mut self.jpeg_history, self.utilization.jpeg),
+            (&mut self.ofa_history, self.utilization.ofa),
+        ];
+        for (history, percent) in engines {
+            if let Some(percent) = percent {
+                history.push(at, percent as f64);
+            }
+        }
+    }
+
+    /// No reading at `at` - a gap in the histories.
+    pub fn poll_gap(&mut self, at: Timestamp) {
+        self.encoder_history.push_gap(at);
+        self.decoder_history.push_gap(at);
+        self.jpeg_history.push_gap(at);
+        self.ofa_history.push_gap(at);
+    }
+
+    /// Whether the GPU reports any media engine at all
+    pub fn has_engines(&self) -> bool {
+        let u = &self.utilization;
+        [u.encoder, u.decoder, u.jpeg, u.ofa].iter().any(Option::is_some) || self.encoder_stats.is_some()
+    }
+}
+
+/// Critical hardware trouble spotted during the run
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuAlert {
+    pub at: Timestamp,
+    pub gpu_index: u32,
+    pub message: String,
+}
+
+/// GPU ECC Monitor - tracks memory error counters, retired pages and remapped rows, and raises
+/// an alert whenever they grow
+pub struct GpuEccMonitor {
+    pub ecc: GpuEcc,
+    pub retired_pages: Option<GpuRetiredPages>,
+    pub remapped_rows: Option<GpuRemappedRows>,
+    /// Alerts raised so far, newest last
+    pub alerts: VecDeque<GpuAlert>,
+    /// Whether there was a reading to compare with
+    seen: bool,
+    gpu_index: u32,
+}
+
+impl SysDataStream for GpuEccMonitor {
+    fn name(&self) -> &'static str {
+        "gpu_ecc"
+    }
+
+    fn gpu_index(&self) -> Option<u32> {
+        Some(self.gpu_index)
+    }
+
+    fn poll(&mut self, sample: &Sample) {
+        // a missing reading changes no counter
+        if let Some(info) = sample.gpu.as_ref().and_then(|g| g.get_gpu(self.gpu_index)) {
+            self.poll_gpu(info, sample.time);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl GpuEccMonitor {
+    pub fn new_for_gpu(gpu_index: u32) -> Self {
+        Self {
+            ecc: GpuEcc::default(),
+            retired_pages: None,
+            remapped_rows: None,
+            alerts: VecDeque::new(),
+            seen: false,
+            gpu_index,
+        }
+    }
+
+    pub fn poll_gpu(&mut self, gpu_info: &GpuInfo, at: Timestamp) {
+        if self.seen {
+            for message in self.changes(gpu_info) {
+                log::error!("CRITICAL GPU {}: {}", self.gpu_index, message);
+                self.alerts.push_back(GpuAlert {
+                    at,
+                    gpu_index: self.gpu_index,
+                    message,
+                });
+                if self.alerts.len() > MAX_ECC_ALERTS {
+                    self.alerts.pop_front();
+                }
+            }
+        }
+        self.seen = true;
+        self.ecc = gpu_info.ecc.clone();
+        // backends without them, or the XML report not read yet, are no news
+        if gpu_info.retired_pages.is_some() {
+            self.retired_pages = gpu_info.retired_pages.clone();
+        }
+        if gpu_info.remapped_rows.is_some() {
+            self.remapped_rows = gpu_info.remapped_rows.clone();
+        }
+    }
+
+    /// What got worse since the previous reading
+    fn changes(&self, gpu_info: &GpuInfo) -> Vec<String> {
+        let mut changes = Vec::new();
+        // volatile counters start over when the driver reloads, aggregate ones are only
+        // updated now and then - errors are whichever grew more
+        let grown = |old: u64, new: u64| new.saturating_sub(old);
+        let (old, new) = (&self.ecc, &gpu_info.ecc);
+        let corrected = grown(old.errors_corrected_volatile.total, new.errors_corrected_volatile.total)
+            .max(grown(old.errors_corrected_aggregate.total, new.errors_corrected_aggregate.total));
+        let uncorrected = grown(old.errors_uncorrected_volatile.total, new.errors_uncorrected_volatile.total)
+            .max(grown(old.errors_uncorrected_aggregate.total, new.errors_uncorrected_aggregate.total));
+        if uncorrected > 0 {
+            changes.push(format!("{} new uncorrected ECC error(s)", uncorrected));
+        }
+        if corrected > 0 {
+            changes.push(format!("{} new corrected ECC error(s)", corrected));
+        }
+
+        if let (Some(old), Some(new)) = (&self.retired_pages, &gpu_info.retired_pages) {
+            let retired = grown(old.single_bit, new.single_bit) + grown(old.double_bit, new.double_bit);
+            if retired > 0 {
+                changes.push(format!("{} more memory page(s) retired", retired));
+            }
+            if new.pending && !old.pending {
+                changes.push("page retirement pending - reset the GPU".to_string());
+            }
+        }
+
+        if let (Some(old), Some(new)) = (&self.remapped_rows, &gpu_info.remapped_rows) {
+            let remapped = grown(old.correctable, new.correctable) + grown(old.uncorrectable, new.uncorrectable);
+            if remapped > 0 {
+                changes.push(format!("{} more memory row(s) remapped", remapped));
+            }
+            if new.pending && !old.pending {
+                changes.push("row remapping pending - reset the GPU".to_string());
+            }
+            if new.failure && !old.failure {
+                changes.push("row remapping failed - the GPU needs service".to_string());
+            }
+        }
+        changes
+    }
+}
+
+/// A traced process on one of its GPUs
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessPlacement {
+    pub affinity: ProcessAffinity,
+    pub gpu_index: u32,
+    pub placement: Placement,
+}
+
+/// GPU Topology Monitor - compares where traced processes may run with where their GPUs are
+/// attached, and warns when one is held on the wrong NUMA node
+#[derive(Default)]
+pub struct GpuTopologyMonitor {
+    /// Latest placement of every traced process on every GPU it uses
+    pub placements: Vec<ProcessPlacement>,
+    /// Warnings raised so far, newest last
+    pub warnings: VecDeque<GpuAlert>,
+    /// Process and GPU pairs that are remote right now - warned about once, and again only
+    /// after they were fixed in between
+    remote: BTreeSet<(u32, u32)>,
+}
+
+impl SysDataStream for GpuTopologyMonitor {
+    fn name(&self) -> &'static str {
+        "gpu_topology"
+    }
+
+    fn poll(&mut self, sample: &Sample) {
+        // a missing reading moves no process
+        if let Some(gpu) = &sample.gpu {
+            self.poll_gpu(gpu, &sample.affinity, sample.time);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl GpuTopologyMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn poll_gpu(&mut self, gpu_readings: &GpuReadings, affinity: &[ProcessAffinity], at: Timestamp) {
+        self.placements.clear();
+        let mut remote = BTreeSet::new();
+        for process in affinity {
+            let mut gpus: Vec<u32> = gpu_readings
+                .processes
+                .iter()
+                .filter(|p| p.pid == process.pid)
+                .map(|p| p.gpu_index)
+                .collect();
+            gpus.sort_unstable();
+            gpus.dedup();
+            for gpu in gpus.iter().filter_map(|index| gpu_readings.get_gpu(*index)) {
+                let placement = process.placement(gpu);
+                if placement == Placement::Remote {
+                    remote.insert((process.pid, gpu.index));
+                    if !self.remote.contains(&(process.pid, gpu.index)) {
+                        let message = wrong_node(process, gpu);
+                        log::warn!("{}", message);
+                        self.warnings.push_back(GpuAlert {
+                            at,
+                            gpu_index: gpu.index,
+                            message,
+                        });
+                        if self.warnings.len() > MAX_NUMA_WARNINGS {
+                            self.warnings.pop_front();
+                        }
+                    }
+                }
+                self.placements.push(ProcessPlacement {
+                    affinity: process.clone(),
+                    gpu_index: gpu.index,
+                    placement,
+                });
             }
-            let last_clock = self.memory_clock_history.last().copied().unwrap_or(clock as f32);
-            self.memory_clock_history.extend_from_slice(
-                utils::interpolate(
-                    last_clock,
-                    clock as f32,
-                    self.interpolation_len,
-                )
-                .as_slice(),
-            );
         }
+        self.remote = remote;
     }
 }
 
-/// GPU Process Monitor - tracks GPU processes
+/// Why `process` is in the wrong place for `gpu`
+fn wrong_node(process: &ProcessAffinity, gpu: &GpuInfo) -> String {
+    let gpu_node = gpu.numa_node.map_or("?".to_string(), |n| n.to_string());
+    format!(
+        "PID {} ({}) uses GPU {} on NUMA node {}, but runs on CPUs {} (node {}) with memory on node {}",
+        process.pid,
+        process.name,
+        gpu.index,
+        gpu_node,
+        format_cpu_list(&process.cpus),
+        format_cpu_list(&process.cpu_nodes),
+        format_cpu_list(&process.memory_nodes),
+    )
+}
+
+/// GPU Process Monitor - tracks GPU processes, and the traced ones that exited
 pub struct GpuProcessMonitor {
     pub processes: Vec<crate::trace::datastreams::gpu_data::GpuProcess>,
+    /// Accounted processes of the traced tree that exited during the run, in order
+    pub exited: Vec<GpuAccountedProcess>,
     pub gpu_index: u32,
 }
 
 impl SysDataStream for GpuProcessMonitor {
-    fn new(_max_hist_len: usize, _inter_len: u16) -> Self {
-        Self {
-            processes: Vec::new(),
-            gpu_index: 0,
+    fn name(&self) -> &'static str {
+        "gpu_processes"
+    }
+
+    fn gpu_index(&self) -> Option<u32> {
+        Some(self.gpu_index)
+    }
+
+    fn poll(&mut self, sample: &Sample) {
+        if let Some(gpu) = &sample.gpu {
+            self.poll_gpu(gpu);
         }
+        self.exited
+            .extend(sample.accounted.iter().filter(|p| p.gpu_index == self.gpu_index).cloned());
     }
 
-    fn poll(&mut self, _system_info: &crate::trace::datastreams::data_stream::Readings) {
-        // This will be updated to work with GPU data
+    fn as_any(&self) -> &dyn Any {
+        self
     }
 }
 
@@ -410,6 +800,7 @@ impl GpuProcessMonitor {
     pub fn new_for_gpu(gpu_index: u32) -> Self {
         Self {
             processes: Vec::new(),
+            exited: Vec::new(),
             gpu_index,
         }
     }
@@ -424,3 +815,83 @@ impl GpuProcessMonitor {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::datastreams::gpu_data::{GpuEccErrors, GpuProcess};
+    use chrono::Local;
+
+    fn with_errors(corrected_volatile: u64, corrected_aggregate: u64, uncorrected: u64) -> GpuInfo {
+        let errors = |total| GpuEccErrors { total, ..Default::default() };
+        let mut info = GpuInfo::default();
+        info.ecc.errors_corrected_volatile = errors(corrected_volatile);
+        info.ecc.errors_corrected_aggregate = errors(corrected_aggregate);
+        info.ecc.errors_uncorrected_volatile = errors(uncorrected);
+        info.ecc.errors_uncorrected_aggregate = errors(uncorrected);
+        info
+    }
+
+    #[test]
+    fn test_numa_warnings_once_per_misplacement() {
+        let mut readings = GpuReadings::new();
+        readings.gpus.push(GpuInfo {
+            numa_node: Some(0),
+            cpu_affinity: (0..8).collect(),
+            ..GpuInfo::default()
+        });
+        readings.processes.push(GpuProcess { pid: 42, gpu_index: 0, ..GpuProcess::default() });
+        let on = |cpus: std::ops::Range<u32>, node| ProcessAffinity {
+            pid: 42,
+            name: "python3".to_string(),
+            cpus: cpus.collect(),
+            cpu_nodes: vec![node],
+            memory_nodes: vec![0, 1],
+        };
+
+        let mut monitor = GpuTopologyMonitor::new();
+        monitor.poll_gpu(&readings, &[on(0..4, 0)], Local::now());
+        assert_eq!(monitor.placements[0].placement, Placement::Local);
+        assert!(monitor.warnings.is_empty());
+
+        // pinned to the other socket
+        monitor.poll_gpu(&readings, &[on(8..12, 1)], Local::now());
+        monitor.poll_gpu(&readings, &[on(8..12, 1)], Local::now());
+        assert_eq!(monitor.placements[0].placement, Placement::Remote);
+        assert_eq!(monitor.warnings.len(), 1);
+        assert_eq!(
+            monitor.warnings[0].message,
+            "PID 42 (python3) uses GPU 0 on NUMA node 0, but runs on CPUs 8-11 (node 1) with memory on node 0-1"
+        );
+
+        // fixed, then wrong again
+        monitor.poll_gpu(&readings, &[on(0..4, 0)], Local::now());
+        monitor.poll_gpu(&readings, &[on(8..12, 1)], Local::now());
+        assert_eq!(monitor.warnings.len(), 2);
+    }
+
+    #[test]
+    fn test_ecc_alerts_on_new_errors_only() {
+        let mut monitor = GpuEccMonitor::new_for_gpu(0);
+        // errors from before the trace are old news
+        monitor.poll_gpu(&with_errors(5, 20, 1), Local::now());
+        assert!(monitor.alerts.is_empty());
+
+        monitor.poll_gpu(&with_errors(7, 20, 2), Local::now());
+        let messages: Vec<_> = monitor.alerts.iter().map(|a| a.message.as_str()).collect();
+        assert_eq!(messages, ["1 new uncorrected ECC error(s)", "2 new corrected ECC error(s)"]);
+
+        // a driver reload zeroes the volatile counters, nothing broke
+        monitor.poll_gpu(&with_errors(0, 20, 2), Local::now());
+        assert_eq!(monitor.alerts.len(), 2);
+
+        let mut info = with_errors(0, 20, 2);
+        info.remapped_rows = Some(GpuRemappedRows { uncorrectable: 1, pending: true, ..Default::default() });
+        // the first report of remapped rows is no news either
+        monitor.poll_gpu(&info, Local::now());
+        assert_eq!(monitor.alerts.len(), 2);
+        info.remapped_rows = Some(GpuRemappedRows { uncorrectable: 1, failure: true, ..Default::default() });
+        monitor.poll_gpu(&info, Local::now());
+        assert_eq!(monitor.alerts.back().unwrap().message, "row remapping failed - the GPU needs service");
+    }
+}
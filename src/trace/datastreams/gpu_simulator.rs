@@ -0,0 +1,439 @@
+use crate::error::GpuError;
+use crate::trace::datastreams::gpu_backend::GpuBackend;
+use crate::trace::datastreams::gpu_data::{
+    GpuClocks, GpuEcc, GpuEccErrors, GpuEncoderStats, GpuInfo, GpuMemory, GpuNvLink, GpuPcie,
+    GpuPeer, GpuPower, GpuProcess, GpuReadings, GpuRemappedRows, GpuTemperature, GpuUtilization,
+    ThrottleReasons,
+};
+use crate::trace::datastreams::gpu_health::GpuHealth;
+use std::time::Duration;
+
+/// Seed used when none is given
+pub const DEFAULT_SIMULATOR_SEED: u64 = 42;
+
+const MEMORY_TOTAL_MB: u64 = 40960;
+const POWER_IDLE_W: f64 = 55.0;
+const POWER_LIMIT_W: f64 = 400.0;
+const AMBIENT_C: f64 = 30.0;
+/// Temperature reached per watt drawn, above ambient
+const HEATING_C_PER_W: f64 = 0.14;
+/// How fast temperature follows power, seconds
+const THERMAL_TAU_S: f64 = 30.0;
+const THROTTLE_ON_C: f64 = 80.0;
+const THROTTLE_OFF_C: f64 = 75.0;
+const CLOCK_MAX_MHZ: u32 = 1410;
+const CLOCK_THROTTLED_MHZ: u32 = 1050;
+const CLOCK_IDLE_MHZ: u32 = 210;
+const CLOCK_MEMORY_MHZ: u32 = 1593;
+const MAX_PROCESSES: usize = 3;
+/// PCIe traffic at full utilization, MB/s - input batches in, a little out
+const PCIE_RX_MBPS: f64 = 6000.0;
+const PCIE_TX_MBPS: f64 = 800.0;
+/// NVLink links between the GPUs when there are several, and their traffic at full utilization
+const NVLINKS: u32 = 4;
+const NVLINK_MBPS: f64 = 12000.0;
+/// Chance of a corrected ECC error per busy second, a hundred times less for an uncorrected one
+const ECC_ERROR_CHANCE: f64 = 0.001;
+const PROCESS_NAMES: [&str; 4] = ["python train.py", "python eval.py", "torchrun", "jupyter-kernel"];
+
+/// Fake GPUs for demos and development on machines without any.
+/// Every call is one step of `step` simulated time, and everything random comes from the seed -
+/// same seed, same run. Jobs come and go in bursts, memory ramps up with them, temperature follows
+/// power and once it gets too hot clocks (and power) go down until it cools off.
+pub struct GpuSimulator {
+    gpus: Vec<SimulatedGpu>,
+    rng: SplitMix64,
+    step: f64,
+    next_pid: u32,
+    health: GpuHealth,
+}
+
+/// State of one simulated GPU
+struct SimulatedGpu {
+    index: u32,
+    busy: bool,
+    phase_left: f64,
+    utilization: f64,
+    memory_used: f64,
+    memory_target: f64,
+    power: f64,
+    temperature: f64,
+    throttling: bool,
+    ecc_corrected: u64,
+    ecc_uncorrected: u64,
+    processes: Vec<SimulatedProcess>,
+}
+
+struct SimulatedProcess {
+    pid: u32,
+    name: &'static str,
+    share: f64,
+    left: f64,
+}
+
+impl GpuSimulator {
+    /// Simulates `count` GPUs, read every `step`.
+    pub fn new(count: u32, seed: u64, step: Duration) -> Self {
+        let mut rng = SplitMix64::new(seed);
+        let gpus = (0..count)
+            .map(|index| SimulatedGpu {
+                index,
+                busy: false,
+                // start phases at different times, so GPUs don't march in lockstep
+                phase_left: rng.range(0.0, 20.0),
+                utilization: 0.0,
+                memory_used: 512.0,
+                memory_target: 512.0,
+                power: POWER_IDLE_W,
+                temperature: AMBIENT_C + HEATING_C_PER_W * POWER_IDLE_W,
+                throttling: false,
+                ecc_corrected: 0,
+                ecc_uncorrected: 0,
+                processes: Vec::new(),
+            })
+            .collect();
+        Self {
+            gpus,
+            rng,
+            step: step.as_secs_f64().max(0.001),
+            next_pid: 100_000,
+            health: GpuHealth::Healthy,
+        }
+    }
+
+    /// Advances the simulation by one step
+    fn advance(&mut self) {
+        let dt = self.step;
+        for gpu in &mut self.gpus {
+            // Bursts: busy for a while, then idle for a while
+            gpu.phase_left -= dt;
+            if gpu.phase_left <= 0.0 {
+                gpu.busy = !gpu.busy;
+                gpu.phase_left = if gpu.busy {
+                    self.rng.range(20.0, 120.0)
+                } else {
+                    self.rng.range(5.0, 30.0)
+                };
+                if gpu.busy {
+                    gpu.memory_target = MEMORY_TOTAL_MB as f64 * self.rng.range(0.6, 0.9);
+                } else if self.rng.chance(0.3) {
+                    // job restarted - memory goes back
+                    gpu.memory_target = 512.0;
+                }
+            }
+
+            gpu.utilization = if gpu.busy {
+                self.rng.range(85.0, 100.0)
+            } else {
+                self.rng.range(0.0, 8.0)
+            };
+
+            // Memory ramps (allocator warm-up), it does not jump
+            let ramp = 2048.0 * dt;
+            gpu.memory_used += (gpu.memory_target - gpu.memory_used).clamp(-4.0 * ramp, ramp);
+
+            // Power follows utilization, capped while throttling
+            let limit = if gpu.throttling { 0.75 * POWER_LIMIT_W } else { POWER_LIMIT_W };
+            gpu.power = (POWER_IDLE_W
+                + gpu.utilization / 100.0 * (POWER_LIMIT_W - POWER_IDLE_W)
+                + self.rng.range(-10.0, 10.0))
+            .clamp(POWER_IDLE_W * 0.8, limit);
+
+            // Temperature follows power with some lag
+            let target = AMBIENT_C + HEATING_C_PER_W * gpu.power;
+            gpu.temperature += (target - gpu.temperature) * (dt / THERMAL_TAU_S).min(1.0);
+            if gpu.temperature >= THROTTLE_ON_C {
+                gpu.throttling = true;
+            } else if gpu.temperature <= THROTTLE_OFF_C {
+                gpu.throttling = false;
+            }
+
+            // Once in a long while memory flips a bit, even more rarely two
+            if gpu.busy && self.rng.chance((ECC_ERROR_CHANCE * dt).min(1.0)) {
+                gpu.ecc_corrected += 1;
+                if self.rng.chance(0.01) {
+                    gpu.ecc_uncorrected += 1;
+                }
+            }
+
+            // Processes come with work and leave after a while
+            for p in &mut gpu.processes {
+                p.left -= dt;
+            }
+            gpu.processes.retain(|p| p.left > 0.0);
+            if gpu.busy
+                && gpu.processes.len() < MAX_PROCESSES
+                && self.rng.chance((0.05 * dt).min(1.0))
+            {
+                gpu.processes.push(SimulatedProcess {
+                    pid: self.next_pid,
+                    name: PROCESS_NAMES[self.rng.below(PROCESS_NAMES.len())],
+                    share: self.rng.range(0.2, 1.0),
+                    left: self.rng.range(30.0, 300.0),
+                });
+                self.next_pid += 1;
+            }
+        }
+    }
+
+    fn readings(&self) -> GpuReadings {
+        let mut readings = GpuReadings::new();
+        readings.gpu_count = self.gpus.len() as u32;
+        for gpu in &self.gpus {
+            let mut info = gpu.info(&readings.timestamp);
+            if self.gpus.len() > 1 {
+                // gradients go around as much as they come in
+                let load = gpu.utilization / 100.0;
+                info.nvlinks = (0..NVLINKS)
+                    .map(|link| GpuNvLink {
+                        link,
+                        tx_throughput: Some(load * NVLINK_MBPS),
+                        rx_throughput: Some(load * NVLINK_MBPS),
+                        errors: [("Replay Errors", 0), ("Recovery Errors", 0), ("CRC Errors", 0)]
+                            .into_iter()
+                            .map(|(name, count)| (name.to_string(), count))
+                            .collect(),
+                    })
+                    .collect();
+                info.peers = self
+                    .gpus
+                    .iter()
+                    .filter(|peer| peer.index != gpu.index)
+                    .map(|peer| GpuPeer {
+                        gpu_index: peer.index,
+                        connection: format!("NV{}", NVLINKS),
+                    })
+                    .collect();
+            }
+            // all on one node, close to every CPU there is
+            info.numa_node = Some(0);
+            let cpus = std::thread::available_parallelism().map_or(1, |n| n.get() as u32);
+            info.cpu_affinity = (0..cpus).collect();
+            readings.gpus.push(info);
+            let shares: f64 = gpu.processes.iter().map(|p| p.share).sum();
+            readings.processes.extend(gpu.processes.iter().map(|p| GpuProcess {
+                pid: p.pid,
+                process_name: p.name.to_string(),
+                gpu_uuid: gpu.uuid(),
+                used_memory: (gpu.memory_used * p.share / shares) as u64,
+                gpu_index: gpu.index,
+                utilization: None,
+                gpu_instance_id: None,
+                compute_instance_id: None,
+                encoder_utilization: None,
+                decoder_utilization: None,
+            }));
+        }
+        readings
+    }
+}
+
+impl GpuBackend for GpuSimulator {
+    fn name(&self) -> &'static str {
+        "simulator"
+    }
+
+    fn get_gpu_info(&mut self) -> Result<GpuReadings, GpuError> {
+        self.advance();
+        Ok(self.readings())
+    }
+
+    fn health(&self) -> &GpuHealth {
+        &self.health
+    }
+}
+
+impl SimulatedGpu {
+    fn uuid(&self) -> String {
+        format!("GPU-5157a7ed-0000-4000-8000-{:012x}", self.index)
+    }
+
+    fn info(&self, timestamp: &str) -> GpuInfo {
+        let used = self.memory_used as u64;
+        let clock = match (self.busy, self.throttling) {
+            (false, _) => CLOCK_IDLE_MHZ,
+            (true, false) => CLOCK_MAX_MHZ,
+            (true, true) => CLOCK_THROTTLED_MHZ,
+        };
+        // too hot also means a lower power cap, see `advance`
+        let throttle_reasons = match (self.busy, self.throttling) {
+            (false, _) => ThrottleReasons::GPU_IDLE,
+            (true, false) => 0,
+            (true, true) => ThrottleReasons::SW_THERMAL_SLOWDOWN | ThrottleReasons::SW_POWER_CAP,
+        };
+        GpuInfo {
+            index: self.index,
+            name: "NVIDIA A100-SXM4-40GB (simulated)".to_string(),
+            uuid: self.uuid(),
+            pci_bus_id: format!("00000000:{:02X}:00.0", 0x10 + self.index),
+            driver_version: "simulated".to_string(),
+            vbios_version: "simulated".to_string(),
+            compute_cap: "8.0".to_string(),
+            pstate: if self.busy { "P0" } else { "P8" }.to_string(),
+            memory: GpuMemory {
+                total: MEMORY_TOTAL_MB,
+                used,
+                free: MEMORY_TOTAL_MB.saturating_sub(used),
+                ..Default::default()
+            },
+            utilization: GpuUtilization {
+                gpu: self.utilization as u32,
+                memory: (self.utilization * 0.6) as u32,
+                // the data loader decodes video and images on the GPU, nothing encodes
+                encoder: Some(0),
+                decoder: Some((self.utilization * 0.25) as u32),
+                jpeg: Some((self.utilization * 0.15) as u32),
+                ofa: Some(0),
+                ..Default::default()
+            },
+            temperature: GpuTemperature {
+                gpu: Some(self.temperature as i32),
+                gpu_tlimit: Some((THROTTLE_ON_C - self.temperature) as i32),
+                memory: Some((self.temperature + 5.0) as i32),
+            },
+            power: GpuPower {
+                draw: Some(self.power as f32),
+                limit: Some(POWER_LIMIT_W as f32),
+                enforced_limit: Some(POWER_LIMIT_W as f32),
+                default_limit: Some(POWER_LIMIT_W as f32),
+                management: "Enabled".to_string(),
+                ..Default::default()
+            },
+            clocks: GpuClocks {
+                graphics: Some(clock),
+                sm: Some(clock),
+                memory: Some(CLOCK_MEMORY_MHZ),
+                max_graphics: Some(CLOCK_MAX_MHZ),
+                max_sm: Some(CLOCK_MAX_MHZ),
+                max_memory: Some(CLOCK_MEMORY_MHZ),
+                ..Default::default()
+            },
+            ecc: GpuEcc {
+                mode_current: "Enabled".to_string(),
+                mode_pending: "Enabled".to_string(),
+                errors_corrected_volatile: ecc_errors(self.ecc_corrected),
+                errors_uncorrected_volatile: ecc_errors(self.ecc_uncorrected),
+                errors_corrected_aggregate: ecc_errors(self.ecc_corrected),
+                errors_uncorrected_aggregate: ecc_errors(self.ecc_uncorrected),
+            },
+            // every uncorrected error costs a row
+            remapped_rows: Some(GpuRemappedRows {
+                uncorrectable: self.ecc_uncorrected,
+                pending: self.ecc_uncorrected > 0,
+                ..Default::default()
+            }),
+            pcie: GpuPcie {
+                gen_current: Some(4),
+                gen_max: Some(4),
+                width_current: Some(16),
+                width_max: Some(16),
+                tx_throughput: Some(self.utilization / 100.0 * PCIE_TX_MBPS),
+                rx_throughput: Some(self.utilization / 100.0 * PCIE_RX_MBPS),
+                ..Default::default()
+            },
+            fan_speed: Some((30.0 + (self.temperature - AMBIENT_C) * 1.5).clamp(30.0, 100.0) as u32),
+            display_mode: "Disabled".to_string(),
+            persistence_mode: "Enabled".to_string(),
+            compute_mode: "Default".to_string(),
+            throttle_reasons: Some(ThrottleReasons(throttle_reasons)),
+            encoder_stats: Some(GpuEncoderStats::default()),
+            timestamp: timestamp.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Small, fast and - most importantly - reproducible random numbers
+struct SplitMix64(u64);
+
+/// Errors all in device memory
+fn ecc_errors(count: u64) -> GpuEccErrors {
+    GpuEccErrors {
+        device_memory: count,
+        dram: count,
+        total: count,
+        ..Default::default()
+    }
+}
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..1`
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn range(&mut self, lo: f64, hi: f64) -> f64 {
+        lo + (hi - lo) * self.next_f64()
+    }
+
+    fn chance(&mut self, p: f64) -> bool {
+        self.next_f64() < p
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn run(seed: u64, steps: usize) -> Vec<GpuReadings> {
+        let mut sim = GpuSimulator::new(2, seed, Duration::from_secs(1));
+        (0..steps).map(|_| sim.get_gpu_info().unwrap()).collect()
+    }
+
+    fn fingerprint(readings: &[GpuReadings]) -> Vec<(u64, u32, Option<i32>, usize)> {
+        readings
+            .iter()
+            .flat_map(|r| {
+                r.gpus.iter().map(|g| {
+                    (g.memory.used, g.utilization.gpu, g.temperature.gpu, r.processes.len())
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_same_seed_same_run() {
+        assert_eq!(fingerprint(&run(7, 300)), fingerprint(&run(7, 300)));
+        assert_ne!(fingerprint(&run(7, 300)), fingerprint(&run(8, 300)));
+    }
+
+    #[test]
+    fn test_looks_like_a_gpu() {
+        let readings = run(DEFAULT_SIMULATOR_SEED, 3600);
+        let gpus: Vec<&GpuInfo> = readings.iter().flat_map(|r| &r.gpus).collect();
+        assert!(gpus.iter().all(|g| g.memory.used <= g.memory.total));
+        assert!(gpus.iter().all(|g| g.power.draw.unwrap() <= POWER_LIMIT_W as f32));
+        // bursts
+        assert!(gpus.iter().any(|g| g.utilization.gpu > 90));
+        assert!(gpus.iter().any(|g| g.utilization.gpu < 10));
+        // it gets hot and throttles
+        assert!(gpus.iter().any(|g| g.clocks.graphics == Some(CLOCK_THROTTLED_MHZ)));
+        assert!(gpus
+            .iter()
+            .filter(|g| g.clocks.graphics == Some(CLOCK_THROTTLED_MHZ))
+            .all(|g| g.throttle_reasons.unwrap().contains(ThrottleReasons::SW_THERMAL_SLOWDOWN)));
+        // and memory has a bad day now and then
+        assert!(gpus.iter().any(|g| g.ecc.errors_corrected_volatile.total > 0));
+        // processes come and go
+        let pids: BTreeSet<u32> = readings.iter().flat_map(|r| &r.processes).map(|p| p.pid).collect();
+        assert!(pids.len() > MAX_PROCESSES * 2);
+        assert!(readings.iter().any(|r| r.processes.is_empty()));
+    }
+}
@@ -0,0 +1,485 @@
+use crate::error::GpuError;
+use crate::trace::datastreams::gpu_backend::GpuBackend;
+use crate::trace::datastreams::gpu_data::{
+    GpuClocks, GpuInfo, GpuPower, GpuProcess, GpuReadings, GpuUtilization,
+};
+use crate::trace::datastreams::gpu_health::{GpuBackoff, GpuHealth};
+use crate::trace::datastreams::tool_runner::ToolRunner;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Places intel_gpu_top is looked for, first found wins
+const INTEL_GPU_TOP_PATHS: [&str; 3] = [
+    "intel_gpu_top",
+    "/usr/bin/intel_gpu_top",
+    "/usr/local/bin/intel_gpu_top",
+];
+
+/// Engine classes counted as "the GPU" - the busiest of them is the GPU utilization
+const COMPUTE_ENGINES: [&str; 2] = ["Render/3D", "Compute"];
+
+/// Last lines of stderr kept to say why intel_gpu_top exited
+const STDERR_TAIL_LINES: usize = 20;
+
+/// Running `intel_gpu_top -J`, with its samples coming in on a reader thread
+struct Stream {
+    child: Child,
+    samples: Receiver<Result<GpuReadings, GpuError>>,
+    /// Drains stderr, so a chatty intel_gpu_top never blocks on a full pipe; ends with its last lines
+    stderr: Option<JoinHandle<VecDeque<String>>>,
+    /// When the last sample came in
+    last_sample: Instant,
+}
+
+impl Stream {
+    fn spawn(program: &str, period: Duration) -> Result<Self, GpuError> {
+        let mut child = Command::new(program)
+            .args(["-J", "-s", &period.as_millis().to_string()])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => GpuError::NotInstalled(program.to_string()),
+                _ => GpuError::Io(format!("Failed to execute {}: {}", program, e)),
+            })?;
+
+        let stderr = child.stderr.take().map(|pipe| {
+            thread::spawn(move || {
+                let mut tail = VecDeque::with_capacity(STDERR_TAIL_LINES);
+                for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+                    if tail.len() == STDERR_TAIL_LINES {
+                        tail.pop_front();
+                    }
+                    tail.push_back(line);
+                }
+                tail
+            })
+        });
+        let stdout = child.stdout.take();
+        let (tx, samples) = mpsc::channel();
+        thread::spawn(move || {
+            let Some(stdout) = stdout else { return };
+            let mut objects = JsonObjects::default();
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                for object in objects.feed(&line) {
+                    if tx.send(parse_sample(&object)).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            samples,
+            stderr,
+            last_sample: Instant::now(),
+        })
+    }
+
+    /// Newest sample since the last call, `None` when none came in.
+    /// Fails when the child is gone.
+    fn newest(&mut self, program: &str) -> Result<Option<GpuReadings>, GpuError> {
+        let mut newest = None;
+        loop {
+            match self.samples.try_recv() {
+                Ok(sample) => newest = Some(sample?),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Err(self.exited(program)),
+            }
+        }
+        if newest.is_some() {
+            self.last_sample = Instant::now();
+        }
+        Ok(newest)
+    }
+
+    /// Why the child stopped sending samples
+    fn exited(&mut self, program: &str) -> GpuError {
+        let status = self.child.wait();
+        let stderr = self
+            .stderr
+            .take()
+            .and_then(|reader| reader.join().ok())
+            .map_or(String::new(), |tail| Vec::from(tail).join("\n"));
+        match status {
+            Ok(status) => GpuError::ExitStatus {
+                command: program.to_string(),
+                code: status.code(),
+                stderr: stderr.trim().to_string(),
+            },
+            Err(e) => GpuError::Io(format!("Failed to wait for {}: {}", program, e)),
+        }
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// intel_gpu_top integration for Intel GPUs (Arc, Flex, integrated). Unlike the `-smi` tools it keeps
+/// running and prints a sample every period; the newest one is taken on each reading.
+/// It watches a single GPU, which becomes GPU 0.
+pub struct IntelGpuTopMonitor {
+    /// Path to intel_gpu_top
+    program: String,
+    /// Sampling period of intel_gpu_top - the refresh of the tracer
+    period: Duration,
+    /// How long past its period a sample may be late before intel_gpu_top is restarted
+    timeout: Duration,
+    stream: Option<Stream>,
+    /// Last sample, handed out again when asked faster than intel_gpu_top samples
+    latest: Option<GpuReadings>,
+    available: bool,
+    last_error: Option<GpuError>,
+    backoff: GpuBackoff,
+}
+
+impl IntelGpuTopMonitor {
+    /// Starts intel_gpu_top sampling every `period` and waits for its first sample
+    pub fn new(program: &str, period: Duration, timeout: Duration) -> Self {
+        let mut monitor = Self {
+            program: program.to_string(),
+            period,
+            timeout,
+            stream: None,
+            latest: None,
+            available: true,
+            last_error: None,
+            backoff: GpuBackoff::new(),
+        };
+        let deadline = Instant::now() + period + timeout;
+        let first = loop {
+            match monitor.next_sample() {
+                Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(10)),
+                Ok(None) => break Err(monitor.timed_out()),
+                Ok(Some(readings)) => break Ok(readings),
+                Err(e) => break Err(e),
+            }
+        };
+        match first {
+            Ok(readings) => monitor.latest = Some(readings),
+            Err(e) => {
+                monitor.stream = None;
+                monitor.available = false;
+                monitor.last_error = Some(e);
+            }
+        }
+        monitor
+    }
+
+    /// Find intel_gpu_top executable path, falling back to `PATH`
+    pub fn find_path() -> String {
+        ToolRunner::find(&INTEL_GPU_TOP_PATHS)
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.available
+    }
+
+    pub fn get_last_error(&self) -> Option<&GpuError> {
+        self.last_error.as_ref()
+    }
+
+    /// Newest sample of intel_gpu_top. It is restarted (after a backoff) when it exits or stalls.
+    pub fn get_gpu_info(&mut self) -> Result<GpuReadings, GpuError> {
+        let now = Instant::now();
+        self.backoff.check(now)?;
+        let result = if self.available {
+            self.read()
+        } else {
+            Err(self
+                .last_error
+                .clone()
+                .unwrap_or_else(|| GpuError::NotInstalled(self.program.clone())))
+        };
+        let result = self.backoff.record(now, result);
+        self.last_error = result.as_ref().err().cloned();
+        result
+    }
+
+    fn read(&mut self) -> Result<GpuReadings, GpuError> {
+        match self.next_sample() {
+            Ok(Some(readings)) => {
+                self.latest = Some(readings.clone());
+                Ok(readings)
+            }
+            Ok(None) if self.stalled() => {
+                let e = self.timed_out();
+                self.stream = None;
+                Err(e)
+            }
+            Ok(None) => self.latest.clone().ok_or_else(|| self.timed_out()),
+            Err(e) => {
+                self.stream = None;
+                Err(e)
+            }
+        }
+    }
+
+    /// Newest sample, starting intel_gpu_top when it is not running
+    fn next_sample(&mut self) -> Result<Option<GpuReadings>, GpuError> {
+        if self.stream.is_none() {
+            self.stream = Some(Stream::spawn(&self.program, self.period)?);
+        }
+        let program = self.program.clone();
+        self.stream.as_mut().map_or(Ok(None), |s| s.newest(&program))
+    }
+
+    fn stalled(&self) -> bool {
+        self.stream
+            .as_ref()
+            .is_some_and(|s| s.last_sample.elapsed() > self.period + self.timeout)
+    }
+
+    fn timed_out(&self) -> GpuError {
+        GpuError::Timeout {
+            command: self.program.clone(),
+            timeout: self.period + self.timeout,
+        }
+    }
+}
+
+impl GpuBackend for IntelGpuTopMonitor {
+    fn name(&self) -> &'static str {
+        "intel_gpu_top"
+    }
+
+    fn get_gpu_info(&mut self) -> Result<GpuReadings, GpuError> {
+        IntelGpuTopMonitor::get_gpu_info(self)
+    }
+
+    fn health(&self) -> &GpuHealth {
+        self.backoff.health()
+    }
+}
+
+/// Splits the output of intel_gpu_top - one JSON array that never ends, `[ {..}, {..}, ...` -
+/// into its objects as they are completed.
+#[derive(Default)]
+struct JsonObjects {
+    current: String,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl JsonObjects {
+    fn feed(&mut self, line: &str) -> Vec<String> {
+        let mut objects = Vec::new();
+        for c in line.chars().chain(std::iter::once('\n')) {
+            if self.depth == 0 && c != '{' {
+                continue; // `[`, `,` and whitespace between objects
+            }
+            self.current.push(c);
+            if self.in_string {
+                match c {
+                    _ if self.escaped => self.escaped = false,
+                    '\\' => self.escaped = true,
+                    '"' => self.in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match c {
+                '"' => self.in_string = true,
+                '{' => self.depth += 1,
+                '}' => {
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        objects.push(std::mem::take(&mut self.current));
+                    }
+                }
+                _ => {}
+            }
+        }
+        objects
+    }
+}
+
+fn parse_sample(object: &str) -> Result<GpuReadings, GpuError> {
+    let json: Value = serde_json::from_str(object).map_err(|e| GpuError::Parse {
+        field: "intel_gpu_top JSON".to_string(),
+        value: e.to_string(),
+    })?;
+
+    // "Render/3D/0" in older versions, "Render/3D" in newer ones
+    let mut engines = std::collections::BTreeMap::new();
+    for (name, engine) in json["engines"].as_object().into_iter().flatten() {
+        let class = engine_class(name);
+        let busy = number(&engine["busy"], &format!("engines {} busy", name))?.unwrap_or(0.0);
+        let busiest = engines.entry(class.to_string()).or_insert(0);
+        *busiest = (busy.round() as u32).max(*busiest);
+    }
+    let gpu = COMPUTE_ENGINES
+        .iter()
+        .filter_map(|e| engines.get(*e))
+        .max()
+        .copied()
+        .unwrap_or(0);
+
+    let mut processes = Vec::new();
+    for (id, client) in json["clients"].as_object().into_iter().flatten() {
+        let pid = client["pid"]
+            .as_str()
+            .and_then(|p| p.parse().ok())
+            .or_else(|| client["pid"].as_u64().map(|p| p as u32));
+        let Some(pid) = pid else {
+            return Err(GpuError::Parse {
+                field: format!("clients {} pid", id),
+                value: client["pid"].to_string(),
+            });
+        };
+        let mut busy = 0.0f64;
+        for (class, usage) in client["engine-classes"].as_object().into_iter().flatten() {
+            if COMPUTE_ENGINES.contains(&class.as_str()) {
+                let field = format!("clients {} {} busy", id, class);
+                busy = busy.max(number(&usage["busy"], &field)?.unwrap_or(0.0));
+            }
+        }
+        processes.push(GpuProcess {
+            pid,
+            process_name: client["name"].as_str().unwrap_or_default().to_string(),
+            utilization: Some(busy.round() as u32),
+            ..GpuProcess::default()
+        });
+    }
+
+    let mut readings = GpuReadings::new();
+    readings.gpus.push(GpuInfo {
+        index: 0,
+        name: "Intel GPU".to_string(),
+        utilization: GpuUtilization {
+            gpu,
+            engines,
+            ..GpuUtilization::default()
+        },
+        power: GpuPower {
+            draw: number(&json["power"]["GPU"], "power GPU")?.map(|w| w as f32),
+            ..GpuPower::default()
+        },
+        clocks: GpuClocks {
+            graphics: number(&json["frequency"]["actual"], "frequency actual")?.map(|f| f.round() as u32),
+            ..GpuClocks::default()
+        },
+        timestamp: readings.timestamp.clone(),
+        ..GpuInfo::default()
+    });
+    readings.gpu_count = 1;
+    readings.processes = processes;
+    Ok(readings)
+}
+
+/// Engine class of an engine name, without its instance number
+fn engine_class(name: &str) -> &str {
+    match name.rsplit_once('/') {
+        Some((class, instance)) if instance.parse::<u32>().is_ok() => class,
+        _ => name,
+    }
+}
+
+/// Number that intel_gpu_top prints as a number or, for clients, as a string. Missing is `None`.
+fn number(value: &Value, field: &str) -> Result<Option<f64>, GpuError> {
+    match value {
+        Value::Null => Ok(None),
+        Value::Number(n) => Ok(n.as_f64()),
+        Value::String(s) => s.trim().parse().map(Some).map_err(|_| GpuError::Parse {
+            field: field.to_string(),
+            value: s.clone(),
+        }),
+        other => Err(GpuError::Parse {
+            field: field.to_string(),
+            value: other.to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARC: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/intel_gpu_top/arc.json"));
+
+    #[test]
+    fn test_parse_recorded_stream() {
+        let mut objects = JsonObjects::default();
+        let samples: Vec<String> = ARC.lines().flat_map(|l| objects.feed(l)).collect();
+        assert_eq!(samples.len(), 2);
+
+        let readings = parse_sample(&samples[1]).unwrap();
+        let gpu = &readings.gpus[0];
+        assert_eq!(gpu.utilization.gpu, 88);
+        assert_eq!(gpu.utilization.engines.get("Video"), Some(&12));
+        assert_eq!(gpu.utilization.engines.get("VideoEnhance"), Some(&0));
+        assert_eq!(gpu.utilization.engines.get("Blitter"), Some(&3));
+        assert_eq!(gpu.clocks.graphics, Some(2050));
+        assert_eq!(gpu.power.draw, Some(142.5));
+        assert_eq!(readings.processes.len(), 1);
+        assert_eq!(readings.processes[0].pid, 4242);
+        assert_eq!(readings.processes[0].process_name, "python3");
+        assert_eq!(readings.processes[0].utilization, Some(86));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fake_intel_gpu_top() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = dir.path().join("arc.json");
+        std::fs::write(&fixture, ARC).unwrap();
+        let path = dir.path().join("intel_gpu_top");
+        std::fs::write(&path, format!("#!/bin/sh\ncat '{}'\nexec sleep 10\n", fixture.display())).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let period = Duration::from_millis(100);
+        let mut monitor = IntelGpuTopMonitor::new(&path.to_string_lossy(), period, Duration::from_secs(5));
+        assert!(monitor.is_available(), "{:?}", monitor.get_last_error());
+        // nothing new yet - the last sample again
+        assert_eq!(monitor.get_gpu_info().unwrap().gpus[0].utilization.gpu, 88);
+
+        let failing = dir.path().join("failing");
+        std::fs::write(&failing, "#!/bin/sh\necho 'No device filter specified and no discrete/integrated i915 devices found' >&2\nexit 1\n").unwrap();
+        std::fs::set_permissions(&failing, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let monitor = IntelGpuTopMonitor::new(&failing.to_string_lossy(), period, Duration::from_secs(5));
+        assert!(!monitor.is_available());
+        match monitor.get_last_error() {
+            Some(GpuError::ExitStatus { code: Some(1), stderr, .. }) => assert!(stderr.contains("no discrete")),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_chatty_stderr_does_not_stall() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = dir.path().join("arc.json");
+        std::fs::write(&fixture, ARC).unwrap();
+        let path = dir.path().join("intel_gpu_top");
+        // far more than a pipe buffer of warnings before the first sample
+        let script = format!(
+            "#!/bin/sh\nhead -c 1000000 /dev/zero | tr '\\0' 'w' | fold -w 100 >&2\ncat '{}'\nexec sleep 10\n",
+            fixture.display()
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let period = Duration::from_millis(100);
+        let mut monitor = IntelGpuTopMonitor::new(&path.to_string_lossy(), period, Duration::from_secs(5));
+        assert!(monitor.is_available(), "{:?}", monitor.get_last_error());
+        // either sample of the fixture, depending on how fast the warnings went
+        assert_eq!(monitor.get_gpu_info().unwrap().gpus.len(), 1);
+    }
+}
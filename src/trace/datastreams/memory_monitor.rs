@@ -1,43 +1,36 @@
-use crate::trace::datastreams::data_stream::Readings;
-use crate::trace::datastreams::{data_stream::SysDataStream, utils};
+use crate::trace::datastreams::retention::{History, Retention};
+use crate::trace::datastreams::{data_stream::SysDataStream, sample::Sample};
+use std::any::Any;
 
 pub struct MemoryMonitor {
     pub memory_usage: u64,
-    pub memory_usage_history: Vec<f64>, //Name, Usage
+    pub memory_usage_history: History, // Used memory in kB
     pub total_memory: u64,
-    max_history_len: usize,
-    interpolation_len: u16,
 }
 
-impl SysDataStream for MemoryMonitor {
-    fn new(max_hist_len: usize, inter_len: u16) -> Self {
+impl MemoryMonitor {
+    pub fn new(retention: &Retention) -> Self {
         Self {
             memory_usage: 0,
             total_memory: 10,
-            memory_usage_history: vec![0.0; max_hist_len],
-            max_history_len: max_hist_len,
-            interpolation_len: inter_len,
+            memory_usage_history: History::new(retention),
         }
     }
+}
 
-    fn poll(&mut self, system_info: &Readings) {
-        self.memory_usage = system_info.get_mem();
-        self.total_memory = system_info.get_total_memory();
+impl SysDataStream for MemoryMonitor {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
 
-        while self.memory_usage_history.len() >= self.max_history_len {
-            self.memory_usage_history.remove(0);
-        }
-        let last_mem = match self.memory_usage_history.last() {
-            Some(l) => *l,
-            None => 0.0,
-        };
-        self.memory_usage_history.extend_from_slice(
-            utils::interpolate(
-                last_mem,
-                self.memory_usage as f64 / self.total_memory as f64,
-                self.interpolation_len,
-            )
-            .as_slice(),
-        );
+    fn poll(&mut self, sample: &Sample) {
+        self.memory_usage = sample.readings.get_mem();
+        self.total_memory = sample.readings.get_total_memory();
+        self.memory_usage_history
+            .push(sample.time, self.memory_usage as f64);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
     }
 }
@@ -1,25 +1,72 @@
+mod app_metrics_monitor;
+mod cgroup;
 mod cpu_monitor;
 mod data_stream;
 mod memory_monitor;
 mod process_monitor;
-mod utils;
+mod registry;
+mod sample;
+mod retention;
+mod time_series;
 mod gpu_data;
+mod gpu_backend;
+mod gpu_health;
+mod gpu_simulator;
+mod intel_gpu_top;
 mod nvidia_smi;
+mod nvidia_smi_xml;
+mod nvidia_smi_mig;
+mod nvidia_smi_links;
+mod nvidia_smi_topo;
+mod nvidia_smi_encoder;
+mod nvidia_smi_accounting;
+mod numa;
+#[cfg(feature = "nvml")]
+mod nvml;
+mod raw_capture;
+mod rocm_smi;
+mod sysfs_drm;
+mod tool_runner;
 mod gpu_monitors;
 
+pub use self::app_metrics_monitor::AppMetricsMonitor;
+pub use self::cgroup::{Cgroup, CgroupReadings, DEFAULT_CGROUP_ROOT};
 pub use self::cpu_monitor::CPUMonitor;
 pub use self::data_stream::Readings;
 pub use self::data_stream::SysDataStream;
 pub use self::memory_monitor::MemoryMonitor;
 pub use self::process_monitor::ProcessMonitor;
+pub use self::registry::StreamRegistry;
+pub use self::sample::Sample;
+pub use self::retention::{
+    lttb, History, Resolution, Retention, Rollup, RollupTier, Segment, DEFAULT_RAW_RETENTION, DEFAULT_TIERS,
+};
+pub use self::time_series::{seconds, TimeSeries, Timestamp};
 
 // GPU monitoring exports
 pub use self::gpu_data::{
     GpuInfo, GpuReadings, GpuMemory, GpuUtilization, GpuTemperature, 
-    GpuPower, GpuClocks, GpuEcc, GpuEccErrors, GpuPcie, GpuProcess, GpuRecord
+    GpuPower, GpuClocks, GpuEcc, GpuEccErrors, GpuPcie, GpuProcess, GpuRecord,
+    GpuRetiredPages, GpuRemappedRows, ThrottleReasons, GpuMigDevice, GpuAccountedProcess, GpuNvLink, GpuPeer,
+    GpuEncoderStats, GpuEncoderSession
 };
-pub use self::nvidia_smi::NvidiaSmiMonitor;
+pub use self::gpu_backend::GpuBackend;
+pub use self::gpu_simulator::{GpuSimulator, DEFAULT_SIMULATOR_SEED};
+pub use self::gpu_health::{GpuBackoff, GpuHealth, BACKOFF_BASE, BACKOFF_MAX};
+pub use self::nvidia_smi::{NvidiaSmiMonitor, DEFAULT_NVIDIA_SMI_TIMEOUT, DEFAULT_REPORT_INTERVAL};
+pub use self::nvidia_smi_xml::XmlReport;
+pub use self::nvidia_smi_mig::MigLayout;
+pub use self::nvidia_smi_topo::Topology;
+pub use self::numa::{format_cpu_list, AffinityReader, Placement, ProcessAffinity};
+#[cfg(feature = "nvml")]
+pub use self::nvml::{NvmlMonitor, DEFAULT_NVML_LIBRARY};
+pub use self::intel_gpu_top::IntelGpuTopMonitor;
+pub use self::rocm_smi::RocmSmiMonitor;
+pub use self::sysfs_drm::{SysfsDrmMonitor, DEFAULT_PROCFS_ROOT, DEFAULT_SYSFS_ROOT};
+pub use self::raw_capture::{Invocation, RawCapture, RawOutput, RawReplay, INVOCATIONS_FILE};
+pub use self::tool_runner::{SharedCapture, ToolRunner};
 pub use self::gpu_monitors::{
     GpuMemoryMonitor, GpuUtilizationMonitor, GpuTemperatureMonitor, 
-    GpuPowerMonitor, GpuClockMonitor, GpuProcessMonitor
+    GpuPowerMonitor, GpuClockMonitor, GpuProcessMonitor, GpuEccMonitor, GpuAlert, GpuInterconnectMonitor,
+    GpuTopologyMonitor, ProcessPlacement, GpuMediaMonitor, GpuStreamFactory, GPU_STREAMS,
 };
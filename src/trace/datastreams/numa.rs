@@ -0,0 +1,280 @@
+use crate::trace::datastreams::gpu_data::{GpuInfo, GpuReadings};
+use crate::trace::datastreams::DEFAULT_PROCFS_ROOT;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where a traced process may run and allocate memory
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessAffinity {
+    pub pid: u32,
+    pub name: String,
+    /// CPUs the process may run on
+    pub cpus: Vec<u32>,
+    /// NUMA nodes of those CPUs
+    pub cpu_nodes: Vec<u32>,
+    /// NUMA nodes the process may allocate memory on
+    pub memory_nodes: Vec<u32>,
+}
+
+/// How a process sits relative to the GPU it uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// Only on CPUs and memory close to the GPU
+    Local,
+    /// Partly close - not pinned at all on a multi-socket machine, say
+    Spread,
+    /// Only on CPUs or memory of another NUMA node
+    Remote,
+    /// Where the GPU is attached is not known
+    Unknown,
+}
+
+impl ProcessAffinity {
+    /// Where the process may run compared with where `gpu` is attached. Memory bound to other
+    /// nodes only is as bad as CPUs of other nodes only.
+    pub fn placement(&self, gpu: &GpuInfo) -> Placement {
+        if let Some(node) = gpu.numa_node {
+            if !self.memory_nodes.is_empty() && !self.memory_nodes.contains(&node) {
+                return Placement::Remote;
+            }
+        }
+        let (near, ours): (Vec<u32>, &[u32]) = match (gpu.cpu_affinity.is_empty(), gpu.numa_node) {
+            (false, _) => (gpu.cpu_affinity.clone(), &self.cpus),
+            (true, Some(node)) => (vec![node], &self.cpu_nodes),
+            (true, None) => return Placement::Unknown,
+        };
+        if ours.is_empty() {
+            Placement::Unknown
+        } else if ours.iter().all(|c| near.contains(c)) {
+            Placement::Local
+        } else if ours.iter().any(|c| near.contains(c)) {
+            Placement::Spread
+        } else {
+            Placement::Remote
+        }
+    }
+}
+
+/// Reads where processes may run from procfs, and where GPUs are attached from sysfs
+pub struct AffinityReader {
+    sysfs_root: PathBuf,
+    procfs_root: PathBuf,
+    /// CPUs of each NUMA node
+    nodes: BTreeMap<u32, Vec<u32>>,
+}
+
+impl AffinityReader {
+    pub fn new(sysfs_root: impl Into<PathBuf>, procfs_root: impl Into<PathBuf>) -> Self {
+        let sysfs_root = sysfs_root.into();
+        let nodes = read_nodes(&sysfs_root.join("devices/system/node"));
+        Self {
+            sysfs_root,
+            procfs_root: procfs_root.into(),
+            nodes,
+        }
+    }
+
+    /// NUMA node and close CPUs of the GPUs the backend left without them, from their PCI device
+    pub fn fill_gpus(&self, readings: &mut GpuReadings) {
+        for gpu in readings.gpus.iter_mut().filter(|g| !g.pci_bus_id.is_empty()) {
+            let device = self.sysfs_root.join("bus/pci/devices").join(sysfs_bus_id(&gpu.pci_bus_id));
+            if gpu.numa_node.is_none() {
+                // -1 when the machine has a single node
+                gpu.numa_node = read_trimmed(&device.join("numa_node")).and_then(|n| n.parse().ok());
+            }
+            if gpu.cpu_affinity.is_empty() {
+                gpu.cpu_affinity = read_trimmed(&device.join("local_cpulist"))
+                    .and_then(|list| parse_cpu_list(&list))
+                    .unwrap_or_default();
+            }
+        }
+    }
+
+    /// Affinity of `pids`, skipping the ones that are gone
+    pub fn read_processes(&self, pids: &[u32]) -> Vec<ProcessAffinity> {
+        pids.iter().filter_map(|pid| self.read_process(*pid)).collect()
+    }
+
+    /// CPUs from `sched_getaffinity`, or `Cpus_allowed_list` when the kernel does not tell;
+    /// memory nodes from `Mems_allowed_list`. With another procfs than the host's, all of it comes
+    /// from there - the host's scheduler knows nothing of its PIDs.
+    fn read_process(&self, pid: u32) -> Option<ProcessAffinity> {
+        let status = fs::read_to_string(self.procfs_root.join(pid.to_string()).join("status")).ok()?;
+        let field = |name: &str| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+                .map(str::trim)
+        };
+        let host = self.procfs_root == Path::new(DEFAULT_PROCFS_ROOT);
+        let cpus = host
+            .then(|| sched_affinity(pid))
+            .flatten()
+            .or_else(|| field("Cpus_allowed_list").and_then(parse_cpu_list))
+            .unwrap_or_default();
+        let mut memory_nodes = field("Mems_allowed_list").and_then(parse_cpu_list).unwrap_or_default();
+        // every node there is, and then some - the kernel lists as many as it was built for
+        if !self.nodes.is_empty() {
+            memory_nodes.retain(|node| self.nodes.contains_key(node));
+        }
+        Some(ProcessAffinity {
+            pid,
+            name: field("Name").unwrap_or_default().to_string(),
+            cpu_nodes: self.nodes_of(&cpus),
+            cpus,
+            memory_nodes,
+        })
+    }
+
+    /// NUMA nodes the CPUs belong to
+    fn nodes_of(&self, cpus: &[u32]) -> Vec<u32> {
+        self.nodes
+            .iter()
+            .filter(|(_, node_cpus)| node_cpus.iter().any(|c| cpus.contains(c)))
+            .map(|(node, _)| *node)
+            .collect()
+    }
+}
+
+/// CPUs of every `nodeN` under `dir`, none without NUMA support
+fn read_nodes(dir: &Path) -> BTreeMap<u32, Vec<u32>> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return BTreeMap::new();
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .filter_map(|entry| {
+            let node = entry.file_name().to_string_lossy().strip_prefix("node")?.parse().ok()?;
+            let cpus = parse_cpu_list(&read_trimmed(&entry.path().join("cpulist"))?)?;
+            Some((node, cpus))
+        })
+        .collect()
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+/// sysfs names PCI devices with a 4 digit domain in lower case, nvidia-smi with 8 in upper case
+fn sysfs_bus_id(bus_id: &str) -> String {
+    let bus_id = bus_id.to_lowercase();
+    match bus_id.split_once(':') {
+        Some((domain, rest)) if domain.len() > 4 => format!("{}:{}", &domain[domain.len() - 4..], rest),
+        _ => bus_id,
+    }
+}
+
+/// The kernel's CPU list format: `0-23,48-71`. Empty lists are empty, garbage is `None`.
+pub fn parse_cpu_list(list: &str) -> Option<Vec<u32>> {
+    let mut cpus = BTreeSet::new();
+    for part in list.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((first, last)) => cpus.extend(first.parse::<u32>().ok()?..=last.parse().ok()?),
+            None => {
+                cpus.insert(part.parse().ok()?);
+            }
+        }
+    }
+    Some(cpus.into_iter().collect())
+}
+
+/// Back to the kernel's format, with ranges where CPUs follow each other
+pub fn format_cpu_list(cpus: &[u32]) -> String {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for cpu in cpus {
+        match ranges.last_mut() {
+            Some((_, last)) if *last + 1 == *cpu => *last = *cpu,
+            _ => ranges.push((*cpu, *cpu)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|(first, last)| match first == last {
+            true => first.to_string(),
+            false => format!("{}-{}", first, last),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// CPUs the process may run on, as the scheduler has it - `None` when it is gone
+fn sched_affinity(pid: u32) -> Option<Vec<u32>> {
+    // SAFETY: cpu_set_t is plain data, zeroed is an empty set the kernel fills in
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<libc::cpu_set_t>();
+    if unsafe { libc::sched_getaffinity(pid as libc::pid_t, size, &mut set) } != 0 {
+        return None;
+    }
+    let cpus = (0..libc::CPU_SETSIZE as usize)
+        .filter(|cpu| unsafe { libc::CPU_ISSET(*cpu, &set) })
+        .map(|cpu| cpu as u32)
+        .collect();
+    Some(cpus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: PathBuf, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_affinity_of_processes_and_gpus() {
+        assert_eq!(parse_cpu_list("0-3,8,10-11\n".trim()), Some(vec![0, 1, 2, 3, 8, 10, 11]));
+        assert_eq!(parse_cpu_list(""), Some(vec![]));
+        assert_eq!(parse_cpu_list("N/A"), None);
+        assert_eq!(format_cpu_list(&[0, 1, 2, 3, 8, 10, 11]), "0-3,8,10-11");
+
+        // two sockets, GPU 0 on the first, GPU 1 on the second
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        write(root.join("sys/devices/system/node/node0/cpulist"), "0-7\n");
+        write(root.join("sys/devices/system/node/node1/cpulist"), "8-15\n");
+        write(root.join("sys/bus/pci/devices/0000:17:00.0/numa_node"), "0\n");
+        write(root.join("sys/bus/pci/devices/0000:17:00.0/local_cpulist"), "0-7\n");
+        write(root.join("sys/bus/pci/devices/0000:ca:00.0/numa_node"), "1\n");
+        write(root.join("sys/bus/pci/devices/0000:ca:00.0/local_cpulist"), "8-15\n");
+        // beyond the highest PID the kernel hands out, and our own PID, pinned in this procfs only
+        let status = "Name:\tpython3\nCpus_allowed_list:\t8-11\nMems_allowed_list:\t0-1023\n";
+        write(root.join("proc/4194305/status"), status);
+        write(root.join("proc").join(std::process::id().to_string()).join("status"), status);
+        let reader = AffinityReader::new(root.join("sys"), root.join("proc"));
+
+        let mut readings = GpuReadings::new();
+        for (index, bus_id) in [(0, "00000000:17:00.0"), (1, "00000000:CA:00.0")] {
+            readings.gpus.push(GpuInfo {
+                index,
+                pci_bus_id: bus_id.to_string(),
+                ..GpuInfo::default()
+            });
+        }
+        reader.fill_gpus(&mut readings);
+        assert_eq!(readings.gpus[1].numa_node, Some(1));
+        assert_eq!(format_cpu_list(&readings.gpus[1].cpu_affinity), "8-15");
+
+        let processes = reader.read_processes(&[4194305, 4194306]);
+        assert_eq!(processes.len(), 1);
+        let process = &processes[0];
+        assert_eq!((process.name.as_str(), process.cpu_nodes.as_slice()), ("python3", &[1][..]));
+        assert_eq!(process.memory_nodes, vec![0, 1]);
+        assert_eq!(process.placement(&readings.gpus[0]), Placement::Remote);
+        assert_eq!(process.placement(&readings.gpus[1]), Placement::Local);
+
+        // topology known only as a node, and memory bound elsewhere
+        let gpu = GpuInfo { numa_node: Some(1), ..GpuInfo::default() };
+        assert_eq!(process.placement(&gpu), Placement::Local);
+        let bound = ProcessAffinity { memory_nodes: vec![0], ..process.clone() };
+        assert_eq!(bound.placement(&gpu), Placement::Remote);
+        assert_eq!(process.placement(&GpuInfo::default()), Placement::Unknown);
+
+        // the host's scheduler knows this one, but is not asked about another procfs
+        let ours = sched_affinity(std::process::id()).unwrap();
+        assert!(!ours.is_empty());
+        let pinned = reader.read_processes(&[std::process::id()]);
+        assert_eq!(format_cpu_list(&pinned[0].cpus), "8-11");
+    }
+}
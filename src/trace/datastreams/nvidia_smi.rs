@@ -1,118 +1,213 @@
 use crate::trace::datastreams::gpu_data::{
-    GpuClocks, GpuEcc, GpuEccErrors, GpuInfo, GpuMemory, GpuPcie, GpuPower, 
-    GpuProcess, GpuReadings, GpuTemperature, GpuUtilization,
+    GpuClocks, GpuEcc, GpuEccErrors, GpuEncoderStats, GpuInfo, GpuMemory, GpuPcie, GpuPower, 
+    GpuProcess, GpuReadings, GpuRemappedRows, GpuRetiredPages, GpuTemperature, GpuUtilization,
+    ThrottleReasons,
 };
-use std::process::Command;
+use crate::error::GpuError;
+use crate::trace::datastreams::gpu_backend::GpuBackend;
+use crate::trace::datastreams::gpu_health::{GpuBackoff, GpuHealth};
+use crate::trace::datastreams::nvidia_smi_accounting::{self, ACCOUNTED_APPS_ARGS};
+use crate::trace::datastreams::nvidia_smi_encoder::{self, ENCODER_SESSIONS_ARGS};
+use crate::trace::datastreams::nvidia_smi_links::{
+    self, NvLinkTracker, PcieStream, PcieThroughput, NVLINK_ERRORS_ARGS, NVLINK_THROUGHPUT_ARGS,
+    PCIE_THROUGHPUT_ARGS,
+};
+use crate::trace::datastreams::nvidia_smi_mig::{
+    self, MigLayout, MIG_COMPUTE_INSTANCES_ARGS, MIG_GPU_INSTANCES_ARGS, MIG_LIST_ARGS,
+};
+use crate::trace::datastreams::nvidia_smi_topo::{self, Topology, TOPOLOGY_ARGS};
+use crate::trace::datastreams::nvidia_smi_xml::{XmlReport, XML_REPORT_ARGS};
+use crate::trace::datastreams::tool_runner::ToolRunner;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// How long a single nvidia-smi call may take before it is killed
+pub const DEFAULT_NVIDIA_SMI_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How often the full `nvidia-smi -q -x` report is read - it is slow, and what it adds changes slowly
+pub const DEFAULT_REPORT_INTERVAL: Duration = Duration::from_secs(30);
+
+const COUNT_ARGS: [&str; 2] = ["--query-gpu=count", "--format=csv,noheader,nounits"];
+
+/// Queried GPU fields, in the order `parse_gpu_line` reads them (removed invalid fields)
+const GPU_QUERY: [&str; 57] = [
+    "timestamp", "name", "uuid", "pci.bus_id", "driver_version", "vbios_version",
+    "compute_cap", "pstate", "memory.total", "memory.used", "memory.free", "memory.reserved",
+    "utilization.gpu", "utilization.memory", "utilization.encoder", "utilization.decoder",
+    "utilization.jpeg", "utilization.ofa", "temperature.gpu", "temperature.gpu.tlimit",
+    "temperature.memory", "power.draw", "power.draw.average", "power.draw.instant",
+    "power.limit", "enforced.power.limit", "power.default_limit", "power.min_limit",
+    "power.max_limit", "power.management", "clocks.current.graphics", "clocks.current.sm",
+    "clocks.current.memory", "clocks.current.video", "clocks.max.graphics", "clocks.max.sm",
+    "clocks.max.memory", "clocks.applications.graphics", "clocks.applications.memory",
+    "ecc.mode.current", "ecc.mode.pending", "ecc.errors.corrected.volatile.total",
+    "ecc.errors.uncorrected.volatile.total", "ecc.errors.corrected.aggregate.total",
+    "ecc.errors.uncorrected.aggregate.total", "retired_pages.sbe", "retired_pages.dbe",
+    "retired_pages.pending", "pcie.link.gen.current", "pcie.link.gen.max",
+    "pcie.link.width.current", "pcie.link.width.max", "fan.speed", "display_mode", 
+    "persistence_mode", "compute_mode", "index"
+];
+
+/// Fields not every driver knows, queried after `GPU_QUERY` when the driver takes them and read by name
+const OPTIONAL_GPU_QUERY: [&str; 9] = [
+    "clocks_throttle_reasons.active", "mig.mode.current", "encoder.stats.sessionCount",
+    "encoder.stats.averageFps", "encoder.stats.averageLatency", "remapped_rows.correctable",
+    "remapped_rows.uncorrectable", "remapped_rows.pending", "remapped_rows.failure"
+];
+
+/// Places nvidia-smi is looked for, first found wins
+const NVIDIA_SMI_PATHS: [&str; 6] = [
+    "nvidia-smi",
+    "/usr/bin/nvidia-smi",
+    "/usr/local/bin/nvidia-smi",
+    "/opt/nvidia/bin/nvidia-smi",
+    "C:\\Program Files\\NVIDIA Corporation\\NVSMI\\nvidia-smi.exe",
+    "C:\\Windows\\System32\\nvidia-smi.exe",
+];
 
 /// nvidia-smi integration for GPU monitoring
 pub struct NvidiaSmiMonitor {
-    /// Path to nvidia-smi executable
-    nvidia_smi_path: String,
+    /// Runs nvidia-smi, or replays a recording of it
+    runner: ToolRunner,
     /// Whether nvidia-smi is available
     available: bool,
-    /// Last error message
-    last_error: Option<String>,
+    /// Last error, while the backend is degraded or why it is not available
+    last_error: Option<GpuError>,
+    /// Health and backoff between retries while nvidia-smi fails
+    backoff: GpuBackoff,
+    /// Fields of the main query - `GPU_QUERY` and the optional ones the driver takes
+    query: Vec<&'static str>,
+    /// How often the XML report is read, never if `None`
+    report_interval: Option<Duration>,
+    /// Last good XML report and when it was read
+    report: Option<(Instant, XmlReport)>,
+    /// When the XML report was asked for last, whether it could be read or not
+    report_asked: Option<Instant>,
+    /// MIG partitioning, read with the report while some GPU has MIG enabled
+    mig: Option<MigLayout>,
+    /// PCIe throughput streamed by a running dmon, and whether dmon turned out not to work
+    pcie: Option<PcieStream>,
+    pcie_off: bool,
+    /// NVLink throughput from the counters of consecutive readings
+    nvlink: NvLinkTracker,
+    /// How GPUs are wired, read once
+    topology: Option<Topology>,
+    /// Whether accounted processes are asked for every reading, and whether that failed before
+    accounting: bool,
+    accounting_failed: bool,
 }
 
 impl NvidiaSmiMonitor {
     /// Create a new NvidiaSmiMonitor instance
     pub fn new() -> Self {
-        let path = Self::find_nvidia_smi_path();
-        let available = Self::check_availability(&path);
-        
-        Self {
-            nvidia_smi_path: path,
-            available,
-            last_error: None,
-        }
+        Self::with_timeout(DEFAULT_NVIDIA_SMI_TIMEOUT)
     }
 
-    /// Find nvidia-smi executable path
-    fn find_nvidia_smi_path() -> String {
-        // Try common paths for nvidia-smi
-        let common_paths = vec![
-            "nvidia-smi",
-            "/usr/bin/nvidia-smi",
-            "/usr/local/bin/nvidia-smi",
-            "/opt/nvidia/bin/nvidia-smi",
-            "C:\\Program Files\\NVIDIA Corporation\\NVSMI\\nvidia-smi.exe",
-            "C:\\Windows\\System32\\nvidia-smi.exe",
-        ];
+    /// Create a new NvidiaSmiMonitor instance killing calls that take longer than `timeout`
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self::with_runner(ToolRunner::new(&Self::find_path(), timeout))
+    }
 
-        for path in common_paths {
-            if Self::check_command_exists(path) {
-                return path.to_string();
+    /// Uses `runner` to call nvidia-smi - a capturing or replaying one, say
+    pub fn with_runner(runner: ToolRunner) -> Self {
+        let mut monitor = Self {
+            runner,
+            available: true,
+            last_error: None,
+            backoff: GpuBackoff::new(),
+            query: full_query(),
+            report_interval: Some(DEFAULT_REPORT_INTERVAL),
+            report: None,
+            report_asked: None,
+            mig: None,
+            pcie: None,
+            pcie_off: false,
+            nvlink: NvLinkTracker::default(),
+            topology: None,
+            accounting: false,
+            accounting_failed: false,
+        };
+        // Check if nvidia-smi is available and working
+        match monitor.runner.run(&COUNT_ARGS).and_then(|output| parse_count(&output)) {
+            Ok(_) => monitor.query = monitor.probe_query(),
+            Err(e) => {
+                monitor.available = false;
+                monitor.last_error = Some(e);
             }
         }
-
-        "nvidia-smi".to_string() // Fallback to PATH
+        monitor
     }
 
-    /// Check if a command exists and is executable
-    fn check_command_exists(command: &str) -> bool {
-        let result = if cfg!(target_os = "windows") {
-            Command::new("where")
-                .arg(command)
-                .output()
-        } else {
-            Command::new("which")
-                .arg(command)
-                .output()
-        };
+    /// Main query without the optional fields this driver rejects - one unknown field would fail
+    /// every reading. Each rejected field is left out and the rest asked again.
+    fn probe_query(&self) -> Vec<&'static str> {
+        let mut query = full_query();
+        loop {
+            match self.runner.run(&["--query-gpu", &query.join(","), "--format=csv,noheader,nounits"]) {
+                Err(GpuError::UnsupportedField(field)) if OPTIONAL_GPU_QUERY.contains(&field.as_str()) => {
+                    log::info!("nvidia-smi does not know {}, not asking for it", field);
+                    query.retain(|name| *name != field);
+                }
+                _ => return query,
+            }
+        }
+    }
 
-        result.map(|output| output.status.success()).unwrap_or(false)
+    /// Reads the full XML report (ECC, retired pages, MIG, ...) every `interval`, or never
+    pub fn set_report_interval(&mut self, interval: Option<Duration>) {
+        self.report_interval = interval;
     }
 
-    /// Check if nvidia-smi is available and working
-    fn check_availability(path: &str) -> bool {
-        let result = Command::new(path)
-            .arg("--query-gpu=count")
-            .arg("--format=csv,noheader,nounits")
-            .output();
+    /// Asks for accounted processes with every reading - they outlive their process, so the
+    /// ones that exited between two readings are not lost
+    pub fn set_accounting(&mut self, accounting: bool) {
+        self.accounting = accounting;
+    }
 
-        match result {
-            Ok(output) => {
-                if output.status.success() {
-                    let count_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                    count_str.parse::<u32>().is_ok()
-                } else {
-                    false
-                }
-            }
-            Err(_) => false,
-        }
+    /// Find nvidia-smi executable path, falling back to `PATH`
+    pub fn find_path() -> String {
+        ToolRunner::find(&NVIDIA_SMI_PATHS)
     }
 
     /// Execute nvidia-smi command and return output
-    fn execute_command(&self, args: &[&str]) -> Result<String, String> {
+    fn execute_command(&self, args: &[&str]) -> Result<String, GpuError> {
         if !self.available {
-            return Err("nvidia-smi is not available".to_string());
+            return Err(self
+                .last_error
+                .clone()
+                .unwrap_or_else(|| GpuError::NotInstalled(self.runner.program().to_string())));
         }
-
-        let output = Command::new(&self.nvidia_smi_path)
-            .args(args)
-            .output()
-            .map_err(|e| format!("Failed to execute nvidia-smi: {}", e))?;
-
-        if !output.status.success() {
-            let error_msg = String::from_utf8_lossy(&output.stderr);
-            let stdout_msg = String::from_utf8_lossy(&output.stdout);
-            return Err(format!("nvidia-smi command failed: stderr='{}', stdout='{}', exit_code={:?}", 
-                error_msg, stdout_msg, output.status.code()));
-        }
-
-        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        self.runner.run(args)
     }
 
     /// Get GPU count
-    pub fn get_gpu_count(&self) -> Result<u32, String> {
-        let output = self.execute_command(&["--query-gpu=count", "--format=csv,noheader,nounits"])?;
-        output.trim().parse::<u32>()
-            .map_err(|e| format!("Failed to parse GPU count: {}", e))
+    pub fn get_gpu_count(&self) -> Result<u32, GpuError> {
+        let output = self.execute_command(&COUNT_ARGS)?;
+        parse_count(&output)
+    }
+
+    /// Get basic GPU information. While nvidia-smi keeps failing, it is asked again only after
+    /// a growing backoff - calls in between fail right away without running it.
+    /// The extra queries follow only a main query that worked, and one that hangs fails the
+    /// reading like the main query would - so a stuck GPU is not asked again until the backoff is over.
+    pub fn get_gpu_info(&mut self) -> Result<GpuReadings, GpuError> {
+        let now = Instant::now();
+        self.backoff.check(now)?;
+        let result = self.query_gpu_info().and_then(|mut readings| {
+            self.read_links(now, &mut readings)?;
+            self.read_encoder_sessions(&mut readings)?;
+            self.merge_topology(&mut readings)?;
+            self.merge_report(now, &mut readings)?;
+            self.read_accounting(&mut readings)?;
+            Ok(readings)
+        });
+        let result = self.backoff.record(now, result);
+        self.last_error = result.as_ref().err().cloned();
+        result
     }
 
-    /// Get basic GPU information
-    pub fn get_gpu_info(&self) -> Result<GpuReadings, String> {
+    /// Query all GPUs and their processes
+    fn query_gpu_info(&self) -> Result<GpuReadings, GpuError> {
         let mut readings = GpuReadings::new();
         
         // Get GPU count
@@ -122,23 +217,7 @@ impl NvidiaSmiMonitor {
             return Ok(readings);
         }
 
-        // Query all GPU metrics (removed invalid fields)
-        let gpu_query = [
-            "timestamp", "name", "uuid", "pci.bus_id", "driver_version", "vbios_version",
-            "compute_cap", "pstate", "memory.total", "memory.used", "memory.free", "memory.reserved",
-            "utilization.gpu", "utilization.memory", "utilization.encoder", "utilization.decoder",
-            "utilization.jpeg", "utilization.ofa", "temperature.gpu", "temperature.gpu.tlimit",
-            "temperature.memory", "power.draw", "power.draw.average", "power.draw.instant",
-            "power.limit", "enforced.power.limit", "power.default_limit", "power.min_limit",
-            "power.max_limit", "power.management", "clocks.current.graphics", "clocks.current.sm",
-            "clocks.current.memory", "clocks.current.video", "clocks.max.graphics", "clocks.max.sm",
-            "clocks.max.memory", "clocks.applications.graphics", "clocks.applications.memory",
-            "ecc.mode.current", "ecc.mode.pending", "pcie.link.gen.current", "pcie.link.gen.max",
-            "pcie.link.width.current", "pcie.link.width.max", "fan.speed", "display_mode", 
-            "persistence_mode", "compute_mode", "index"
-        ];
-
-        let query_str = gpu_query.join(",");
+        let query_str = self.query.join(",");
         let output = self.execute_command(&[
             "--query-gpu",
             &query_str,
@@ -150,7 +229,7 @@ impl NvidiaSmiMonitor {
         log::trace!("nvidia-smi output lines: {}", lines.len());
         for (i, line) in lines.iter().enumerate() {
             log::trace!("Line {}: {}", i, line);
-            match self.parse_gpu_line(line, gpu_query.len()) {
+            match self.parse_gpu_line(line, &self.query) {
                 Ok(gpu_info) => {
                     log::debug!("Successfully parsed GPU {}: Memory {}/{}MB", gpu_info.index, gpu_info.memory.used, gpu_info.memory.total);
                     readings.gpus.push(gpu_info);
@@ -169,106 +248,300 @@ impl NvidiaSmiMonitor {
         Ok(readings)
     }
 
-    /// Parse a single GPU line from CSV output
-    fn parse_gpu_line(&self, line: &str, expected_fields: usize) -> Result<GpuInfo, String> {
-        let fields: Vec<&str> = line.split(',').collect();
-        
-        if fields.len() < expected_fields {
-            return Err(format!("Expected {} fields, got {}", expected_fields, fields.len()));
+    /// Adds PCIe throughput and NVLink links. Either failing is only logged, and NVLink is not
+    /// asked again once it turned out there are no links.
+    fn read_links(&mut self, now: Instant, readings: &mut GpuReadings) -> Result<(), GpuError> {
+        let pcie = match self.runner.can_stream() {
+            true => self.stream_pcie(),
+            false => self
+                .execute_command(&PCIE_THROUGHPUT_ARGS)
+                .and_then(|output| nvidia_smi_links::parse_pcie_throughput(&output)),
+        };
+        for row in extra("dmon", pcie)?.unwrap_or_default() {
+            if let Some(gpu) = readings.gpus.iter_mut().find(|g| g.index == row.gpu_index) {
+                gpu.pcie.rx_throughput = row.rx;
+                gpu.pcie.tx_throughput = row.tx;
+            }
         }
 
-        let mut field_iter = fields.iter();
-        
-        // Helper function to parse optional numeric values
-        let parse_optional_u32 = |s: &str| -> Option<u32> {
-            let trimmed = s.trim();
-            if trimmed.is_empty() || trimmed == "N/A" || trimmed == "[N/A]" { None } else { trimmed.parse().ok() }
+        if !self.nvlink.wanted() {
+            return Ok(());
+        }
+        let read = |args: &[&str]| {
+            self.execute_command(args)
+                .and_then(|output| nvidia_smi_links::parse_nvlink(&output))
         };
-        
-        let parse_optional_f32 = |s: &str| -> Option<f32> {
-            let trimmed = s.trim();
-            if trimmed.is_empty() || trimmed == "N/A" || trimmed == "[N/A]" { None } else { trimmed.parse().ok() }
+        if let Some(data) = extra("nvlink", read(&NVLINK_THROUGHPUT_ARGS))? {
+            let errors = match data.is_empty() {
+                true => Vec::new(),
+                false => extra("nvlink -e", read(&NVLINK_ERRORS_ARGS))?.unwrap_or_default(),
+            };
+            self.nvlink.merge_into(now, data, &errors, readings);
+        }
+        Ok(())
+    }
+
+    /// Latest rows of the running dmon, started with the first reading and again after it exited.
+    /// A dmon that exits before any row is not started again.
+    fn stream_pcie(&mut self) -> Result<Vec<PcieThroughput>, GpuError> {
+        if self.pcie_off || !self.available {
+            return Ok(Vec::new());
+        }
+        let mut stream = match self.pcie.take() {
+            Some(stream) => stream,
+            None => PcieStream::spawn(self.runner.program()).map_err(|e| {
+                self.pcie_off = true;
+                GpuError::Io(e.to_string())
+            })?,
         };
-        
-        let parse_optional_i32 = |s: &str| -> Option<i32> {
-            let trimmed = s.trim();
-            if trimmed.is_empty() || trimmed == "N/A" || trimmed == "[N/A]" { None } else { trimmed.parse().ok() }
+        let result = stream.latest();
+        match result {
+            Ok(_) => self.pcie = Some(stream),
+            Err(_) => self.pcie_off = !stream.has_rows(),
+        }
+        result
+    }
+
+    /// Adds NVENC sessions of the GPUs whose `encoder.stats.sessionCount` of this reading is not zero -
+    /// nvidia-smi is not asked at all while none is encoding. Failing is only logged.
+    fn read_encoder_sessions(&self, readings: &mut GpuReadings) -> Result<(), GpuError> {
+        let encoding: Vec<u32> = readings
+            .gpus
+            .iter()
+            .filter(|gpu| gpu.encoder_stats.is_some_and(|stats| stats.session_count > 0))
+            .map(|gpu| gpu.index)
+            .collect();
+        if encoding.is_empty() {
+            return Ok(());
+        }
+        let sessions = self
+            .execute_command(&ENCODER_SESSIONS_ARGS)
+            .and_then(|output| nvidia_smi_encoder::parse_encoder_sessions(&output));
+        if let Some(mut sessions) = extra("encodersessions", sessions)? {
+            sessions.retain(|session| encoding.contains(&session.gpu_index));
+            readings.encoder_sessions = sessions;
+        }
+        Ok(())
+    }
+
+    /// Replaces accounted processes of the XML report with fresh ones, when accounting is asked for.
+    /// Failing - accounting off, or not supported - is a warning the first time only.
+    fn read_accounting(&mut self, readings: &mut GpuReadings) -> Result<(), GpuError> {
+        if !self.accounting || readings.gpus.is_empty() {
+            return Ok(());
+        }
+        match self
+            .execute_command(&ACCOUNTED_APPS_ARGS)
+            .and_then(|output| nvidia_smi_accounting::parse_accounted_apps(&output))
+        {
+            Ok(apps) => {
+                nvidia_smi_accounting::merge_into(&apps, readings);
+                self.accounting_failed = false;
+            }
+            Err(e @ GpuError::Timeout { .. }) => return Err(e),
+            Err(e) if self.accounting_failed => log::debug!("nvidia-smi accounted apps: {}", e),
+            Err(e) => {
+                log::warn!("nvidia-smi accounted apps: {} - is accounting on (nvidia-smi -am 1)?", e);
+                self.accounting_failed = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds peers and CPU/NUMA affinity of `nvidia-smi topo -m`, read with the first GPUs. Wiring
+    /// does not change - when reading it fails, it is not asked again.
+    fn merge_topology(&mut self, readings: &mut GpuReadings) -> Result<(), GpuError> {
+        if self.topology.is_none() && !readings.gpus.is_empty() {
+            let topology = self
+                .execute_command(&TOPOLOGY_ARGS)
+                .and_then(|output| nvidia_smi_topo::parse_topology(&output));
+            self.topology = Some(extra("topo -m", topology)?.unwrap_or_default());
+        }
+        if let Some(topology) = &self.topology {
+            topology.merge_into(readings);
+        }
+        Ok(())
+    }
+
+    /// Adds the XML report and MIG layout, read again when they are `report_interval` old. A report
+    /// that cannot be read is only logged - the readings keep the last good one, with its age.
+    fn merge_report(&mut self, now: Instant, readings: &mut GpuReadings) -> Result<(), GpuError> {
+        let Some(interval) = self.report_interval else {
+            return Ok(());
         };
+        let due = self.report_asked.is_none_or(|asked| now.duration_since(asked) >= interval);
+        if due {
+            match self.execute_command(&XML_REPORT_ARGS).and_then(|xml| XmlReport::parse(&xml)) {
+                Ok(report) => self.report = Some((now, report)),
+                Err(e @ GpuError::Timeout { .. }) => return Err(e),
+                Err(e) => log::warn!("nvidia-smi XML report: {}", e),
+            }
+            // a report that failed is not asked for again before the next interval either
+            self.report_asked = Some(now);
+            self.mig = readings
+                .gpus
+                .iter()
+                .any(|gpu| gpu.mig_mode == "Enabled")
+                .then(|| self.read_mig_layout());
+        }
+        if let Some((read, report)) = &self.report {
+            report.merge_into(readings, now.duration_since(*read));
+            // only asked for with accounting - fresh ones of `read_accounting` replace them
+            if self.accounting {
+                readings.accounted_processes = report.accounted_processes(readings);
+            }
+        }
+        if let Some(mig) = &self.mig {
+            mig.merge_into(readings);
+        }
+        Ok(())
+    }
 
-        let timestamp = field_iter.next().map_or("", |v| v).to_string();
-        let name = field_iter.next().map_or("Unknown", |v| v).to_string();
-        let uuid = field_iter.next().map_or("", |v| v).to_string();
-        let pci_bus_id = field_iter.next().map_or("", |v| v).to_string();
-        let driver_version = field_iter.next().map_or("", |v| v).to_string();
-        let vbios_version = field_iter.next().map_or("", |v| v).to_string();
-        let compute_cap = field_iter.next().map_or("", |v| v).to_string();
-        let pstate = field_iter.next().map_or("", |v| v).to_string();
+    /// Lists MIG devices and instances. Listing instances may take root - what fails is only logged.
+    fn read_mig_layout(&self) -> MigLayout {
+        MigLayout {
+            listed: self.read_mig(&MIG_LIST_ARGS, nvidia_smi_mig::parse_list),
+            gpu_instances: self.read_mig(&MIG_GPU_INSTANCES_ARGS, nvidia_smi_mig::parse_gpu_instances),
+            compute_instances: self.read_mig(&MIG_COMPUTE_INSTANCES_ARGS, nvidia_smi_mig::parse_compute_instances),
+        }
+    }
+
+    fn read_mig<T>(&self, args: &[&str], parse: fn(&str) -> Result<Vec<T>, GpuError>) -> Vec<T> {
+        self.execute_command(args)
+            .and_then(|output| parse(&output))
+            .unwrap_or_else(|e| {
+                log::debug!("nvidia-smi {}: {}", args.join(" "), e);
+                Vec::new()
+            })
+    }
+
+    /// Parse a single GPU line from CSV output
+    fn parse_gpu_line(&self, line: &str, query: &[&str]) -> Result<GpuInfo, GpuError> {
+        let fields: Vec<&str> = line.split(',').collect();
+        
+        if fields.len() < query.len() {
+            // First field that is not there
+            return Err(GpuError::Parse {
+                field: query[fields.len()].to_string(),
+                value: String::new(),
+            });
+        }
+
+        let mut f = CsvFields::new(query, &fields);
+
+        let timestamp = f.text();
+        let name = f.text();
+        let uuid = f.text();
+        let pci_bus_id = f.text();
+        let driver_version = f.text();
+        let vbios_version = f.text();
+        let compute_cap = f.text();
+        let pstate = f.text();
         
         // Memory fields
-        let memory_total_field = field_iter.next().map_or("0", |v| v);
-        let memory_used_field = field_iter.next().map_or("0", |v| v);
-        let memory_free_field = field_iter.next().map_or("0", |v| v);
-        let memory_reserved_field = field_iter.next().map_or("0", |v| v);
+        let memory_total = f.num::<u64>()?.unwrap_or(0);
+        let memory_used = f.num::<u64>()?.unwrap_or(0);
+        let memory_free = f.num::<u64>()?.unwrap_or(0);
+        let memory_reserved = f.num::<u64>()?.unwrap_or(0);
         
         log::trace!("Memory fields: total='{}', used='{}', free='{}', reserved='{}'", 
-                  memory_total_field, memory_used_field, memory_free_field, memory_reserved_field);
-        
-        let memory_total = parse_optional_u32(memory_total_field).unwrap_or(0) as u64;
-        let memory_used = parse_optional_u32(memory_used_field).unwrap_or(0) as u64;
-        let memory_free = parse_optional_u32(memory_free_field).unwrap_or(0) as u64;
-        let memory_reserved = parse_optional_u32(memory_reserved_field).unwrap_or(0) as u64;
+                  memory_total, memory_used, memory_free, memory_reserved);
         
         // Utilization fields
-        let gpu_util = parse_optional_u32(field_iter.next().map_or("0", |v| v)).unwrap_or(0);
-        let mem_util = parse_optional_u32(field_iter.next().map_or("0", |v| v)).unwrap_or(0);
-        let encoder_util = parse_optional_u32(field_iter.next().map_or("", |v| v));
-        let decoder_util = parse_optional_u32(field_iter.next().map_or("", |v| v));
-        let jpeg_util = parse_optional_u32(field_iter.next().map_or("", |v| v));
-        let ofa_util = parse_optional_u32(field_iter.next().map_or("", |v| v));
+        let gpu_util = f.num()?.unwrap_or(0);
+        let mem_util = f.num()?.unwrap_or(0);
+        let encoder_util = f.num()?;
+        let decoder_util = f.num()?;
+        let jpeg_util = f.num()?;
+        let ofa_util = f.num()?;
         
         // Temperature fields
-        let temp_gpu = parse_optional_i32(field_iter.next().map_or("", |v| v));
-        let temp_gpu_tlimit = parse_optional_i32(field_iter.next().map_or("", |v| v));
-        let temp_memory = parse_optional_i32(field_iter.next().map_or("", |v| v));
+        let temp_gpu = f.num()?;
+        let temp_gpu_tlimit = f.num()?;
+        let temp_memory = f.num()?;
         
         // Power fields
-        let power_draw = parse_optional_f32(field_iter.next().map_or("", |v| v));
-        let power_draw_avg = parse_optional_f32(field_iter.next().map_or("", |v| v));
-        let power_draw_instant = parse_optional_f32(field_iter.next().map_or("", |v| v));
-        let power_limit = parse_optional_f32(field_iter.next().map_or("", |v| v));
-        let power_enforced_limit = parse_optional_f32(field_iter.next().map_or("", |v| v));
-        let power_default_limit = parse_optional_f32(field_iter.next().map_or("", |v| v));
-        let power_min_limit = parse_optional_f32(field_iter.next().map_or("", |v| v));
-        let power_max_limit = parse_optional_f32(field_iter.next().map_or("", |v| v));
-        let power_management = field_iter.next().map_or("", |v| v).to_string();
+        let power_draw = f.num()?;
+        let power_draw_avg = f.num()?;
+        let power_draw_instant = f.num()?;
+        let power_limit = f.num()?;
+        let power_enforced_limit = f.num()?;
+        let power_default_limit = f.num()?;
+        let power_min_limit = f.num()?;
+        let power_max_limit = f.num()?;
+        let power_management = f.text();
         
         // Clock fields
-        let clock_graphics = parse_optional_u32(field_iter.next().map_or("", |v| v));
-        let clock_sm = parse_optional_u32(field_iter.next().map_or("", |v| v));
-        let clock_memory = parse_optional_u32(field_iter.next().map_or("", |v| v));
-        let clock_video = parse_optional_u32(field_iter.next().map_or("", |v| v));
-        let clock_max_graphics = parse_optional_u32(field_iter.next().map_or("", |v| v));
-        let clock_max_sm = parse_optional_u32(field_iter.next().map_or("", |v| v));
-        let clock_max_memory = parse_optional_u32(field_iter.next().map_or("", |v| v));
-        let clock_app_graphics = parse_optional_u32(field_iter.next().map_or("", |v| v));
-        let clock_app_memory = parse_optional_u32(field_iter.next().map_or("", |v| v));
+        let clock_graphics = f.num()?;
+        let clock_sm = f.num()?;
+        let clock_memory = f.num()?;
+        let clock_video = f.num()?;
+        let clock_max_graphics = f.num()?;
+        let clock_max_sm = f.num()?;
+        let clock_max_memory = f.num()?;
+        let clock_app_graphics = f.num()?;
+        let clock_app_memory = f.num()?;
         
         // ECC fields
-        let ecc_mode_current = field_iter.next().map_or("", |v| v).to_string();
-        let ecc_mode_pending = field_iter.next().map_or("", |v| v).to_string();
+        let ecc_mode_current = f.text();
+        let ecc_mode_pending = f.text();
+        // totals only, the XML report has them per location
+        let ecc_total = |total: Option<u64>| GpuEccErrors {
+            total: total.unwrap_or(0),
+            ..GpuEccErrors::default()
+        };
+        let ecc_corrected_volatile = ecc_total(f.num()?);
+        let ecc_uncorrected_volatile = ecc_total(f.num()?);
+        let ecc_corrected_aggregate = ecc_total(f.num()?);
+        let ecc_uncorrected_aggregate = ecc_total(f.num()?);
+        let retired_single_bit = f.num()?;
+        let retired_double_bit = f.num()?;
+        let retired_pending = f.text();
+        let retired_pages = match (retired_single_bit, retired_double_bit) {
+            (Some(single_bit), Some(double_bit)) => Some(GpuRetiredPages {
+                single_bit,
+                double_bit,
+                pending: retired_pending == "Yes",
+            }),
+            _ => None,
+        };
         
         // PCIe fields (removed domain, bus, device as they're not valid query fields)
-        let pcie_gen_current = parse_optional_u32(field_iter.next().map_or("", |v| v));
-        let pcie_gen_max = parse_optional_u32(field_iter.next().map_or("", |v| v));
-        let pcie_width_current = parse_optional_u32(field_iter.next().map_or("", |v| v));
-        let pcie_width_max = parse_optional_u32(field_iter.next().map_or("", |v| v));
+        let pcie_gen_current = f.num()?;
+        let pcie_gen_max = f.num()?;
+        let pcie_width_current = f.num()?;
+        let pcie_width_max = f.num()?;
         
         // Other fields
-        let fan_speed = parse_optional_u32(field_iter.next().map_or("", |v| v));
-        let display_mode = field_iter.next().map_or("", |v| v).to_string();
-        let persistence_mode = field_iter.next().map_or("", |v| v).to_string();
-        let compute_mode = field_iter.next().map_or("", |v| v).to_string();
-        let index = parse_optional_u32(field_iter.next().map_or("0", |v| v)).unwrap_or(0);
+        let fan_speed = f.num()?;
+        let display_mode = f.text();
+        let persistence_mode = f.text();
+        let compute_mode = f.text();
+        let index = f.num()?.unwrap_or(0);
+        // the old name, newer drivers still know it as an alias of clocks_event_reasons.active
+        let throttle_reasons = f.at("clocks_throttle_reasons.active").hex()?.map(ThrottleReasons);
+        let mig_mode = f.at("mig.mode.current").text();
+        let encoder_sessions = f.at("encoder.stats.sessionCount").num()?;
+        let encoder_fps = f.at("encoder.stats.averageFps").num()?;
+        let encoder_latency = f.at("encoder.stats.averageLatency").num()?;
+        let encoder_stats = encoder_sessions.map(|session_count| GpuEncoderStats {
+            session_count,
+            average_fps: encoder_fps.unwrap_or(0),
+            average_latency: encoder_latency.unwrap_or(0),
+        });
+        let remapped_correctable = f.at("remapped_rows.correctable").num()?;
+        let remapped_uncorrectable = f.at("remapped_rows.uncorrectable").num()?;
+        let remapped_pending = f.at("remapped_rows.pending").text();
+        let remapped_failure = f.at("remapped_rows.failure").text();
+        let remapped_rows = match (remapped_correctable, remapped_uncorrectable) {
+            (Some(correctable), Some(uncorrectable)) => Some(GpuRemappedRows {
+                correctable,
+                uncorrectable,
+                pending: matches!(remapped_pending.as_str(), "Yes" | "1"),
+                failure: matches!(remapped_failure.as_str(), "Yes" | "1"),
+            }),
+            _ => None,
+        };
 
         Ok(GpuInfo {
             index,
@@ -295,6 +568,7 @@ impl NvidiaSmiMonitor {
                 decoder: decoder_util,
                 jpeg: jpeg_util,
                 ofa: ofa_util,
+                ..GpuUtilization::default()
             },
             temperature: GpuTemperature {
                 gpu: temp_gpu,
@@ -326,10 +600,10 @@ impl NvidiaSmiMonitor {
             ecc: GpuEcc {
                 mode_current: ecc_mode_current,
                 mode_pending: ecc_mode_pending,
-                errors_corrected_volatile: GpuEccErrors::default(),
-                errors_uncorrected_volatile: GpuEccErrors::default(),
-                errors_corrected_aggregate: GpuEccErrors::default(),
-                errors_uncorrected_aggregate: GpuEccErrors::default(),
+                errors_corrected_volatile: ecc_corrected_volatile,
+                errors_uncorrected_volatile: ecc_uncorrected_volatile,
+                errors_corrected_aggregate: ecc_corrected_aggregate,
+                errors_uncorrected_aggregate: ecc_uncorrected_aggregate,
             },
             pcie: GpuPcie {
                 gen_current: pcie_gen_current,
@@ -339,17 +613,24 @@ impl NvidiaSmiMonitor {
                 domain: None, // Not available via nvidia-smi query
                 bus: None,    // Not available via nvidia-smi query
                 device: None, // Not available via nvidia-smi query
+                ..GpuPcie::default() // throughput comes from dmon
             },
             fan_speed,
             display_mode,
             persistence_mode,
             compute_mode,
             timestamp,
+            throttle_reasons,
+            retired_pages,
+            remapped_rows,
+            mig_mode,
+            encoder_stats,
+            ..GpuInfo::default()
         })
     }
 
     /// Get GPU processes
-    fn get_gpu_processes(&self) -> Result<Vec<GpuProcess>, String> {
+    fn get_gpu_processes(&self) -> Result<Vec<GpuProcess>, GpuError> {
         // Use nvidia-smi pmon to get process information
         let output = self.execute_command(&[
             "pmon", "-c", "1", "-s", "u"
@@ -387,12 +668,21 @@ impl NvidiaSmiMonitor {
                         // We could potentially get this from a separate query if needed
                         let used_memory = 0u64;
                         
+                        // enc and dec are "-" when the process does not use them
+                        let encoder_utilization = fields[5].parse().ok();
+                        let decoder_utilization = fields[6].parse().ok();
+
                         let process = GpuProcess {
                             pid,
                             process_name,
                             gpu_uuid: String::new(), // Not available in pmon output
                             used_memory,
                             gpu_index,
+                            utilization: None,
+                            gpu_instance_id: None,
+                            compute_instance_id: None,
+                            encoder_utilization,
+                            decoder_utilization,
                         };
                         
                         log::debug!("Found GPU process: GPU {}, PID {}, Name: {}, Type: {}", 
@@ -418,10 +708,15 @@ impl NvidiaSmiMonitor {
         self.available
     }
 
-    /// Get the last error message
-    pub fn get_last_error(&self) -> Option<&String> {
+    /// Get the last error
+    pub fn get_last_error(&self) -> Option<&GpuError> {
         self.last_error.as_ref()
     }
+
+    /// Health of nvidia-smi: healthy, or degraded since the first failure of an outage
+    pub fn health(&self) -> &GpuHealth {
+        self.backoff.health()
+    }
 }
 
 impl Default for NvidiaSmiMonitor {
@@ -430,6 +725,114 @@ impl Default for NvidiaSmiMonitor {
     }
 }
 
+impl GpuBackend for NvidiaSmiMonitor {
+    fn name(&self) -> &'static str {
+        if self.runner.is_replay() {
+            "nvidia-smi replay"
+        } else {
+            "nvidia-smi"
+        }
+    }
+
+    fn get_gpu_info(&mut self) -> Result<GpuReadings, GpuError> {
+        NvidiaSmiMonitor::get_gpu_info(self)
+    }
+
+    fn health(&self) -> &GpuHealth {
+        NvidiaSmiMonitor::health(self)
+    }
+}
+
+/// Result of a query adding to the main one: failing is only logged, except for a hung nvidia-smi -
+/// that fails the whole reading.
+fn extra<T>(what: &str, result: Result<T, GpuError>) -> Result<Option<T>, GpuError> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e @ GpuError::Timeout { .. }) => Err(e),
+        Err(e) => {
+            log::debug!("nvidia-smi {}: {}", what, e);
+            Ok(None)
+        }
+    }
+}
+
+/// Every field the main query may ask for
+fn full_query() -> Vec<&'static str> {
+    GPU_QUERY.iter().chain(&OPTIONAL_GPU_QUERY).copied().collect()
+}
+
+fn parse_count(output: &str) -> Result<u32, GpuError> {
+    // one line per GPU, all the same
+    let value = output.lines().next().unwrap_or_default().trim();
+    value.parse().map_err(|_| GpuError::Parse {
+        field: "count".to_string(),
+        value: value.to_string(),
+    })
+}
+
+/// Values of one CSV line together with the names of queried fields, for errors that say which field broke
+struct CsvFields<'a> {
+    names: &'a [&'a str],
+    values: &'a [&'a str],
+    /// Position of the next value
+    next: usize,
+}
+
+impl<'a> CsvFields<'a> {
+    fn new(names: &'a [&'a str], values: &'a [&'a str]) -> Self {
+        Self { names, values, next: 0 }
+    }
+
+    /// Moves to the field called `name`, the next value read is its own - none if it was not queried
+    fn at(&mut self, name: &str) -> &mut Self {
+        self.next = self.names.iter().position(|n| *n == name).unwrap_or(self.names.len());
+        self
+    }
+
+    /// Name and value of the next field
+    fn field(&mut self) -> Option<(&'a str, &'a str)> {
+        let field = self.names.get(self.next).zip(self.values.get(self.next));
+        self.next += 1;
+        field.map(|(name, value)| (*name, *value))
+    }
+
+    /// Next value as text
+    fn text(&mut self) -> String {
+        self.field().map_or(String::new(), |(_, v)| v.trim().to_string())
+    }
+
+    /// Next value as number - N/A and "[Not Supported]" are `None`, anything else unparsable is an error
+    fn num<T: FromStr>(&mut self) -> Result<Option<T>, GpuError> {
+        let Some((name, value)) = self.field() else {
+            return Ok(None);
+        };
+        let value = value.trim();
+        if value.is_empty() || value.contains("N/A") || value.contains("Not Supported") {
+            return Ok(None);
+        }
+        value.parse().map(Some).map_err(|_| GpuError::Parse {
+            field: name.to_string(),
+            value: value.to_string(),
+        })
+    }
+
+    /// Next value as a hex bitmask like `0x0000000000000004`, `None` like `num`
+    fn hex(&mut self) -> Result<Option<u64>, GpuError> {
+        let Some((name, value)) = self.field() else {
+            return Ok(None);
+        };
+        let value = value.trim();
+        if value.is_empty() || value.contains("N/A") || value.contains("Not Supported") {
+            return Ok(None);
+        }
+        let digits = value.trim_start_matches("0x").trim_start_matches("0X");
+        u64::from_str_radix(digits, 16).map(Some).map_err(|_| GpuError::Parse {
+                field: name.to_string(),
+                value: value.to_string(),
+            })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -447,6 +850,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(unused_comparisons, clippy::absurd_extreme_comparisons)]
     fn test_gpu_count() {
         let monitor = NvidiaSmiMonitor::new();
         if monitor.is_available() {
@@ -465,7 +869,7 @@ mod tests {
 
     #[test]
     fn test_gpu_info_parsing() {
-        let monitor = NvidiaSmiMonitor::new();
+        let mut monitor = NvidiaSmiMonitor::new();
         if monitor.is_available() {
             match monitor.get_gpu_info() {
                 Ok(readings) => {
@@ -491,4 +895,298 @@ mod tests {
             }
         }
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hung_nvidia_smi_is_killed_and_backed_off() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nvidia-smi");
+        std::fs::write(&path, "#!/bin/sh\nsleep 10\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut monitor = NvidiaSmiMonitor {
+            runner: ToolRunner::new(&path.to_string_lossy(), Duration::from_millis(100)),
+            available: true,
+            last_error: None,
+            backoff: GpuBackoff::new(),
+            query: full_query(),
+            report_interval: None,
+            report: None,
+            report_asked: None,
+            mig: None,
+            pcie: None,
+            pcie_off: false,
+            nvlink: NvLinkTracker::default(),
+            topology: None,
+            accounting: false,
+            accounting_failed: false,
+        };
+
+        let started = Instant::now();
+        let err = monitor.get_gpu_info().unwrap_err();
+        assert!(matches!(err, GpuError::Timeout { .. }), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(!monitor.health().is_healthy());
+        assert_eq!(monitor.get_last_error(), Some(&err));
+
+        // next call does not even run it
+        let started = Instant::now();
+        assert!(matches!(monitor.get_gpu_info().unwrap_err(), GpuError::BackingOff(_)));
+        assert!(started.elapsed() < Duration::from_millis(50));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hung_extra_query_backs_off() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nvidia-smi");
+        let line = vec!["1"; GPU_QUERY.len() + OPTIONAL_GPU_QUERY.len()].join(", ");
+        let script = format!(
+            "#!/bin/sh\ncase \"$1\" in\n\
+             --query-gpu=count) echo 1 ;;\n\
+             --query-gpu) echo '{}' ;;\n\
+             nvlink) exec sleep 10 ;;\n\
+             esac\n",
+            line
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut monitor =
+            NvidiaSmiMonitor::with_runner(ToolRunner::new(&path.to_string_lossy(), Duration::from_millis(300)));
+        assert!(monitor.is_available(), "{:?}", monitor.get_last_error());
+        let err = monitor.get_gpu_info().unwrap_err();
+        assert!(matches!(err, GpuError::Timeout { .. }), "{}", err);
+        assert!(!monitor.health().is_healthy());
+
+        // neither the main query nor nvlink run while backing off
+        let started = Instant::now();
+        assert!(matches!(monitor.get_gpu_info().unwrap_err(), GpuError::BackingOff(_)));
+        assert!(started.elapsed() < Duration::from_millis(50));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pcie_throughput_streams_from_one_dmon() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nvidia-smi");
+        let starts = dir.path().join("dmon-starts");
+        let line = vec!["1"; GPU_QUERY.len() + OPTIONAL_GPU_QUERY.len()].join(", ");
+        let script = format!(
+            "#!/bin/sh\ncase \"$1\" in\n\
+             --query-gpu=count) echo 1 ;;\n\
+             --query-gpu) echo '{}' ;;\n\
+             dmon) echo started >> '{}'; echo '# gpu rxpci txpci'; echo '# Idx MB/s MB/s';\n\
+             while true; do echo '    1   310    42'; sleep 0.05; done ;;\n\
+             esac\n",
+            line,
+            starts.display()
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let runner = ToolRunner::new(&path.to_string_lossy(), Duration::from_secs(5));
+        let mut monitor = NvidiaSmiMonitor::with_runner(runner);
+        monitor.set_report_interval(None);
+        let begin = Instant::now();
+        loop {
+            let readings = monitor.get_gpu_info().unwrap();
+            if readings.gpus[0].pcie.rx_throughput == Some(310.0) {
+                assert_eq!(readings.gpus[0].pcie.tx_throughput, Some(42.0));
+                break;
+            }
+            assert!(begin.elapsed() < Duration::from_secs(5), "no PCIe throughput from dmon");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        monitor.get_gpu_info().unwrap();
+        assert_eq!(std::fs::read_to_string(&starts).unwrap().lines().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_encoder_sessions_only_while_encoding() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nvidia-smi");
+        let asked = dir.path().join("encodersessions");
+        let sessions = dir.path().join("sessions");
+        std::fs::write(&sessions, "0").unwrap();
+        // GPU 1, with as many sessions as the file says
+        let line = full_query()
+            .iter()
+            .map(|name| if *name == "encoder.stats.sessionCount" { "'\"$(cat sessions)\"'" } else { "1" })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let script = format!(
+            "#!/bin/sh\ncd '{}'\ncase \"$1\" in\n\
+             --query-gpu=count) echo 1 ;;\n\
+             --query-gpu) echo '{}' ;;\n\
+             encodersessions) echo asked >> '{}'; echo '# GPU Session Process Codec H V FPS Latency';\n\
+             echo '    0       3    4000  H264 1280 720 60 800';\n\
+             echo '    1       7    4242  HEVC 1920 1080 30 1253' ;;\n\
+             esac\n",
+            dir.path().display(),
+            line,
+            asked.display()
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let runner = ToolRunner::new(&path.to_string_lossy(), Duration::from_secs(5));
+        let mut monitor = NvidiaSmiMonitor::with_runner(runner);
+        monitor.set_report_interval(None);
+        for _ in 0..2 {
+            assert!(monitor.get_gpu_info().unwrap().encoder_sessions.is_empty());
+        }
+        assert!(!asked.exists());
+
+        std::fs::write(&sessions, "1").unwrap();
+        let readings = monitor.get_gpu_info().unwrap();
+        // only the sessions of the GPU that says it encodes
+        let pids: Vec<u32> = readings.encoder_sessions.iter().map(|s| s.pid).collect();
+        assert_eq!(pids, vec![4242]);
+        assert_eq!(std::fs::read_to_string(&asked).unwrap().lines().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rejected_optional_field_is_left_out() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nvidia-smi");
+        // a driver without MIG that reports the clocks as throttled by the power cap
+        let line = full_query()
+            .iter()
+            .filter(|name| **name != "mig.mode.current")
+            .map(|name| if name.starts_with("clocks_throttle") { "0x4" } else { "1" })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let script = format!(
+            "#!/bin/sh\ncase \"$1\" in\n\
+             --query-gpu=count) echo 1 ;;\n\
+             --query-gpu) case \"$2\" in\n\
+             *mig.mode.current*) echo 'Field \"mig.mode.current\" is not a valid field to query.'; exit 2 ;;\n\
+             *) echo '{}' ;;\n\
+             esac ;;\n\
+             esac\n",
+            line
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let runner = ToolRunner::new(&path.to_string_lossy(), Duration::from_secs(5));
+        let mut monitor = NvidiaSmiMonitor::with_runner(runner);
+        monitor.set_report_interval(None);
+        assert!(!monitor.query.contains(&"mig.mode.current"));
+        let readings = monitor.get_gpu_info().unwrap();
+        let gpu = &readings.gpus[0];
+        assert_eq!(gpu.mig_mode, "");
+        assert_eq!(gpu.throttle_reasons, Some(ThrottleReasons(ThrottleReasons::SW_POWER_CAP)));
+        assert_eq!(gpu.encoder_stats.map(|stats| stats.session_count), Some(1));
+        assert_eq!(gpu.remapped_rows.as_ref().map(|rows| (rows.correctable, rows.pending)), Some((1, true)));
+        assert_eq!(gpu.report_age_secs, None);
+    }
+
+    #[test]
+    fn test_errors_say_what_broke() {
+        let query = ["name", "memory.used", "power.draw"];
+        let mut fields = CsvFields::new(&query, &[" A100", " 1024", " [N/A]"]);
+        assert_eq!(fields.text(), "A100");
+        assert_eq!(fields.num::<u64>(), Ok(Some(1024)));
+        assert_eq!(fields.num::<f32>(), Ok(None));
+
+        let reasons = ["clocks_throttle_reasons.active"; 3];
+        let mut fields = CsvFields::new(&reasons, &[" 0x0000000000000044", " [N/A]", " Active"]);
+        assert_eq!(fields.hex(), Ok(Some(ThrottleReasons::SW_POWER_CAP | ThrottleReasons::HW_THERMAL_SLOWDOWN)));
+        assert_eq!(fields.hex(), Ok(None));
+        assert!(matches!(fields.hex(), Err(GpuError::Parse { .. })));
+        // bits above the 32 nvidia-smi knows today are kept, not cut off
+        let mut fields = CsvFields::new(&reasons[..1], &[" 0x0000000100000004"]);
+        assert_eq!(fields.hex(), Ok(Some(1 << 32 | ThrottleReasons::SW_POWER_CAP)));
+        // optional fields are found by name, or are not there
+        let mut fields = CsvFields::new(&query, &[" A100", " 1024", " 250.5"]);
+        assert_eq!(fields.at("power.draw").num::<f32>(), Ok(Some(250.5)));
+        assert_eq!(fields.at("mig.mode.current").text(), "");
+
+        let mut fields = CsvFields::new(&query[1..], &["12 MiB"]);
+        assert_eq!(
+            fields.num::<u64>(),
+            Err(GpuError::Parse {
+                field: "memory.used".to_string(),
+                value: "12 MiB".to_string()
+            })
+        );
+
+        assert!(matches!(parse_count("2\n2\n"), Ok(2)));
+        assert!(GpuError::UnsupportedField("x".to_string()).is_permanent());
+        assert!(!GpuError::BackingOff(Duration::ZERO).is_permanent());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_capture_and_replay() {
+        use crate::trace::datastreams::raw_capture::RawCapture;
+        use std::os::unix::fs::PermissionsExt;
+        use std::sync::{Arc, Mutex};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nvidia-smi");
+        let line = vec!["1"; GPU_QUERY.len() + OPTIONAL_GPU_QUERY.len()].join(", ");
+        let script = format!(
+            "#!/bin/sh\ncase \"$1\" in\n\
+             --query-gpu=count) echo 1 ;;\n\
+             --query-gpu) echo '{}' ;;\n\
+             pmon) echo '# gpu pid type sm mem enc dec jpg ofa command'; \
+             echo '    1   4242     C    90    40    12     -     -     -  python' ;;\n\
+             encodersessions) echo '# GPU Session Process Codec H V FPS Latency'; \
+             echo '    1       7    4242  HEVC 1920 1080 30 1253' ;;\n\
+             dmon) echo '# gpu rxpci txpci'; echo '    1   310    42' ;;\n\
+             --query-accounted-apps=*) echo '1, 4250, 80, 20, 512, 180' ;;\n\
+             topo) printf '\\tGPU1\\tCPU Affinity\\tNUMA Affinity\\nGPU1\\t X \\t0-7\\t0\\n' ;;\n\
+             esac\n",
+            line
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let capture_dir = dir.path().join("capture");
+        let capture = Arc::new(Mutex::new(RawCapture::create(&capture_dir).unwrap()));
+        let runner = ToolRunner::new(&path.to_string_lossy(), Duration::from_secs(5)).capturing(capture);
+        let mut monitor = NvidiaSmiMonitor::with_runner(runner);
+        monitor.set_accounting(true);
+        assert!(monitor.is_available(), "{:?}", monitor.get_last_error());
+        let live: Vec<GpuReadings> = (0..2).map(|_| monitor.get_gpu_info().unwrap()).collect();
+        let lines = std::fs::read_to_string(capture_dir.join(crate::trace::datastreams::INVOCATIONS_FILE)).unwrap();
+        // availability and probing the query, then count, query, pmon, dmon, encoder sessions and accounted
+        // processes per reading, NVLink until it has no links, topology and the XML report once
+        assert_eq!(lines.lines().count(), 2 + 2 * 6 + 1 + 1 + 1);
+
+        // the real binary is gone - replay does not need it
+        std::fs::remove_file(&path).unwrap();
+        let mut replay = NvidiaSmiMonitor::with_runner(ToolRunner::replay("nvidia-smi", &capture_dir).unwrap());
+        replay.set_accounting(true);
+        assert!(replay.is_available());
+        assert_eq!(GpuBackend::name(&replay), "nvidia-smi replay");
+        for expected in &live {
+            let readings = replay.get_gpu_info().unwrap();
+            assert_eq!(readings.gpus[0].memory.used, expected.gpus[0].memory.used);
+            assert_eq!(readings.processes[0].pid, 4242);
+            assert_eq!(readings.gpus[0].pcie.rx_throughput, Some(310.0));
+            assert_eq!(readings.gpus[0].numa_node, Some(0));
+            assert_eq!(readings.processes[0].encoder_utilization, Some(12));
+            assert_eq!(readings.get_encoder_sessions(1, 4242)[0].codec, "HEVC");
+            let accounted = &readings.accounted_processes[0];
+            assert_eq!((accounted.pid, accounted.max_memory_usage), (4250, Some(512)));
+            assert!(!accounted.is_running);
+        }
+        assert!(matches!(replay.get_gpu_info(), Err(GpuError::Io(_))));
+    }
 }
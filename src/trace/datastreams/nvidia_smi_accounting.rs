@@ -0,0 +1,106 @@
+use crate::error::GpuError;
+use crate::trace::datastreams::gpu_data::{GpuAccountedProcess, GpuReadings};
+
+/// Processes recorded by accounting mode, also the ones that exited since. Needs accounting on
+/// (`nvidia-smi -am 1`, as root).
+pub const ACCOUNTED_APPS_ARGS: [&str; 2] = [
+    "--query-accounted-apps=gpu_uuid,pid,gpu_utilization,mem_utilization,max_memory_usage,time",
+    "--format=csv,noheader,nounits",
+];
+
+/// One line of `--query-accounted-apps`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccountedApp {
+    pub gpu_uuid: String,
+    pub process: GpuAccountedProcess,
+}
+
+/// `GPU-3f0c..., 4242, 87, 31, 20480, 5230` per process - utilization averaged over its lifetime,
+/// peak memory in MiB and run time in ms. No lines when nothing was accounted.
+pub fn parse_accounted_apps(output: &str) -> Result<Vec<AccountedApp>, GpuError> {
+    let mut apps = Vec::new();
+    for line in output.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if fields.len() < 6 {
+            return Err(GpuError::Parse {
+                field: "nvidia-smi --query-accounted-apps".to_string(),
+                value: line.to_string(),
+            });
+        }
+        let num = |i: usize, name: &str| -> Result<Option<u64>, GpuError> {
+            let value = fields[i];
+            if value.is_empty() || value.contains("N/A") || value.contains("Not Supported") {
+                return Ok(None);
+            }
+            value.parse().map(Some).map_err(|_| GpuError::Parse {
+                field: format!("accounted {}", name),
+                value: value.to_string(),
+            })
+        };
+        apps.push(AccountedApp {
+            gpu_uuid: fields[0].to_string(),
+            process: GpuAccountedProcess {
+                pid: num(1, "pid")?.unwrap_or(0) as u32,
+                gpu_index: 0,
+                gpu_utilization: num(2, "gpu_utilization")?.map(|u| u as u32),
+                memory_utilization: num(3, "mem_utilization")?.map(|u| u as u32),
+                max_memory_usage: num(4, "max_memory_usage")?,
+                time_ms: num(5, "time")?,
+                is_running: false,
+            },
+        });
+    }
+    Ok(apps)
+}
+
+/// Replaces the accounted processes of `readings` with `apps` on known GPUs. The query does not
+/// say which are still running - those among the GPU's processes are.
+pub fn merge_into(apps: &[AccountedApp], readings: &mut GpuReadings) {
+    readings.accounted_processes = apps
+        .iter()
+        .filter_map(|app| {
+            let gpu = readings.get_gpu_by_uuid(&app.gpu_uuid)?;
+            let is_running = readings
+                .get_gpu_processes(gpu.index)
+                .iter()
+                .any(|p| p.pid == app.process.pid);
+            Some(GpuAccountedProcess {
+                gpu_index: gpu.index,
+                is_running,
+                ..app.process.clone()
+            })
+        })
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::datastreams::gpu_data::{GpuInfo, GpuProcess};
+
+    #[test]
+    fn test_accounted_apps() {
+        let output = "GPU-0b1c, 4242, 87, 31, 20480, 5230\n\
+                      GPU-0b1c, 4250, [N/A], [N/A], 512, 180\n\
+                      GPU-ffff, 4300, 10, 5, 100, 10\n";
+        let apps = parse_accounted_apps(output).unwrap();
+        assert_eq!(apps.len(), 3);
+        assert_eq!(apps[1].process.gpu_utilization, None);
+        assert_eq!(apps[1].process.time_ms, Some(180));
+
+        let mut readings = GpuReadings::new();
+        readings.gpus.push(GpuInfo { index: 2, uuid: "GPU-0b1c".to_string(), ..GpuInfo::default() });
+        readings.processes.push(GpuProcess { pid: 4242, gpu_index: 2, ..GpuProcess::default() });
+        merge_into(&apps, &mut readings);
+        let accounted: Vec<(u32, u32, bool)> = readings
+            .accounted_processes
+            .iter()
+            .map(|p| (p.pid, p.gpu_index, p.is_running))
+            .collect();
+        assert_eq!(accounted, vec![(4242, 2, true), (4250, 2, false)]);
+
+        assert!(parse_accounted_apps("").unwrap().is_empty());
+        assert!(parse_accounted_apps("Accounting mode is disabled").is_err());
+        assert!(parse_accounted_apps("GPU-0b1c, 4242, 87%, 31, 20480, 5230").is_err());
+    }
+}
@@ -0,0 +1,81 @@
+use crate::error::GpuError;
+use crate::trace::datastreams::gpu_data::GpuEncoderSession;
+
+/// NVENC sessions of all GPUs, one line each
+pub const ENCODER_SESSIONS_ARGS: [&str; 1] = ["encodersessions"];
+
+/// `nvidia-smi encodersessions`, whitespace separated under `#` headers:
+/// ```text
+/// # GPU  Session   Process    Codec       H       V  Average  Average
+/// # Idx       Id        Id     Type     Res     Res      FPS  Latency(us)
+///     0        1     21512    H.264    1920    1080       30     1253
+///     1        -         -        -       -       -        -        -
+/// ```
+/// GPUs without sessions get a line of dashes, which is skipped.
+pub fn parse_encoder_sessions(output: &str) -> Result<Vec<GpuEncoderSession>, GpuError> {
+    let mut sessions = Vec::new();
+    for line in output.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 8 {
+            return Err(GpuError::Parse {
+                field: "nvidia-smi encodersessions".to_string(),
+                value: line.to_string(),
+            });
+        }
+        if fields[1] == "-" {
+            continue;
+        }
+        let num = |i: usize, name: &str| {
+            fields[i].parse::<u32>().map_err(|_| GpuError::Parse {
+                field: format!("nvidia-smi encodersessions {}", name),
+                value: fields[i].to_string(),
+            })
+        };
+        sessions.push(GpuEncoderSession {
+            gpu_index: num(0, "GPU")?,
+            session_id: num(1, "session")?,
+            pid: num(2, "process")?,
+            codec: fields[3].to_string(),
+            h_resolution: num(4, "H res")?,
+            v_resolution: num(5, "V res")?,
+            // 0 rather than a dash while the session has not encoded a frame yet
+            average_fps: fields[6].parse().unwrap_or(0),
+            average_latency: fields[7].parse().unwrap_or(0),
+        });
+    }
+    Ok(sessions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SESSIONS: &str =
+        include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nvidia-smi/encodersessions.txt"));
+
+    #[test]
+    fn test_encoder_sessions() {
+        let sessions = parse_encoder_sessions(SESSIONS).unwrap();
+        assert_eq!(sessions.len(), 3);
+        assert_eq!(
+            sessions[1],
+            GpuEncoderSession {
+                gpu_index: 0,
+                session_id: 2,
+                pid: 21512,
+                codec: "HEVC".to_string(),
+                h_resolution: 3840,
+                v_resolution: 2160,
+                average_fps: 24,
+                average_latency: 4120,
+            }
+        );
+        assert_eq!(sessions[2].codec, "AV1");
+
+        assert!(parse_encoder_sessions("").unwrap().is_empty());
+        assert!(parse_encoder_sessions("Failed to initialize NVML: Driver/library version mismatch").is_err());
+    }
+}
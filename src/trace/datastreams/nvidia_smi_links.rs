@@ -0,0 +1,305 @@
+use crate::error::GpuError;
+use crate::trace::datastreams::gpu_data::{GpuNvLink, GpuReadings};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::Instant;
+
+/// One sample of PCIe throughput per GPU
+pub const PCIE_THROUGHPUT_ARGS: [&str; 5] = ["dmon", "-c", "1", "-s", "t"];
+/// PCIe throughput per GPU every second, until killed
+pub const PCIE_STREAM_ARGS: [&str; 5] = ["dmon", "-d", "1", "-s", "t"];
+/// Data sent and received per NVLink link since the driver was loaded
+pub const NVLINK_THROUGHPUT_ARGS: [&str; 3] = ["nvlink", "-gt", "d"];
+/// Error counters per NVLink link
+pub const NVLINK_ERRORS_ARGS: [&str; 2] = ["nvlink", "-e"];
+
+/// PCIe throughput of one GPU in MB/s
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PcieThroughput {
+    pub gpu_index: u32,
+    pub rx: Option<f64>,
+    pub tx: Option<f64>,
+}
+
+/// `nvidia-smi dmon -s t` kept running - a single `dmon -c 1` takes a second to sample, too long to
+/// fork with every reading. Rows come in on a reader thread, the latest of each GPU is kept.
+pub struct PcieStream {
+    child: Child,
+    rows: Receiver<PcieThroughput>,
+    latest: BTreeMap<u32, PcieThroughput>,
+}
+
+impl PcieStream {
+    pub fn spawn(program: &str) -> io::Result<Self> {
+        let mut child = Command::new(program)
+            .args(PCIE_STREAM_ARGS)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdout = child.stdout.take();
+        let (tx, rows) = mpsc::channel();
+        thread::spawn(move || {
+            let Some(stdout) = stdout else {
+                return;
+            };
+            // the first header names the columns, dmon repeats it every so often
+            let mut header = String::new();
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if line.trim_start().starts_with('#') {
+                    if header.is_empty() {
+                        header = line;
+                    }
+                    continue;
+                }
+                let rows = match parse_pcie_throughput(&format!("{}\n{}", header, line)) {
+                    Ok(rows) => rows,
+                    Err(e) => {
+                        log::debug!("nvidia-smi dmon: {}", e);
+                        continue;
+                    }
+                };
+                if rows.into_iter().try_for_each(|row| tx.send(row)).is_err() {
+                    return; // nobody is listening
+                }
+            }
+        });
+        Ok(Self {
+            child,
+            rows,
+            latest: BTreeMap::new(),
+        })
+    }
+
+    /// Latest throughput of every GPU that reported any, `Err` once dmon exited
+    pub fn latest(&mut self) -> Result<Vec<PcieThroughput>, GpuError> {
+        loop {
+            match self.rows.try_recv() {
+                Ok(row) => {
+                    self.latest.insert(row.gpu_index, row);
+                }
+                Err(TryRecvError::Empty) => return Ok(self.latest.values().copied().collect()),
+                Err(TryRecvError::Disconnected) => {
+                    let status = self.child.wait();
+                    return Err(GpuError::ExitStatus {
+                        command: "nvidia-smi dmon".to_string(),
+                        code: status.ok().and_then(|s| s.code()),
+                        stderr: String::new(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Whether any row came in yet
+    pub fn has_rows(&self) -> bool {
+        !self.latest.is_empty()
+    }
+}
+
+impl Drop for PcieStream {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Counters of one NVLink link, by name as nvidia-smi prints them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NvLinkCounters {
+    pub gpu_uuid: String,
+    pub link: u32,
+    pub counters: BTreeMap<String, u64>,
+}
+
+/// Turns cumulative NVLink data counters into throughput, and remembers whether there are links
+/// at all - GPUs without them are not asked again.
+#[derive(Debug, Default)]
+pub struct NvLinkTracker {
+    previous: Option<(Instant, Vec<NvLinkCounters>)>,
+    has_links: Option<bool>,
+}
+
+impl NvLinkTracker {
+    /// Whether nvidia-smi is worth asking about NVLink
+    pub fn wanted(&self) -> bool {
+        self.has_links != Some(false)
+    }
+
+    /// Adds links with their throughput since the previous call and their errors to `readings`
+    pub fn merge_into(&mut self, now: Instant, data: Vec<NvLinkCounters>, errors: &[NvLinkCounters], readings: &mut GpuReadings) {
+        self.has_links = Some(!data.is_empty());
+        for gpu in readings.gpus.iter_mut() {
+            gpu.nvlinks = data
+                .iter()
+                .filter(|c| c.gpu_uuid == gpu.uuid)
+                .map(|c| {
+                    let rate = |name: &str| self.rate(now, c, name);
+                    GpuNvLink {
+                        link: c.link,
+                        tx_throughput: rate("Data Tx"),
+                        rx_throughput: rate("Data Rx"),
+                        errors: errors
+                            .iter()
+                            .find(|e| e.gpu_uuid == c.gpu_uuid && e.link == c.link)
+                            .map_or(BTreeMap::new(), |e| e.counters.clone()),
+                    }
+                })
+                .collect();
+        }
+        self.previous = Some((now, data));
+    }
+
+    /// MB/s of a KiB counter since the previous call, `None` the first time or when it went back
+    fn rate(&self, now: Instant, counters: &NvLinkCounters, name: &str) -> Option<f64> {
+        let (then, previous) = self.previous.as_ref()?;
+        let before = previous
+            .iter()
+            .find(|p| p.gpu_uuid == counters.gpu_uuid && p.link == counters.link)?
+            .counters
+            .get(name)?;
+        let secs = now.duration_since(*then).as_secs_f64();
+        let kib = counters.counters.get(name)?.checked_sub(*before)?;
+        (secs > 0.0).then(|| kib as f64 / 1024.0 / secs)
+    }
+}
+
+/// `nvidia-smi dmon -s t` with columns named in the first header line:
+/// ```text
+/// # gpu  rxpci  txpci
+/// # Idx   MB/s   MB/s
+///     0     24     51
+/// ```
+pub fn parse_pcie_throughput(output: &str) -> Result<Vec<PcieThroughput>, GpuError> {
+    let mut columns: Vec<&str> = Vec::new();
+    let mut rows = Vec::new();
+    for line in output.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(header) = line.strip_prefix('#') {
+            if columns.is_empty() {
+                columns = header.split_whitespace().collect();
+            }
+            continue;
+        }
+        let values: Vec<&str> = line.split_whitespace().collect();
+        let value = |name: &str| -> Result<Option<f64>, GpuError> {
+            let Some(value) = columns.iter().position(|c| *c == name).and_then(|i| values.get(i)) else {
+                return Ok(None);
+            };
+            if *value == "-" {
+                return Ok(None);
+            }
+            value.parse().map(Some).map_err(|_| GpuError::Parse {
+                field: format!("nvidia-smi dmon {}", name),
+                value: value.to_string(),
+            })
+        };
+        let gpu_index = value("gpu")?.ok_or_else(|| GpuError::Parse {
+            field: "nvidia-smi dmon gpu".to_string(),
+            value: line.to_string(),
+        })?;
+        rows.push(PcieThroughput {
+            gpu_index: gpu_index as u32,
+            rx: value("rxpci")?,
+            tx: value("txpci")?,
+        });
+    }
+    Ok(rows)
+}
+
+/// Per-link counters of `nvidia-smi nvlink -gt d` or `-e`, which look like
+/// ```text
+/// GPU 0: NVIDIA A100-SXM4-40GB (UUID: GPU-5d1c9a3e-...)
+///      Link 0: Data Tx: 140523 KiB
+///      Link 0: Replay Errors: 0
+/// ```
+pub fn parse_nvlink(output: &str) -> Result<Vec<NvLinkCounters>, GpuError> {
+    let mut gpu_uuid = None;
+    let mut links: Vec<NvLinkCounters> = Vec::new();
+    for line in output.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if line.starts_with("GPU ") {
+            gpu_uuid = line
+                .rsplit_once("(UUID: ")
+                .map(|(_, rest)| rest.trim_end_matches(')').to_string());
+            continue;
+        }
+        let Some(rest) = line.strip_prefix("Link ") else {
+            continue;
+        };
+        let parse_error = || GpuError::Parse {
+            field: "nvidia-smi nvlink".to_string(),
+            value: line.to_string(),
+        };
+        let mut parts = rest.splitn(3, ':').map(str::trim);
+        let (Some(link), Some(name), Some(value)) = (parts.next(), parts.next(), parts.next()) else {
+            // "Link 0: <inactive>" and the like
+            continue;
+        };
+        let link: u32 = link.parse().map_err(|_| parse_error())?;
+        let value: u64 = value
+            .split_whitespace()
+            .next()
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(parse_error)?;
+        let gpu_uuid = gpu_uuid.clone().ok_or_else(parse_error)?;
+        match links.iter_mut().find(|l| l.gpu_uuid == gpu_uuid && l.link == link) {
+            Some(counters) => {
+                counters.counters.insert(name.to_string(), value);
+            }
+            None => links.push(NvLinkCounters {
+                gpu_uuid,
+                link,
+                counters: BTreeMap::from([(name.to_string(), value)]),
+            }),
+        }
+    }
+    Ok(links)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::datastreams::gpu_data::GpuInfo;
+    use std::time::Duration;
+
+    const DMON: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nvidia-smi/dmon_pcie.txt"));
+    const NVLINK_DATA: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nvidia-smi/nvlink_data.txt"));
+    const NVLINK_ERRORS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nvidia-smi/nvlink_errors.txt"));
+
+    #[test]
+    fn test_pcie_and_nvlink_throughput() {
+        let pcie = parse_pcie_throughput(DMON).unwrap();
+        assert_eq!(pcie[1], PcieThroughput { gpu_index: 1, rx: Some(11250.0), tx: Some(24.0) });
+
+        let data = parse_nvlink(NVLINK_DATA).unwrap();
+        let errors = parse_nvlink(NVLINK_ERRORS).unwrap();
+        assert_eq!(data.len(), 4);
+        assert_eq!(errors[1].counters["CRC Errors"], 7);
+
+        let mut readings = GpuReadings::new();
+        readings.gpus.push(GpuInfo {
+            uuid: "GPU-5d1c9a3e-0b7f-4e28-9a61-c2d84f13b7e9".to_string(),
+            ..GpuInfo::default()
+        });
+        let mut tracker = NvLinkTracker::default();
+        let start = Instant::now();
+        tracker.merge_into(start, data.clone(), &errors, &mut readings);
+        assert_eq!(readings.gpus[0].nvlinks.len(), 2);
+        assert_eq!(readings.gpus[0].nvlinks[0].tx_throughput, None);
+        assert_eq!(readings.gpus[0].nvlinks[1].error_count(), 9);
+
+        // two seconds later, 40 MiB more went out of link 0
+        let mut later = data;
+        *later[0].counters.get_mut("Data Tx").unwrap() += 40 * 1024;
+        tracker.merge_into(start + Duration::from_secs(2), later, &errors, &mut readings);
+        assert_eq!(readings.gpus[0].nvlinks[0].tx_throughput, Some(20.0));
+        assert_eq!(readings.gpus[0].nvlinks[0].rx_throughput, Some(0.0));
+        assert!(tracker.wanted());
+
+        tracker.merge_into(start, Vec::new(), &[], &mut readings);
+        assert!(!tracker.wanted());
+        assert!(readings.gpus[0].nvlinks.is_empty());
+    }
+}
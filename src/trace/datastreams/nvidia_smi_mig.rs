@@ -0,0 +1,222 @@
+use crate::error::GpuError;
+use crate::trace::datastreams::gpu_data::{GpuMigDevice, GpuReadings};
+
+/// Lists GPUs and their MIG devices with UUIDs
+pub const MIG_LIST_ARGS: [&str; 1] = ["-L"];
+/// Lists GPU instances
+pub const MIG_GPU_INSTANCES_ARGS: [&str; 2] = ["mig", "-lgi"];
+/// Lists compute instances
+pub const MIG_COMPUTE_INSTANCES_ARGS: [&str; 2] = ["mig", "-lci"];
+
+/// A MIG device line of `nvidia-smi -L`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MigListing {
+    pub gpu_uuid: String,
+    pub device_index: u32,
+    pub profile: String,
+    pub uuid: String,
+}
+
+/// A row of `nvidia-smi mig -lgi` or `-lci` - compute instance ID only in the latter
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MigInstance {
+    pub gpu_index: u32,
+    pub gpu_instance_id: u32,
+    pub compute_instance_id: Option<u32>,
+    pub profile: String,
+}
+
+/// How MIG GPUs are partitioned, as far as nvidia-smi told
+#[derive(Debug, Clone, Default)]
+pub struct MigLayout {
+    pub listed: Vec<MigListing>,
+    pub gpu_instances: Vec<MigInstance>,
+    pub compute_instances: Vec<MigInstance>,
+}
+
+impl MigLayout {
+    /// Names and UUIDs the MIG devices of `readings`. Without devices from the XML report, they are
+    /// made of the compute instances, numbered in GPU then compute instance order like nvidia-smi does.
+    pub fn merge_into(&self, readings: &mut GpuReadings) {
+        for gpu in readings.gpus.iter_mut() {
+            if gpu.mig_devices.is_empty() {
+                let mut instances: Vec<_> = self
+                    .compute_instances
+                    .iter()
+                    .filter(|i| i.gpu_index == gpu.index)
+                    .collect();
+                instances.sort_by_key(|i| (i.gpu_instance_id, i.compute_instance_id));
+                gpu.mig_devices = instances
+                    .iter()
+                    .enumerate()
+                    .map(|(index, i)| GpuMigDevice {
+                        index: index as u32,
+                        gpu_instance_id: i.gpu_instance_id,
+                        compute_instance_id: i.compute_instance_id.unwrap_or(0),
+                        ..GpuMigDevice::default()
+                    })
+                    .collect();
+            }
+            for device in gpu.mig_devices.iter_mut() {
+                let listed = self
+                    .listed
+                    .iter()
+                    .find(|l| l.gpu_uuid == gpu.uuid && l.device_index == device.index);
+                if let Some(listed) = listed {
+                    device.uuid = listed.uuid.clone();
+                    device.profile = listed.profile.clone();
+                }
+                if device.profile.is_empty() {
+                    device.profile = self
+                        .gpu_instances
+                        .iter()
+                        .find(|i| i.gpu_index == gpu.index && i.gpu_instance_id == device.gpu_instance_id)
+                        .map_or(String::new(), |i| i.profile.clone());
+                }
+            }
+        }
+    }
+}
+
+/// MIG devices of `nvidia-smi -L`, which looks like
+/// ```text
+/// GPU 0: NVIDIA A100-SXM4-40GB (UUID: GPU-5d1c9a3e-...)
+///   MIG 3g.20gb     Device  0: (UUID: MIG-c6d4f1e2-...)
+/// ```
+pub fn parse_list(output: &str) -> Result<Vec<MigListing>, GpuError> {
+    let mut gpu_uuid = None;
+    let mut listed = Vec::new();
+    for line in output.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let uuid = line
+            .rsplit_once("(UUID: ")
+            .map(|(_, rest)| rest.trim_end_matches(')').to_string());
+        if line.starts_with("GPU ") {
+            gpu_uuid = uuid;
+        } else if let Some(rest) = line.strip_prefix("MIG ") {
+            let parse_error = || GpuError::Parse {
+                field: "nvidia-smi -L".to_string(),
+                value: line.to_string(),
+            };
+            let (profile, rest) = rest.split_once("Device").ok_or_else(parse_error)?;
+            let device_index = rest
+                .split(':')
+                .next()
+                .and_then(|i| i.trim().parse().ok())
+                .ok_or_else(parse_error)?;
+            listed.push(MigListing {
+                gpu_uuid: gpu_uuid.clone().ok_or_else(parse_error)?,
+                device_index,
+                profile: profile.trim().to_string(),
+                uuid: uuid.ok_or_else(parse_error)?,
+            });
+        }
+    }
+    Ok(listed)
+}
+
+/// Rows of the `nvidia-smi mig -lgi` table: GPU, name, profile ID, instance ID and placement
+pub fn parse_gpu_instances(output: &str) -> Result<Vec<MigInstance>, GpuError> {
+    table_rows(output)
+        .map(|row| {
+            // GPU "MIG" name profile-id instance-id placement
+            let [gpu, _, name, _, id, _] = row.as_slice() else {
+                return Err(row_error("nvidia-smi mig -lgi", &row));
+            };
+            Ok(MigInstance {
+                gpu_index: number("nvidia-smi mig -lgi", gpu)?,
+                gpu_instance_id: number("nvidia-smi mig -lgi", id)?,
+                compute_instance_id: None,
+                profile: name.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Rows of the `nvidia-smi mig -lci` table: GPU, GPU instance ID, name, profile ID, instance ID
+/// and placement
+pub fn parse_compute_instances(output: &str) -> Result<Vec<MigInstance>, GpuError> {
+    table_rows(output)
+        .map(|row| {
+            // GPU gpu-instance-id "MIG" name profile-id instance-id placement
+            let [gpu, gpu_instance, _, name, _, id, _] = row.as_slice() else {
+                return Err(row_error("nvidia-smi mig -lci", &row));
+            };
+            Ok(MigInstance {
+                gpu_index: number("nvidia-smi mig -lci", gpu)?,
+                gpu_instance_id: number("nvidia-smi mig -lci", gpu_instance)?,
+                compute_instance_id: Some(number("nvidia-smi mig -lci", id)?),
+                profile: name.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Words of the table rows that start with a GPU index, skipping borders and headers. "No GPU
+/// instances found" and the like have no rows.
+fn table_rows(output: &str) -> impl Iterator<Item = Vec<&str>> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix('|')?.strip_suffix('|'))
+        .map(|row| row.split_whitespace().collect::<Vec<_>>())
+        .filter(|words| words.first().is_some_and(|w| w.chars().all(|c| c.is_ascii_digit())))
+}
+
+fn number(command: &str, word: &str) -> Result<u32, GpuError> {
+    word.parse().map_err(|_| GpuError::Parse {
+        field: command.to_string(),
+        value: word.to_string(),
+    })
+}
+
+fn row_error(command: &str, row: &[&str]) -> GpuError {
+    GpuError::Parse {
+        field: command.to_string(),
+        value: row.join(" "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::datastreams::gpu_data::GpuInfo;
+
+    const LIST: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nvidia-smi/a100_mig_list.txt"));
+    const LGI: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nvidia-smi/a100_mig_lgi.txt"));
+    const LCI: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nvidia-smi/a100_mig_lci.txt"));
+
+    #[test]
+    fn test_mig_devices_from_listings() {
+        let layout = MigLayout {
+            listed: parse_list(LIST).unwrap(),
+            gpu_instances: parse_gpu_instances(LGI).unwrap(),
+            compute_instances: parse_compute_instances(LCI).unwrap(),
+        };
+        assert_eq!(layout.listed.len(), 2);
+        assert_eq!(layout.listed[1].uuid, "MIG-7e2b9c14-3d5f-5a8e-9c21-b4f06d8e1a37");
+        assert_eq!(layout.gpu_instances[1].gpu_instance_id, 2);
+        assert_eq!(layout.compute_instances[1].compute_instance_id, Some(0));
+
+        let mut readings = GpuReadings::new();
+        for (index, uuid) in [(0, "GPU-5d1c9a3e-0b7f-4e28-9a61-c2d84f13b7e9"), (1, "GPU-0a4e7b21-6c3d-4f89-8e12-5b9d3c7a6f04")] {
+            readings.gpus.push(GpuInfo {
+                index,
+                uuid: uuid.to_string(),
+                ..GpuInfo::default()
+            });
+        }
+        layout.merge_into(&mut readings);
+        let devices = &readings.gpus[0].mig_devices;
+        assert_eq!(devices.len(), 2);
+        assert_eq!((devices[1].gpu_instance_id, devices[1].profile.as_str()), (2, "3g.20gb"));
+        assert!(readings.gpus[1].mig_devices.is_empty());
+        let (gpu, device) = readings
+            .get_mig_device_by_uuid("MIG-c6d4f1e2-8a3b-5c9d-b1e7-2f6a9d0c4e18")
+            .unwrap();
+        assert_eq!((gpu.index, device.gpu_instance_id), (0, 1));
+
+        // not partitioned, or not allowed to look
+        assert!(parse_gpu_instances("No GPU instances found: Not Found\n").unwrap().is_empty());
+        assert!(parse_compute_instances("").unwrap().is_empty());
+        assert!(matches!(parse_list("  MIG 1g.5gb Device 0: (UUID: MIG-x)"), Err(GpuError::Parse { .. })));
+    }
+}
@@ -0,0 +1,151 @@
+use crate::error::GpuError;
+use crate::trace::datastreams::gpu_data::{GpuPeer, GpuReadings};
+use crate::trace::datastreams::numa::parse_cpu_list;
+
+/// Connection matrix between GPUs, with the CPUs and NUMA node close to each
+pub const TOPOLOGY_ARGS: [&str; 2] = ["topo", "-m"];
+
+/// A GPU row of `nvidia-smi topo -m`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TopologyRow {
+    pub gpu_index: u32,
+    pub peers: Vec<GpuPeer>,
+    pub cpu_affinity: Vec<u32>,
+    pub numa_node: Option<u32>,
+}
+
+/// How GPUs are wired, as far as nvidia-smi told - it does not change while tracing
+#[derive(Debug, Clone, Default)]
+pub struct Topology {
+    pub rows: Vec<TopologyRow>,
+}
+
+impl Topology {
+    /// Adds peers, close CPUs and NUMA node to the GPUs of `readings`
+    pub fn merge_into(&self, readings: &mut GpuReadings) {
+        for row in &self.rows {
+            if let Some(gpu) = readings.gpus.iter_mut().find(|g| g.index == row.gpu_index) {
+                gpu.peers = row.peers.clone();
+                gpu.cpu_affinity = row.cpu_affinity.clone();
+                gpu.numa_node = row.numa_node;
+            }
+        }
+    }
+}
+
+/// `nvidia-smi topo -m`, a tab separated matrix with affinity columns at the end:
+/// ```text
+///         GPU0    GPU1    NIC0    CPU Affinity    NUMA Affinity   GPU NUMA ID
+/// GPU0     X      NV4     PXB     0-23,48-71      0               N/A
+/// ```
+/// Rows of NICs and the legend below are skipped. Older drivers have no NUMA column.
+pub fn parse_topology(output: &str) -> Result<Topology, GpuError> {
+    let output = strip_escapes(output);
+    let mut columns: Vec<String> = Vec::new();
+    let mut rows = Vec::new();
+    for line in output.lines() {
+        let cells: Vec<&str> = line.split('\t').map(str::trim).collect();
+        if columns.is_empty() {
+            if cells.len() > 1 && cells[0].is_empty() {
+                columns = cells.iter().map(|c| c.to_string()).collect();
+            }
+            continue;
+        }
+        let Some(gpu_index) = gpu_column(cells[0]) else {
+            continue;
+        };
+        let cell = |name: &str| {
+            columns
+                .iter()
+                .position(|c| c == name)
+                .and_then(|i| cells.get(i))
+                .filter(|value| !value.is_empty() && **value != "N/A")
+        };
+        let peers = columns
+            .iter()
+            .zip(&cells)
+            .filter_map(|(column, connection)| {
+                let peer = gpu_column(column)?;
+                (peer != gpu_index).then(|| GpuPeer {
+                    gpu_index: peer,
+                    connection: connection.to_string(),
+                })
+            })
+            .collect();
+        let cpu_affinity = match cell("CPU Affinity") {
+            Some(list) => parse_cpu_list(list).ok_or_else(|| GpuError::Parse {
+                field: "nvidia-smi topo CPU Affinity".to_string(),
+                value: list.to_string(),
+            })?,
+            None => Vec::new(),
+        };
+        rows.push(TopologyRow {
+            gpu_index,
+            peers,
+            cpu_affinity,
+            // a range when the GPU is close to several nodes - then it is close to none in particular
+            numa_node: cell("NUMA Affinity").and_then(|node| node.parse().ok()),
+        });
+    }
+    if columns.is_empty() {
+        return Err(GpuError::Parse {
+            field: "nvidia-smi topo header".to_string(),
+            value: output.lines().next().unwrap_or_default().to_string(),
+        });
+    }
+    Ok(Topology { rows })
+}
+
+/// N of a `GPUN` column or row
+fn gpu_column(name: &str) -> Option<u32> {
+    name.strip_prefix("GPU")?.parse().ok()
+}
+
+/// Drops the underlining some versions print around the header
+fn strip_escapes(output: &str) -> String {
+    let mut text = String::with_capacity(output.len());
+    let mut chars = output.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // CSI sequence: ESC [ parameters final-letter
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            text.push(c);
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::datastreams::gpu_data::GpuInfo;
+
+    const TOPO: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nvidia-smi/topo_4gpu.txt"));
+
+    #[test]
+    fn test_topology_matrix() {
+        let topology = parse_topology(TOPO).unwrap();
+        assert_eq!(topology.rows.len(), 4);
+        let row = &topology.rows[2];
+        assert_eq!(row.numa_node, Some(1));
+        assert_eq!(row.cpu_affinity.len(), 48);
+        let peers: Vec<_> = row.peers.iter().map(|p| (p.gpu_index, p.connection.as_str())).collect();
+        assert_eq!(peers, [(0, "SYS"), (1, "SYS"), (3, "NV4")]);
+
+        let mut readings = GpuReadings::new();
+        readings.gpus.push(GpuInfo { index: 1, ..GpuInfo::default() });
+        topology.merge_into(&mut readings);
+        assert!(readings.gpus[0].peers[0].is_nvlink());
+        assert!(readings.gpus[0].peers[2].crosses_numa());
+
+        // a single GPU of an older driver, underlined when it thinks it talks to a terminal
+        let single = parse_topology("\t\x1b[4mGPU0\x1b[0m\t\x1b[4mCPU Affinity\x1b[0m\nGPU0\t X \t0-7\n").unwrap();
+        assert_eq!(single.rows[0], TopologyRow { cpu_affinity: (0..8).collect(), ..TopologyRow::default() });
+        assert!(parse_topology("Failed to initialize NVML").is_err());
+    }
+}
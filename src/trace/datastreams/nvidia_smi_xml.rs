@@ -0,0 +1,441 @@
+use crate::error::GpuError;
+use crate::trace::datastreams::gpu_data::{
+    GpuAccountedProcess, GpuEccErrors, GpuInfo, GpuMigDevice, GpuReadings, GpuRemappedRows,
+    GpuRetiredPages, ThrottleReasons,
+};
+use roxmltree::{Document, Node, ParsingOptions};
+use std::time::Duration;
+
+/// Arguments of the full XML report
+pub const XML_REPORT_ARGS: [&str; 2] = ["-q", "-x"];
+
+/// What `nvidia-smi -q -x` knows about one GPU beyond `--query-gpu`
+#[derive(Debug, Clone, Default)]
+pub struct XmlGpu {
+    pub uuid: String,
+    pub pci_bus_id: String,
+    pub ecc_errors_corrected_volatile: GpuEccErrors,
+    pub ecc_errors_uncorrected_volatile: GpuEccErrors,
+    pub ecc_errors_corrected_aggregate: GpuEccErrors,
+    pub ecc_errors_uncorrected_aggregate: GpuEccErrors,
+    pub retired_pages: Option<GpuRetiredPages>,
+    pub remapped_rows: Option<GpuRemappedRows>,
+    pub throttle_reasons: Option<ThrottleReasons>,
+    pub mig_mode: String,
+    pub mig_devices: Vec<GpuMigDevice>,
+    /// GPU and compute instance of each running process, on a MIG partitioned GPU
+    pub process_instances: Vec<ProcessInstance>,
+    pub accounted_processes: Vec<GpuAccountedProcess>,
+}
+
+/// Which MIG instance a process runs on
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ProcessInstance {
+    pub pid: u32,
+    pub gpu_instance_id: u32,
+    pub compute_instance_id: u32,
+}
+
+/// Parsed `nvidia-smi -q -x` report
+#[derive(Debug, Clone, Default)]
+pub struct XmlReport {
+    pub gpus: Vec<XmlGpu>,
+}
+
+impl XmlReport {
+    pub fn parse(xml: &str) -> Result<Self, GpuError> {
+        let options = ParsingOptions {
+            allow_dtd: true,
+            ..ParsingOptions::default()
+        };
+        let doc = Document::parse_with_options(xml, options).map_err(|e| GpuError::Parse {
+            field: "nvidia-smi XML report".to_string(),
+            value: e.to_string(),
+        })?;
+        let gpus = doc
+            .root_element()
+            .children()
+            .filter(|n| n.has_tag_name("gpu"))
+            .map(parse_gpu)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { gpus })
+    }
+
+    /// Adds the report, read `age` ago, to readings of `--query-gpu`, matching GPUs by UUID, then by PCI
+    /// bus ID. What the reading has itself is newer and kept. Accounted processes are left out, see
+    /// [`XmlReport::accounted_processes`].
+    pub fn merge_into(&self, readings: &mut GpuReadings, age: Duration) {
+        for gpu in readings.gpus.iter_mut() {
+            let Some(report) = self.find(gpu) else {
+                continue;
+            };
+            gpu.report_age_secs = Some(age.as_secs());
+            // per location from the report, but totals of this tick may already be newer
+            let ecc = &mut gpu.ecc;
+            merge_ecc(&mut ecc.errors_corrected_volatile, &report.ecc_errors_corrected_volatile);
+            merge_ecc(&mut ecc.errors_uncorrected_volatile, &report.ecc_errors_uncorrected_volatile);
+            merge_ecc(&mut ecc.errors_corrected_aggregate, &report.ecc_errors_corrected_aggregate);
+            merge_ecc(&mut ecc.errors_uncorrected_aggregate, &report.ecc_errors_uncorrected_aggregate);
+            if gpu.retired_pages.is_none() {
+                gpu.retired_pages = report.retired_pages.clone();
+            }
+            if gpu.remapped_rows.is_none() {
+                gpu.remapped_rows = report.remapped_rows.clone();
+            }
+            // per-tick reasons, when there are any, are newer than the report
+            if gpu.throttle_reasons.is_none() {
+                gpu.throttle_reasons = report.throttle_reasons;
+            }
+            if gpu.mig_mode.is_empty() {
+                gpu.mig_mode = report.mig_mode.clone();
+            }
+            gpu.mig_devices = report.mig_devices.clone();
+            for process in readings.processes.iter_mut().filter(|p| p.gpu_index == gpu.index) {
+                if let Some(instance) = report.process_instances.iter().find(|i| i.pid == process.pid) {
+                    process.gpu_instance_id = Some(instance.gpu_instance_id);
+                    process.compute_instance_id = Some(instance.compute_instance_id);
+                }
+            }
+        }
+    }
+
+    /// Accounted processes of the report, with the GPU index of `readings`
+    pub fn accounted_processes(&self, readings: &GpuReadings) -> Vec<GpuAccountedProcess> {
+        readings
+            .gpus
+            .iter()
+            .filter_map(|gpu| self.find(gpu).map(|report| (gpu.index, report)))
+            .flat_map(|(gpu_index, report)| {
+                report.accounted_processes.iter().map(move |p| GpuAccountedProcess {
+                    gpu_index,
+                    ..p.clone()
+                })
+            })
+            .collect()
+    }
+
+    /// Report of `gpu`, by UUID, then by PCI bus ID
+    fn find(&self, gpu: &GpuInfo) -> Option<&XmlGpu> {
+        self.gpus
+            .iter()
+            .find(|r| !r.uuid.is_empty() && r.uuid == gpu.uuid)
+            .or_else(|| self.gpus.iter().find(|r| same_bus_id(&r.pci_bus_id, &gpu.pci_bus_id)))
+    }
+}
+
+fn merge_ecc(tick: &mut GpuEccErrors, report: &GpuEccErrors) {
+    let total = tick.total.max(report.total);
+    *tick = GpuEccErrors {
+        total,
+        ..report.clone()
+    };
+}
+
+/// `00000000:07:00.0` of the report is `00000000:07:00.0` or `0000:07:00.0` elsewhere
+fn same_bus_id(a: &str, b: &str) -> bool {
+    let short = |id: &str| id.trim_start_matches('0').to_lowercase();
+    !a.is_empty() && short(a) == short(b)
+}
+
+fn parse_gpu(gpu: Node) -> Result<XmlGpu, GpuError> {
+    let ecc = child(gpu, "ecc_errors");
+    let errors = |kind: &str, corrected: bool| ecc.and_then(|e| child(e, kind)).map(|n| ecc_errors(n, corrected));
+
+    Ok(XmlGpu {
+        uuid: text(gpu, "uuid"),
+        pci_bus_id: gpu.attribute("id").unwrap_or_default().to_string(),
+        ecc_errors_corrected_volatile: errors("volatile", true).transpose()?.unwrap_or_default(),
+        ecc_errors_uncorrected_volatile: errors("volatile", false).transpose()?.unwrap_or_default(),
+        ecc_errors_corrected_aggregate: errors("aggregate", true).transpose()?.unwrap_or_default(),
+        ecc_errors_uncorrected_aggregate: errors("aggregate", false).transpose()?.unwrap_or_default(),
+        retired_pages: child(gpu, "retired_pages").map(retired_pages).transpose()?.flatten(),
+        remapped_rows: child(gpu, "remapped_rows").map(remapped_rows).transpose()?.flatten(),
+        throttle_reasons: child(gpu, "clocks_event_reasons")
+            .or_else(|| child(gpu, "clocks_throttle_reasons"))
+            .map(throttle_reasons),
+        mig_mode: child(gpu, "mig_mode").map_or(String::new(), |m| text(m, "current_mig")),
+        mig_devices: child(gpu, "mig_devices")
+            .map(|m| m.children().filter(|n| n.has_tag_name("mig_device")).map(mig_device).collect())
+            .transpose()?
+            .unwrap_or_default(),
+        process_instances: child(gpu, "processes")
+            .map(|p| {
+                p.children()
+                    .filter(|n| n.has_tag_name("process_info"))
+                    .map(process_instance)
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?
+            .map_or(Vec::new(), |instances| instances.into_iter().flatten().collect()),
+        accounted_processes: child(gpu, "accounted_processes")
+            .map(|a| {
+                a.children()
+                    .filter(|n| n.has_tag_name("accounted_process_info"))
+                    .map(accounted_process)
+                    .collect()
+            })
+            .transpose()?
+            .unwrap_or_default(),
+    })
+}
+
+/// ECC counters of `volatile` or `aggregate`. Drivers before Ampere count `single_bit`/`double_bit`
+/// per location, later ones `sram_*`/`dram_*`.
+fn ecc_errors(node: Node, corrected: bool) -> Result<GpuEccErrors, GpuError> {
+    let mut errors = GpuEccErrors::default();
+    if let Some(bits) = child(node, if corrected { "single_bit" } else { "double_bit" }) {
+        errors.device_memory = count(bits, "device_memory")?.unwrap_or(0);
+        errors.dram = count(bits, "dram")?.unwrap_or(0);
+        errors.register_file = count(bits, "register_file")?.unwrap_or(0);
+        errors.l1_cache = count(bits, "l1_cache")?.unwrap_or(0);
+        errors.l2_cache = count(bits, "l2_cache")?.unwrap_or(0);
+        errors.texture_memory = count(bits, "texture_memory")?.unwrap_or(0);
+        errors.cbu = count(bits, "cbu")?.unwrap_or(0);
+        errors.sram = count(bits, "sram")?.unwrap_or(0);
+        errors.total = count(bits, "total")?.unwrap_or(0);
+    } else if corrected {
+        errors.sram = count(node, "sram_correctable")?.unwrap_or(0);
+        errors.dram = count(node, "dram_correctable")?.unwrap_or(0);
+    } else {
+        // newer drivers split it by how it was detected
+        errors.sram = match count(node, "sram_uncorrectable")? {
+            Some(sram) => sram,
+            None => {
+                count(node, "sram_uncorrectable_parity")?.unwrap_or(0)
+                    + count(node, "sram_uncorrectable_secded")?.unwrap_or(0)
+            }
+        };
+        errors.dram = count(node, "dram_uncorrectable")?.unwrap_or(0);
+    }
+    if errors.total == 0 {
+        errors.total = errors.device_memory
+            + errors.dram
+            + errors.register_file
+            + errors.l1_cache
+            + errors.l2_cache
+            + errors.texture_memory
+            + errors.cbu
+            + errors.sram;
+    }
+    Ok(errors)
+}
+
+/// `None` where retirement is not supported (`<retired_pages>N/A</retired_pages>`)
+fn retired_pages(node: Node) -> Result<Option<GpuRetiredPages>, GpuError> {
+    let retired = |kind| {
+        child(node, kind)
+            .map(|n| count(n, "retired_count"))
+            .transpose()
+            .map(Option::flatten)
+    };
+    let (Some(single_bit), Some(double_bit)) = (
+        retired("multiple_single_bit_retirement")?,
+        retired("double_bit_retirement")?,
+    ) else {
+        return Ok(None);
+    };
+    let pending = text(node, "pending_retirement") == "Yes" || text(node, "pending_blacklist") == "Yes";
+    Ok(Some(GpuRetiredPages {
+        single_bit,
+        double_bit,
+        pending,
+    }))
+}
+
+/// `None` where remapping is not supported
+fn remapped_rows(node: Node) -> Result<Option<GpuRemappedRows>, GpuError> {
+    let (Some(correctable), Some(uncorrectable)) =
+        (count(node, "remapped_row_corr")?, count(node, "remapped_row_unc")?)
+    else {
+        return Ok(None);
+    };
+    Ok(Some(GpuRemappedRows {
+        correctable,
+        uncorrectable,
+        pending: text(node, "remapped_row_pending") == "Yes",
+        failure: text(node, "remapped_row_failure") == "Yes",
+    }))
+}
+
+/// `<clocks_event_reason_sw_power_cap>Active</...>` and its older `clocks_throttle_reason_` spelling
+fn throttle_reasons(node: Node) -> ThrottleReasons {
+    let mut reasons = ThrottleReasons::default();
+    for reason in node.children().filter(|n| n.is_element()) {
+        let tag = reason.tag_name().name();
+        let name = tag
+            .strip_prefix("clocks_event_reason_")
+            .or_else(|| tag.strip_prefix("clocks_throttle_reason_"))
+            .unwrap_or(tag);
+        if reason.text().map(str::trim) == Some("Active") {
+            if let Some(bit) = ThrottleReasons::bit(name) {
+                reasons.insert(bit);
+            }
+        }
+    }
+    reasons
+}
+
+fn mig_device(node: Node) -> Result<GpuMigDevice, GpuError> {
+    let memory = child(node, "fb_memory_usage");
+    let mib = |field| memory.map(|m| count(m, field)).transpose().map(|v| v.flatten().unwrap_or(0));
+    Ok(GpuMigDevice {
+        index: count(node, "index")?.unwrap_or(0) as u32,
+        // the report has neither, `nvidia-smi -L` and `mig -lgi` do
+        uuid: String::new(),
+        profile: String::new(),
+        gpu_instance_id: count(node, "gpu_instance_id")?.unwrap_or(0) as u32,
+        compute_instance_id: count(node, "compute_instance_id")?.unwrap_or(0) as u32,
+        multiprocessor_count: child(node, "device_attributes")
+            .and_then(|a| child(a, "shared"))
+            .map(|s| count(s, "multiprocessor_count"))
+            .transpose()?
+            .flatten()
+            .map(|c| c as u32),
+        memory_total: mib("total")?,
+        memory_used: mib("used")?,
+    })
+}
+
+/// Instance of a process, if the GPU is partitioned - N/A otherwise
+fn process_instance(node: Node) -> Result<Option<ProcessInstance>, GpuError> {
+    let (Some(pid), Some(gpu_instance_id), Some(compute_instance_id)) = (
+        count(node, "pid")?,
+        count(node, "gpu_instance_id")?,
+        count(node, "compute_instance_id")?,
+    ) else {
+        return Ok(None);
+    };
+    Ok(Some(ProcessInstance {
+        pid: pid as u32,
+        gpu_instance_id: gpu_instance_id as u32,
+        compute_instance_id: compute_instance_id as u32,
+    }))
+}
+
+fn accounted_process(node: Node) -> Result<GpuAccountedProcess, GpuError> {
+    Ok(GpuAccountedProcess {
+        pid: count(node, "pid")?.unwrap_or(0) as u32,
+        gpu_index: 0,
+        gpu_utilization: count(node, "gpu_util")?.map(|u| u as u32),
+        memory_utilization: count(node, "memory_util")?.map(|u| u as u32),
+        max_memory_usage: count(node, "max_memory_usage")?,
+        time_ms: count(node, "time")?,
+        is_running: text(node, "is_running") == "Yes",
+    })
+}
+
+fn child<'a, 'i>(node: Node<'a, 'i>, tag: &str) -> Option<Node<'a, 'i>> {
+    node.children().find(|n| n.has_tag_name(tag))
+}
+
+fn text(node: Node, tag: &str) -> String {
+    child(node, tag)
+        .and_then(|n| n.text())
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+/// Number of `tag`, units like `MiB`, `%` or `ms` dropped. Missing and N/A are `None`.
+fn count(node: Node, tag: &str) -> Result<Option<u64>, GpuError> {
+    let value = text(node, tag);
+    let number = value.split_whitespace().next().unwrap_or_default().trim_end_matches('%');
+    if number.is_empty() || number == "N/A" || value.contains("Not Supported") {
+        return Ok(None);
+    }
+    number.parse().map(Some).map_err(|_| GpuError::Parse {
+        field: tag.to_string(),
+        value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::datastreams::gpu_data::GpuProcess;
+
+    const V100: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nvidia-smi/v100.xml"));
+    const A100_MIG: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nvidia-smi/a100_mig.xml"));
+
+    #[test]
+    fn test_pre_ampere_report() {
+        let report = XmlReport::parse(V100).unwrap();
+        assert_eq!(report.gpus.len(), 1);
+        let gpu = &report.gpus[0];
+        assert_eq!(gpu.pci_bus_id, "00000000:00:1E.0");
+        assert_eq!(gpu.ecc_errors_corrected_volatile.device_memory, 3);
+        assert_eq!(gpu.ecc_errors_corrected_volatile.l2_cache, 1);
+        assert_eq!(gpu.ecc_errors_corrected_volatile.total, 4);
+        assert_eq!(gpu.ecc_errors_uncorrected_aggregate.register_file, 1);
+        assert_eq!(
+            gpu.retired_pages,
+            Some(GpuRetiredPages {
+                single_bit: 2,
+                double_bit: 1,
+                pending: true
+            })
+        );
+        assert_eq!(gpu.remapped_rows, None);
+        let reasons = gpu.throttle_reasons.unwrap();
+        assert_eq!(reasons.names(), vec!["sw_power_cap", "hw_thermal_slowdown"]);
+        assert_eq!(gpu.accounted_processes.len(), 2);
+        assert_eq!(gpu.accounted_processes[1].max_memory_usage, Some(1024));
+        assert!(!gpu.accounted_processes[1].is_running);
+        // not partitioned, the process runs on no instance
+        assert!(gpu.process_instances.is_empty());
+
+        let mut readings = GpuReadings::new();
+        readings.gpus.push(GpuInfo {
+            index: 1,
+            pci_bus_id: "0000:00:1e.0".to_string(),
+            ..GpuInfo::default()
+        });
+        report.merge_into(&mut readings, Duration::from_secs(12));
+        assert_eq!(readings.gpus[0].report_age_secs, Some(12));
+        assert!(readings.accounted_processes.is_empty());
+        let accounted = report.accounted_processes(&readings);
+        assert_eq!(accounted.iter().map(|p| p.gpu_index).collect::<Vec<_>>(), vec![1, 1]);
+    }
+
+    #[test]
+    fn test_mig_report_merges_into_readings() {
+        let report = XmlReport::parse(A100_MIG).unwrap();
+        let gpu = &report.gpus[0];
+        assert_eq!(gpu.ecc_errors_corrected_aggregate.sram, 5);
+        assert_eq!(gpu.ecc_errors_uncorrected_aggregate.dram, 1);
+        assert_eq!(gpu.retired_pages, None);
+        assert_eq!(gpu.remapped_rows.as_ref().map(|r| (r.correctable, r.pending)), Some((2, true)));
+        assert_eq!(gpu.mig_mode, "Enabled");
+        assert_eq!(gpu.mig_devices.len(), 2);
+        assert_eq!(gpu.mig_devices[1].gpu_instance_id, 2);
+        assert_eq!(gpu.mig_devices[1].multiprocessor_count, Some(42));
+        assert_eq!((gpu.mig_devices[1].memory_used, gpu.mig_devices[1].memory_total), (1024, 19968));
+
+        let mut readings = GpuReadings::new();
+        readings.gpus.push(GpuInfo {
+            index: 3,
+            pci_bus_id: "0000:07:00.0".to_string(),
+            // read with this sample, newer than the report
+            remapped_rows: Some(GpuRemappedRows { correctable: 3, ..Default::default() }),
+            ..GpuInfo::default()
+        });
+        readings.processes.push(GpuProcess {
+            pid: 51342,
+            gpu_index: 3,
+            ..GpuProcess::default()
+        });
+        report.merge_into(&mut readings, Duration::ZERO);
+        assert_eq!(readings.gpus[0].mig_devices.len(), 2);
+        assert_eq!(readings.gpus[0].mig_mode, "Enabled");
+        assert_eq!(readings.gpus[0].remapped_rows.as_ref().map(|r| r.correctable), Some(3));
+        let process = &readings.processes[0];
+        assert_eq!((process.gpu_instance_id, process.compute_instance_id), (Some(2), Some(0)));
+        let instance = &readings.gpus[0].mig_devices[1];
+        assert_eq!(readings.get_mig_device_processes(3, instance).len(), 1);
+        assert_eq!(readings.gpus[0].throttle_reasons, Some(ThrottleReasons(ThrottleReasons::GPU_IDLE)));
+        assert!(readings.accounted_processes.is_empty());
+        assert!(report.accounted_processes(&readings).is_empty());
+
+        assert!(matches!(XmlReport::parse("<nvidia_smi_log><gpu>"), Err(GpuError::Parse { .. })));
+    }
+}
@@ -0,0 +1,762 @@
+use crate::error::GpuError;
+use crate::trace::datastreams::gpu_backend::GpuBackend;
+use crate::trace::datastreams::gpu_data::{
+    GpuClocks, GpuEcc, GpuEccErrors, GpuEncoderSession, GpuEncoderStats, GpuInfo, GpuMemory,
+    GpuMigDevice, GpuPcie, GpuPower, GpuProcess, GpuReadings, GpuRemappedRows, GpuTemperature,
+    GpuUtilization, ThrottleReasons,
+};
+use crate::trace::datastreams::gpu_health::{GpuBackoff, GpuHealth};
+use libc::{c_char, c_int, c_uint, c_ulonglong, c_void};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::time::Instant;
+
+/// NVML shipped with the NVIDIA driver
+pub const DEFAULT_NVML_LIBRARY: &str = "libnvidia-ml.so.1";
+
+const NVML_SUCCESS: c_int = 0;
+const NVML_ERROR_INSUFFICIENT_SIZE: c_int = 7;
+const NVML_ERROR_LIB_RM_VERSION_MISMATCH: c_int = 18;
+
+const NVML_TEMPERATURE_GPU: c_uint = 0;
+const NVML_CLOCK_GRAPHICS: c_uint = 0;
+const NVML_CLOCK_SM: c_uint = 1;
+const NVML_CLOCK_MEM: c_uint = 2;
+const NVML_CLOCK_VIDEO: c_uint = 3;
+const NVML_MEMORY_ERROR_CORRECTED: c_uint = 0;
+const NVML_MEMORY_ERROR_UNCORRECTED: c_uint = 1;
+const NVML_VOLATILE_ECC: c_uint = 0;
+const NVML_AGGREGATE_ECC: c_uint = 1;
+const NVML_DEVICE_MIG_ENABLE: c_uint = 1;
+const NVML_PCIE_UTIL_TX_BYTES: c_uint = 0;
+const NVML_PCIE_UTIL_RX_BYTES: c_uint = 1;
+/// GPU and compute instance ID of processes on a GPU without MIG
+const NO_INSTANCE: c_uint = 0xFFFF_FFFF;
+
+/// Buffer big enough for names, UUIDs and versions
+const STRING_LEN: usize = 96;
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+type Device = *mut c_void;
+
+#[repr(C)]
+#[derive(Default)]
+struct Memory {
+    total: c_ulonglong,
+    free: c_ulonglong,
+    used: c_ulonglong,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Utilization {
+    gpu: c_uint,
+    memory: c_uint,
+}
+
+/// `nvmlProcessInfo_t` of the `_v2`/`_v3` calls
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct ProcessInfo {
+    pid: c_uint,
+    used_gpu_memory: c_ulonglong,
+    gpu_instance_id: c_uint,
+    compute_instance_id: c_uint,
+}
+
+/// `nvmlEncoderSessionInfo_t`
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct EncoderSessionInfo {
+    session_id: c_uint,
+    pid: c_uint,
+    vgpu_instance: c_uint,
+    codec_type: c_uint,
+    h_resolution: c_uint,
+    v_resolution: c_uint,
+    average_fps: c_uint,
+    average_latency: c_uint,
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct ProcessUtilizationSample {
+    pid: c_uint,
+    time_stamp: c_ulonglong,
+    sm_util: c_uint,
+    mem_util: c_uint,
+    enc_util: c_uint,
+    dec_util: c_uint,
+}
+
+type StringFn = unsafe extern "C" fn(Device, *mut c_char, c_uint) -> c_int;
+type UintFn = unsafe extern "C" fn(Device, *mut c_uint) -> c_int;
+type ClockFn = unsafe extern "C" fn(Device, c_uint, *mut c_uint) -> c_int;
+type MigModeFn = unsafe extern "C" fn(Device, *mut c_uint, *mut c_uint) -> c_int;
+type MigDeviceFn = unsafe extern "C" fn(Device, c_uint, *mut Device) -> c_int;
+type RemappedRowsFn = unsafe extern "C" fn(Device, *mut c_uint, *mut c_uint, *mut c_uint, *mut c_uint) -> c_int;
+type ProcessesFn = unsafe extern "C" fn(Device, *mut c_uint, *mut ProcessInfo) -> c_int;
+/// Busy percentage of a media engine, and the period it was sampled over
+type EngineUtilizationFn = unsafe extern "C" fn(Device, *mut c_uint, *mut c_uint) -> c_int;
+type EncoderStatsFn = unsafe extern "C" fn(Device, *mut c_uint, *mut c_uint, *mut c_uint) -> c_int;
+type EncoderSessionsFn = unsafe extern "C" fn(Device, *mut c_uint, *mut EncoderSessionInfo) -> c_int;
+type ProcessUtilizationFn =
+    unsafe extern "C" fn(Device, *mut ProcessUtilizationSample, *mut c_uint, c_ulonglong) -> c_int;
+
+/// The NVML functions used, looked up in the loaded library
+struct Api {
+    library: *mut c_void,
+    shutdown: unsafe extern "C" fn() -> c_int,
+    error_string: unsafe extern "C" fn(c_int) -> *const c_char,
+    driver_version: unsafe extern "C" fn(*mut c_char, c_uint) -> c_int,
+    process_name: Option<unsafe extern "C" fn(c_uint, *mut c_char, c_uint) -> c_int>,
+    count: unsafe extern "C" fn(*mut c_uint) -> c_int,
+    handle_by_index: unsafe extern "C" fn(c_uint, *mut Device) -> c_int,
+    name: StringFn,
+    uuid: StringFn,
+    memory: unsafe extern "C" fn(Device, *mut Memory) -> c_int,
+    utilization: unsafe extern "C" fn(Device, *mut Utilization) -> c_int,
+    temperature: ClockFn,
+    power_usage: UintFn,
+    power_limit: UintFn,
+    fan_speed: UintFn,
+    clock: ClockFn,
+    max_clock: ClockFn,
+    processes: ProcessesFn,
+    process_utilization: Option<ProcessUtilizationFn>,
+    throttle_reasons: Option<unsafe extern "C" fn(Device, *mut c_ulonglong) -> c_int>,
+    ecc_errors: Option<unsafe extern "C" fn(Device, c_uint, c_uint, *mut c_ulonglong) -> c_int>,
+    remapped_rows: Option<RemappedRowsFn>,
+    pcie_gen: Option<UintFn>,
+    pcie_max_gen: Option<UintFn>,
+    pcie_width: Option<UintFn>,
+    pcie_max_width: Option<UintFn>,
+    pcie_throughput: Option<ClockFn>,
+    mig_mode: Option<MigModeFn>,
+    max_mig_devices: Option<UintFn>,
+    mig_device_by_index: Option<MigDeviceFn>,
+    gpu_instance_id: Option<UintFn>,
+    compute_instance_id: Option<UintFn>,
+    encoder_utilization: Option<EngineUtilizationFn>,
+    decoder_utilization: Option<EngineUtilizationFn>,
+    jpeg_utilization: Option<EngineUtilizationFn>,
+    ofa_utilization: Option<EngineUtilizationFn>,
+    encoder_stats: Option<EncoderStatsFn>,
+    encoder_sessions: Option<EncoderSessionsFn>,
+}
+
+// NVML is thread-safe, and the monitor only ever lives on one thread at a time
+unsafe impl Send for Api {}
+
+impl Api {
+    /// dlopens `library` and initializes NVML
+    fn load(library: &str) -> Result<Self, GpuError> {
+        let path = CString::new(library).map_err(|_| GpuError::NotInstalled(library.to_string()))?;
+        // SAFETY: plain dlopen, the handle is closed in `Drop`
+        let handle = unsafe { libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        if handle.is_null() {
+            return Err(GpuError::NotInstalled(library.to_string()));
+        }
+
+        macro_rules! required {
+            ($name:literal) => {
+                // SAFETY: the symbol has the signature of the NVML header
+                match unsafe { symbol(handle, $name) } {
+                    Some(f) => f,
+                    None => {
+                        unsafe { libc::dlclose(handle) };
+                        return Err(GpuError::UnsupportedField($name.to_string()));
+                    }
+                }
+            };
+        }
+
+        let init: unsafe extern "C" fn() -> c_int = required!("nvmlInit_v2");
+        let api = Self {
+            library: handle,
+            shutdown: required!("nvmlShutdown"),
+            error_string: required!("nvmlErrorString"),
+            driver_version: required!("nvmlSystemGetDriverVersion"),
+            process_name: unsafe { symbol(handle, "nvmlSystemGetProcessName") },
+            count: required!("nvmlDeviceGetCount_v2"),
+            handle_by_index: required!("nvmlDeviceGetHandleByIndex_v2"),
+            name: required!("nvmlDeviceGetName"),
+            uuid: required!("nvmlDeviceGetUUID"),
+            memory: required!("nvmlDeviceGetMemoryInfo"),
+            utilization: required!("nvmlDeviceGetUtilizationRates"),
+            temperature: required!("nvmlDeviceGetTemperature"),
+            power_usage: required!("nvmlDeviceGetPowerUsage"),
+            power_limit: required!("nvmlDeviceGetEnforcedPowerLimit"),
+            fan_speed: required!("nvmlDeviceGetFanSpeed"),
+            clock: required!("nvmlDeviceGetClockInfo"),
+            max_clock: required!("nvmlDeviceGetMaxClockInfo"),
+            // v3 on recent drivers, v2 (same layout) on older ones
+            processes: match unsafe { symbol(handle, "nvmlDeviceGetComputeRunningProcesses_v3") } {
+                Some(f) => f,
+                None => required!("nvmlDeviceGetComputeRunningProcesses_v2"),
+            },
+            process_utilization: unsafe { symbol(handle, "nvmlDeviceGetProcessUtilization") },
+            // renamed to clocks "events" in 535, the old name went away later
+            throttle_reasons: match unsafe { symbol(handle, "nvmlDeviceGetCurrentClocksEventReasons") } {
+                Some(f) => Some(f),
+                None => unsafe { symbol(handle, "nvmlDeviceGetCurrentClocksThrottleReasons") },
+            },
+            ecc_errors: unsafe { symbol(handle, "nvmlDeviceGetTotalEccErrors") },
+            remapped_rows: unsafe { symbol(handle, "nvmlDeviceGetRemappedRows") },
+            pcie_gen: unsafe { symbol(handle, "nvmlDeviceGetCurrPcieLinkGeneration") },
+            pcie_max_gen: unsafe { symbol(handle, "nvmlDeviceGetMaxPcieLinkGeneration") },
+            pcie_width: unsafe { symbol(handle, "nvmlDeviceGetCurrPcieLinkWidth") },
+            pcie_max_width: unsafe { symbol(handle, "nvmlDeviceGetMaxPcieLinkWidth") },
+            pcie_throughput: unsafe { symbol(handle, "nvmlDeviceGetPcieThroughput") },
+            mig_mode: unsafe { symbol(handle, "nvmlDeviceGetMigMode") },
+            max_mig_devices: unsafe { symbol(handle, "nvmlDeviceGetMaxMigDeviceCount") },
+            mig_device_by_index: unsafe { symbol(handle, "nvmlDeviceGetMigDeviceHandleByIndex") },
+            gpu_instance_id: unsafe { symbol(handle, "nvmlDeviceGetGpuInstanceId") },
+            compute_instance_id: unsafe { symbol(handle, "nvmlDeviceGetComputeInstanceId") },
+            encoder_utilization: unsafe { symbol(handle, "nvmlDeviceGetEncoderUtilization") },
+            decoder_utilization: unsafe { symbol(handle, "nvmlDeviceGetDecoderUtilization") },
+            // JPEG and OFA engines since Ampere, asked for since 12.x drivers
+            jpeg_utilization: unsafe { symbol(handle, "nvmlDeviceGetJpgUtilization") },
+            ofa_utilization: unsafe { symbol(handle, "nvmlDeviceGetOfaUtilization") },
+            encoder_stats: unsafe { symbol(handle, "nvmlDeviceGetEncoderStats") },
+            encoder_sessions: unsafe { symbol(handle, "nvmlDeviceGetEncoderSessions") },
+        };
+        // SAFETY: no arguments; shut down again when `api` is dropped
+        api.check("nvmlInit_v2", unsafe { init() })?;
+        Ok(api)
+    }
+
+    /// Error of a failed call, named after the function
+    fn check(&self, function: &str, ret: c_int) -> Result<(), GpuError> {
+        if ret == NVML_SUCCESS {
+            return Ok(());
+        }
+        // SAFETY: NVML returns a static string for any code
+        let message = unsafe { CStr::from_ptr((self.error_string)(ret)) }
+            .to_string_lossy()
+            .to_string();
+        if ret == NVML_ERROR_LIB_RM_VERSION_MISMATCH {
+            return Err(GpuError::DriverMismatch(message));
+        }
+        Err(GpuError::ExitStatus {
+            command: function.to_string(),
+            code: Some(ret),
+            stderr: message,
+        })
+    }
+
+    /// String filled in by `f`, empty when it fails
+    fn string(&self, f: impl FnOnce(*mut c_char, c_uint) -> c_int) -> String {
+        let mut buf = [0 as c_char; STRING_LEN];
+        if f(buf.as_mut_ptr(), STRING_LEN as c_uint) != NVML_SUCCESS {
+            return String::new();
+        }
+        // SAFETY: NVML terminates the string within the buffer
+        unsafe { CStr::from_ptr(buf.as_ptr()) }.to_string_lossy().to_string()
+    }
+
+    /// Value filled in by `f`, `None` when not supported (or failing) on this GPU
+    fn optional<T: Default>(f: impl FnOnce(*mut T) -> c_int) -> Option<T> {
+        let mut value = T::default();
+        (f(&mut value) == NVML_SUCCESS).then_some(value)
+    }
+}
+
+impl Drop for Api {
+    fn drop(&mut self) {
+        // SAFETY: initialized in `load`, nothing uses the library after this
+        unsafe {
+            (self.shutdown)();
+            libc::dlclose(self.library);
+        }
+    }
+}
+
+/// Function pointer of `name` in the library, `None` when it does not export it.
+///
+/// # Safety
+/// `T` has to be the function pointer type of the symbol.
+unsafe fn symbol<T: Copy>(library: *mut c_void, name: &str) -> Option<T> {
+    let name = CString::new(name).ok()?;
+    let pointer = libc::dlsym(library, name.as_ptr());
+    (!pointer.is_null()).then(|| std::mem::transmute_copy(&pointer))
+}
+
+/// NVML backend: asks the driver library directly instead of forking nvidia-smi - faster,
+/// no output format to drift, and it has per-process utilization samples that nvidia-smi hides.
+/// The library is loaded at runtime, so the tracer still starts where it is missing.
+pub struct NvmlMonitor {
+    api: Option<Api>,
+    driver_version: String,
+    /// Timestamp (µs) of the newest process utilization sample seen, to ask only for newer ones
+    last_seen: c_ulonglong,
+    last_error: Option<GpuError>,
+    backoff: GpuBackoff,
+}
+
+impl NvmlMonitor {
+    /// NVML of the installed driver
+    pub fn new() -> Self {
+        Self::with_library(DEFAULT_NVML_LIBRARY)
+    }
+
+    /// NVML from `library` - a path, or a name for the dynamic linker to find
+    pub fn with_library(library: &str) -> Self {
+        match Api::load(library) {
+            Ok(api) => Self {
+                driver_version: api.string(|buf, len| unsafe { (api.driver_version)(buf, len) }),
+                api: Some(api),
+                last_seen: 0,
+                last_error: None,
+                backoff: GpuBackoff::new(),
+            },
+            Err(e) => Self {
+                api: None,
+                driver_version: String::new(),
+                last_seen: 0,
+                last_error: Some(e),
+                backoff: GpuBackoff::new(),
+            },
+        }
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.api.is_some()
+    }
+
+    pub fn get_last_error(&self) -> Option<&GpuError> {
+        self.last_error.as_ref()
+    }
+
+    /// Reads all GPUs and their processes, backing off while NVML fails
+    pub fn get_gpu_info(&mut self) -> Result<GpuReadings, GpuError> {
+        let now = Instant::now();
+        self.backoff.check(now)?;
+        let result = self.read();
+        let result = self.backoff.record(now, result);
+        self.last_error = result.as_ref().err().cloned();
+        result
+    }
+
+    fn read(&mut self) -> Result<GpuReadings, GpuError> {
+        let Some(api) = &self.api else {
+            return Err(self
+                .last_error
+                .clone()
+                .unwrap_or_else(|| GpuError::NotInstalled(DEFAULT_NVML_LIBRARY.to_string())));
+        };
+
+        let mut count: c_uint = 0;
+        api.check("nvmlDeviceGetCount_v2", unsafe { (api.count)(&mut count) })?;
+
+        let mut readings = GpuReadings::new();
+        let mut last_seen = self.last_seen;
+        for index in 0..count {
+            let mut device: Device = std::ptr::null_mut();
+            api.check("nvmlDeviceGetHandleByIndex_v2", unsafe {
+                (api.handle_by_index)(index, &mut device)
+            })?;
+            readings.gpus.push(self.read_device(api, index, device, &readings.timestamp)?);
+
+            let samples = process_utilization(api, device, self.last_seen);
+            last_seen = samples.values().map(|s| s.time_stamp).max().unwrap_or(0).max(last_seen);
+            readings.processes.extend(read_processes(api, index, device, &samples)?);
+            readings.encoder_sessions.extend(read_encoder_sessions(api, index, device));
+        }
+        self.last_seen = last_seen;
+        readings.gpu_count = count;
+        Ok(readings)
+    }
+
+    fn read_device(&self, api: &Api, index: u32, device: Device, timestamp: &str) -> Result<GpuInfo, GpuError> {
+        // SAFETY (all calls below): `device` is a valid handle and the out pointers point to
+        // values of the type NVML writes
+        let mut memory = Memory::default();
+        api.check("nvmlDeviceGetMemoryInfo", unsafe { (api.memory)(device, &mut memory) })?;
+        let utilization = Api::optional(|u| unsafe { (api.utilization)(device, u) }).unwrap_or_default();
+        let clock = |f: ClockFn, kind| Api::optional(|c| unsafe { f(device, kind, c) });
+        let link = |f: Option<UintFn>| f.and_then(|f| Api::optional(|v| unsafe { f(device, v) }));
+        // KB/s over the last 20 ms
+        let pcie_throughput = |counter| {
+            api.pcie_throughput
+                .and_then(|f| Api::optional(|v| unsafe { f(device, counter, v) }))
+                .map(|kb| kb as f64 / 1000.0)
+        };
+        let engine = |f: Option<EngineUtilizationFn>| {
+            f.and_then(|f| {
+                let (mut percent, mut period) = (0, 0);
+                (unsafe { f(device, &mut percent, &mut period) } == NVML_SUCCESS).then_some(percent)
+            })
+        };
+        let encoder_stats = api.encoder_stats.and_then(|f| {
+            let (mut session_count, mut average_fps, mut average_latency) = (0, 0, 0);
+            let ret = unsafe { f(device, &mut session_count, &mut average_fps, &mut average_latency) };
+            (ret == NVML_SUCCESS).then_some(GpuEncoderStats {
+                session_count,
+                average_fps,
+                average_latency,
+            })
+        });
+        let milliwatts = |f: UintFn| Api::optional(|p| unsafe { f(device, p) }).map(|mw| mw as f32 / 1000.0);
+        let ecc_total = |kind, counter| GpuEccErrors {
+            total: api
+                .ecc_errors
+                .and_then(|f| Api::optional(|n| unsafe { f(device, kind, counter, n) }))
+                .unwrap_or(0),
+            ..GpuEccErrors::default()
+        };
+        let mig_enabled = api.mig_mode.and_then(|f| {
+            let (mut current, mut pending) = (0, 0);
+            let ret = unsafe { f(device, &mut current, &mut pending) };
+            (ret == NVML_SUCCESS).then_some(current == NVML_DEVICE_MIG_ENABLE)
+        });
+        let remapped_rows = api.remapped_rows.and_then(|f| {
+            let (mut correctable, mut uncorrectable, mut pending, mut failure) = (0, 0, 0, 0);
+            let ret = unsafe { f(device, &mut correctable, &mut uncorrectable, &mut pending, &mut failure) };
+            (ret == NVML_SUCCESS).then_some(GpuRemappedRows {
+                correctable: correctable as u64,
+                uncorrectable: uncorrectable as u64,
+                pending: pending != 0,
+                failure: failure != 0,
+            })
+        });
+
+        Ok(GpuInfo {
+            index,
+            name: api.string(|buf, len| unsafe { (api.name)(device, buf, len) }),
+            uuid: api.string(|buf, len| unsafe { (api.uuid)(device, buf, len) }),
+            driver_version: self.driver_version.clone(),
+            memory: GpuMemory {
+                total: memory.total / BYTES_PER_MB,
+                used: memory.used / BYTES_PER_MB,
+                free: memory.free / BYTES_PER_MB,
+                ..GpuMemory::default()
+            },
+            utilization: GpuUtilization {
+                gpu: utilization.gpu,
+                memory: utilization.memory,
+                encoder: engine(api.encoder_utilization),
+                decoder: engine(api.decoder_utilization),
+                jpeg: engine(api.jpeg_utilization),
+                ofa: engine(api.ofa_utilization),
+                ..GpuUtilization::default()
+            },
+            temperature: GpuTemperature {
+                gpu: clock(api.temperature, NVML_TEMPERATURE_GPU).map(|t| t as i32),
+                ..GpuTemperature::default()
+            },
+            power: GpuPower {
+                draw: milliwatts(api.power_usage),
+                enforced_limit: milliwatts(api.power_limit),
+                limit: milliwatts(api.power_limit),
+                ..GpuPower::default()
+            },
+            clocks: GpuClocks {
+                graphics: clock(api.clock, NVML_CLOCK_GRAPHICS),
+                sm: clock(api.clock, NVML_CLOCK_SM),
+                memory: clock(api.clock, NVML_CLOCK_MEM),
+                video: clock(api.clock, NVML_CLOCK_VIDEO),
+                max_graphics: clock(api.max_clock, NVML_CLOCK_GRAPHICS),
+                max_sm: clock(api.max_clock, NVML_CLOCK_SM),
+                max_memory: clock(api.max_clock, NVML_CLOCK_MEM),
+                ..GpuClocks::default()
+            },
+            ecc: GpuEcc {
+                errors_corrected_volatile: ecc_total(NVML_MEMORY_ERROR_CORRECTED, NVML_VOLATILE_ECC),
+                errors_uncorrected_volatile: ecc_total(NVML_MEMORY_ERROR_UNCORRECTED, NVML_VOLATILE_ECC),
+                errors_corrected_aggregate: ecc_total(NVML_MEMORY_ERROR_CORRECTED, NVML_AGGREGATE_ECC),
+                errors_uncorrected_aggregate: ecc_total(NVML_MEMORY_ERROR_UNCORRECTED, NVML_AGGREGATE_ECC),
+                ..GpuEcc::default()
+            },
+            remapped_rows,
+            pcie: GpuPcie {
+                gen_current: link(api.pcie_gen),
+                gen_max: link(api.pcie_max_gen),
+                width_current: link(api.pcie_width),
+                width_max: link(api.pcie_max_width),
+                tx_throughput: pcie_throughput(NVML_PCIE_UTIL_TX_BYTES),
+                rx_throughput: pcie_throughput(NVML_PCIE_UTIL_RX_BYTES),
+                ..GpuPcie::default()
+            },
+            fan_speed: Api::optional(|s| unsafe { (api.fan_speed)(device, s) }),
+            throttle_reasons: api
+                .throttle_reasons
+                .and_then(|f| Api::optional(|r| unsafe { f(device, r) }))
+                .map(ThrottleReasons),
+            encoder_stats,
+            timestamp: timestamp.to_string(),
+            mig_mode: match mig_enabled {
+                Some(true) => "Enabled",
+                Some(false) => "Disabled",
+                None => "N/A",
+            }
+            .to_string(),
+            mig_devices: if mig_enabled == Some(true) {
+                read_mig_devices(api, device)
+            } else {
+                Vec::new()
+            },
+            ..GpuInfo::default()
+        })
+    }
+}
+
+impl Default for NvmlMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GpuBackend for NvmlMonitor {
+    fn name(&self) -> &'static str {
+        "nvml"
+    }
+
+    fn get_gpu_info(&mut self) -> Result<GpuReadings, GpuError> {
+        NvmlMonitor::get_gpu_info(self)
+    }
+
+    fn health(&self) -> &GpuHealth {
+        self.backoff.health()
+    }
+}
+
+/// Newest utilization sample per PID since `last_seen` - empty where the driver has none
+fn process_utilization(api: &Api, device: Device, last_seen: c_ulonglong) -> HashMap<u32, ProcessUtilizationSample> {
+    let Some(f) = api.process_utilization else {
+        return HashMap::new();
+    };
+    let mut count: c_uint = 0;
+    // SAFETY: a null buffer asks for the number of samples
+    let ret = unsafe { f(device, std::ptr::null_mut(), &mut count, last_seen) };
+    if ret != NVML_ERROR_INSUFFICIENT_SIZE || count == 0 {
+        return HashMap::new();
+    }
+    let mut samples = vec![ProcessUtilizationSample::default(); count as usize];
+    // SAFETY: the buffer holds `count` samples
+    if unsafe { f(device, samples.as_mut_ptr(), &mut count, last_seen) } != NVML_SUCCESS {
+        return HashMap::new();
+    }
+    let mut newest: HashMap<u32, ProcessUtilizationSample> = HashMap::new();
+    for sample in &samples[..(count as usize).min(samples.len())] {
+        if newest.get(&sample.pid).is_none_or(|s| s.time_stamp < sample.time_stamp) {
+            newest.insert(sample.pid, *sample);
+        }
+    }
+    newest
+}
+
+/// NVENC sessions of the device - empty where the driver cannot list them
+fn read_encoder_sessions(api: &Api, index: u32, device: Device) -> Vec<GpuEncoderSession> {
+    let Some(f) = api.encoder_sessions else {
+        return Vec::new();
+    };
+    let mut count: c_uint = 0;
+    // SAFETY: a count of 0 asks for the number of sessions
+    if unsafe { f(device, &mut count, std::ptr::null_mut()) } != NVML_SUCCESS || count == 0 {
+        return Vec::new();
+    }
+    // sessions may start in between - leave some room
+    count += 4;
+    let mut infos = vec![EncoderSessionInfo::default(); count as usize];
+    // SAFETY: the buffer holds `count` entries
+    if unsafe { f(device, &mut count, infos.as_mut_ptr()) } != NVML_SUCCESS {
+        return Vec::new();
+    }
+    infos[..(count as usize).min(infos.len())]
+        .iter()
+        .map(|info| GpuEncoderSession {
+            gpu_index: index,
+            session_id: info.session_id,
+            pid: info.pid,
+            codec: match info.codec_type {
+                0 => "H.264",
+                1 => "HEVC",
+                2 => "AV1",
+                _ => "unknown",
+            }
+            .to_string(),
+            h_resolution: info.h_resolution,
+            v_resolution: info.v_resolution,
+            average_fps: info.average_fps,
+            average_latency: info.average_latency,
+        })
+        .collect()
+}
+
+/// MIG devices of a partitioned GPU, in slot order - empty where the driver cannot list them
+fn read_mig_devices(api: &Api, device: Device) -> Vec<GpuMigDevice> {
+    let (Some(max_count), Some(by_index), Some(gpu_instance_id), Some(compute_instance_id)) = (
+        api.max_mig_devices,
+        api.mig_device_by_index,
+        api.gpu_instance_id,
+        api.compute_instance_id,
+    ) else {
+        return Vec::new();
+    };
+    // SAFETY (all calls below): `device` and `mig` are valid handles, the out pointers point
+    // to values of the type NVML writes
+    let slots = Api::optional(|c| unsafe { max_count(device, c) }).unwrap_or(0);
+    (0..slots)
+        .filter_map(|slot| {
+            let mut mig: Device = std::ptr::null_mut();
+            // empty slots are not found
+            if unsafe { by_index(device, slot, &mut mig) } != NVML_SUCCESS {
+                return None;
+            }
+            let memory = Api::optional(|m| unsafe { (api.memory)(mig, m) }).unwrap_or_default();
+            // "NVIDIA A100-SXM4-80GB MIG 3g.40gb"
+            let name = api.string(|buf, len| unsafe { (api.name)(mig, buf, len) });
+            Some(GpuMigDevice {
+                uuid: api.string(|buf, len| unsafe { (api.uuid)(mig, buf, len) }),
+                profile: name.rsplit_once("MIG ").map_or(String::new(), |(_, p)| p.to_string()),
+                gpu_instance_id: Api::optional(|i| unsafe { gpu_instance_id(mig, i) })?,
+                compute_instance_id: Api::optional(|i| unsafe { compute_instance_id(mig, i) })?,
+                memory_total: memory.total / BYTES_PER_MB,
+                memory_used: memory.used / BYTES_PER_MB,
+                ..GpuMigDevice::default()
+            })
+        })
+        .enumerate()
+        .map(|(index, device)| GpuMigDevice {
+            index: index as u32,
+            ..device
+        })
+        .collect()
+}
+
+/// Compute processes of the device with their memory and, when sampled, utilization
+fn read_processes(
+    api: &Api,
+    index: u32,
+    device: Device,
+    samples: &HashMap<u32, ProcessUtilizationSample>,
+) -> Result<Vec<GpuProcess>, GpuError> {
+    let function = "nvmlDeviceGetComputeRunningProcesses";
+    let mut count: c_uint = 0;
+    // SAFETY: a null buffer asks for the number of processes
+    let ret = unsafe { (api.processes)(device, &mut count, std::ptr::null_mut()) };
+    if ret == NVML_SUCCESS || count == 0 {
+        return Ok(Vec::new());
+    }
+    if ret != NVML_ERROR_INSUFFICIENT_SIZE {
+        api.check(function, ret)?;
+    }
+    // processes may start in between - leave some room
+    count += 8;
+    let mut infos = vec![ProcessInfo::default(); count as usize];
+    // SAFETY: the buffer holds `count` entries
+    api.check(function, unsafe { (api.processes)(device, &mut count, infos.as_mut_ptr()) })?;
+
+    Ok(infos[..(count as usize).min(infos.len())]
+        .iter()
+        .map(|info| GpuProcess {
+            pid: info.pid,
+            process_name: api.process_name.map_or(String::new(), |f| {
+                api.string(|buf, len| unsafe { f(info.pid, buf, len) })
+            }),
+            used_memory: info.used_gpu_memory / BYTES_PER_MB,
+            gpu_index: index,
+            utilization: samples.get(&info.pid).map(|s| s.sm_util),
+            encoder_utilization: samples.get(&info.pid).map(|s| s.enc_util),
+            decoder_utilization: samples.get(&info.pid).map(|s| s.dec_util),
+            gpu_instance_id: (info.gpu_instance_id != NO_INSTANCE).then_some(info.gpu_instance_id),
+            compute_instance_id: (info.compute_instance_id != NO_INSTANCE).then_some(info.compute_instance_id),
+            ..GpuProcess::default()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::process::Command;
+
+    /// Builds the stub NVML of `fixtures/nvml` into `dir`, `None` without a C compiler
+    fn stub_library(dir: &Path) -> Option<String> {
+        let source = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/nvml/stub_nvml.c");
+        let library = dir.join("libnvidia-ml-stub.so");
+        let built = Command::new("cc")
+            .args(["-shared", "-fPIC", "-o"])
+            .arg(&library)
+            .arg(source)
+            .status()
+            .is_ok_and(|s| s.success());
+        built.then(|| library.to_string_lossy().to_string())
+    }
+
+    #[test]
+    fn test_stub_library() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = stub_library(dir.path()).expect("the NVML stub test needs a C compiler (cc)");
+
+        let mut monitor = NvmlMonitor::with_library(&library);
+        assert!(monitor.is_available(), "{:?}", monitor.get_last_error());
+        let readings = monitor.get_gpu_info().unwrap();
+        assert_eq!(readings.gpu_count, 2);
+        let gpu = &readings.gpus[1];
+        assert_eq!(gpu.name, "NVIDIA A100-SXM4-80GB");
+        assert_eq!(gpu.uuid, "GPU-00000000-0000-0000-0000-000000000001");
+        assert_eq!(gpu.driver_version, "550.54.15");
+        assert_eq!((gpu.memory.used, gpu.memory.total), (20480, 81920));
+        assert_eq!((gpu.utilization.gpu, gpu.utilization.memory), (97, 41));
+        assert_eq!(gpu.temperature.gpu, Some(67));
+        assert_eq!((gpu.power.draw, gpu.power.limit), (Some(312.5), Some(400.0)));
+        assert_eq!((gpu.clocks.graphics, gpu.clocks.max_graphics), (Some(1410), Some(1410)));
+        assert_eq!(gpu.throttle_reasons, Some(ThrottleReasons(ThrottleReasons::SW_POWER_CAP)));
+        assert_eq!(readings.gpus[0].throttle_reasons, Some(ThrottleReasons(ThrottleReasons::GPU_IDLE)));
+        assert_eq!(gpu.ecc.errors_corrected_aggregate.total, 12);
+        assert_eq!(gpu.ecc.errors_uncorrected_volatile.total, 0);
+        assert_eq!(gpu.remapped_rows.as_ref().map(|r| (r.correctable, r.pending)), Some((3, true)));
+        assert_eq!(gpu.fan_speed, None); // SXM - no fan
+        assert_eq!((gpu.pcie.gen_current, gpu.pcie.width_current), (Some(4), Some(8)));
+        assert!(gpu.pcie.is_degraded());
+        assert_eq!((gpu.pcie.tx_throughput, gpu.pcie.rx_throughput), (Some(1500.0), Some(24.0)));
+        assert_eq!((gpu.mig_mode.as_str(), readings.gpus[0].mig_mode.as_str()), ("Enabled", "Disabled"));
+        let instances: Vec<(u32, &str, &str, u32, u64)> = gpu
+            .mig_devices
+            .iter()
+            .map(|d| (d.index, d.uuid.as_str(), d.profile.as_str(), d.gpu_instance_id, d.memory_used))
+            .collect();
+        assert_eq!(
+            instances,
+            vec![
+                (0, "MIG-00000000-0000-0000-0000-000000000001", "3g.40gb", 1, 20480),
+                (1, "MIG-00000000-0000-0000-0000-000000000002", "3g.40gb", 2, 512),
+            ]
+        );
+        assert_eq!(readings.get_mig_device_processes(1, &gpu.mig_devices[1])[0].pid, 4243);
+
+        let video = &readings.gpus[0];
+        assert_eq!((video.utilization.encoder, video.utilization.decoder), (Some(35), Some(60)));
+        assert_eq!((video.utilization.jpeg, video.utilization.ofa), (None, None));
+        let stats = video.encoder_stats.unwrap();
+        assert_eq!((stats.session_count, stats.average_fps, stats.average_latency), (2, 29, 1180));
+        let sessions: Vec<(u32, u32, &str, u32)> = readings
+            .encoder_sessions
+            .iter()
+            .map(|s| (s.gpu_index, s.pid, s.codec.as_str(), s.v_resolution))
+            .collect();
+        assert_eq!(sessions, vec![(0, 5150, "HEVC", 2160), (0, 5150, "H.264", 1080)]);
+        assert_eq!(gpu.encoder_stats.map(|s| s.session_count), Some(0));
+
+        let processes: Vec<(u32, &str, u64, u32, Option<u32>)> = readings
+            .processes
+            .iter()
+            .map(|p| (p.pid, p.process_name.as_str(), p.used_memory, p.gpu_index, p.utilization))
+            .collect();
+        assert_eq!(
+            processes,
+            vec![(4242, "python3", 20480, 1, Some(95)), (4243, "python3", 512, 1, None)]
+        );
+        assert_eq!(readings.processes[0].decoder_utilization, Some(12));
+    }
+
+    #[test]
+    fn test_missing_library() {
+        let mut monitor = NvmlMonitor::with_library("/nonexistent/libnvidia-ml.so.1");
+        assert!(!monitor.is_available());
+        assert!(matches!(monitor.get_gpu_info(), Err(GpuError::NotInstalled(_))));
+    }
+}
@@ -1,23 +1,35 @@
-use crate::trace::datastreams::data_stream::{Readings, SysDataStream};
+use crate::trace::datastreams::data_stream::SysDataStream;
+use crate::trace::datastreams::sample::Sample;
+use std::any::Any;
 
+#[derive(Default)]
 pub struct ProcessMonitor {
     pub processes: Vec<(u32, String, f32, u64)>, //PID, Command, CPU. mem (kb)
 }
 
+impl ProcessMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
 impl SysDataStream for ProcessMonitor {
-    fn new(_max_hist_len: usize, _inter_len: u16) -> Self {
-        Self {
-            processes: Vec::new(),
-        }
+    fn name(&self) -> &'static str {
+        "process"
     }
 
-    fn poll(&mut self, system_info: &Readings) {
+    fn poll(&mut self, sample: &Sample) {
+        let readings = &sample.readings;
         self.processes.clear();
         self.processes.push((
-            system_info.get_pid(),
-            system_info.get_process(),
-            system_info.get_cpu(),
-            system_info.get_mem(),
+            readings.get_pid(),
+            readings.get_process(),
+            readings.get_cpu(),
+            readings.get_mem(),
         ));
     }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 }
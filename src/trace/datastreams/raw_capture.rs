@@ -0,0 +1,193 @@
+use crate::error::{GpuError, Result};
+use crate::trace::datastreams::time_series::Timestamp;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// File with all invocations, one JSON per line, in the capture directory
+pub const INVOCATIONS_FILE: &str = "invocations.jsonl";
+
+/// What a finished command left behind
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawOutput {
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// One recorded call of a GPU tool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Invocation {
+    /// Tool that was run; one capture can hold calls of several
+    #[serde(default)]
+    pub program: String,
+    /// Arguments, without the program
+    pub args: Vec<String>,
+    /// When it was started, RFC 3339
+    pub started: String,
+    /// How long it took
+    pub duration_ms: u64,
+    /// Exit code, `None` when killed or not started at all
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// It did not finish in time and was killed
+    #[serde(default)]
+    pub timed_out: bool,
+    /// It could not be run at all
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl Invocation {
+    pub fn new(
+        program: &str,
+        args: &[&str],
+        started: Timestamp,
+        duration: Duration,
+        result: &std::result::Result<RawOutput, GpuError>,
+    ) -> Self {
+        let mut invocation = Self {
+            program: program.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            started: started.to_rfc3339(),
+            duration_ms: duration.as_millis() as u64,
+            exit_code: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            timed_out: false,
+            error: None,
+        };
+        match result {
+            Ok(output) => {
+                invocation.exit_code = output.code;
+                invocation.stdout = output.stdout.clone();
+                invocation.stderr = output.stderr.clone();
+            }
+            Err(GpuError::Timeout { .. }) => invocation.timed_out = true,
+            Err(e) => invocation.error = Some(e.to_string()),
+        }
+        invocation
+    }
+
+    /// Recorded call of `program`, compared by file name - `/usr/bin/nvidia-smi` is `nvidia-smi` too.
+    /// Captures from before the program was recorded match any.
+    pub fn is_call_of(&self, program: &str) -> bool {
+        let name = |path: &str| Path::new(path).file_name().map(|n| n.to_os_string());
+        self.program.is_empty() || name(&self.program) == name(program)
+    }
+
+    /// Same outcome as when it was recorded
+    pub fn replay(&self, program: &str) -> std::result::Result<RawOutput, GpuError> {
+        if self.timed_out {
+            return Err(GpuError::Timeout {
+                command: program.to_string(),
+                timeout: Duration::from_millis(self.duration_ms),
+            });
+        }
+        if let Some(e) = &self.error {
+            return Err(GpuError::Io(e.clone()));
+        }
+        Ok(RawOutput {
+            code: self.exit_code,
+            stdout: self.stdout.clone(),
+            stderr: self.stderr.clone(),
+        })
+    }
+}
+
+/// Appends every invocation into a capture directory, for bug reports.
+pub struct RawCapture {
+    file: File,
+}
+
+impl RawCapture {
+    /// Creates the directory when needed; an earlier capture in it is replaced.
+    pub fn create(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let file = File::create(dir.join(INVOCATIONS_FILE))?;
+        Ok(Self { file })
+    }
+
+    pub fn record(&mut self, invocation: &Invocation) {
+        let line = serde_json::to_string(invocation).unwrap_or_default();
+        // Flushed line by line - a capture of a crashed session is still useful.
+        if let Err(e) = writeln!(self.file, "{}", line).and_then(|_| self.file.flush()) {
+            log::warn!("Cannot write raw capture: {}", e);
+        }
+    }
+}
+
+/// Plays a capture directory back, invocation by invocation.
+pub struct RawReplay {
+    invocations: VecDeque<Invocation>,
+}
+
+impl RawReplay {
+    pub fn open(dir: &Path) -> Result<Self> {
+        let path = dir.join(INVOCATIONS_FILE);
+        let file = File::open(&path)?;
+        let mut invocations = VecDeque::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let invocation = serde_json::from_str(&line).map_err(|e| GpuError::Parse {
+                field: format!("{} line {}", path.display(), i + 1),
+                value: e.to_string(),
+            })?;
+            invocations.push_back(invocation);
+        }
+        log::info!("Replaying {} invocations from {}", invocations.len(), path.display());
+        Ok(Self { invocations })
+    }
+
+    /// Next recorded invocation of `program` with these arguments. Each program and argument list is
+    /// replayed in its own order, so calls made at another cadence (e.g. the XML report) or by another
+    /// tool of the same capture do not throw away the others.
+    pub fn next(&mut self, program: &str, args: &[&str]) -> Option<Invocation> {
+        let i = self
+            .invocations
+            .iter()
+            .position(|inv| inv.is_call_of(program) && inv.args == args)?;
+        self.invocations.remove(i)
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.invocations.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Local;
+
+    #[test]
+    fn test_replay_tells_programs_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut capture = RawCapture::create(dir.path()).unwrap();
+        let output = |stdout: &str| {
+            Ok(RawOutput {
+                code: Some(0),
+                stdout: stdout.to_string(),
+                stderr: String::new(),
+            })
+        };
+        for (program, stdout) in [("/usr/bin/nvidia-smi", "NVIDIA"), ("/opt/rocm/bin/rocm-smi", "AMD")] {
+            let invocation = Invocation::new(program, &["--version"], Local::now(), Duration::ZERO, &output(stdout));
+            capture.record(&invocation);
+        }
+
+        let mut replay = RawReplay::open(dir.path()).unwrap();
+        let amd = replay.next("rocm-smi", &["--version"]).unwrap();
+        assert_eq!(amd.replay("rocm-smi").unwrap().stdout, "AMD");
+        assert!(replay.next("rocm-smi", &["--version"]).is_none());
+        assert_eq!(replay.next("nvidia-smi", &["--version"]).unwrap().stdout, "NVIDIA");
+        assert_eq!(replay.remaining(), 0);
+    }
+}
@@ -0,0 +1,102 @@
+use crate::trace::datastreams::data_stream::SysDataStream;
+use crate::trace::datastreams::sample::Sample;
+
+/// All streams fed with samples. New collectors only need to be registered here.
+#[derive(Default)]
+pub struct StreamRegistry {
+    streams: Vec<Box<dyn SysDataStream>>,
+}
+
+impl StreamRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, stream: Box<dyn SysDataStream>) {
+        self.streams.push(stream);
+    }
+
+    /// Feed sample to every stream
+    pub fn poll(&mut self, sample: &Sample) {
+        for stream in &mut self.streams {
+            stream.poll(sample);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.streams.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+
+    /// Names of registered streams, in registration order
+    pub fn names(&self) -> Vec<&str> {
+        self.streams.iter().map(|s| s.name()).collect()
+    }
+
+    /// All streams of given type
+    pub fn all<T: SysDataStream + 'static>(&self) -> impl Iterator<Item = &T> {
+        self.streams.iter().filter_map(|s| s.as_any().downcast_ref::<T>())
+    }
+
+    /// First stream of given type
+    pub fn get<T: SysDataStream + 'static>(&self) -> Option<&T> {
+        self.all::<T>().next()
+    }
+
+    /// Stream of given type following given GPU
+    pub fn gpu<T: SysDataStream + 'static>(&self, gpu_index: u32) -> Option<&T> {
+        self.all::<T>().find(|s| s.gpu_index() == Some(gpu_index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trace::datastreams::{
+        CPUMonitor, GpuInfo, GpuReadings, GpuUtilizationMonitor, Readings, Retention,
+    };
+    use chrono::Local;
+    use std::collections::BTreeMap;
+    use sysinfo::{Pid, System, SystemExt};
+
+    #[test]
+    fn test_registry_polls_and_finds_streams() {
+        let retention = Retention::latest_only();
+        let mut registry = StreamRegistry::new();
+        registry.register(Box::new(CPUMonitor::new(&retention)));
+        registry.register(Box::new(GpuUtilizationMonitor::new_for_gpu(&retention, 0)));
+        registry.register(Box::new(GpuUtilizationMonitor::new_for_gpu(&retention, 1)));
+
+        let mut gpu = GpuReadings::new();
+        for index in 0..2 {
+            let mut info = GpuInfo {
+                index,
+                ..Default::default()
+            };
+            info.utilization.gpu = 10 + index;
+            gpu.gpus.push(info);
+        }
+        let sample = Sample {
+            index: 1,
+            time: Local::now(),
+            readings: Readings::new(&mut System::new(), Pid::from(std::process::id() as i32)),
+            cgroup: None,
+            traced_pids: vec![],
+            gpu: Some(gpu),
+            gpu_health: None,
+            affinity: Vec::new(),
+            accounted: Vec::new(),
+            metrics: BTreeMap::new(),
+        };
+        registry.poll(&sample);
+
+        assert_eq!(registry.len(), 3);
+        assert_eq!(registry.names(), vec!["cpu", "gpu_utilization", "gpu_utilization"]);
+        assert!(registry.get::<CPUMonitor>().is_some());
+        assert_eq!(registry.gpu::<GpuUtilizationMonitor>(1).unwrap().current_gpu_util, 11);
+        assert!(registry.gpu::<GpuUtilizationMonitor>(2).is_none());
+    }
+}
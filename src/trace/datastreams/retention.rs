@@ -0,0 +1,422 @@
+use crate::trace::datastreams::time_series::{seconds, TimeSeries, Timestamp};
+use chrono::{Local, TimeZone};
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Duration;
+
+/// How long readings are kept at full resolution by default
+pub const DEFAULT_RAW_RETENTION: Duration = Duration::from_secs(10 * 60);
+
+/// Default rollup tiers: bucket length and how many buckets are kept.
+/// 10s buckets for a day, 1min buckets for a month - about 50k buckets per stream at most.
+pub const DEFAULT_TIERS: [(Duration, usize); 2] = [
+    (Duration::from_secs(10), 24 * 60 * 6),
+    (Duration::from_secs(60), 30 * 24 * 60),
+];
+
+/// How much history every stream keeps
+#[derive(Debug, Clone)]
+pub struct Retention {
+    /// Number of raw readings kept
+    pub raw_len: usize,
+    /// Rollup tiers: bucket length and number of buckets, finest first
+    pub tiers: Vec<(Duration, usize)>,
+}
+
+impl Retention {
+    /// Keep `raw` of full resolution readings taken every `refresh`, then default rollups.
+    pub fn new(refresh: Duration, raw: Duration) -> Self {
+        let refresh = refresh.max(Duration::from_millis(1));
+        Self {
+            raw_len: (raw.as_millis() / refresh.as_millis()) as usize + 1,
+            tiers: DEFAULT_TIERS.to_vec(),
+        }
+    }
+
+    /// Only the latest reading, no rollups - for when nobody looks at charts.
+    pub fn latest_only() -> Self {
+        Self {
+            raw_len: 1,
+            tiers: Vec::new(),
+        }
+    }
+}
+
+/// Min/avg/max of readings within one bucket
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rollup {
+    pub start: Timestamp,
+    pub min: f64,
+    pub max: f64,
+    pub sum: f64,
+    pub count: u64,
+}
+
+impl Rollup {
+    fn new(start: Timestamp, value: f64) -> Self {
+        Self {
+            start,
+            min: value,
+            max: value,
+            sum: value,
+            count: 1,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+        self.count += 1;
+    }
+
+    pub fn avg(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+}
+
+/// Rollups of fixed length buckets, aligned to wall clock (e.g. full minutes).
+#[derive(Debug, Clone)]
+pub struct RollupTier {
+    bucket: Duration,
+    buckets: VecDeque<Rollup>,
+    capacity: usize,
+}
+
+impl RollupTier {
+    pub fn new(bucket: Duration, capacity: usize) -> Self {
+        Self {
+            bucket: bucket.max(Duration::from_millis(1)),
+            buckets: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Bucket length
+    pub fn bucket(&self) -> Duration {
+        self.bucket
+    }
+
+    /// Kept buckets, oldest first
+    pub fn buckets(&self) -> &VecDeque<Rollup> {
+        &self.buckets
+    }
+
+    fn bucket_start(&self, at: Timestamp) -> Timestamp {
+        let ms = self.bucket.as_millis() as i64;
+        let start = at.timestamp_millis().div_euclid(ms) * ms;
+        Local.timestamp_millis_opt(start).single().unwrap_or(at)
+    }
+
+    pub fn push(&mut self, at: Timestamp, value: f64) {
+        let start = self.bucket_start(at);
+        match self.buckets.back_mut() {
+            Some(last) if last.start >= start => last.add(value),
+            _ => {
+                if self.buckets.len() == self.capacity {
+                    self.buckets.pop_front();
+                }
+                self.buckets.push_back(Rollup::new(start, value));
+            }
+        }
+    }
+
+    /// Buckets overlapping `from..=to`
+    pub fn range(&self, from: Timestamp, to: Timestamp) -> impl Iterator<Item = &Rollup> {
+        let from = self.bucket_start(from);
+        let start = self.buckets.partition_point(|r| r.start < from);
+        let end = self.buckets.partition_point(|r| r.start <= to).max(start);
+        self.buckets.range(start..end)
+    }
+
+    fn oldest(&self) -> Option<Timestamp> {
+        self.buckets.front().map(|r| r.start)
+    }
+}
+
+/// Resolution of the data a chart shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// Every reading
+    Raw,
+    /// Averages of buckets of given length
+    Rollup(Duration),
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Resolution::Raw => write!(f, "raw"),
+            Resolution::Rollup(d) if d.as_secs() >= 60 && d.as_secs() % 60 == 0 => {
+                write!(f, "{}m avg", d.as_secs() / 60)
+            }
+            Resolution::Rollup(d) => write!(f, "{}s avg", d.as_secs_f64()),
+        }
+    }
+}
+
+/// Consecutive chart points without a gap
+pub type Segment = Vec<(f64, f64)>;
+
+/// Multi-resolution history of a single value: full resolution for the latest readings,
+/// min/avg/max rollups for the rest of the run.
+#[derive(Debug, Clone)]
+pub struct History {
+    raw: TimeSeries,
+    tiers: Vec<RollupTier>,
+    started: Option<Timestamp>,
+}
+
+impl History {
+    pub fn new(retention: &Retention) -> Self {
+        Self {
+            raw: TimeSeries::new(retention.raw_len),
+            tiers: retention
+                .tiers
+                .iter()
+                .map(|(bucket, capacity)| RollupTier::new(*bucket, *capacity))
+                .collect(),
+            started: None,
+        }
+    }
+
+    pub fn push(&mut self, at: Timestamp, value: f64) {
+        self.started.get_or_insert(at);
+        self.raw.push(at, value);
+        for tier in &mut self.tiers {
+            tier.push(at, value);
+        }
+    }
+
+    /// No reading could be taken at `at`: a gap in raw readings, rollups just get nothing.
+    pub fn push_gap(&mut self, at: Timestamp) {
+        self.started.get_or_insert(at);
+        self.raw.push_gap(at);
+    }
+
+    /// Full resolution readings
+    pub fn raw(&self) -> &TimeSeries {
+        &self.raw
+    }
+
+    /// Rollup tiers, finest first
+    pub fn tiers(&self) -> &[RollupTier] {
+        &self.tiers
+    }
+
+    /// Most recent reading, gaps skipped
+    pub fn latest(&self) -> Option<(Timestamp, f64)> {
+        self.raw.iter().rev().find(|(_, v)| !v.is_nan()).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+
+    /// Finest resolution that still has data back to `from` (or back to the start of the run).
+    pub fn resolution_for(&self, from: Timestamp) -> Resolution {
+        let Some(started) = self.started else {
+            return Resolution::Raw;
+        };
+        let needed = from.max(started);
+        if self.raw.oldest().is_some_and(|(t, _)| t <= needed) {
+            return Resolution::Raw;
+        }
+        self.tiers
+            .iter()
+            .find(|tier| tier.oldest().is_some_and(|t| t <= needed))
+            .or(self.tiers.last())
+            .map_or(Resolution::Raw, |tier| Resolution::Rollup(tier.bucket))
+    }
+
+    fn tier(&self, resolution: Resolution) -> Option<&RollupTier> {
+        match resolution {
+            Resolution::Raw => None,
+            Resolution::Rollup(bucket) => self.tiers.iter().find(|t| t.bucket == bucket),
+        }
+    }
+
+    /// Largest value within the range, bucket maximums included - so spikes are never averaged away.
+    pub fn max_in(&self, from: Timestamp, to: Timestamp) -> Option<f64> {
+        match self.tier(self.resolution_for(from)) {
+            None => self.raw.max_in(from, to),
+            Some(tier) => tier.range(from, to).map(|r| r.max).reduce(f64::max),
+        }
+    }
+
+    /// Points for charts at the resolution fitting the range, x is seconds relative to `to`.
+    /// Split into segments at gaps (missing readings or rollup buckets), so lines are not drawn across outages.
+    /// More than `max_points` points are downsampled with LTTB, which keeps peaks and dips.
+    pub fn chart_points(&self, from: Timestamp, to: Timestamp, max_points: usize) -> (Resolution, Vec<Segment>) {
+        let resolution = self.resolution_for(from);
+        let mut segments: Vec<Segment> = Vec::new();
+        match self.tier(resolution) {
+            None => {
+                let mut gap = true;
+                for (t, v) in self.raw.range(from, to) {
+                    if v.is_nan() {
+                        gap = true;
+                        continue;
+                    }
+                    if std::mem::take(&mut gap) {
+                        segments.push(Vec::new());
+                    }
+                    segments.last_mut().expect("segment started above").push((seconds(*t - to), *v));
+                }
+            }
+            Some(tier) => {
+                let half = chrono::Duration::from_std(tier.bucket / 2).unwrap_or_default();
+                let bucket = chrono::Duration::from_std(tier.bucket).unwrap_or_default();
+                let mut previous: Option<Timestamp> = None;
+                for r in tier.range(from, to) {
+                    if previous.is_none_or(|p| r.start - p > bucket) {
+                        segments.push(Vec::new());
+                    }
+                    previous = Some(r.start);
+                    let point = (seconds(r.start + half - to).min(0.0), r.avg());
+                    segments.last_mut().expect("segment started above").push(point);
+                }
+            }
+        }
+        // every segment gets its share of the points
+        let total: usize = segments.iter().map(Vec::len).sum();
+        let segments = segments
+            .iter()
+            .map(|points| lttb(points, (max_points * points.len()).div_ceil(total.max(1))))
+            .collect();
+        (resolution, segments)
+    }
+}
+
+/// Largest-Triangle-Three-Buckets downsampling: keeps first and last point and from every bucket
+/// in between the point forming the largest triangle with its neighbours.
+pub fn lttb(points: &[(f64, f64)], threshold: usize) -> Vec<(f64, f64)> {
+    if threshold >= points.len() || threshold < 3 {
+        return points.to_vec();
+    }
+
+    let mut sampled = Vec::with_capacity(threshold);
+    let every = (points.len() - 2) as f64 / (threshold - 2) as f64;
+    let mut a = 0;
+    sampled.push(points[0]);
+
+    for i in 0..threshold - 2 {
+        // average of the next bucket is the third triangle point
+        let next_start = ((i + 1) as f64 * every) as usize + 1;
+        let next_end = (((i + 2) as f64 * every) as usize + 1).min(points.len());
+        let next = &points[next_start..next_end];
+        let (sum_x, sum_y) = next
+            .iter()
+            .fold((0.0, 0.0), |(x, y), p| (x + p.0, y + p.1));
+        let (avg_x, avg_y) = (sum_x / next.len() as f64, sum_y / next.len() as f64);
+
+        let start = (i as f64 * every) as usize + 1;
+        let (ax, ay) = points[a];
+        let mut best = start;
+        let mut best_area = -1.0;
+        for (j, (x, y)) in points.iter().enumerate().take(next_start).skip(start) {
+            let area = ((ax - avg_x) * (y - ay) - (ax - x) * (avg_y - ay)).abs();
+            if area > best_area {
+                best_area = area;
+                best = j;
+            }
+        }
+        sampled.push(points[best]);
+        a = best;
+    }
+
+    sampled.push(points[points.len() - 1]);
+    sampled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: i64) -> Timestamp {
+        Local.timestamp_opt(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    #[test]
+    fn test_rollups() {
+        let mut tier = RollupTier::new(Duration::from_secs(10), 2);
+        for (s, v) in [(0, 1.0), (5, 3.0), (12, 7.0), (25, 2.0)] {
+            tier.push(at(s), v);
+        }
+        // first bucket dropped - capacity 2
+        let buckets: Vec<_> = tier.buckets().iter().collect();
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].start, at(10));
+        assert_eq!(buckets[0].avg(), 7.0);
+        assert_eq!(buckets[1].count, 1);
+    }
+
+    #[test]
+    fn test_tier_switching() {
+        let retention = Retention {
+            raw_len: 10,
+            tiers: vec![(Duration::from_secs(10), 100), (Duration::from_secs(60), 100)],
+        };
+        let mut history = History::new(&retention);
+        for s in 0..600 {
+            history.push(at(s), (s % 60) as f64);
+        }
+
+        // last 10 readings are raw
+        assert_eq!(history.resolution_for(at(595)), Resolution::Raw);
+        assert_eq!(history.resolution_for(at(300)), Resolution::Rollup(Duration::from_secs(10)));
+        // asking for more than the run still fits the finest tier holding whole run
+        assert_eq!(history.resolution_for(at(-600)), Resolution::Rollup(Duration::from_secs(10)));
+
+        let (resolution, segments) = history.chart_points(at(0), at(599), 1000);
+        assert_eq!(resolution, Resolution::Rollup(Duration::from_secs(10)));
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].len(), 60);
+        assert_eq!(segments[0][0].1, 4.5); // average of 0..=9
+        assert_eq!(history.max_in(at(0), at(599)), Some(59.0));
+    }
+
+    #[test]
+    fn test_gaps_split_segments() {
+        let history = |raw_len| {
+            let retention = Retention {
+                raw_len,
+                tiers: vec![(Duration::from_secs(10), 100)],
+            };
+            let mut history = History::new(&retention);
+            for s in 0..60 {
+                // the tool was not answering for half a minute
+                if (20..50).contains(&s) {
+                    history.push_gap(at(s));
+                } else {
+                    history.push(at(s), 1.0);
+                }
+            }
+            history
+        };
+
+        let (resolution, segments) = history(100).chart_points(at(0), at(59), 1000);
+        assert_eq!(resolution, Resolution::Raw);
+        let lengths: Vec<usize> = segments.iter().map(Vec::len).collect();
+        assert_eq!(lengths, vec![20, 10]);
+        assert_eq!(segments[0].last().unwrap().0, -40.0);
+        assert_eq!(segments[1][0].0, -9.0);
+
+        // rollups have no buckets for the outage
+        let (resolution, segments) = history(10).chart_points(at(0), at(59), 1000);
+        assert_eq!(resolution, Resolution::Rollup(Duration::from_secs(10)));
+        let lengths: Vec<usize> = segments.iter().map(Vec::len).collect();
+        assert_eq!(lengths, vec![2, 1]);
+    }
+
+    #[test]
+    fn test_lttb_keeps_spike() {
+        let mut points: Vec<(f64, f64)> = (0..1000).map(|i| (i as f64, 0.0)).collect();
+        points[500].1 = 100.0;
+        let sampled = lttb(&points, 20);
+        assert_eq!(sampled.len(), 20);
+        assert_eq!(sampled[0], points[0]);
+        assert_eq!(sampled[19], points[999]);
+        assert!(sampled.contains(&(500.0, 100.0)));
+    }
+}
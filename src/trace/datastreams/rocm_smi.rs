@@ -0,0 +1,440 @@
+use crate::error::GpuError;
+use crate::trace::datastreams::gpu_backend::GpuBackend;
+use crate::trace::datastreams::gpu_data::{
+    GpuClocks, GpuInfo, GpuMemory, GpuPower, GpuProcess, GpuReadings, GpuTemperature,
+    GpuUtilization,
+};
+use crate::trace::datastreams::gpu_health::{GpuBackoff, GpuHealth};
+use crate::trace::datastreams::tool_runner::ToolRunner;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// Places rocm-smi is looked for, first found wins
+const ROCM_SMI_PATHS: [&str; 3] = ["rocm-smi", "/opt/rocm/bin/rocm-smi", "/usr/bin/rocm-smi"];
+
+/// Lists the cards, to see that rocm-smi works at all
+const ID_ARGS: [&str; 2] = ["--showid", "--json"];
+
+/// Everything read per sample. `--showmeminfo vram` gives the bytes behind `--showmemuse`.
+const QUERY_ARGS: [&str; 10] = [
+    "--showproductname", "--showuse", "--showmemuse", "--showmeminfo", "vram", "--showtemp",
+    "--showpower", "--showclocks", "--showpids", "--json",
+];
+
+/// Which GPUs each process uses. There is no JSON for it, so the text is parsed.
+const PID_GPUS_ARGS: [&str; 1] = ["--showpidgpus"];
+
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+/// rocm-smi integration for AMD GPUs. Metrics rocm-smi does not report for a card
+/// (no power sensor on an APU, say) stay `None`, as with nvidia-smi.
+pub struct RocmSmiMonitor {
+    /// Runs rocm-smi, or replays a recording of it
+    runner: ToolRunner,
+    /// Whether rocm-smi is available
+    available: bool,
+    /// Last error, while the backend is degraded or why it is not available
+    last_error: Option<GpuError>,
+    /// Health and backoff between retries while rocm-smi fails
+    backoff: GpuBackoff,
+}
+
+impl RocmSmiMonitor {
+    /// Killing calls that take longer than `timeout`
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self::with_runner(ToolRunner::new(&Self::find_path(), timeout))
+    }
+
+    /// Uses `runner` to call rocm-smi - a capturing or replaying one, say
+    pub fn with_runner(runner: ToolRunner) -> Self {
+        let mut monitor = Self {
+            runner,
+            available: true,
+            last_error: None,
+            backoff: GpuBackoff::new(),
+        };
+        if let Err(e) = monitor.runner.run(&ID_ARGS).and_then(|output| parse_json(&output)) {
+            monitor.available = false;
+            monitor.last_error = Some(e);
+        }
+        monitor
+    }
+
+    /// Find rocm-smi executable path, falling back to `PATH`
+    pub fn find_path() -> String {
+        ToolRunner::find(&ROCM_SMI_PATHS)
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.available
+    }
+
+    pub fn get_last_error(&self) -> Option<&GpuError> {
+        self.last_error.as_ref()
+    }
+
+    /// Reads all cards and the processes using them, backing off while rocm-smi fails
+    pub fn get_gpu_info(&mut self) -> Result<GpuReadings, GpuError> {
+        let now = Instant::now();
+        self.backoff.check(now)?;
+        let result = if self.available {
+            self.runner
+                .run(&QUERY_ARGS)
+                .and_then(|output| parse_readings(&output))
+                .and_then(|readings| self.assign_processes(readings))
+        } else {
+            Err(self
+                .last_error
+                .clone()
+                .unwrap_or_else(|| GpuError::NotInstalled(self.runner.program().to_string())))
+        };
+        let result = self.backoff.record(now, result);
+        self.last_error = result.as_ref().err().cloned();
+        result
+    }
+}
+
+impl RocmSmiMonitor {
+    /// Books processes on the GPUs they use. When rocm-smi cannot tell (the call fails or does not
+    /// list the process), the process is left out rather than guessed - only a hung rocm-smi fails the reading.
+    fn assign_processes(&self, mut readings: GpuReadings) -> Result<GpuReadings, GpuError> {
+        if readings.processes.is_empty() {
+            return Ok(readings);
+        }
+        let pid_gpus = match self.runner.run(&PID_GPUS_ARGS) {
+            Ok(output) => parse_pid_gpus(&output),
+            Err(e @ GpuError::Timeout { .. }) => return Err(e),
+            Err(e) => {
+                log::debug!("rocm-smi --showpidgpus: {}", e);
+                BTreeMap::new()
+            }
+        };
+        readings.processes = assign_gpus(std::mem::take(&mut readings.processes), &pid_gpus);
+        Ok(readings)
+    }
+}
+
+impl GpuBackend for RocmSmiMonitor {
+    fn name(&self) -> &'static str {
+        if self.runner.is_replay() {
+            "rocm-smi replay"
+        } else {
+            "rocm-smi"
+        }
+    }
+
+    fn get_gpu_info(&mut self) -> Result<GpuReadings, GpuError> {
+        RocmSmiMonitor::get_gpu_info(self)
+    }
+
+    fn health(&self) -> &GpuHealth {
+        self.backoff.health()
+    }
+}
+
+/// Top level JSON object. Older rocm-smi versions print warnings ahead of it, so they are skipped.
+fn parse_json(output: &str) -> Result<Map<String, Value>, GpuError> {
+    let json = output.find('{').map_or("", |start| &output[start..]);
+    serde_json::from_str(json).map_err(|e| GpuError::Parse {
+        field: "rocm-smi JSON".to_string(),
+        value: e.to_string(),
+    })
+}
+
+fn parse_readings(output: &str) -> Result<GpuReadings, GpuError> {
+    let mut readings = GpuReadings::new();
+    for (key, value) in parse_json(output)? {
+        let fields = CardFields::new(&key, &value);
+        if let Some(index) = key.strip_prefix("card").and_then(|i| i.parse().ok()) {
+            readings.gpus.push(parse_card(index, &fields)?);
+        } else if key == "system" {
+            readings.processes = parse_processes(&fields)?;
+        }
+    }
+    readings.gpus.sort_by_key(|gpu| gpu.index);
+    readings.gpu_count = readings.gpus.len() as u32;
+    Ok(readings)
+}
+
+fn parse_card(index: u32, f: &CardFields) -> Result<GpuInfo, GpuError> {
+    let total = f.num("VRAM Total Memory (B)")?.map_or(0, |b| b as u64 / BYTES_PER_MB);
+    let used = f.num("VRAM Total Used Memory (B)")?.map_or(0, |b| b as u64 / BYTES_PER_MB);
+    let name = f.text(&["Card Series", "Card series", "Card model"]);
+    Ok(GpuInfo {
+        index,
+        name: if name.is_empty() { format!("AMD GPU {}", index) } else { name },
+        memory: GpuMemory {
+            total,
+            used,
+            free: total.saturating_sub(used),
+            ..GpuMemory::default()
+        },
+        utilization: GpuUtilization {
+            gpu: f.num("GPU use (%)")?.unwrap_or(0.0) as u32,
+            memory: f
+                .first_num(&["GPU memory use (%)", "GPU Memory Read/Write Activity (%)"])?
+                .unwrap_or(0.0) as u32,
+            ..GpuUtilization::default()
+        },
+        temperature: GpuTemperature {
+            gpu: f
+                .first_num(&["Temperature (Sensor edge) (C)", "Temperature (Sensor junction) (C)"])?
+                .map(|t| t.round() as i32),
+            gpu_tlimit: None,
+            memory: f.num("Temperature (Sensor memory) (C)")?.map(|t| t.round() as i32),
+        },
+        power: GpuPower {
+            draw: f
+                .first_num(&[
+                    "Average Graphics Package Power (W)",
+                    "Current Socket Graphics Package Power (W)",
+                ])?
+                .map(|w| w as f32),
+            ..GpuPower::default()
+        },
+        clocks: GpuClocks {
+            graphics: f.clock("sclk clock speed:")?,
+            memory: f.clock("mclk clock speed:")?,
+            ..GpuClocks::default()
+        },
+        timestamp: chrono::Local::now().format("%Y/%m/%d %H:%M:%S%.3f").to_string(),
+        ..GpuInfo::default()
+    })
+}
+
+/// `"PID4242": "python3, 1, 8589934592, 0, 0"` - name, GPU count, VRAM bytes, SDMA, CU occupancy.
+/// Which GPUs a process uses comes from another call, see `assign_gpus`.
+fn parse_processes(f: &CardFields) -> Result<Vec<GpuProcess>, GpuError> {
+    let mut processes = Vec::new();
+    for (key, value) in f.fields.iter() {
+        let Some(pid) = key.strip_prefix("PID").and_then(|p| p.parse().ok()) else {
+            continue;
+        };
+        let value = value.as_str().unwrap_or_default();
+        let parts: Vec<&str> = value.split(',').map(str::trim).collect();
+        let vram = parts.get(2).copied().unwrap_or("0");
+        let bytes: u64 = vram.parse().map_err(|_| GpuError::Parse {
+            field: format!("system {}", key),
+            value: value.to_string(),
+        })?;
+        processes.push(GpuProcess {
+            pid,
+            process_name: parts[0].to_string(),
+            gpu_uuid: String::new(),
+            used_memory: bytes / BYTES_PER_MB,
+            gpu_index: 0, // until assigned
+            utilization: None,
+            gpu_instance_id: None,
+            compute_instance_id: None,
+            encoder_utilization: None,
+            decoder_utilization: None,
+        });
+    }
+    Ok(processes)
+}
+
+/// GPUs of each process from `--showpidgpus`:
+/// `PID 4242 is using 2 DRM device(s):` and the indices on the next line.
+fn parse_pid_gpus(output: &str) -> BTreeMap<u32, Vec<u32>> {
+    let mut pid_gpus = BTreeMap::new();
+    let mut lines = output.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        let Some(rest) = line.strip_prefix("PID ") else {
+            continue;
+        };
+        let Some((pid, count)) = rest.split_once(" is using ") else {
+            continue;
+        };
+        let (Ok(pid), Some(Ok(count))) = (pid.parse::<u32>(), count.split_whitespace().next().map(str::parse::<usize>))
+        else {
+            continue;
+        };
+        let gpus: Vec<u32> = match count {
+            0 => Vec::new(),
+            _ => lines
+                .next()
+                .unwrap_or_default()
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter_map(|i| i.parse().ok())
+                .collect(),
+        };
+        pid_gpus.insert(pid, gpus);
+    }
+    pid_gpus
+}
+
+/// One entry per GPU a process uses. rocm-smi reports VRAM per process only, so it is split evenly.
+fn assign_gpus(processes: Vec<GpuProcess>, pid_gpus: &BTreeMap<u32, Vec<u32>>) -> Vec<GpuProcess> {
+    let mut assigned = Vec::new();
+    for process in processes {
+        let gpus = pid_gpus.get(&process.pid).map(Vec::as_slice).unwrap_or_default();
+        if gpus.is_empty() {
+            log::debug!("rocm-smi does not say which GPU process {} uses, left out", process.pid);
+        }
+        for gpu in gpus {
+            assigned.push(GpuProcess {
+                gpu_index: *gpu,
+                used_memory: process.used_memory / gpus.len() as u64,
+                ..process.clone()
+            });
+        }
+    }
+    assigned
+}
+
+/// Values of one card (or the `system` section), all of them strings, for errors that say which field broke
+struct CardFields<'a> {
+    card: &'a str,
+    fields: Map<String, Value>,
+}
+
+impl<'a> CardFields<'a> {
+    fn new(card: &'a str, value: &Value) -> Self {
+        Self {
+            card,
+            fields: value.as_object().cloned().unwrap_or_default(),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.fields.get(key).and_then(Value::as_str).map(str::trim)
+    }
+
+    /// First of `keys` that is there
+    fn text(&self, keys: &[&str]) -> String {
+        keys.iter()
+            .find_map(|k| self.get(k))
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    /// Number of `key` - missing and N/A are `None`, anything else unparsable is an error
+    fn num(&self, key: &str) -> Result<Option<f64>, GpuError> {
+        match self.get(key) {
+            None | Some("N/A") | Some("") => Ok(None),
+            Some(value) => value.parse().map(Some).map_err(|_| self.error(key, value)),
+        }
+    }
+
+    /// Number of the first of `keys` that has one
+    fn first_num(&self, keys: &[&str]) -> Result<Option<f64>, GpuError> {
+        for key in keys {
+            if let Some(value) = self.num(key)? {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Clock like `(1502Mhz)` in MHz
+    fn clock(&self, key: &str) -> Result<Option<u32>, GpuError> {
+        let Some(value) = self.get(key) else {
+            return Ok(None);
+        };
+        let mhz = value
+            .trim_matches(|c| c == '(' || c == ')')
+            .trim_end_matches("Mhz")
+            .trim_end_matches("MHz");
+        mhz.parse().map(Some).map_err(|_| self.error(key, value))
+    }
+
+    fn error(&self, key: &str, value: &str) -> GpuError {
+        GpuError::Parse {
+            field: format!("{} {}", self.card, key),
+            value: value.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MI250: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/rocm-smi/mi250.json"));
+    const RADEON: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/rocm-smi/radeon.json"));
+    const PID_GPUS: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/rocm-smi/showpidgpus.txt"));
+
+    #[test]
+    fn test_parse_recorded_output() {
+        let readings = parse_readings(MI250).unwrap();
+        assert_eq!(readings.gpu_count, 2);
+        let gpu = &readings.gpus[1];
+        assert_eq!(gpu.index, 1);
+        assert_eq!(gpu.name, "AMD INSTINCT MI250 (MCM) OAM AC MBA");
+        assert_eq!((gpu.memory.used, gpu.memory.total), (30720, 65520));
+        assert_eq!((gpu.utilization.gpu, gpu.utilization.memory), (87, 41));
+        assert_eq!((gpu.temperature.gpu, gpu.temperature.memory), (Some(61), Some(66)));
+        assert_eq!(gpu.power.draw, Some(312.0));
+        assert_eq!((gpu.clocks.graphics, gpu.clocks.memory), (Some(1700), Some(1600)));
+        assert_eq!(readings.processes.len(), 1);
+        assert_eq!(readings.processes[0].pid, 4242);
+        assert_eq!(readings.processes[0].process_name, "python3");
+        assert_eq!(readings.processes[0].used_memory, 30720);
+
+        let pid_gpus = parse_pid_gpus(PID_GPUS);
+        assert_eq!(pid_gpus.get(&4242), Some(&vec![1]));
+        assert_eq!(pid_gpus.get(&5150), Some(&vec![0, 1]));
+        assert_eq!(pid_gpus.get(&6000), Some(&vec![]));
+        let mut processes = readings.processes.clone();
+        processes.push(GpuProcess {
+            pid: 5150,
+            used_memory: 2048,
+            ..readings.processes[0].clone()
+        });
+        processes.push(GpuProcess {
+            pid: 7000, // not listed - no guessing
+            ..readings.processes[0].clone()
+        });
+        let assigned: Vec<(u32, u32, u64)> = assign_gpus(processes, &pid_gpus)
+            .iter()
+            .map(|p| (p.pid, p.gpu_index, p.used_memory))
+            .collect();
+        assert_eq!(assigned, vec![(4242, 1, 30720), (5150, 0, 1024), (5150, 1, 1024)]);
+
+        // a desktop card without junction, memory sensor or power - and a warning ahead of the JSON
+        let readings = parse_readings(RADEON).unwrap();
+        let gpu = &readings.gpus[0];
+        assert_eq!(gpu.temperature.gpu, Some(45));
+        assert_eq!(gpu.temperature.memory, None);
+        assert_eq!(gpu.power.draw, None);
+        assert!(readings.processes.is_empty());
+
+        assert!(matches!(
+            parse_readings("{\"card0\": {\"GPU use (%)\": \"lots\"}}"),
+            Err(GpuError::Parse { field, .. }) if field == "card0 GPU use (%)"
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fake_rocm_smi() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = dir.path().join("mi250.json");
+        std::fs::write(&fixture, MI250).unwrap();
+        let path = dir.path().join("rocm-smi");
+        let script = format!(
+            "#!/bin/sh\ncase \"$1\" in\n\
+             --showid) echo '{{\"card0\": {{\"GPU ID\": \"0x740c\"}}, \"card1\": {{\"GPU ID\": \"0x740c\"}}}}' ;;\n\
+             --showpidgpus) printf 'PID 4242 is using 1 DRM device(s):\\n1\\n' ;;\n\
+             *) cat '{}' ;;\n\
+             esac\n",
+            fixture.display()
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut monitor = RocmSmiMonitor::with_runner(ToolRunner::new(&path.to_string_lossy(), Duration::from_secs(5)));
+        assert!(monitor.is_available(), "{:?}", monitor.get_last_error());
+        let readings = monitor.get_gpu_info().unwrap();
+        assert_eq!(readings.gpus.len(), 2);
+        assert_eq!(readings.get_gpu_processes(1)[0].pid, 4242);
+        assert!(GpuBackend::health(&monitor).is_healthy());
+
+        let missing = RocmSmiMonitor::with_runner(ToolRunner::new("/nonexistent/rocm-smi", Duration::from_secs(1)));
+        assert!(!missing.is_available());
+        assert!(matches!(missing.get_last_error(), Some(GpuError::NotInstalled(_))));
+    }
+}
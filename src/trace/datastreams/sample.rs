@@ -0,0 +1,32 @@
+use crate::trace::datastreams::cgroup::CgroupReadings;
+use crate::trace::datastreams::data_stream::Readings;
+use crate::trace::datastreams::gpu_data::{GpuAccountedProcess, GpuReadings};
+use crate::trace::datastreams::gpu_health::GpuHealth;
+use crate::trace::datastreams::numa::ProcessAffinity;
+use crate::trace::datastreams::time_series::Timestamp;
+use std::collections::BTreeMap;
+
+/// Everything collected at one point in time - the single input of every `SysDataStream`.
+#[derive(Debug, Clone)]
+pub struct Sample {
+    /// Number of the sample, counted from 1
+    pub index: u64,
+    /// When it was taken
+    pub time: Timestamp,
+    /// CPU and memory of traced processes
+    pub readings: Readings,
+    /// cgroup counters, when tracing a cgroup
+    pub cgroup: Option<CgroupReadings>,
+    /// PIDs of the traced process tree or cgroup members
+    pub traced_pids: Vec<u32>,
+    /// GPU readings, when GPUs are monitored and reading them worked
+    pub gpu: Option<GpuReadings>,
+    /// Health of the GPU backend, when GPUs are monitored
+    pub gpu_health: Option<GpuHealth>,
+    /// Application metrics extracted from its output since the previous sample
+    pub metrics: BTreeMap<String, f64>,
+    /// Where the traced processes using a GPU may run and allocate memory
+    pub affinity: Vec<ProcessAffinity>,
+    /// GPU processes of the traced tree that exited since the previous sample, as accounting recorded them
+    pub accounted: Vec<GpuAccountedProcess>,
+}
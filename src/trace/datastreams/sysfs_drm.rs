@@ -0,0 +1,573 @@
+use crate::error::GpuError;
+use crate::trace::datastreams::gpu_backend::GpuBackend;
+use crate::trace::datastreams::gpu_data::{
+    GpuClocks, GpuInfo, GpuMemory, GpuPower, GpuProcess, GpuReadings, GpuTemperature,
+    GpuUtilization,
+};
+use crate::trace::datastreams::gpu_health::{GpuBackoff, GpuHealth};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Default mount point of sysfs
+pub const DEFAULT_SYSFS_ROOT: &str = "/sys";
+/// Default mount point of procfs
+pub const DEFAULT_PROCFS_ROOT: &str = "/proc";
+
+const BYTES_PER_MB: u64 = 1024 * 1024;
+
+/// Drivers whose sysfs and fdinfo are read - display-only ones (ast, mgag200, virtio_gpu, bochs-drm, ...)
+/// have cards too, but no GPU worth tracing
+const DRIVERS: [&str; 3] = ["amdgpu", "i915", "xe"];
+
+/// Per-client memory in fdinfo, first one found wins: amdgpu, then the common DRM keys
+/// for dedicated (vram/local) and shared (system) memory.
+const FDINFO_MEMORY_KEYS: [&str; 5] = [
+    "drm-memory-vram",
+    "drm-total-vram0",
+    "drm-total-local0",
+    "drm-total-system0",
+    "drm-total-system",
+];
+
+/// One DRM card found in sysfs
+#[derive(Debug, Clone)]
+struct DrmCard {
+    /// N of `cardN`
+    index: u32,
+    /// `/sys/class/drm/cardN`
+    dir: PathBuf,
+    /// Kernel driver: amdgpu, i915, xe, ...
+    driver: String,
+    /// PCI slot, matches `drm-pdev` of fdinfo
+    pci_bus_id: String,
+}
+
+impl DrmCard {
+    fn device(&self, file: &str) -> PathBuf {
+        self.dir.join("device").join(file)
+    }
+
+    /// First hwmon directory of the device, where temperatures and power live
+    fn hwmon(&self) -> Option<PathBuf> {
+        let mut dirs: Vec<PathBuf> = fs::read_dir(self.device("hwmon"))
+            .ok()?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .collect();
+        dirs.sort();
+        dirs.into_iter().next()
+    }
+}
+
+/// DRM client (one open GPU file) seen in `/proc/<pid>/fdinfo`
+#[derive(Debug, Clone, Default, PartialEq)]
+struct DrmClient {
+    pdev: String,
+    client_id: u64,
+    /// Bytes of the first of `FDINFO_MEMORY_KEYS` reported
+    memory: u64,
+    /// Busy time per engine
+    engines: BTreeMap<String, EngineTime>,
+}
+
+/// How busy one engine of a client has been so far
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EngineTime {
+    /// Nanoseconds (`drm-engine-gfx: 123 ns`) - amdgpu, i915
+    Ns(u64),
+    /// GPU cycles busy out of the cycles that passed (`drm-cycles-rcs`, `drm-total-cycles-rcs`) - xe
+    Cycles { busy: u64, total: u64 },
+}
+
+impl EngineTime {
+    /// Busy since `was`, with the cycles that passed when counted in cycles - `None` when they do not compare
+    fn since(self, was: EngineTime) -> Option<(u64, Option<u64>)> {
+        match (self, was) {
+            (Self::Ns(ns), Self::Ns(was)) => Some((ns.saturating_sub(was), None)),
+            (Self::Cycles { busy, total }, Self::Cycles { busy: was, total: was_total }) => {
+                Some((busy.saturating_sub(was), Some(total.saturating_sub(was_total))))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Vendor neutral backend reading Linux sysfs and DRM fdinfo - no vendor tools needed.
+/// Works for amdgpu, i915 and xe; what a driver does not expose stays `None`.
+pub struct SysfsDrmMonitor {
+    /// Where `class/drm` is looked for
+    sysfs_root: PathBuf,
+    /// Where `<pid>/fdinfo` is looked for
+    procfs_root: PathBuf,
+    cards: Vec<DrmCard>,
+    /// Engine busy time per (pdev, client id, engine) at the last reading, for utilization
+    last_engines: HashMap<(String, u64, String), EngineTime>,
+    last_read: Option<Instant>,
+    available: bool,
+    last_error: Option<GpuError>,
+    backoff: GpuBackoff,
+}
+
+impl SysfsDrmMonitor {
+    /// Looks for cards in `<sysfs_root>/class/drm` and their clients in `<procfs_root>/*/fdinfo`
+    pub fn new(sysfs_root: impl AsRef<Path>, procfs_root: impl AsRef<Path>) -> Self {
+        let mut monitor = Self {
+            sysfs_root: sysfs_root.as_ref().to_path_buf(),
+            procfs_root: procfs_root.as_ref().to_path_buf(),
+            cards: Vec::new(),
+            last_engines: HashMap::new(),
+            last_read: None,
+            available: true,
+            last_error: None,
+            backoff: GpuBackoff::new(),
+        };
+        match monitor.find_cards() {
+            Ok(cards) if !cards.is_empty() => monitor.cards = cards,
+            Ok(_) => {
+                let drm = monitor.sysfs_root.join("class/drm");
+                monitor.available = false;
+                monitor.last_error = Some(GpuError::NotInstalled(format!("GPU under {}", drm.display())));
+            }
+            Err(e) => {
+                monitor.available = false;
+                monitor.last_error = Some(e);
+            }
+        }
+        monitor
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.available
+    }
+
+    pub fn get_last_error(&self) -> Option<&GpuError> {
+        self.last_error.as_ref()
+    }
+
+    /// Reads all cards and the processes using them, backing off while sysfs fails
+    pub fn get_gpu_info(&mut self) -> Result<GpuReadings, GpuError> {
+        let now = Instant::now();
+        self.backoff.check(now)?;
+        let result = if self.available {
+            self.read(now)
+        } else {
+            Err(self
+                .last_error
+                .clone()
+                .unwrap_or_else(|| GpuError::NotInstalled("GPU".to_string())))
+        };
+        let result = self.backoff.record(now, result);
+        self.last_error = result.as_ref().err().cloned();
+        result
+    }
+
+    /// `cardN` entries (not connectors like `card0-DP-1`) of `DRIVERS` with a PCI device behind them
+    fn find_cards(&self) -> Result<Vec<DrmCard>, GpuError> {
+        let drm = self.sysfs_root.join("class/drm");
+        let entries = match fs::read_dir(&drm) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(GpuError::Io(format!("Cannot read {}: {}", drm.display(), e))),
+        };
+        let mut cards = Vec::new();
+        for entry in entries.filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Some(index) = name.strip_prefix("card").and_then(|i| i.parse().ok()) else {
+                continue;
+            };
+            let dir = entry.path();
+            let uevent = fs::read_to_string(dir.join("device/uevent")).unwrap_or_default();
+            let Some(pci_bus_id) = uevent_value(&uevent, "PCI_SLOT_NAME") else {
+                continue;
+            };
+            let driver = uevent_value(&uevent, "DRIVER").unwrap_or_default();
+            if !DRIVERS.contains(&driver.as_str()) {
+                log::debug!("Skipping {} of driver {:?}", name, driver);
+                continue;
+            }
+            cards.push(DrmCard {
+                index,
+                dir,
+                driver,
+                pci_bus_id,
+            });
+        }
+        cards.sort_by_key(|card| card.index);
+        Ok(cards)
+    }
+
+    fn read(&mut self, now: Instant) -> Result<GpuReadings, GpuError> {
+        let clients = self.read_clients();
+        let busy = self.engine_busy(&clients, now);
+
+        let mut readings = GpuReadings::new();
+        for card in &self.cards {
+            if !card.dir.exists() {
+                return Err(GpuError::Io(format!("{} is gone", card.dir.display())));
+            }
+            readings.gpus.push(read_card(card, busy.get(&card.pci_bus_id).copied()));
+        }
+        readings.gpu_count = readings.gpus.len() as u32;
+
+        for (pid, clients) in clients {
+            // memory per card, a process may use several
+            let mut per_card: BTreeMap<u32, u64> = BTreeMap::new();
+            for client in clients {
+                if let Some(card) = self.cards.iter().find(|c| c.pci_bus_id == client.pdev) {
+                    *per_card.entry(card.index).or_default() += client.memory;
+                }
+            }
+            let process_name = fs::read_to_string(self.procfs_root.join(pid.to_string()).join("comm"))
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            for (gpu_index, memory) in per_card {
+                readings.processes.push(GpuProcess {
+                    pid,
+                    process_name: process_name.clone(),
+                    gpu_uuid: String::new(),
+                    used_memory: memory / BYTES_PER_MB,
+                    gpu_index,
+                    utilization: None,
+                    gpu_instance_id: None,
+                    compute_instance_id: None,
+                    encoder_utilization: None,
+                    decoder_utilization: None,
+                });
+            }
+        }
+        Ok(readings)
+    }
+
+    /// DRM clients per PID. A client shows up in every fd sharing it (dup, fork) - only its first
+    /// PID gets it. fdinfo of other users' processes is not readable and skipped.
+    fn read_clients(&self) -> BTreeMap<u32, Vec<DrmClient>> {
+        let mut pids: Vec<u32> = fs::read_dir(&self.procfs_root)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok()?.file_name().to_str()?.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        pids.sort_unstable();
+
+        let mut seen = Vec::new();
+        let mut clients: BTreeMap<u32, Vec<DrmClient>> = BTreeMap::new();
+        for pid in pids {
+            let Ok(fds) = fs::read_dir(self.procfs_root.join(pid.to_string()).join("fdinfo")) else {
+                continue;
+            };
+            let mut paths: Vec<PathBuf> = fds.filter_map(|e| e.ok().map(|e| e.path())).collect();
+            paths.sort();
+            for path in paths {
+                let Some(client) = fs::read_to_string(path).ok().and_then(|info| parse_fdinfo(&info)) else {
+                    continue;
+                };
+                let key = (client.pdev.clone(), client.client_id);
+                if !seen.contains(&key) {
+                    seen.push(key);
+                    clients.entry(pid).or_default().push(client);
+                }
+            }
+        }
+        clients
+    }
+
+    /// Busiest engine per card (by pdev) in percent since the last reading, from fdinfo engine times:
+    /// nanoseconds out of the time passed, or cycles out of the cycles passed
+    fn engine_busy(&mut self, clients: &BTreeMap<u32, Vec<DrmClient>>, now: Instant) -> HashMap<String, u32> {
+        let elapsed = self.last_read.map(|at| now.saturating_duration_since(at).as_nanos() as u64);
+        self.last_read = Some(now);
+
+        let mut engines = HashMap::new();
+        // busy per (pdev, engine), and the cycles that passed when it is counted in cycles
+        let mut busy_time: HashMap<(String, String), (u64, Option<u64>)> = HashMap::new();
+        for client in clients.values().flatten() {
+            for (engine, time) in &client.engines {
+                let key = (client.pdev.clone(), client.client_id, engine.clone());
+                if let Some((busy, passed)) = self.last_engines.get(&key).and_then(|was| time.since(*was)) {
+                    let entry = busy_time.entry((client.pdev.clone(), engine.clone())).or_default();
+                    entry.0 += busy;
+                    entry.1 = entry.1.max(passed);
+                }
+                engines.insert(key, *time);
+            }
+        }
+        self.last_engines = engines;
+
+        let mut busy = HashMap::new();
+        for ((pdev, _), (time, cycles)) in busy_time {
+            let Some(passed) = cycles.or(elapsed).filter(|passed| *passed > 0) else {
+                continue;
+            };
+            let percent = (time.saturating_mul(100) / passed).min(100) as u32;
+            let max = busy.entry(pdev).or_insert(0);
+            *max = percent.max(*max);
+        }
+        busy
+    }
+}
+
+impl GpuBackend for SysfsDrmMonitor {
+    fn name(&self) -> &'static str {
+        "sysfs"
+    }
+
+    fn get_gpu_info(&mut self) -> Result<GpuReadings, GpuError> {
+        SysfsDrmMonitor::get_gpu_info(self)
+    }
+
+    fn health(&self) -> &GpuHealth {
+        self.backoff.health()
+    }
+}
+
+/// Everything sysfs knows about the card. `engine_busy` stands in for `gpu_busy_percent`
+/// on drivers without it (i915, xe).
+fn read_card(card: &DrmCard, engine_busy: Option<u32>) -> GpuInfo {
+    let hwmon = card.hwmon();
+    let sensor = |kind: &str, label: &str| hwmon.as_deref().and_then(|dir| labeled(dir, kind, label));
+    let temperature = |label| sensor("temp", label).map(|mc| (mc as f64 / 1000.0).round() as i32);
+    let mhz = |label| sensor("freq", label).map(|hz| (hz / 1_000_000) as u32);
+    let watts = |file: &str| {
+        hwmon
+            .as_deref()
+            .and_then(|dir| read_u64(&dir.join(file)))
+            .map(|uw| uw as f32 / 1_000_000.0)
+    };
+
+    let total = read_u64(&card.device("mem_info_vram_total")).unwrap_or(0) / BYTES_PER_MB;
+    let used = read_u64(&card.device("mem_info_vram_used")).unwrap_or(0) / BYTES_PER_MB;
+    let name = fs::read_to_string(card.device("product_name"))
+        .map(|n| n.trim().to_string())
+        .ok()
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| format!("{} card{}", card.driver, card.index));
+
+    GpuInfo {
+        index: card.index,
+        name,
+        pci_bus_id: card.pci_bus_id.clone(),
+        memory: GpuMemory {
+            total,
+            used,
+            free: total.saturating_sub(used),
+            ..GpuMemory::default()
+        },
+        utilization: GpuUtilization {
+            gpu: read_u64(&card.device("gpu_busy_percent"))
+                .map(|p| p as u32)
+                .or(engine_busy)
+                .unwrap_or(0),
+            memory: read_u64(&card.device("mem_busy_percent")).unwrap_or(0) as u32,
+            ..GpuUtilization::default()
+        },
+        temperature: GpuTemperature {
+            gpu: temperature("edge").or_else(|| temperature("")),
+            gpu_tlimit: None,
+            memory: temperature("mem"),
+        },
+        power: GpuPower {
+            draw: watts("power1_average").or_else(|| watts("power1_input")),
+            limit: watts("power1_cap"),
+            ..GpuPower::default()
+        },
+        clocks: GpuClocks {
+            // amdgpu reports clocks in hwmon, i915 next to the card
+            graphics: mhz("sclk").or_else(|| read_u64(&card.dir.join("gt_cur_freq_mhz")).map(|f| f as u32)),
+            memory: mhz("mclk"),
+            max_graphics: read_u64(&card.dir.join("gt_max_freq_mhz")).map(|f| f as u32),
+            ..GpuClocks::default()
+        },
+        timestamp: chrono::Local::now().format("%Y/%m/%d %H:%M:%S%.3f").to_string(),
+        ..GpuInfo::default()
+    }
+}
+
+/// Input of the hwmon sensor of `kind` (temp, freq, ...) labeled `label`;
+/// an empty label takes the first sensor.
+fn labeled(hwmon: &Path, kind: &str, label: &str) -> Option<u64> {
+    (1..=9).find_map(|i| {
+        let input = read_u64(&hwmon.join(format!("{}{}_input", kind, i)))?;
+        if label.is_empty() {
+            return Some(input);
+        }
+        let name = fs::read_to_string(hwmon.join(format!("{}{}_label", kind, i))).ok()?;
+        (name.trim() == label).then_some(input)
+    })
+}
+
+fn read_u64(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn uevent_value(uevent: &str, key: &str) -> Option<String> {
+    uevent
+        .lines()
+        .find_map(|l| l.strip_prefix(key)?.strip_prefix('='))
+        .map(str::to_string)
+}
+
+/// DRM client of one fdinfo file, `None` for files that are not DRM clients.
+/// Sizes come as `123 KiB`, engine times as `456 ns` or as cycles next to their total.
+fn parse_fdinfo(info: &str) -> Option<DrmClient> {
+    let fields: HashMap<&str, &str> = info
+        .lines()
+        .filter_map(|l| l.split_once(':'))
+        .map(|(k, v)| (k.trim(), v.trim()))
+        .collect();
+    fields.get("drm-driver")?;
+    let mut client = DrmClient {
+        pdev: fields.get("drm-pdev")?.to_string(),
+        client_id: fields.get("drm-client-id")?.parse().ok()?,
+        ..DrmClient::default()
+    };
+    client.memory = FDINFO_MEMORY_KEYS
+        .iter()
+        .find_map(|k| fields.get(k).and_then(|v| parse_bytes(v)))
+        .unwrap_or(0);
+    for (key, value) in &fields {
+        if let Some(engine) = key.strip_prefix("drm-engine-") {
+            if let Some(ns) = value.strip_suffix("ns").and_then(|v| v.trim().parse().ok()) {
+                client.engines.insert(engine.to_string(), EngineTime::Ns(ns));
+            }
+        } else if let Some(engine) = key.strip_prefix("drm-cycles-") {
+            let total = fields.get(format!("drm-total-cycles-{}", engine).as_str());
+            if let (Ok(busy), Some(Ok(total))) = (value.parse(), total.map(|t| t.parse())) {
+                client.engines.insert(engine.to_string(), EngineTime::Cycles { busy, total });
+            }
+        }
+    }
+    Some(client)
+}
+
+fn parse_bytes(value: &str) -> Option<u64> {
+    let mut parts = value.split_whitespace();
+    let number: u64 = parts.next()?.parse().ok()?;
+    let unit = match parts.next() {
+        None => 1,
+        Some("KiB") => 1024,
+        Some("MiB") => BYTES_PER_MB,
+        Some("GiB") => 1024 * BYTES_PER_MB,
+        Some(_) => return None,
+    };
+    Some(number * unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn write(path: PathBuf, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// amdgpu card0 with connector card0-DP-1, i915 card1, BMC display card2, xe card3, and fdinfo of
+    /// three processes
+    fn fake_tree(root: &Path) {
+        let amd = root.join("sys/class/drm/card0");
+        write(amd.join("device/uevent"), "DRIVER=amdgpu\nPCI_SLOT_NAME=0000:03:00.0\n");
+        write(amd.join("device/gpu_busy_percent"), "73\n");
+        write(amd.join("device/mem_busy_percent"), "20\n");
+        write(amd.join("device/mem_info_vram_total"), "17163091968\n");
+        write(amd.join("device/mem_info_vram_used"), "2147483648\n");
+        let hwmon = amd.join("device/hwmon/hwmon4");
+        for (file, value) in [
+            ("temp1_input", "52000"), ("temp1_label", "edge"),
+            ("temp2_input", "58000"), ("temp2_label", "junction"),
+            ("temp3_input", "64000"), ("temp3_label", "mem"),
+            ("power1_average", "187000000"), ("power1_cap", "255000000"),
+            ("freq1_input", "2450000000"), ("freq1_label", "sclk"),
+            ("freq2_input", "1000000000"), ("freq2_label", "mclk"),
+        ] {
+            write(hwmon.join(file), value);
+        }
+        write(root.join("sys/class/drm/card0-DP-1/status"), "connected\n");
+
+        let intel = root.join("sys/class/drm/card1");
+        write(intel.join("device/uevent"), "DRIVER=i915\nPCI_SLOT_NAME=0000:00:02.0\n");
+        write(intel.join("gt_cur_freq_mhz"), "1100\n");
+        write(intel.join("gt_max_freq_mhz"), "1450\n");
+        write(root.join("sys/class/drm/card2/device/uevent"), "DRIVER=ast\nPCI_SLOT_NAME=0000:02:00.0\n");
+        write(root.join("sys/class/drm/card3/device/uevent"), "DRIVER=xe\nPCI_SLOT_NAME=0000:4d:00.0\n");
+
+        let amd_client = "pos:\t0\nflags:\t02100002\ndrm-driver:\tamdgpu\ndrm-pdev:\t0000:03:00.0\n\
+                          drm-client-id:\t42\ndrm-memory-vram:\t1048576 KiB\ndrm-engine-gfx:\t1000 ns\n";
+        write(root.join("proc/100/comm"), "python3\n");
+        write(root.join("proc/100/fdinfo/5"), amd_client);
+        write(root.join("proc/100/fdinfo/6"), amd_client); // dup of the same client
+        write(root.join("proc/100/fdinfo/0"), "pos:\t0\nflags:\t02\n");
+        write(root.join("proc/200/comm"), "glxgears\n");
+        write(
+            root.join("proc/200/fdinfo/3"),
+            "drm-driver:\ti915\ndrm-pdev:\t0000:00:02.0\ndrm-client-id:\t7\n\
+             drm-total-system0:\t64 MiB\ndrm-engine-render:\t1000000000 ns\n",
+        );
+        write(root.join("proc/300/comm"), "ffmpeg\n");
+        write(root.join("proc/300/fdinfo/4"), &xe_client(1000, 20000));
+    }
+
+    /// xe client counting its render engine in cycles
+    fn xe_client(busy: u64, total: u64) -> String {
+        format!(
+            "drm-driver:\txe\ndrm-pdev:\t0000:4d:00.0\ndrm-client-id:\t3\n\
+             drm-cycles-rcs:\t{}\ndrm-total-cycles-rcs:\t{}\n",
+            busy, total
+        )
+    }
+
+    #[test]
+    fn test_reads_fake_tree() {
+        let root = tempfile::tempdir().unwrap();
+        fake_tree(root.path());
+        let mut monitor = SysfsDrmMonitor::new(root.path().join("sys"), root.path().join("proc"));
+        assert!(monitor.is_available(), "{:?}", monitor.get_last_error());
+
+        let now = Instant::now();
+        let readings = monitor.read(now).unwrap();
+        // the BMC's display is no GPU
+        assert_eq!(readings.gpu_count, 3);
+        assert_eq!(readings.gpus[2].index, 3);
+        let amd = &readings.gpus[0];
+        assert_eq!(amd.name, "amdgpu card0");
+        assert_eq!((amd.utilization.gpu, amd.utilization.memory), (73, 20));
+        assert_eq!((amd.memory.used, amd.memory.total), (2048, 16368));
+        assert_eq!((amd.temperature.gpu, amd.temperature.memory), (Some(52), Some(64)));
+        assert_eq!((amd.power.draw, amd.power.limit), (Some(187.0), Some(255.0)));
+        assert_eq!((amd.clocks.graphics, amd.clocks.memory), (Some(2450), Some(1000)));
+
+        let intel = &readings.gpus[1];
+        assert_eq!((intel.clocks.graphics, intel.clocks.max_graphics), (Some(1100), Some(1450)));
+        assert_eq!(intel.temperature.gpu, None);
+        assert_eq!(intel.utilization.gpu, 0); // no engine times to compare with yet
+
+        let processes: Vec<(u32, &str, u64, u32)> = readings
+            .processes
+            .iter()
+            .map(|p| (p.pid, p.process_name.as_str(), p.used_memory, p.gpu_index))
+            .collect();
+        assert_eq!(processes, vec![(100, "python3", 1024, 0), (200, "glxgears", 64, 1), (300, "ffmpeg", 0, 3)]);
+
+        // render engine busy for half of the second since, xe's for a quarter of the cycles
+        write(
+            root.path().join("proc/200/fdinfo/3"),
+            "drm-driver:\ti915\ndrm-pdev:\t0000:00:02.0\ndrm-client-id:\t7\n\
+             drm-engine-render:\t1500000000 ns\n",
+        );
+        write(root.path().join("proc/300/fdinfo/4"), &xe_client(3500, 30000));
+        let readings = monitor.read(now + Duration::from_secs(1)).unwrap();
+        assert_eq!(readings.gpus[1].utilization.gpu, 50);
+        assert_eq!(readings.gpus[2].utilization.gpu, 25);
+    }
+
+    #[test]
+    fn test_no_gpu() {
+        let root = tempfile::tempdir().unwrap();
+        let mut monitor = SysfsDrmMonitor::new(root.path(), root.path());
+        assert!(!monitor.is_available());
+        assert!(matches!(monitor.get_gpu_info(), Err(GpuError::NotInstalled(_))));
+    }
+}
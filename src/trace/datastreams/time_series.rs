@@ -0,0 +1,133 @@
+use chrono::{DateTime, Duration, Local};
+use std::collections::{vec_deque, VecDeque};
+
+/// Wall clock time of a reading
+pub type Timestamp = DateTime<Local>;
+
+/// Fixed-capacity ring buffer of `(timestamp, value)` readings, oldest first.
+/// Pushing into a full series drops the oldest reading in O(1). NaN values mark gaps - times
+/// when the reading could not be taken.
+#[derive(Debug, Clone)]
+pub struct TimeSeries {
+    points: VecDeque<(Timestamp, f64)>,
+    capacity: usize,
+}
+
+impl TimeSeries {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            points: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Add reading. Timestamps never go backwards - an older one is moved to the last reading time.
+    pub fn push(&mut self, at: Timestamp, value: f64) {
+        let at = match self.points.back() {
+            Some((last, _)) if *last > at => *last,
+            _ => at,
+        };
+        if self.points.len() == self.capacity {
+            self.points.pop_front();
+        }
+        self.points.push_back((at, value));
+    }
+
+    /// Mark a gap: no reading could be taken at `at`.
+    pub fn push_gap(&mut self, at: Timestamp) {
+        self.push(at, f64::NAN);
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Most recent reading
+    pub fn latest(&self) -> Option<(Timestamp, f64)> {
+        self.points.back().copied()
+    }
+
+    /// Oldest kept reading
+    pub fn oldest(&self) -> Option<(Timestamp, f64)> {
+        self.points.front().copied()
+    }
+
+    /// All readings, oldest first
+    pub fn iter(&self) -> vec_deque::Iter<'_, (Timestamp, f64)> {
+        self.points.iter()
+    }
+
+    /// Readings with `from <= time <= to`
+    pub fn range(&self, from: Timestamp, to: Timestamp) -> vec_deque::Iter<'_, (Timestamp, f64)> {
+        let start = self.points.partition_point(|(t, _)| *t < from);
+        let end = self.points.partition_point(|(t, _)| *t <= to).max(start);
+        self.points.range(start..end)
+    }
+
+    /// Largest value within the range, gaps left out
+    pub fn max_in(&self, from: Timestamp, to: Timestamp) -> Option<f64> {
+        self.range(from, to)
+            .map(|(_, v)| *v)
+            .filter(|v| !v.is_nan())
+            .reduce(f64::max)
+    }
+}
+
+/// Duration as fractional seconds
+pub fn seconds(d: Duration) -> f64 {
+    d.num_microseconds()
+        .map_or(d.num_milliseconds() as f64 / 1e3, |us| us as f64 / 1e6)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(base: Timestamp, secs: i64) -> Timestamp {
+        base + Duration::seconds(secs)
+    }
+
+    #[test]
+    fn test_ring_buffer_drops_oldest() {
+        let base = Local::now();
+        let mut ts = TimeSeries::new(3);
+        for i in 0..5 {
+            ts.push(at(base, i), i as f64);
+        }
+        assert_eq!(ts.len(), 3);
+        assert_eq!(ts.oldest().unwrap().1, 2.0);
+        assert_eq!(ts.latest().unwrap().1, 4.0);
+
+        // going back in time keeps the order
+        ts.push(at(base, 1), 5.0);
+        assert_eq!(ts.latest().unwrap().0, at(base, 4));
+    }
+
+    #[test]
+    fn test_range_and_gaps() {
+        let base = Local::now();
+        let mut ts = TimeSeries::new(10);
+        for (s, v) in [(0, 0.0), (10, 10.0), (20, 0.0)] {
+            ts.push(at(base, s), v);
+        }
+
+        let values: Vec<f64> = ts.range(at(base, 5), at(base, 20)).map(|(_, v)| *v).collect();
+        assert_eq!(values, vec![10.0, 0.0]);
+        assert_eq!(ts.range(at(base, 21), at(base, 30)).count(), 0);
+        assert_eq!(ts.max_in(at(base, 15), at(base, 30)), Some(0.0));
+
+        // gaps are nothing, not zeros
+        ts.push_gap(at(base, 30));
+        ts.push(at(base, 40), 4.0);
+        assert_eq!(ts.max_in(at(base, 25), at(base, 40)), Some(4.0));
+    }
+}
@@ -0,0 +1,226 @@
+use crate::error::{GpuError, Result};
+use crate::trace::datastreams::raw_capture::{Invocation, RawCapture, RawOutput, RawReplay};
+use chrono::Local;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Capture shared by all tools of one session
+pub type SharedCapture = Arc<Mutex<RawCapture>>;
+
+/// Where the tool output comes from
+enum Source {
+    /// Path to the executable
+    Binary(String),
+    /// Recorded session, with the name of the tool it stands in for
+    Replay(String, Mutex<RawReplay>),
+}
+
+/// Runs a command line GPU tool (nvidia-smi, rocm-smi, ...) with a timeout - or replays a
+/// recorded session of it - and tells apart the ways it can fail.
+pub struct ToolRunner {
+    source: Source,
+    capture: Option<SharedCapture>,
+    timeout: Duration,
+}
+
+impl ToolRunner {
+    /// Runs `program`, killing calls that take longer than `timeout`
+    pub fn new(program: &str, timeout: Duration) -> Self {
+        Self {
+            source: Source::Binary(program.to_string()),
+            capture: None,
+            timeout,
+        }
+    }
+
+    /// Plays back a capture directory in place of `program`
+    pub fn replay(program: &str, dir: &Path) -> Result<Self> {
+        Ok(Self {
+            source: Source::Replay(program.to_string(), Mutex::new(RawReplay::open(dir)?)),
+            capture: None,
+            timeout: Duration::ZERO,
+        })
+    }
+
+    /// Every invocation (args, outputs, exit code, timing) goes into `capture` too
+    pub fn capturing(mut self, capture: SharedCapture) -> Self {
+        self.capture = Some(capture);
+        self
+    }
+
+    /// First of `candidates` found on this machine, or the first one (left for `PATH` to find)
+    pub fn find(candidates: &[&str]) -> String {
+        candidates
+            .iter()
+            .find(|c| command_exists(c))
+            .or(candidates.first())
+            .map_or(String::new(), |c| c.to_string())
+    }
+
+    /// Whether a long-running child of the tool can stand in for repeated calls - a live binary
+    /// that is not captured, captures and replays need every call
+    pub fn can_stream(&self) -> bool {
+        matches!(self.source, Source::Binary(_)) && self.capture.is_none()
+    }
+
+    pub fn is_replay(&self) -> bool {
+        matches!(self.source, Source::Replay(..))
+    }
+
+    pub fn program(&self) -> &str {
+        match &self.source {
+            Source::Binary(path) => path,
+            Source::Replay(program, _) => program,
+        }
+    }
+
+    /// Stdout of the tool, capturing the invocation when asked to
+    pub fn run(&self, args: &[&str]) -> std::result::Result<String, GpuError> {
+        let output = match &self.source {
+            Source::Binary(path) => {
+                let started = Local::now();
+                let clock = Instant::now();
+                let mut command = Command::new(path);
+                command.args(args);
+                let output = output_with_timeout(&mut command, self.timeout);
+                if let Some(capture) = &self.capture {
+                    let invocation = Invocation::new(path, args, started, clock.elapsed(), &output);
+                    capture.lock().unwrap().record(&invocation);
+                }
+                output?
+            }
+            Source::Replay(program, replay) => replay
+                .lock()
+                .unwrap()
+                .next(program, args)
+                .ok_or_else(|| GpuError::Io(format!("Capture has no more `{} {}` calls", program, args.join(" "))))?
+                .replay(program)?,
+        };
+        check_output(output, self.program())
+    }
+}
+
+/// Check if a command exists and is executable
+fn command_exists(command: &str) -> bool {
+    let result = if cfg!(target_os = "windows") {
+        Command::new("where").arg(command).output()
+    } else {
+        Command::new("which").arg(command).output()
+    };
+
+    result.map(|output| output.status.success()).unwrap_or(false)
+}
+
+/// Stdout of a successful run, or how it failed
+fn check_output(output: RawOutput, program: &str) -> std::result::Result<String, GpuError> {
+    if output.code == Some(0) {
+        return Ok(output.stdout);
+    }
+
+    // Tools print some errors to stdout, some to stderr
+    let message = format!("{} {}", output.stderr, output.stdout)
+        .trim()
+        .to_string();
+    if message.contains("version mismatch") {
+        return Err(GpuError::DriverMismatch(message));
+    }
+    if let Some(field) = unsupported_field(&message) {
+        return Err(GpuError::UnsupportedField(field));
+    }
+    Err(GpuError::ExitStatus {
+        command: program.to_string(),
+        code: output.code,
+        stderr: message,
+    })
+}
+
+/// Field from `Field "xyz" is not a valid field to query.`
+fn unsupported_field(message: &str) -> Option<String> {
+    let rest = &message[message.find("Field \"")? + 7..];
+    rest.contains("not a valid field")
+        .then(|| rest.split('"').next().unwrap_or_default().to_string())
+}
+
+fn program(command: &Command) -> String {
+    command.get_program().to_string_lossy().to_string()
+}
+
+/// Runs the command, killing it when it does not finish within `timeout`.
+/// Outputs are read on their own threads, so a chatty child can't block on a full pipe.
+fn output_with_timeout(command: &mut Command, timeout: Duration) -> std::result::Result<RawOutput, GpuError> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => GpuError::NotInstalled(program(command)),
+            _ => GpuError::Io(format!("Failed to execute {}: {}", program(command), e)),
+        })?;
+
+    let stdout = read_all(child.stdout.take());
+    let stderr = read_all(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(GpuError::Timeout {
+                    command: program(command),
+                    timeout,
+                });
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(5)),
+            Err(e) => return Err(GpuError::Io(format!("Failed to wait for {}: {}", program(command), e))),
+        }
+    };
+
+    Ok(RawOutput {
+        code: status.code(),
+        stdout: String::from_utf8_lossy(&stdout.join().unwrap_or_default()).to_string(),
+        stderr: String::from_utf8_lossy(&stderr.join().unwrap_or_default()).to_string(),
+    })
+}
+
+/// Reads the pipe to its end on a new thread
+fn read_all<R: Read + Send + 'static>(pipe: Option<R>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errors_say_what_broke() {
+        assert_eq!(
+            unsupported_field("Field \"utilization.ofa\" is not a valid field to query."),
+            Some("utilization.ofa".to_string())
+        );
+        let failed = RawOutput {
+            code: Some(18),
+            stdout: "Failed to initialize NVML: Driver/library version mismatch".to_string(),
+            stderr: String::new(),
+        };
+        assert!(matches!(check_output(failed, "nvidia-smi"), Err(GpuError::DriverMismatch(_))));
+    }
+
+    #[test]
+    fn test_missing_tool_is_not_installed() {
+        let err = ToolRunner::new("/nonexistent/nvidia-smi", Duration::from_secs(1)).run(&["-L"]);
+        assert_eq!(err, Err(GpuError::NotInstalled("/nonexistent/nvidia-smi".to_string())));
+    }
+}
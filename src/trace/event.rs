@@ -1,7 +1,9 @@
+use crate::trace::datastreams::Sample;
 use termion::event;
 
 pub enum Event {
     Input(event::Key),
     Tick,
+    Sample(Box<Sample>),
     Quit,
 }
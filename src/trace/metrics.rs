@@ -0,0 +1,119 @@
+use crate::error::{Result, TraceError};
+use regex::Regex;
+use std::collections::BTreeMap;
+
+/// Application metrics extracted from output lines with `--metric-regex` rules.
+/// Every named capture group of a rule becomes a metric, e.g. `it/s=(?P<throughput>[0-9.]+)`.
+#[derive(Debug, Clone)]
+pub struct MetricExtractor {
+    rules: Vec<Regex>,
+}
+
+impl MetricExtractor {
+    /// Compile rules, each one needs at least one named capture group.
+    pub fn new(patterns: &[String]) -> Result<Self> {
+        let rules = patterns
+            .iter()
+            .map(|p| {
+                let regex = Regex::new(p).map_err(|e| {
+                    TraceError::Unknown(format!("Invalid metric regex '{}': {}", p, e))
+                })?;
+                if regex.capture_names().flatten().next().is_none() {
+                    return Err(TraceError::Unknown(format!(
+                        "Metric regex '{}' has no named capture group, use (?P<name>...)",
+                        p
+                    )));
+                }
+                Ok(regex)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Names of all metrics the rules can produce
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .rules
+            .iter()
+            .flat_map(|r| r.capture_names().flatten().map(String::from))
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Numeric values of named captures found in the line
+    pub fn extract(&self, line: &str) -> Vec<(String, f64)> {
+        let mut values = Vec::new();
+        for rule in &self.rules {
+            for caps in rule.captures_iter(line) {
+                for name in rule.capture_names().flatten() {
+                    if let Some(value) = caps
+                        .name(name)
+                        .and_then(|m| m.as_str().trim().parse::<f64>().ok())
+                    {
+                        values.push((name.to_string(), value));
+                    }
+                }
+            }
+        }
+        values
+    }
+}
+
+/// Metric values collected between two samples, latest value wins.
+#[derive(Debug, Default)]
+pub struct PendingMetrics {
+    values: BTreeMap<String, f64>,
+}
+
+impl PendingMetrics {
+    pub fn record(&mut self, name: String, value: f64) {
+        self.values.insert(name, value);
+    }
+
+    /// Values since the last call
+    pub fn take(&mut self) -> BTreeMap<String, f64> {
+        std::mem::take(&mut self.values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_named_captures() {
+        let extractor = MetricExtractor::new(&[
+            r"it/s=(?P<throughput>[0-9.]+)".to_string(),
+            r"step=(?P<step>\d+) loss=(?P<loss>[0-9.]+)".to_string(),
+        ])
+        .unwrap();
+
+        assert_eq!(extractor.names(), vec!["loss", "step", "throughput"]);
+        assert_eq!(
+            extractor.extract("step=120 loss=0.42 it/s=3.1"),
+            vec![
+                ("throughput".to_string(), 3.1),
+                ("step".to_string(), 120.0),
+                ("loss".to_string(), 0.42),
+            ]
+        );
+        assert!(extractor.extract("nothing here").is_empty());
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(MetricExtractor::new(&["([0-9]+".to_string()]).is_err());
+        assert!(MetricExtractor::new(&["loss=([0-9.]+)".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_pending_latest_wins() {
+        let mut pending = PendingMetrics::default();
+        pending.record("loss".to_string(), 1.0);
+        pending.record("loss".to_string(), 0.5);
+        assert_eq!(pending.take().get("loss"), Some(&0.5));
+        assert!(pending.take().is_empty());
+    }
+}
@@ -2,11 +2,15 @@ use serde_derive::{Deserialize, Serialize};
 
 pub mod app;
 pub mod cmd;
+pub mod collector;
 pub mod datastreams;
 pub mod event;
+pub mod metrics;
+pub mod output;
+pub mod summary;
 pub mod ui;
 
-mod app_data_streams;
+pub mod app_data_streams;
 
 /// CSV output record for CPU monitoring (legacy)
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -23,6 +27,18 @@ impl Record<'static> {
     }
 }
 
+/// CSV output record for application metrics extracted with `--metric-regex`
+#[derive(Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct MetricRecord {
+    pub timestamp: String,
+    pub sample: u64,
+    pub metric: String,
+    pub value: f64,
+    pub gpu_power_draw_watts: Option<f32>,
+    pub gpu_memory_used_mb: Option<u64>,
+}
+
 /// CSV output record for GPU monitoring
 #[derive(Debug, Serialize, Deserialize, Default)]
 #[serde(rename_all = "PascalCase")]
@@ -44,6 +60,30 @@ pub struct GpuCsvRecord {
     pub pstate: String,
     pub driver_version: String,
     pub compute_capability: String,
+    /// Active clock throttle reasons, `|` separated - empty when the backend does not report them
+    pub throttle_reasons: String,
+    /// Seconds since the previous reading, spent in `throttle_reasons`
+    pub throttle_secs: f64,
+    /// PCIe link generation and width
+    pub pcie_gen: Option<u32>,
+    pub pcie_width: Option<u32>,
+    /// Whether the link runs below its maximum generation or width
+    pub pcie_degraded: bool,
+    pub pcie_tx_mbps: Option<f64>,
+    pub pcie_rx_mbps: Option<f64>,
+    /// All NVLink links together - empty without NVLink
+    pub nvlink_tx_mbps: Option<f64>,
+    pub nvlink_rx_mbps: Option<f64>,
+    pub nvlink_errors: Option<u64>,
+    /// Video engines, empty where the GPU has none or does not report them
+    pub encoder_percent: Option<u32>,
+    pub decoder_percent: Option<u32>,
+    pub jpeg_percent: Option<u32>,
+    pub ofa_percent: Option<u32>,
+    /// NVENC sessions, their average frame rate and latency
+    pub encoder_sessions: Option<u32>,
+    pub encoder_fps: Option<u32>,
+    pub encoder_latency_us: Option<u32>,
 }
 
 impl GpuCsvRecord {
@@ -72,6 +112,27 @@ impl GpuCsvRecord {
             pstate: gpu_info.pstate.clone(),
             driver_version: gpu_info.driver_version.clone(),
             compute_capability: gpu_info.compute_cap.clone(),
+            throttle_reasons: gpu_info.throttle_reasons.map_or(String::new(), |r| match r.0 {
+                0 => "none".to_string(),
+                _ => r.names().join("|"),
+            }),
+            throttle_secs: 0.0,
+            pcie_gen: gpu_info.pcie.gen_current,
+            pcie_width: gpu_info.pcie.width_current,
+            pcie_degraded: gpu_info.pcie.is_degraded(),
+            pcie_tx_mbps: gpu_info.pcie.tx_throughput,
+            pcie_rx_mbps: gpu_info.pcie.rx_throughput,
+            nvlink_tx_mbps: gpu_info.nvlink_tx(),
+            nvlink_rx_mbps: gpu_info.nvlink_rx(),
+            nvlink_errors: (!gpu_info.nvlinks.is_empty())
+                .then(|| gpu_info.nvlinks.iter().map(|l| l.error_count()).sum()),
+            encoder_percent: gpu_info.utilization.encoder,
+            decoder_percent: gpu_info.utilization.decoder,
+            jpeg_percent: gpu_info.utilization.jpeg,
+            ofa_percent: gpu_info.utilization.ofa,
+            encoder_sessions: gpu_info.encoder_stats.map(|s| s.session_count),
+            encoder_fps: gpu_info.encoder_stats.map(|s| s.average_fps),
+            encoder_latency_us: gpu_info.encoder_stats.map(|s| s.average_latency),
         }
     }
 }
@@ -0,0 +1,275 @@
+use crate::trace::metrics::{MetricExtractor, PendingMetrics};
+use chrono::{DateTime, Local};
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// How many lines of application output are kept for the viewer.
+pub const DEFAULT_OUTPUT_LINES: usize = 10_000;
+
+/// Which output of the application the line came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Single line printed by the application
+#[derive(Debug, Clone)]
+pub struct OutputLine {
+    /// When we read the line
+    pub time: DateTime<Local>,
+    /// stdout or stderr
+    pub stream: OutputStream,
+    /// Line without trailing newline
+    pub text: String,
+    /// Number of the sample that was current when the line was printed
+    pub sample: u64,
+    /// GPU memory used (MB) at that sample, if GPUs are monitored
+    pub gpu_memory_used: Option<u64>,
+}
+
+/// Captured application output, shared between reader threads and UI
+pub struct OutputLog {
+    lines: VecDeque<OutputLine>,
+    max_lines: usize,
+    sample: u64,
+    gpu_memory_used: Option<u64>,
+    total_lines: u64,
+    extractor: Option<MetricExtractor>,
+    metrics: PendingMetrics,
+}
+
+/// Output log as shared by reader threads, UI and metric extraction
+pub type SharedOutputLog = Arc<Mutex<OutputLog>>;
+
+impl OutputLog {
+    /// New log, with metrics extracted from every line when `extractor` is given
+    pub fn new(max_lines: usize, extractor: Option<MetricExtractor>) -> Self {
+        Self {
+            lines: VecDeque::with_capacity(max_lines.min(1024)),
+            max_lines,
+            sample: 0,
+            gpu_memory_used: None,
+            total_lines: 0,
+            extractor,
+            metrics: PendingMetrics::default(),
+        }
+    }
+
+    /// New log wrapped for sharing
+    pub fn shared(max_lines: usize, extractor: Option<MetricExtractor>) -> SharedOutputLog {
+        Arc::new(Mutex::new(Self::new(max_lines, extractor)))
+    }
+
+    /// Metric values extracted since the last call
+    pub fn take_metrics(&mut self) -> BTreeMap<String, f64> {
+        self.metrics.take()
+    }
+
+    /// Mark the start of a new sample - following lines are correlated with it.
+    pub fn mark_sample(&mut self, sample: u64, gpu_memory_used: Option<u64>) {
+        self.sample = sample;
+        self.gpu_memory_used = gpu_memory_used;
+    }
+
+    /// Add line, dropping the oldest one when full
+    pub fn push(&mut self, stream: OutputStream, text: String) {
+        if let Some(extractor) = &self.extractor {
+            for (name, value) in extractor.extract(&text) {
+                self.metrics.record(name, value);
+            }
+        }
+        while self.lines.len() >= self.max_lines {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(OutputLine {
+            time: Local::now(),
+            stream,
+            text,
+            sample: self.sample,
+            gpu_memory_used: self.gpu_memory_used,
+        });
+        self.total_lines += 1;
+    }
+
+    /// Kept lines, oldest first
+    pub fn lines(&self) -> &VecDeque<OutputLine> {
+        &self.lines
+    }
+
+    /// Number of lines ever received (including dropped ones)
+    pub fn total_lines(&self) -> u64 {
+        self.total_lines
+    }
+
+    /// Index of the next line after `from` (wrapping) containing `pattern`, case insensitive.
+    pub fn find(&self, pattern: &str, from: usize, forward: bool) -> Option<usize> {
+        let len = self.lines.len();
+        if pattern.is_empty() || len == 0 {
+            return None;
+        }
+        let pattern = pattern.to_lowercase();
+        (1..=len)
+            .map(|step| {
+                if forward {
+                    (from + step) % len
+                } else {
+                    (from + len - step % len) % len
+                }
+            })
+            .find(|&i| self.lines[i].text.to_lowercase().contains(&pattern))
+    }
+}
+
+/// Copies `reader` line by line into `file` (if any) and into the shared log.
+pub fn tee<R: Read + Send + 'static>(
+    reader: R,
+    mut file: Option<File>,
+    stream: OutputStream,
+    log: SharedOutputLog,
+) -> JoinHandle<()> {
+    thread::Builder::new()
+        .name(format!("{:?}", stream).to_lowercase())
+        .spawn(move || {
+            let mut reader = BufReader::new(reader);
+            let mut buf = Vec::new();
+            loop {
+                buf.clear();
+                match reader.read_until(b'\n', &mut buf) {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        if let Some(f) = &mut file {
+                            if let Err(e) = f.write_all(&buf) {
+                                log::error!("Cannot write application {:?}: {}", stream, e);
+                                file = None;
+                            }
+                        }
+                        let text = String::from_utf8_lossy(&buf)
+                            .trim_end_matches(['\n', '\r'])
+                            .to_string();
+                        if let Ok(mut log) = log.lock() {
+                            log.push(stream, text);
+                        }
+                    }
+                    Err(e) => {
+                        log::error!("Cannot read application {:?}: {}", stream, e);
+                        break;
+                    }
+                }
+            }
+        })
+        .expect("Cannot start output reader thread")
+}
+
+/// Scroll and search state of the output viewer
+#[derive(Debug, Default)]
+pub struct OutputView {
+    /// Selected line index, `None` means following the end of the log
+    pub selected: Option<usize>,
+    /// Current search pattern
+    pub search: String,
+    /// Typing search pattern right now
+    pub searching: bool,
+}
+
+impl OutputView {
+    /// Move selection by `delta` lines within log of `len` lines.
+    pub fn scroll(&mut self, delta: isize, len: usize) {
+        if len == 0 {
+            return;
+        }
+        let current = self.selected.unwrap_or(len - 1) as isize;
+        let next = (current + delta).clamp(0, len as isize - 1) as usize;
+        // scrolling past the end switches back to follow mode
+        self.selected = if delta > 0 && next == len - 1 {
+            None
+        } else {
+            Some(next)
+        };
+    }
+
+    /// Jump to next (or previous) line matching current search.
+    pub fn find(&mut self, log: &OutputLog, forward: bool) {
+        let from = self
+            .selected
+            .unwrap_or_else(|| log.lines().len().saturating_sub(1));
+        if let Some(i) = log.find(&self.search, from, forward) {
+            self.selected = Some(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_log_trim_and_correlation() {
+        let mut log = OutputLog::new(2, None);
+        log.push(OutputStream::Stdout, "one".to_string());
+        log.mark_sample(3, Some(1024));
+        log.push(OutputStream::Stderr, "two".to_string());
+        log.push(OutputStream::Stdout, "three".to_string());
+
+        assert_eq!(log.lines().len(), 2);
+        assert_eq!(log.total_lines(), 3);
+        assert_eq!(log.lines()[0].text, "two");
+        assert_eq!(log.lines()[0].stream, OutputStream::Stderr);
+        assert_eq!(log.lines()[0].sample, 3);
+        assert_eq!(log.lines()[0].gpu_memory_used, Some(1024));
+    }
+
+    #[test]
+    fn test_metrics_from_lines() {
+        let extractor = MetricExtractor::new(&[r"loss=(?P<loss>[0-9.]+)".to_string()]).unwrap();
+        let mut log = OutputLog::new(10, Some(extractor));
+        log.push(OutputStream::Stdout, "step=1 loss=0.9".to_string());
+        log.push(OutputStream::Stderr, "step=2 loss=0.7".to_string());
+        assert_eq!(log.take_metrics().get("loss"), Some(&0.7));
+        assert!(log.take_metrics().is_empty());
+    }
+
+    #[test]
+    fn test_search_and_scroll() {
+        let mut log = OutputLog::new(10, None);
+        for text in ["loss=1.0", "warning: slow", "loss=0.5", "done"] {
+            log.push(OutputStream::Stdout, text.to_string());
+        }
+
+        let mut view = OutputView {
+            search: "LOSS".to_string(),
+            ..Default::default()
+        };
+        view.find(&log, true);
+        assert_eq!(view.selected, Some(0));
+        view.find(&log, true);
+        assert_eq!(view.selected, Some(2));
+        view.find(&log, false);
+        assert_eq!(view.selected, Some(0));
+
+        view.scroll(-5, log.lines().len());
+        assert_eq!(view.selected, Some(0));
+        view.scroll(10, log.lines().len());
+        assert_eq!(view.selected, None);
+    }
+
+    #[test]
+    fn test_tee_writes_file_and_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.out");
+        let log = OutputLog::shared(10, None);
+        let input: &[u8] = b"first\nsecond\r\nlast";
+        tee(input, Some(File::create(&path).unwrap()), OutputStream::Stdout, log.clone())
+            .join()
+            .unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), input);
+        let log = log.lock().unwrap();
+        let texts: Vec<&str> = log.lines().iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts, vec!["first", "second", "last"]);
+    }
+}
@@ -0,0 +1,419 @@
+use crate::error::Result;
+use crate::trace::datastreams::{GpuAccountedProcess, Sample, Timestamp};
+use chrono::{DateTime, Local};
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+/// Exited GPU processes logged one by one, the summary file has all of them
+const MAX_LOGGED_PROCESSES: usize = 20;
+
+/// Min/avg/max of a value over the run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Stat {
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub last: f64,
+    pub count: u64,
+}
+
+impl Stat {
+    pub fn add(&mut self, value: f64) {
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.avg += (value - self.avg) / (self.count + 1) as f64;
+        self.last = value;
+        self.count += 1;
+    }
+}
+
+/// Per GPU statistics
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GpuSummary {
+    pub index: u32,
+    pub name: String,
+    pub utilization_percent: Stat,
+    pub memory_used_mb: Stat,
+    pub power_draw_watts: Stat,
+    pub temperature_celsius: Stat,
+    /// Seconds spent in each clock throttle reason, `none` for unthrottled time
+    pub throttle_secs: BTreeMap<String, f64>,
+    /// Video engines, where the GPU reports them
+    pub encoder_percent: Stat,
+    pub decoder_percent: Stat,
+    /// NVENC sessions, and their frame rate while there are any
+    pub encoder_sessions: Stat,
+    pub encoder_fps: Stat,
+}
+
+/// Single value of application metric
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricPoint {
+    pub sample: u64,
+    pub elapsed_secs: f64,
+    pub value: f64,
+}
+
+/// Application metric statistics and efficiency
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MetricSummary {
+    #[serde(flatten)]
+    pub stat: Stat,
+    /// Metric per watt of total GPU power, e.g. samples/s per W
+    pub per_watt: Option<f64>,
+    /// Metric per GB of used GPU memory
+    pub per_gb: Option<f64>,
+    pub series: Vec<MetricPoint>,
+}
+
+/// Whole run summary, printed at exit and written with `--summary`
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    pub started: String,
+    pub finished: String,
+    pub duration_secs: f64,
+    pub samples: u64,
+    pub cpu_percent: Stat,
+    pub memory_kb: Stat,
+    pub gpus: Vec<GpuSummary>,
+    pub metrics: BTreeMap<String, MetricSummary>,
+    /// GPU processes of the traced tree that exited, as accounting mode recorded them
+    pub accounted_processes: Vec<GpuAccountedProcess>,
+}
+
+#[derive(Debug, Default)]
+struct MetricAccumulator {
+    summary: MetricSummary,
+    value_with_power: f64,
+    power: f64,
+    value_with_memory: f64,
+    memory_gb: f64,
+}
+
+/// Collects statistics sample by sample
+pub struct SummaryBuilder {
+    started: DateTime<Local>,
+    start: Instant,
+    last_sample: Option<Timestamp>,
+    samples: u64,
+    cpu: Stat,
+    memory: Stat,
+    gpus: BTreeMap<u32, GpuSummary>,
+    metrics: BTreeMap<String, MetricAccumulator>,
+    accounted: Vec<GpuAccountedProcess>,
+}
+
+impl SummaryBuilder {
+    pub fn new() -> Self {
+        Self {
+            started: Local::now(),
+            start: Instant::now(),
+            last_sample: None,
+            samples: 0,
+            cpu: Stat::default(),
+            memory: Stat::default(),
+            gpus: BTreeMap::new(),
+            metrics: BTreeMap::new(),
+            accounted: Vec::new(),
+        }
+    }
+
+    /// Add one sample: process readings, GPU readings (if monitored) and metrics extracted since previous sample.
+    pub fn record(&mut self, sample: &Sample) {
+        self.samples += 1;
+        self.cpu.add(sample.readings.get_cpu() as f64);
+        self.memory.add(sample.readings.get_mem() as f64);
+        // time since the previous sample counts as spent in the throttle reasons active now
+        let secs = self
+            .last_sample
+            .replace(sample.time)
+            .map_or(0.0, |last| (sample.time - last).num_milliseconds() as f64 / 1000.0);
+
+        let mut total_power = None;
+        let mut total_memory_gb = None;
+        if let Some(gpu) = sample.gpu.as_ref().filter(|g| !g.gpus.is_empty()) {
+            for info in &gpu.gpus {
+                let s = self.gpus.entry(info.index).or_insert_with(|| GpuSummary {
+                    index: info.index,
+                    name: info.name.clone(),
+                    ..Default::default()
+                });
+                s.utilization_percent.add(info.utilization.gpu as f64);
+                s.memory_used_mb.add(info.memory.used as f64);
+                if let Some(p) = info.power.draw {
+                    s.power_draw_watts.add(p as f64);
+                    *total_power.get_or_insert(0.0) += p as f64;
+                }
+                if let Some(t) = info.temperature.gpu {
+                    s.temperature_celsius.add(t as f64);
+                }
+                if let Some(e) = info.utilization.encoder {
+                    s.encoder_percent.add(e as f64);
+                }
+                if let Some(d) = info.utilization.decoder {
+                    s.decoder_percent.add(d as f64);
+                }
+                if let Some(stats) = info.encoder_stats {
+                    s.encoder_sessions.add(stats.session_count as f64);
+                    if stats.session_count > 0 {
+                        s.encoder_fps.add(stats.average_fps as f64);
+                    }
+                }
+                if let Some(reasons) = info.throttle_reasons {
+                    let names = match reasons.0 {
+                        0 => vec!["none"],
+                        _ => reasons.names(),
+                    };
+                    for name in names {
+                        *s.throttle_secs.entry(name.to_string()).or_default() += secs;
+                    }
+                }
+            }
+            total_memory_gb = Some(gpu.get_total_memory_used() as f64 / 1024.0);
+        }
+
+        self.accounted.extend(sample.accounted.iter().cloned());
+
+        let elapsed_secs = self.start.elapsed().as_secs_f64();
+        for (name, &value) in &sample.metrics {
+            let acc = self.metrics.entry(name.clone()).or_default();
+            acc.summary.stat.add(value);
+            acc.summary.series.push(MetricPoint {
+                sample: sample.index,
+                elapsed_secs,
+                value,
+            });
+            if let Some(power) = total_power.filter(|p| *p > 0.0) {
+                acc.value_with_power += value;
+                acc.power += power;
+            }
+            if let Some(memory) = total_memory_gb.filter(|m| *m > 0.0) {
+                acc.value_with_memory += value;
+                acc.memory_gb += memory;
+            }
+        }
+    }
+
+    /// Summary of everything recorded so far
+    pub fn finish(&self) -> RunSummary {
+        RunSummary {
+            started: self.started.to_rfc3339(),
+            finished: Local::now().to_rfc3339(),
+            duration_secs: self.start.elapsed().as_secs_f64(),
+            samples: self.samples,
+            cpu_percent: self.cpu.clone(),
+            memory_kb: self.memory.clone(),
+            gpus: self.gpus.values().cloned().collect(),
+            metrics: self
+                .metrics
+                .iter()
+                .map(|(name, acc)| {
+                    let mut summary = acc.summary.clone();
+                    summary.per_watt = (acc.power > 0.0).then(|| acc.value_with_power / acc.power);
+                    summary.per_gb =
+                        (acc.memory_gb > 0.0).then(|| acc.value_with_memory / acc.memory_gb);
+                    (name.clone(), summary)
+                })
+                .collect(),
+            accounted_processes: self.accounted.clone(),
+        }
+    }
+}
+
+impl Default for SummaryBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RunSummary {
+    /// Human readable summary into the log
+    pub fn log(&self) {
+        log::info!(
+            "Run summary: {:.1}s, {} samples, CPU avg {:.1}% max {:.1}%, memory max {} kB",
+            self.duration_secs,
+            self.samples,
+            self.cpu_percent.avg,
+            self.cpu_percent.max,
+            self.memory_kb.max
+        );
+        for gpu in &self.gpus {
+            log::info!(
+                "GPU {} ({}): utilization avg {:.1}%, memory max {} MB, power avg {:.1} W",
+                gpu.index,
+                gpu.name,
+                gpu.utilization_percent.avg,
+                gpu.memory_used_mb.max,
+                gpu.power_draw_watts.avg
+            );
+            if !gpu.throttle_secs.is_empty() {
+                let times: Vec<String> = gpu
+                    .throttle_secs
+                    .iter()
+                    .map(|(reason, secs)| format!("{} {:.1}s", reason, secs))
+                    .collect();
+                log::info!("GPU {} clock throttle reasons: {}", gpu.index, times.join(", "));
+            }
+            let media = [&gpu.encoder_percent, &gpu.decoder_percent, &gpu.encoder_sessions];
+            if media.iter().any(|stat| stat.max > 0.0) {
+                log::info!(
+                    "GPU {} media: encoder avg {:.1}%, decoder avg {:.1}%, NVENC sessions max {}, {:.1} fps avg",
+                    gpu.index,
+                    gpu.encoder_percent.avg,
+                    gpu.decoder_percent.avg,
+                    gpu.encoder_sessions.max,
+                    gpu.encoder_fps.avg
+                );
+            }
+        }
+        for p in self.accounted_processes.iter().take(MAX_LOGGED_PROCESSES) {
+            let percent = |p: Option<u32>| p.map_or("N/A".to_string(), |p| format!("{}%", p));
+            log::info!(
+                "GPU {} exited PID {}: utilization {}, memory utilization {}, max memory {} MB, ran {}",
+                p.gpu_index,
+                p.pid,
+                percent(p.gpu_utilization),
+                percent(p.memory_utilization),
+                p.max_memory_usage.map_or("N/A".to_string(), |m| m.to_string()),
+                p.time_ms.map_or("N/A".to_string(), |ms| format!("{:.1}s", ms as f64 / 1000.0))
+            );
+        }
+        if self.accounted_processes.len() > MAX_LOGGED_PROCESSES {
+            log::info!(
+                "... and {} more exited GPU processes",
+                self.accounted_processes.len() - MAX_LOGGED_PROCESSES
+            );
+        }
+        for (name, metric) in &self.metrics {
+            log::info!(
+                "Metric {}: avg {:.3}, min {:.3}, max {:.3}, per W {}, per GB {}",
+                name,
+                metric.stat.avg,
+                metric.stat.min,
+                metric.stat.max,
+                metric
+                    .per_watt
+                    .map_or("N/A".to_string(), |v| format!("{:.4}", v)),
+                metric
+                    .per_gb
+                    .map_or("N/A".to_string(), |v| format!("{:.4}", v))
+            );
+        }
+    }
+
+    /// Write summary as pretty JSON
+    pub fn write_json(&self, path: &str) -> Result<()> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(file, self)
+            .map_err(|e| crate::error::TraceError::IoError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stat() {
+        let mut stat = Stat::default();
+        for v in [2.0, 4.0, 0.0] {
+            stat.add(v);
+        }
+        assert_eq!(stat.min, 0.0);
+        assert_eq!(stat.max, 4.0);
+        assert_eq!(stat.avg, 2.0);
+        assert_eq!(stat.last, 0.0);
+        assert_eq!(stat.count, 3);
+    }
+
+    #[test]
+    fn test_metric_efficiency() {
+        use crate::trace::datastreams::{GpuInfo, GpuReadings, Readings};
+        use sysinfo::{Pid, System, SystemExt};
+
+        let mut sys = System::new();
+        let readings = Readings::new(&mut sys, Pid::from(std::process::id() as i32));
+        let mut gpu = GpuReadings::new();
+        let mut info = GpuInfo::default();
+        info.memory.used = 2048;
+        info.power.draw = Some(100.0);
+        gpu.gpus.push(info);
+
+        let mut sample = Sample {
+            index: 1,
+            time: chrono::Local::now(),
+            readings,
+            cgroup: None,
+            traced_pids: vec![],
+            gpu: Some(gpu),
+            gpu_health: None,
+            affinity: Vec::new(),
+            accounted: vec![GpuAccountedProcess { pid: 4251, time_ms: Some(180), ..Default::default() }],
+            metrics: BTreeMap::from([("throughput".to_string(), 50.0)]),
+        };
+        let mut builder = SummaryBuilder::new();
+        builder.record(&sample);
+        sample.index = 2;
+        sample.metrics.clear();
+        sample.accounted.clear();
+        builder.record(&sample);
+
+        let summary = builder.finish();
+        assert_eq!(summary.samples, 2);
+        assert!(summary.gpus[0].throttle_secs.is_empty());
+        assert_eq!(summary.gpus[0].power_draw_watts.avg, 100.0);
+        let throughput = &summary.metrics["throughput"];
+        assert_eq!(throughput.series.len(), 1);
+        assert_eq!(throughput.per_watt, Some(0.5));
+        assert_eq!(throughput.per_gb, Some(25.0));
+        assert_eq!(summary.accounted_processes.len(), 1);
+    }
+
+    #[test]
+    fn test_time_per_throttle_reason() {
+        use crate::trace::datastreams::{GpuInfo, GpuReadings, Readings, ThrottleReasons};
+        use sysinfo::{Pid, System, SystemExt};
+
+        let mut sys = System::new();
+        let readings = Readings::new(&mut sys, Pid::from(std::process::id() as i32));
+        let start = chrono::Local::now();
+        let mut builder = SummaryBuilder::new();
+        let reasons = [
+            ThrottleReasons::GPU_IDLE,
+            0,
+            ThrottleReasons::SW_POWER_CAP | ThrottleReasons::HW_THERMAL_SLOWDOWN,
+            ThrottleReasons::SW_POWER_CAP,
+        ];
+        for (i, bits) in reasons.into_iter().enumerate() {
+            let mut gpu = GpuReadings::new();
+            gpu.gpus.push(GpuInfo {
+                throttle_reasons: Some(ThrottleReasons(bits)),
+                ..GpuInfo::default()
+            });
+            builder.record(&Sample {
+                index: i as u64 + 1,
+                time: start + chrono::Duration::seconds(2 * i as i64),
+                readings: readings.clone(),
+                cgroup: None,
+                traced_pids: vec![],
+                gpu: Some(gpu),
+                gpu_health: None,
+                affinity: Vec::new(),
+                accounted: Vec::new(),
+                metrics: BTreeMap::new(),
+            });
+        }
+
+        let throttle = &builder.finish().gpus[0].throttle_secs;
+        assert_eq!(throttle["gpu_idle"], 0.0); // first sample, no time before it
+        assert_eq!(throttle["none"], 2.0);
+        assert_eq!(throttle["sw_power_cap"], 4.0);
+        assert_eq!(throttle["hw_thermal_slowdown"], 2.0);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::trace::app::App;
+
+use super::utils::{chart_title, line_datasets, time_labels};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Span;
+use ratatui::widgets::{Axis, Block, Borders, Chart};
+use ratatui::Frame;
+
+/// Charts of at most that many metrics are shown side by side
+const MAX_METRIC_CHARTS: usize = 4;
+
+const COLORS: [Color; MAX_METRIC_CHARTS] = [
+    Color::LightGreen,
+    Color::LightMagenta,
+    Color::LightYellow,
+    Color::LightCyan,
+];
+
+pub fn app_metrics_panel(f: &mut Frame, app: &App, area: Rect) {
+    let metrics: Vec<_> = app
+        .datastreams
+        .app_metrics()
+        .metrics
+        .iter()
+        .take(MAX_METRIC_CHARTS)
+        .collect();
+    if metrics.is_empty() {
+        return;
+    }
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![
+            Constraint::Ratio(1, metrics.len() as u32);
+            metrics.len()
+        ])
+        .split(area);
+
+    let (from, to) = app.chart_range();
+    for (i, (name, history)) in metrics.into_iter().enumerate() {
+        let (resolution, points) = history.chart_points(from, to, chunks[i].width as usize * 2);
+        let (y_min, y_max) = points.iter().flatten().fold((f64::MAX, f64::MIN), |(lo, hi), (_, y)| {
+            (lo.min(*y), hi.max(*y))
+        });
+        let last = history.latest().map_or(0.0, |(_, y)| y);
+        // Flat lines (or nothing in the window) still need some room
+        let (y_min, y_max) = if points.is_empty() {
+            (last - 1.0, last + 1.0)
+        } else if (y_max - y_min).abs() < f64::EPSILON {
+            (y_min - 1.0, y_max + 1.0)
+        } else {
+            (y_min, y_max)
+        };
+
+        let style = Style::default().add_modifier(Modifier::ITALIC);
+        let labels = vec![
+            Span::styled(format!("{:.2}", y_min), style),
+            Span::styled(format!("{:.2}", (y_min + y_max) / 2.0), style),
+            Span::styled(format!("{:.2}", y_max), style),
+        ];
+        let datasets = line_datasets(format!("{:.3}", last), COLORS[i], &points);
+
+        let chart = Chart::new(datasets)
+            .block(
+                Block::default()
+                    .title(Span::styled(
+                        chart_title(name, resolution),
+                        Style::default()
+                            .fg(Color::Cyan)
+                            .add_modifier(Modifier::BOLD),
+                    ))
+                    .style(Style::default().fg(Color::Gray))
+                    .borders(Borders::ALL),
+            )
+            .x_axis(
+                Axis::default()
+                    .style(Style::default().fg(Color::Gray))
+                    .labels(time_labels(app.window))
+                    .bounds(app.chart_bounds()),
+            )
+            .y_axis(
+                Axis::default()
+                    .style(Style::default().fg(Color::Gray))
+                    .bounds([y_min, y_max])
+                    .labels(labels),
+            );
+        f.render_widget(chart, chunks[i]);
+    }
+}
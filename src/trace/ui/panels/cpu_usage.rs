@@ -1,32 +1,21 @@
 use crate::trace::app::App;
 
-use itertools::Itertools;
+use super::utils::{chart_title, line_datasets, time_labels};
 use ratatui::layout::Rect;
 use ratatui::style::{Color, Modifier, Style};
-use ratatui::symbols::Marker;
 use ratatui::text::Span;
-use ratatui::widgets::{Axis, Block, Borders, Chart, Dataset};
+use ratatui::widgets::{Axis, Block, Borders, Chart};
 use ratatui::Frame;
 
 pub fn cpu_usage_history_panel(f: &mut Frame, app: &App, area: Rect) {
-    let ds: Vec<(f64, f64)> = app
-        .cpu_panel_memory
-        .iter()
-        .map(|(x, y)| (*x, *y))
-        .collect_vec();
-    let datasets = vec![Dataset::default()
-        .name(String::from(&app.cpu_usage_str))
-        .marker(Marker::Braille)
-        .style(Style::default().fg(Color::LightRed))
-        .data(&ds)];
+    let history = &app.datastreams.cpu().cpu_usage_history;
+    let (from, to) = app.chart_range();
+    let (resolution, ds) = history.chart_points(from, to, area.width as usize * 2);
+    let datasets = line_datasets(app.cpu_usage_str.as_str(), Color::LightRed, &ds);
 
     let cpus = if app.autoscale {
-        let cpu_data = &app.cpu_panel_memory;
-        let auto = cpu_data.iter().map(|(_x, y)| y).collect_vec();
-        // println!("auto:{:?}", auto);
-        let auto = auto.iter().max_by(|a, b| a.total_cmp(b)).or(Some(&&1.0));
-        // println!("MAX:{:?}", auto);
-        let m = auto.unwrap().max(1.0);
+        let auto = history.max_in(from, to).unwrap_or(100.0) / 100.0;
+        let m = auto.max(1.0);
         let m = m.min(app.datastreams.readings.get_cpus_count() as f64);
 
         m.ceil()
@@ -50,7 +39,7 @@ pub fn cpu_usage_history_panel(f: &mut Frame, app: &App, area: Rect) {
         .block(
             Block::default()
                 .title(Span::styled(
-                    "CPU Usage",
+                    chart_title("CPU Usage", resolution),
                     Style::default()
                         .fg(Color::Cyan)
                         .add_modifier(Modifier::BOLD),
@@ -62,14 +51,14 @@ pub fn cpu_usage_history_panel(f: &mut Frame, app: &App, area: Rect) {
             Axis::default()
                 .title(format!("time[{}ms]", app.refresh))
                 .style(Style::default().fg(Color::Gray))
-                // .labels(label)
-                .bounds(app.window),
+                .labels(time_labels(app.window))
+                .bounds(app.chart_bounds()),
         )
         .y_axis(
             Axis::default()
                 .title("Usage (%)")
                 .style(Style::default().fg(Color::Gray))
-                .bounds([0.0, cpus])
+                .bounds([0.0, cpus * 100.0])
                 .labels(labels),
         );
 
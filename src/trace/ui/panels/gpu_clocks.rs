@@ -0,0 +1,196 @@
+use crate::trace::app::App;
+use crate::trace::datastreams::{
+    GpuClockMonitor, GpuReadings, History, Resolution, Segment, ThrottleReasons, Timestamp,
+};
+
+use super::utils::{chart_title, line_datasets, time_labels};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Axis, Block, Borders, Chart, Paragraph};
+use ratatui::Frame;
+
+/// Width of the reason names in front of the timeline
+const REASON_WIDTH: usize = 24;
+
+pub fn gpu_clocks_panel(f: &mut Frame, app: &App, gpu_readings: &GpuReadings, area: Rect) {
+    // For now, show the first GPU (we'll add multi-GPU support later)
+    let Some(clocks) = gpu_readings
+        .gpus
+        .first()
+        .and_then(|gpu| app.datastreams.streams.gpu::<GpuClockMonitor>(gpu.index))
+    else {
+        let no_gpu_text = Paragraph::new("No GPUs detected")
+            .block(
+                Block::default()
+                    .title("GPU Clocks")
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::Gray)),
+            )
+            .style(Style::default().fg(Color::Red));
+        f.render_widget(no_gpu_text, area);
+        return;
+    };
+    let gpu = &gpu_readings.gpus[0];
+
+    let (from, to) = app.chart_range();
+    let seen = seen_reasons(clocks, from, to);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(5), Constraint::Length(seen.len() as u16 + 2)])
+        .split(area);
+
+    // Clocks as percent of their maximum, so graphics, SM and memory share an axis
+    let width = chunks[0].width as usize * 2;
+    let series = [
+        ("Graphics", &clocks.graphics_clock_history, clocks.current_graphics_clock, clocks.max_graphics_clock, Color::LightCyan),
+        ("SM", &clocks.sm_clock_history, clocks.current_sm_clock, clocks.max_sm_clock, Color::LightGreen),
+        ("Memory", &clocks.memory_clock_history, clocks.current_memory_clock, clocks.max_memory_clock, Color::LightBlue),
+    ];
+    let mut resolution = None;
+    let mut labels = Vec::new();
+    let mut points = Vec::new();
+    let mut colors = Vec::new();
+    for (name, history, current, max, color) in series {
+        let Some(max) = max.filter(|m| *m > 0) else {
+            continue; // nothing to compare with
+        };
+        let (r, p) = percent_of(history, max as f64, from, to, width);
+        resolution.get_or_insert(r);
+        labels.push(format!("{} {}/{} MHz", name, current.map_or("-".to_string(), |c| c.to_string()), max));
+        points.push(p);
+        colors.push(color);
+    }
+    let datasets = labels
+        .iter()
+        .zip(&points)
+        .zip(colors)
+        .flat_map(|((label, segments), color)| line_datasets(label.as_str(), color, segments))
+        .collect();
+
+    let style = Style::default().add_modifier(Modifier::ITALIC);
+    let chart = Chart::new(datasets)
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    chart_title(
+                        &format!("GPU {} Clocks (% of max)", gpu.index),
+                        resolution.unwrap_or(Resolution::Raw),
+                    ),
+                    Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD),
+                ))
+                .style(Style::default().fg(Color::Gray))
+                .borders(Borders::ALL),
+        )
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(time_labels(app.window))
+                .bounds(app.chart_bounds()),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds([0.0, 100.0])
+                .labels(vec![
+                    Span::styled("0", style),
+                    Span::styled("50", style),
+                    Span::styled("100", style),
+                ]),
+        );
+    f.render_widget(chart, chunks[0]);
+
+    let title = match clocks.throttle_reasons {
+        None => "Throttle reasons: not reported".to_string(),
+        Some(reasons) if reasons.0 == 0 => "Throttle reasons: none".to_string(),
+        Some(reasons) => format!("Throttle reasons: {}", reasons.names().join(", ")),
+    };
+    let title_style = match clocks.throttle_reasons {
+        Some(reasons) if is_slowdown(reasons.0) => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        _ => Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+    };
+    let columns = (chunks[1].width as usize).saturating_sub(REASON_WIDTH + 3).max(1);
+    let lines: Vec<Line> = seen
+        .iter()
+        .map(|(bit, name)| {
+            let bar: String = (0..columns)
+                .map(|c| {
+                    let at = time_at(from, to, (c as f64 + 0.5) / columns as f64);
+                    match clocks.throttle_at(at) {
+                        Some(reasons) if reasons.contains(*bit) => '█',
+                        Some(_) => '·',
+                        None => ' ',
+                    }
+                })
+                .collect();
+            Line::from(vec![
+                Span::raw(format!("{:<width$} ", name, width = REASON_WIDTH)),
+                Span::styled(bar, Style::default().fg(reason_color(*bit))),
+            ])
+        })
+        .collect();
+    let timeline = Paragraph::new(lines).block(
+        Block::default()
+            .title(Span::styled(title, title_style))
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Gray)),
+    );
+    f.render_widget(timeline, chunks[1]);
+}
+
+/// Chart points of a clock history relative to `max`
+fn percent_of(
+    history: &History,
+    max: f64,
+    from: Timestamp,
+    to: Timestamp,
+    width: usize,
+) -> (Resolution, Vec<Segment>) {
+    let (resolution, segments) = history.chart_points(from, to, width);
+    let segments = segments
+        .into_iter()
+        .map(|segment| segment.into_iter().map(|(x, y)| (x, y / max * 100.0)).collect())
+        .collect();
+    (resolution, segments)
+}
+
+/// Reasons active at some point of the window, or right now
+fn seen_reasons(clocks: &GpuClockMonitor, from: Timestamp, to: Timestamp) -> Vec<(u64, &'static str)> {
+    let at_start = clocks.throttle_at(from).map_or(0, |r| r.0);
+    let active = clocks
+        .throttle_timeline
+        .iter()
+        .filter(|(since, _)| *since > from && *since <= to)
+        .fold(at_start, |bits, (_, r)| bits | r.map_or(0, |r| r.0));
+    ThrottleReasons::ALL
+        .iter()
+        .copied()
+        .filter(|(bit, _)| active & bit != 0)
+        .collect()
+}
+
+/// Time `fraction` of the way from `from` to `to`
+fn time_at(from: Timestamp, to: Timestamp, fraction: f64) -> Timestamp {
+    let span = (to - from).num_milliseconds() as f64;
+    from + chrono::Duration::milliseconds((span * fraction) as i64)
+}
+
+/// Reasons that actually cost performance, unlike idling or clocks set on purpose
+fn is_slowdown(bits: u64) -> bool {
+    bits & !(ThrottleReasons::GPU_IDLE
+        | ThrottleReasons::APPLICATIONS_CLOCKS_SETTING
+        | ThrottleReasons::DISPLAY_CLOCK_SETTING)
+        != 0
+}
+
+fn reason_color(bit: u64) -> Color {
+    match bit {
+        ThrottleReasons::SW_THERMAL_SLOWDOWN | ThrottleReasons::HW_THERMAL_SLOWDOWN => Color::Red,
+        ThrottleReasons::SW_POWER_CAP | ThrottleReasons::HW_POWER_BRAKE_SLOWDOWN => Color::Yellow,
+        ThrottleReasons::HW_SLOWDOWN => Color::LightRed,
+        _ if !is_slowdown(bit) => Color::DarkGray,
+        _ => Color::Magenta,
+    }
+}
@@ -0,0 +1,136 @@
+use crate::trace::app::App;
+use crate::trace::datastreams::{GpuEccErrors, GpuEccMonitor, GpuInfo, GpuReadings};
+
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Wrap};
+use ratatui::Frame;
+use std::cmp::Reverse;
+
+/// Alerts shown below the counters of each GPU, the rest is in the log
+const SHOWN_ALERTS: usize = 3;
+
+pub fn gpu_health_panel(f: &mut Frame, app: &App, gpu_readings: &GpuReadings, area: Rect) {
+    let mut watched: Vec<(&GpuInfo, &GpuEccMonitor)> = gpu_readings
+        .gpus
+        .iter()
+        .filter_map(|gpu| app.datastreams.streams.gpu::<GpuEccMonitor>(gpu.index).map(|health| (gpu, health)))
+        .collect();
+    if watched.is_empty() {
+        let no_gpu_text = Paragraph::new("No GPUs detected")
+            .block(
+                Block::default()
+                    .title("GPU Health")
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::Gray)),
+            )
+            .style(Style::default().fg(Color::Red));
+        f.render_widget(no_gpu_text, area);
+        return;
+    }
+    // worst first - the panel may not fit them all
+    watched.sort_by_key(|(gpu, health)| (Reverse(severity(health)), gpu.index));
+
+    let alert_style = Style::default().fg(Color::Red).add_modifier(Modifier::BOLD);
+    let mut lines = Vec::new();
+    for (gpu, health) in &watched {
+        let heading = if health.alerts.is_empty() {
+            Span::styled(format!("GPU {}", gpu.index), Style::default().fg(Color::Cyan))
+        } else {
+            Span::styled(format!("GPU {} - CRITICAL ({} alerts)", gpu.index, health.alerts.len()), alert_style)
+        };
+        lines.push(Line::from(heading));
+        lines.extend(health_lines(gpu, health, alert_style));
+    }
+
+    let alerts: usize = watched.iter().map(|(_, health)| health.alerts.len()).sum();
+    let (title, title_style) = if alerts == 0 {
+        (
+            "GPU Health".to_string(),
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        )
+    } else {
+        (format!("GPU Health - CRITICAL ({} alerts)", alerts), alert_style)
+    };
+    let panel = Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .title(Span::styled(title, title_style))
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Gray)),
+    );
+    f.render_widget(panel, area);
+}
+
+/// How bad it is: alerts raised, then uncorrected and corrected errors
+fn severity(health: &GpuEccMonitor) -> (usize, u64, u64) {
+    let ecc = &health.ecc;
+    (
+        health.alerts.len(),
+        ecc.errors_uncorrected_volatile.total.max(ecc.errors_uncorrected_aggregate.total),
+        ecc.errors_corrected_volatile.total.max(ecc.errors_corrected_aggregate.total),
+    )
+}
+
+/// ECC mode, counters and the latest alerts of one GPU
+fn health_lines(gpu: &GpuInfo, health: &GpuEccMonitor, alert_style: Style) -> Vec<Line<'static>> {
+    let ecc = &health.ecc;
+    let mode = if ecc.mode_current.is_empty() { "N/A" } else { ecc.mode_current.as_str() };
+    let mut lines = vec![
+        Line::from(if ecc.mode_pending.is_empty() || ecc.mode_pending == ecc.mode_current {
+            format!("ECC: {}", mode)
+        } else {
+            format!("ECC: {} ({} after reset)", mode, ecc.mode_pending)
+        }),
+        counts_line("Corrected", &ecc.errors_corrected_volatile, &ecc.errors_corrected_aggregate, Color::Yellow),
+        counts_line("Uncorrected", &ecc.errors_uncorrected_volatile, &ecc.errors_uncorrected_aggregate, Color::Red),
+    ];
+    if let Some(pages) = &health.retired_pages {
+        lines.push(Line::from(format!(
+            "Retired pages: {} single bit, {} double bit{}",
+            pages.single_bit,
+            pages.double_bit,
+            if pages.pending { ", pending" } else { "" }
+        )));
+    }
+    if let Some(rows) = &health.remapped_rows {
+        let state = if rows.failure {
+            ", FAILED"
+        } else if rows.pending {
+            ", pending"
+        } else {
+            ""
+        };
+        lines.push(Line::from(format!(
+            "Remapped rows: {} correctable, {} uncorrectable{}",
+            rows.correctable, rows.uncorrectable, state
+        )));
+    }
+    if let Some(age) = gpu.report_age_secs {
+        lines.push(Line::from(Span::styled(
+            format!("XML report: {}s old", age),
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    let shown = health.alerts.len().saturating_sub(SHOWN_ALERTS);
+    for alert in health.alerts.iter().skip(shown).rev() {
+        lines.push(Line::from(Span::styled(
+            format!("{} {}", alert.at.format("%H:%M:%S"), alert.message),
+            alert_style,
+        )));
+    }
+    lines
+}
+
+/// Volatile and aggregate error totals, colored once there are any
+fn counts_line(name: &str, volatile: &GpuEccErrors, aggregate: &GpuEccErrors, color: Color) -> Line<'static> {
+    let text = format!(
+        "{} errors: {} since boot, {} lifetime",
+        name, volatile.total, aggregate.total
+    );
+    if volatile.total > 0 || aggregate.total > 0 {
+        Line::from(Span::styled(text, Style::default().fg(color)))
+    } else {
+        Line::from(text)
+    }
+}
@@ -0,0 +1,138 @@
+use crate::trace::app::App;
+use crate::trace::datastreams::{GpuInterconnectMonitor, GpuPcie, GpuReadings, Resolution};
+
+use super::utils::{chart_title, line_datasets, time_labels};
+use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Axis, Block, Borders, Chart, Paragraph};
+use ratatui::Frame;
+
+pub fn gpu_interconnect_panel(f: &mut Frame, app: &App, gpu_readings: &GpuReadings, area: Rect) {
+    // For now, show the first GPU (we'll add multi-GPU support later)
+    let Some(links) = gpu_readings
+        .gpus
+        .first()
+        .and_then(|gpu| app.datastreams.streams.gpu::<GpuInterconnectMonitor>(gpu.index))
+    else {
+        let no_gpu_text = Paragraph::new("No GPUs detected")
+            .block(
+                Block::default()
+                    .title("GPU Interconnect")
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::Gray)),
+            )
+            .style(Style::default().fg(Color::Red));
+        f.render_widget(no_gpu_text, area);
+        return;
+    };
+    let gpu = &gpu_readings.gpus[0];
+
+    let (from, to) = app.chart_range();
+    let width = area.width as usize * 2;
+    let mut series = vec![
+        ("PCIe TX", &links.pcie_tx_history, links.pcie.tx_throughput, Color::LightCyan),
+        ("PCIe RX", &links.pcie_rx_history, links.pcie.rx_throughput, Color::LightBlue),
+    ];
+    if !links.nvlinks.is_empty() {
+        series.push(("NVLink TX", &links.nvlink_tx_history, links.nvlink_tx, Color::LightGreen));
+        series.push(("NVLink RX", &links.nvlink_rx_history, links.nvlink_rx, Color::Green));
+    }
+    let mut resolution = None;
+    let mut labels = Vec::new();
+    let mut points = Vec::new();
+    let mut colors = Vec::new();
+    for (name, history, current, color) in series {
+        let (r, p) = history.chart_points(from, to, width);
+        resolution.get_or_insert(r);
+        labels.push(format!("{} {}", name, current.map_or("-".to_string(), throughput)));
+        points.push(p);
+        colors.push(color);
+    }
+    let top = points
+        .iter()
+        .flatten()
+        .flatten()
+        .map(|(_, y)| *y)
+        .fold(1.0, f64::max)
+        * 1.1;
+    let datasets = labels
+        .iter()
+        .zip(&points)
+        .zip(colors)
+        .flat_map(|((label, segments), color)| line_datasets(label.as_str(), color, segments))
+        .collect();
+
+    let mut title = vec![Span::styled(
+        chart_title(&format!("GPU {} PCIe/NVLink", gpu.index), resolution.unwrap_or(Resolution::Raw)),
+        Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+    )];
+    if let Some(link) = pcie_link(&links.pcie) {
+        // idle GPUs drop to a lower generation to save power, busy ones should not
+        let style = match (links.pcie.is_degraded(), gpu.utilization.gpu > 0) {
+            (true, true) => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            (true, false) => Style::default().fg(Color::Yellow),
+            (false, _) => Style::default().fg(Color::Gray),
+        };
+        title.push(Span::styled(format!(" {} ", link), style));
+    }
+    if !links.nvlinks.is_empty() {
+        let errors = links.nvlink_errors();
+        let style = match errors {
+            0 => Style::default().fg(Color::Gray),
+            _ => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        };
+        title.push(Span::styled(
+            format!(" NVLink {} links, {} errors ", links.nvlinks.len(), errors),
+            style,
+        ));
+    }
+
+    let style = Style::default().add_modifier(Modifier::ITALIC);
+    let chart = Chart::new(datasets)
+        .block(
+            Block::default()
+                .title(Line::from(title))
+                .style(Style::default().fg(Color::Gray))
+                .borders(Borders::ALL),
+        )
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(time_labels(app.window))
+                .bounds(app.chart_bounds()),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds([0.0, top])
+                .labels(vec![
+                    Span::styled("0", style),
+                    Span::styled(throughput(top / 2.0), style),
+                    Span::styled(throughput(top), style),
+                ]),
+        );
+    f.render_widget(chart, area);
+}
+
+/// `Gen4 x16`, with the maximum when the link runs below it - `None` when not reported
+fn pcie_link(pcie: &GpuPcie) -> Option<String> {
+    let link = |generation: Option<u32>, width: Option<u32>| match (generation, width) {
+        (Some(g), Some(w)) => Some(format!("Gen{} x{}", g, w)),
+        _ => None,
+    };
+    let current = link(pcie.gen_current, pcie.width_current)?;
+    Some(match (pcie.is_degraded(), link(pcie.gen_max, pcie.width_max)) {
+        (true, Some(max)) => format!("PCIe {} (max {})", current, max),
+        _ => format!("PCIe {}", current),
+    })
+}
+
+/// MB/s, as GB/s once it gets big
+fn throughput(mbps: f64) -> String {
+    if mbps >= 1000.0 {
+        format!("{:.1} GB/s", mbps / 1000.0)
+    } else {
+        format!("{:.0} MB/s", mbps)
+    }
+}
@@ -0,0 +1,131 @@
+use crate::trace::app::App;
+use crate::trace::datastreams::{GpuMediaMonitor, GpuReadings, Resolution};
+
+use super::utils::{chart_title, line_datasets, time_labels};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Axis, Block, Borders, Cell, Chart, Paragraph, Row, Table};
+use ratatui::Frame;
+
+/// Video encoder, decoder, JPEG and optical flow engines over time, with the NVENC sessions
+pub fn gpu_media_panel(f: &mut Frame, app: &App, gpu_readings: &GpuReadings, area: Rect) {
+    // For now, show the first GPU (we'll add multi-GPU support later)
+    let media = gpu_readings
+        .gpus
+        .first()
+        .and_then(|gpu| app.datastreams.streams.gpu::<GpuMediaMonitor>(gpu.index))
+        .filter(|media| media.has_engines());
+    let Some(media) = media else {
+        let no_media_text = Paragraph::new("Media engines not reported")
+            .block(
+                Block::default()
+                    .title("GPU Media Engines")
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::Gray)),
+            )
+            .style(Style::default().fg(Color::Yellow));
+        f.render_widget(no_media_text, area);
+        return;
+    };
+    let gpu = &gpu_readings.gpus[0];
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(60), // Engines
+            Constraint::Percentage(40), // NVENC sessions
+        ])
+        .split(area);
+
+    let (from, to) = app.chart_range();
+    let width = chunks[0].width as usize * 2;
+    let u = &media.utilization;
+    let series = [
+        ("ENC", &media.encoder_history, u.encoder, Color::LightRed),
+        ("DEC", &media.decoder_history, u.decoder, Color::LightGreen),
+        ("JPG", &media.jpeg_history, u.jpeg, Color::LightYellow),
+        ("OFA", &media.ofa_history, u.ofa, Color::LightMagenta),
+    ];
+    let mut resolution = None;
+    let mut labels = Vec::new();
+    let mut points = Vec::new();
+    let mut colors = Vec::new();
+    // engines the GPU does not have stay out of the legend
+    for (name, history, current, color) in series.into_iter().filter(|s| s.2.is_some()) {
+        let (r, p) = history.chart_points(from, to, width);
+        resolution.get_or_insert(r);
+        labels.push(format!("{} {}%", name, current.unwrap_or(0)));
+        points.push(p);
+        colors.push(color);
+    }
+    let datasets = labels
+        .iter()
+        .zip(&points)
+        .zip(colors)
+        .flat_map(|((label, segments), color)| line_datasets(label.as_str(), color, segments))
+        .collect();
+
+    let style = Style::default().add_modifier(Modifier::ITALIC);
+    let chart = Chart::new(datasets)
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    chart_title(&format!("GPU {} Media Engines", gpu.index), resolution.unwrap_or(Resolution::Raw)),
+                    Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                ))
+                .style(Style::default().fg(Color::Gray))
+                .borders(Borders::ALL),
+        )
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(time_labels(app.window))
+                .bounds(app.chart_bounds()),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds([0.0, 100.0])
+                .labels(vec![Span::styled("0", style), Span::styled("100%", style)]),
+        );
+    f.render_widget(chart, chunks[0]);
+
+    let title = match media.encoder_stats {
+        Some(stats) if stats.session_count > 0 => Line::from(Span::styled(
+            format!(
+                "NVENC {} sessions, {} fps, {:.1} ms",
+                stats.session_count,
+                stats.average_fps,
+                stats.average_latency as f64 / 1000.0
+            ),
+            Style::default().fg(Color::LightRed).add_modifier(Modifier::BOLD),
+        )),
+        Some(_) => Line::from("NVENC idle"),
+        None => Line::from("NVENC not reported"),
+    };
+    let rows: Vec<Row> = media
+        .sessions
+        .iter()
+        .map(|s| {
+            Row::new(vec![
+                Cell::from(s.pid.to_string()),
+                Cell::from(s.codec.clone()),
+                Cell::from(format!("{}x{}", s.h_resolution, s.v_resolution)),
+                Cell::from(s.average_fps.to_string()),
+            ])
+        })
+        .collect();
+    let widths = [
+        Constraint::Length(8),
+        Constraint::Length(6),
+        Constraint::Min(9),
+        Constraint::Length(4),
+    ];
+    let sessions = Table::new(rows, widths)
+        .header(Row::new(vec!["PID", "Codec", "Res", "FPS"]).style(Style::default().fg(Color::LightBlue)))
+        .column_spacing(1)
+        .style(Style::default().fg(Color::Gray))
+        .block(Block::default().title(title).borders(Borders::ALL));
+    f.render_widget(sessions, chunks[1]);
+}
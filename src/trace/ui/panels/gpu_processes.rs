@@ -1,5 +1,5 @@
 use crate::trace::app::App;
-use crate::trace::datastreams::GpuReadings;
+use crate::trace::datastreams::{GpuAccountedProcess, GpuProcess, GpuProcessMonitor, GpuReadings};
 
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Modifier, Style};
@@ -21,21 +21,46 @@ pub fn gpu_processes_panel(f: &mut Frame, app: &App, gpu_readings: &GpuReadings,
         return;
     }
 
-    // Split area into GPU info and processes
+    // Split area into GPU info with its MIG devices and processes
+    let gpu = &gpu_readings.gpus[0];
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(3), // GPU info header
-            Constraint::Min(0),    // Processes list
+            Constraint::Length(3 + gpu.mig_devices.len() as u16), // GPU info header
+            Constraint::Min(0),                                    // Processes list
         ])
         .split(area);
 
-    // Show GPU information header
-    let gpu = &gpu_readings.gpus[0];
-    let gpu_info = Paragraph::new(format!(
+    // Show GPU information header, MIG devices below their GPU
+    let mut info_lines = vec![Line::from(format!(
         "GPU {}: {} | Driver: {} | Compute: {} | P-State: {}",
         gpu.index, gpu.name, gpu.driver_version, gpu.compute_cap, gpu.pstate
-    ))
+    ))];
+    for device in &gpu.mig_devices {
+        let selected = app.selected_mig.contains(&device.uuid);
+        let style = if selected {
+            Style::default().fg(Color::LightMagenta).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Cyan)
+        };
+        info_lines.push(Line::from(Span::styled(
+            format!(
+                " {} MIG {} {:<8} | GI {} CI {} |{} Memory: {:.1} / {:.1} GB | Processes: {} {}",
+                if selected { "▶" } else { "└" },
+                device.index,
+                device.profile,
+                device.gpu_instance_id,
+                device.compute_instance_id,
+                device.multiprocessor_count.map_or(String::new(), |sm| format!(" {} SMs |", sm)),
+                device.memory_used as f64 / 1024.0,
+                device.memory_total as f64 / 1024.0,
+                gpu_readings.get_mig_device_processes(gpu.index, device).len(),
+                device.uuid,
+            ),
+            style,
+        )));
+    }
+    let gpu_info = Paragraph::new(info_lines)
     .block(
         Block::default()
             .title("GPU Information")
@@ -46,8 +71,13 @@ pub fn gpu_processes_panel(f: &mut Frame, app: &App, gpu_readings: &GpuReadings,
 
     f.render_widget(gpu_info, chunks[0]);
 
-    // Show GPU processes
-    if gpu_readings.processes.is_empty() {
+    // Show GPU processes, then the traced ones that exited, as accounting mode recorded them
+    let exited = app
+        .datastreams
+        .streams
+        .gpu::<GpuProcessMonitor>(gpu.index)
+        .map_or(&[][..], |m| m.exited.as_slice());
+    if gpu_readings.processes.is_empty() && exited.is_empty() {
         let no_processes_text = Paragraph::new("No GPU processes currently running")
             .block(
                 Block::default()
@@ -58,13 +88,14 @@ pub fn gpu_processes_panel(f: &mut Frame, app: &App, gpu_readings: &GpuReadings,
             .style(Style::default().fg(Color::Yellow));
         f.render_widget(no_processes_text, chunks[1]);
     } else {
-        let process_items: Vec<ListItem> = gpu_readings
+        let mut process_items: Vec<ListItem> = gpu_readings
             .processes
             .iter()
             .enumerate()
             .map(|(i, process)| {
                 let memory_mb = process.used_memory as f64 / 1024.0;
                 let is_selected = i == app.selected_proc;
+                let is_traced = app.datastreams.traced_pids.contains(&process.pid);
                 
                 let style = if is_selected {
                     Style::default()
@@ -75,6 +106,10 @@ pub fn gpu_processes_panel(f: &mut Frame, app: &App, gpu_readings: &GpuReadings,
                 };
 
                 ListItem::new(Line::from(vec![
+                    Span::styled(
+                        if is_traced { "* " } else { "  " },
+                        style.fg(Color::LightMagenta),
+                    ),
                     Span::styled(
                         format!("PID: {:>8} | ", process.pid),
                         style.fg(Color::Cyan),
@@ -87,14 +122,31 @@ pub fn gpu_processes_panel(f: &mut Frame, app: &App, gpu_readings: &GpuReadings,
                         format!("Memory: {:>8.1} MB", memory_mb),
                         style.fg(Color::Yellow),
                     ),
+                    Span::styled(
+                        process
+                            .utilization
+                            .map_or(String::new(), |u| format!(" | GPU: {:>3}%", u)),
+                        style.fg(Color::LightBlue),
+                    ),
+                    Span::styled(instance_label(gpu_readings, process), style.fg(Color::Cyan)),
+                    Span::styled(media_label(gpu_readings, process), style.fg(Color::LightRed)),
                 ]))
             })
             .collect();
+        // newest first
+        process_items.extend(exited.iter().rev().map(exited_item));
 
         let processes_list = List::new(process_items)
             .block(
                 Block::default()
-                    .title(format!("GPU Processes ({} running)", gpu_readings.processes.len()))
+                    .title(format!(
+                        "GPU Processes ({} running, {} traced, {} exited)",
+                        gpu_readings.processes.len(),
+                        gpu_readings
+                            .get_processes_for_pids(&app.datastreams.traced_pids)
+                            .len(),
+                        exited.len()
+                    ))
                     .borders(Borders::ALL)
                     .style(Style::default().fg(Color::Gray)),
             )
@@ -164,3 +216,57 @@ pub fn gpu_summary_panel(f: &mut Frame, _app: &App, gpu_readings: &GpuReadings,
 
     f.render_widget(summary_paragraph, area);
 }
+
+/// Traced process that exited, with what it used of the GPU over its lifetime
+fn exited_item(process: &GpuAccountedProcess) -> ListItem<'static> {
+    let percent = |p: Option<u32>| p.map_or("-".to_string(), |p| format!("{}%", p));
+    let style = Style::default().fg(Color::DarkGray);
+    ListItem::new(Line::from(vec![
+        Span::styled("x ", style.fg(Color::LightMagenta)),
+        Span::styled(format!("PID: {:>8} | ", process.pid), style),
+        Span::styled(format!("{:>20} | ", "exited"), style),
+        Span::styled(
+            format!(
+                "Max memory: {:>8} MB | GPU: {:>4} | Mem: {:>4} | Ran: {}",
+                process.max_memory_usage.map_or("-".to_string(), |m| m.to_string()),
+                percent(process.gpu_utilization),
+                percent(process.memory_utilization),
+                process.time_ms.map_or("-".to_string(), |ms| format!("{:.1}s", ms as f64 / 1000.0)),
+            ),
+            style,
+        ),
+    ]))
+}
+
+/// MIG device a process runs on, or its instance IDs where the devices are not known
+fn instance_label(gpu_readings: &GpuReadings, process: &GpuProcess) -> String {
+    let Some(gpu_instance_id) = process.gpu_instance_id else {
+        return String::new();
+    };
+    let device = gpu_readings
+        .get_gpu(process.gpu_index)
+        .and_then(|gpu| gpu.mig_devices.iter().find(|d| d.runs(process)));
+    match device {
+        Some(device) => format!(" | MIG {} {}", device.index, device.profile),
+        None => format!(
+            " | GI {} CI {}",
+            gpu_instance_id,
+            process.compute_instance_id.map_or("-".to_string(), |ci| ci.to_string())
+        ),
+    }
+}
+
+/// Video engines a process keeps busy and its NVENC sessions, nothing when it uses none
+fn media_label(gpu_readings: &GpuReadings, process: &GpuProcess) -> String {
+    let mut label = String::new();
+    for (engine, percent) in [("ENC", process.encoder_utilization), ("DEC", process.decoder_utilization)] {
+        if let Some(percent) = percent.filter(|p| *p > 0) {
+            label.push_str(&format!(" | {}: {:>3}%", engine, percent));
+        }
+    }
+    let sessions = gpu_readings.get_encoder_sessions(process.gpu_index, process.pid).len();
+    if sessions > 0 {
+        label.push_str(&format!(" | NVENC: {}", sessions));
+    }
+    label
+}
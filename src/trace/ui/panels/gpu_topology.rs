@@ -0,0 +1,181 @@
+use crate::trace::app::App;
+use crate::trace::datastreams::{format_cpu_list, GpuPeer, GpuReadings, Placement};
+
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, Wrap};
+use ratatui::Frame;
+
+/// Warnings shown below the processes, the rest is in the log
+const SHOWN_WARNINGS: usize = 5;
+
+/// Connection matrix between GPUs, with the CPUs and NUMA node close to each
+pub fn gpu_topology_panel(f: &mut Frame, _app: &App, gpu_readings: &GpuReadings, area: Rect) {
+    if gpu_readings.gpus.is_empty() {
+        let no_gpu_text = Paragraph::new("No GPUs detected")
+            .block(
+                Block::default()
+                    .title("GPU Topology")
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::Gray)),
+            )
+            .style(Style::default().fg(Color::Red));
+        f.render_widget(no_gpu_text, area);
+        return;
+    }
+
+    let gpus = &gpu_readings.gpus;
+    let mut header = vec![String::new()];
+    header.extend(gpus.iter().map(|gpu| format!("GPU{}", gpu.index)));
+    header.extend(["CPU Affinity".to_string(), "NUMA".to_string()]);
+
+    let rows: Vec<Row> = gpus
+        .iter()
+        .map(|gpu| {
+            let name = Cell::from(format!("GPU{}", gpu.index)).style(Style::default().fg(Color::LightBlue));
+            let mut cells = vec![name];
+            cells.extend(gpus.iter().map(|other| {
+                if other.index == gpu.index {
+                    return Cell::from(" X");
+                }
+                match gpu.peers.iter().find(|p| p.gpu_index == other.index) {
+                    Some(peer) => Cell::from(peer.connection.clone()).style(connection_style(peer)),
+                    None => Cell::from("-"),
+                }
+            }));
+            cells.push(Cell::from(match gpu.cpu_affinity.is_empty() {
+                true => "-".to_string(),
+                false => format_cpu_list(&gpu.cpu_affinity),
+            }));
+            cells.push(Cell::from(gpu.numa_node.map_or("-".to_string(), |n| n.to_string())));
+            Row::new(cells)
+        })
+        .collect();
+
+    let mut widths = vec![Constraint::Length(6)];
+    widths.extend(gpus.iter().map(|_| Constraint::Length(6)));
+    widths.extend([Constraint::Min(14), Constraint::Length(5)]);
+    let legend = "NV# NVLink  PIX/PXB PCIe bridges  PHB host bridge  NODE same node  SYS across nodes";
+    let table = Table::new(rows, widths)
+        .header(Row::new(header).style(Style::default().fg(Color::LightBlue)))
+        .column_spacing(1)
+        .style(Style::default().fg(Color::Gray))
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    "GPU Topology",
+                    Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                ))
+                .title_bottom(Line::from(legend))
+                .borders(Borders::ALL),
+        );
+    f.render_widget(table, area);
+}
+
+/// Traced processes using a GPU, where they may run compared with where the GPU is attached,
+/// and the warnings about those on the wrong NUMA node
+pub fn gpu_affinity_panel(f: &mut Frame, app: &App, area: Rect) {
+    let topology = app.datastreams.topology();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),                            // Processes
+            Constraint::Length(SHOWN_WARNINGS as u16 + 2), // Warnings
+        ])
+        .split(area);
+
+    let rows: Vec<Row> = topology
+        .placements
+        .iter()
+        .map(|p| {
+            let gpu = app.gpu_readings.get_gpu(p.gpu_index);
+            let (verdict, style) = match p.placement {
+                Placement::Local => ("local", Style::default().fg(Color::Green)),
+                Placement::Spread => ("spread", Style::default().fg(Color::Yellow)),
+                Placement::Remote => (
+                    "WRONG NODE",
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                ),
+                Placement::Unknown => ("unknown", Style::default().fg(Color::Gray)),
+            };
+            Row::new(vec![
+                Cell::from(p.affinity.pid.to_string()),
+                Cell::from(p.affinity.name.clone()),
+                Cell::from(p.gpu_index.to_string()),
+                Cell::from(gpu.and_then(|g| g.numa_node).map_or("-".to_string(), |n| n.to_string())),
+                Cell::from(format_cpu_list(&p.affinity.cpus)),
+                Cell::from(format_cpu_list(&p.affinity.cpu_nodes)),
+                Cell::from(format_cpu_list(&p.affinity.memory_nodes)),
+                Cell::from(verdict).style(style),
+            ])
+        })
+        .collect();
+    let remote = topology.placements.iter().filter(|p| p.placement == Placement::Remote).count();
+    let title_style = match remote {
+        0 => Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        _ => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+    };
+    let widths = [
+        Constraint::Length(8),
+        Constraint::Length(16),
+        Constraint::Length(4),
+        Constraint::Length(9),
+        Constraint::Min(12),
+        Constraint::Length(10),
+        Constraint::Length(10),
+        Constraint::Length(10),
+    ];
+    let table = Table::new(rows, widths)
+        .header(
+            Row::new(vec!["PID", "Command", "GPU", "GPU node", "CPUs", "CPU nodes", "Mem nodes", "Placement"])
+                .style(Style::default().fg(Color::LightBlue)),
+        )
+        .column_spacing(1)
+        .style(Style::default().fg(Color::Gray))
+        .block(
+            Block::default()
+                .title(Span::styled(
+                    format!(
+                        "NUMA Affinity ({} traced GPU processes, {} on the wrong node)",
+                        topology.placements.len(),
+                        remote
+                    ),
+                    title_style,
+                ))
+                .borders(Borders::ALL),
+        );
+    f.render_widget(table, chunks[0]);
+
+    let shown = topology.warnings.len().saturating_sub(SHOWN_WARNINGS);
+    let lines: Vec<Line> = topology
+        .warnings
+        .iter()
+        .skip(shown)
+        .rev()
+        .map(|w| {
+            Line::from(Span::styled(
+                format!("{} {}", w.at.format("%H:%M:%S"), w.message),
+                Style::default().fg(Color::Red),
+            ))
+        })
+        .collect();
+    let warnings = Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .title(format!("Warnings ({})", topology.warnings.len()))
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Gray)),
+    );
+    f.render_widget(warnings, chunks[1]);
+}
+
+/// NVLink is the fast way, crossing NUMA nodes the slow one
+fn connection_style(peer: &GpuPeer) -> Style {
+    if peer.is_nvlink() {
+        Style::default().fg(Color::Green)
+    } else if peer.crosses_numa() {
+        Style::default().fg(Color::Red)
+    } else {
+        Style::default().fg(Color::Yellow)
+    }
+}
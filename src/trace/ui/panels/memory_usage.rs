@@ -1,36 +1,32 @@
 use crate::trace::app::App;
+use crate::trace::datastreams::Segment;
 
-use itertools::Itertools;
+use super::utils::{chart_title, line_datasets, time_labels};
 use ratatui::layout::Rect;
 use ratatui::style::{Color, Modifier, Style};
-use ratatui::symbols::Marker;
 use ratatui::text::Span;
-use ratatui::widgets::{Axis, Block, Borders, Chart, Dataset};
+use ratatui::widgets::{Axis, Block, Borders, Chart};
 use ratatui::Frame;
 
 pub fn mem_history_panel(f: &mut Frame, app: &App, area: Rect) {
+    const KB_IN_GB: f64 = 1024.0 * 1024.0;
     let total = (app.datastreams.readings.get_total_memory() / 1024 / 1024) as f64;
+    let history = &app.datastreams.memory().memory_usage_history;
+    let (from, to) = app.chart_range();
 
     let mem = if app.autoscale {
-        let auto = &app.mem_panel_memory.iter().map(|(_x, y)| y).collect_vec();
-        let auto = auto.iter().max_by(|a, b| a.total_cmp(b)).or(Some(&&0.0));
-        let m = auto.unwrap();
-
-        (*m * total + 0.9).round() // to not to be 100% almost all the time
+        let m = history.max_in(from, to).unwrap_or(0.0) / KB_IN_GB;
+        (m + 0.9).round() // to not to be 100% almost all the time
     } else {
         total
     };
 
-    let ds: Vec<(f64, f64)> = app
-        .mem_panel_memory
-        .iter()
-        .map(|(x, y)| (*x, *y * total / mem))
-        .collect_vec();
-    let datasets = vec![Dataset::default()
-        .name(String::from(&app.mem_usage_str))
-        .marker(Marker::Braille)
-        .style(Style::default().fg(Color::LightGreen))
-        .data(&ds)];
+    let (resolution, ds) = history.chart_points(from, to, area.width as usize * 2);
+    let ds: Vec<Segment> = ds
+        .into_iter()
+        .map(|segment| segment.into_iter().map(|(x, kb)| (x, kb / KB_IN_GB)).collect())
+        .collect();
+    let datasets = line_datasets(app.mem_usage_str.as_str(), Color::LightGreen, &ds);
 
     let c100 = format!("{}", mem);
     let c75 = format!("{}", mem * 0.75);
@@ -47,7 +43,7 @@ pub fn mem_history_panel(f: &mut Frame, app: &App, area: Rect) {
         Span::styled(&c100, style),
     ];
     let title = Span::styled(
-        "Memory Usage",
+        chart_title("Memory Usage", resolution),
         Style::default()
             .fg(Color::Cyan)
             .add_modifier(Modifier::BOLD),
@@ -64,13 +60,14 @@ pub fn mem_history_panel(f: &mut Frame, app: &App, area: Rect) {
             Axis::default()
                 .title("")
                 .style(Style::default().fg(Color::Gray))
-                .bounds(app.window), // .labels(label),
+                .labels(time_labels(app.window))
+                .bounds(app.chart_bounds()),
         )
         .y_axis(
             Axis::default()
                 .title("Usage (GB)")
                 .style(Style::default().fg(Color::Gray))
-                .bounds([0.0, 1.0])
+                .bounds([0.0, mem])
                 .labels(labels),
         );
 
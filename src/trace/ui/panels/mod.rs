@@ -5,10 +5,19 @@ mod gpu_memory;
 mod gpu_utilization;
 mod gpu_temperature;
 mod gpu_power;
+mod gpu_clocks;
+mod gpu_health;
+mod gpu_interconnect;
+mod gpu_media;
+mod gpu_topology;
 mod gpu_processes;
+mod output;
+mod app_metrics;
 
 pub use self::cpu_usage::cpu_usage_history_panel;
 pub use self::memory_usage::mem_history_panel;
+pub use self::app_metrics::app_metrics_panel;
+pub use self::output::output_panel;
 pub use self::process::process_panel;
 
 // GPU panels
@@ -16,6 +25,11 @@ pub use self::gpu_memory::gpu_memory_panel;
 pub use self::gpu_utilization::gpu_utilization_panel;
 pub use self::gpu_temperature::gpu_temperature_panel;
 pub use self::gpu_power::gpu_power_panel;
+pub use self::gpu_clocks::gpu_clocks_panel;
+pub use self::gpu_health::gpu_health_panel;
+pub use self::gpu_interconnect::gpu_interconnect_panel;
+pub use self::gpu_media::gpu_media_panel;
+pub use self::gpu_topology::{gpu_affinity_panel, gpu_topology_panel};
 pub use self::gpu_processes::{gpu_processes_panel, gpu_summary_panel};
 
 mod utils;
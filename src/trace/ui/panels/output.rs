@@ -0,0 +1,130 @@
+use crate::trace::app::App;
+use crate::trace::output::OutputStream;
+
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::Frame;
+
+pub fn output_panel(f: &mut Frame, app: &App, area: Rect) {
+    let Some(log) = &app.output_log else {
+        let no_output_text = Paragraph::new("Application output is not captured")
+            .block(
+                Block::default()
+                    .title("Application Output")
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::Gray)),
+            )
+            .style(Style::default().fg(Color::Red));
+        f.render_widget(no_output_text, area);
+        return;
+    };
+    let log = log.lock().unwrap();
+    let view = &app.output_view;
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),    // Lines
+            Constraint::Length(3), // Search / help
+        ])
+        .split(area);
+
+    // Keep selected line in the middle, or stick to the end when following
+    let height = chunks[0].height.saturating_sub(2) as usize;
+    let len = log.lines().len();
+    let selected = view.selected.unwrap_or(len.saturating_sub(1));
+    let start = if view.selected.is_some() {
+        selected
+            .saturating_sub(height / 2)
+            .min(len.saturating_sub(height))
+    } else {
+        len.saturating_sub(height)
+    };
+    let pattern = view.search.to_lowercase();
+
+    let lines: Vec<Line> = log
+        .lines()
+        .iter()
+        .enumerate()
+        .skip(start)
+        .take(height)
+        .map(|(i, line)| {
+            let mut style = match line.stream {
+                OutputStream::Stdout => Style::default().fg(Color::White),
+                OutputStream::Stderr => Style::default().fg(Color::LightRed),
+            };
+            if !pattern.is_empty() && line.text.to_lowercase().contains(&pattern) {
+                style = style.fg(Color::Black).bg(Color::Yellow);
+            }
+            if view.selected == Some(i) {
+                style = style.bg(Color::DarkGray).add_modifier(Modifier::BOLD);
+            }
+
+            let mut spans = vec![
+                Span::styled(
+                    line.time.format("%H:%M:%S%.3f ").to_string(),
+                    Style::default().fg(Color::DarkGray),
+                ),
+                Span::styled(
+                    format!("#{:<6}", line.sample),
+                    Style::default().fg(Color::Gray),
+                ),
+            ];
+            if let Some(mem) = line.gpu_memory_used {
+                spans.push(Span::styled(
+                    format!("{:>8} MB ", mem),
+                    Style::default().fg(Color::LightBlue),
+                ));
+            }
+            spans.push(Span::styled(line.text.clone(), style));
+            Line::from(spans)
+        })
+        .collect();
+
+    let output = Paragraph::new(lines).block(
+        Block::default()
+            .title(Span::styled(
+                format!(
+                    "Application Output ({} lines{})",
+                    log.total_lines(),
+                    if view.selected.is_none() { ", following" } else { "" }
+                ),
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ))
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Gray)),
+    );
+    f.render_widget(output, chunks[0]);
+
+    let footer = if view.searching {
+        Line::from(vec![
+            Span::styled("/", Style::default().fg(Color::Yellow)),
+            Span::styled(format!("{}_", view.search), Style::default().fg(Color::White)),
+        ])
+    } else {
+        Line::from(vec![
+            Span::styled(
+                "Up/Down/PgUp/PgDn scroll  Home/End top/follow  / search  n/N next/prev",
+                Style::default().fg(Color::Gray),
+            ),
+            Span::styled(
+                if view.search.is_empty() {
+                    String::new()
+                } else {
+                    format!("   [{}]", view.search)
+                },
+                Style::default().fg(Color::Yellow),
+            ),
+        ])
+    };
+    let footer = Paragraph::new(footer).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Gray)),
+    );
+    f.render_widget(footer, chunks[1]);
+}
@@ -8,7 +8,7 @@ use ratatui::widgets::{Block, Borders, Row, Table};
 use ratatui::Frame;
 
 pub fn process_panel(f: &mut Frame, app: &App, area: Rect) {
-    let mut process_by_cpu = app.datastreams.process_info.processes.clone();
+    let mut process_by_cpu = app.datastreams.processes().processes.clone();
     process_by_cpu.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap());
 
     let (_, process_to_display) = utils::scrolling(area, app.selected_proc, &process_by_cpu[..]);
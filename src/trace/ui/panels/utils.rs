@@ -1,4 +1,10 @@
+use crate::trace::datastreams::{Resolution, Segment};
 use ratatui::layout::Rect;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::symbols::Marker;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Dataset, GraphType};
+use std::time::Duration;
 
 pub fn scrolling<T: Clone>(area: Rect, absolute_postion: usize, data: &[T]) -> (usize, Vec<T>) {
     let capacity: usize = area.height as usize - 4; //For the header
@@ -16,3 +22,50 @@ pub fn scrolling<T: Clone>(area: Rect, absolute_postion: usize, data: &[T]) -> (
 
     (selected_row, displayed_data)
 }
+
+/// X axis labels for time charts: window start, middle and now.
+pub fn time_labels(window: Duration) -> Vec<Span<'static>> {
+    let style = Style::default().add_modifier(Modifier::ITALIC);
+    let secs = window.as_secs_f64();
+    vec![
+        Span::styled(format_secs(-secs), style),
+        Span::styled(format_secs(-secs / 2.0), style),
+        Span::styled("now", style),
+    ]
+}
+
+fn format_secs(secs: f64) -> String {
+    if secs.abs() >= 3600.0 {
+        format!("{:.1}h", secs / 3600.0)
+    } else if secs.abs() >= 120.0 {
+        format!("{:.0}m", secs / 60.0)
+    } else if secs.abs() < 10.0 && secs.fract() != 0.0 {
+        format!("{:.1}s", secs)
+    } else {
+        format!("{:.0}s", secs)
+    }
+}
+
+/// Chart title, with the resolution when charts show rollups instead of raw readings.
+pub fn chart_title(name: &str, resolution: Resolution) -> String {
+    match resolution {
+        Resolution::Raw => name.to_string(),
+        rollup => format!("{} ({})", name, rollup),
+    }
+}
+
+/// Line datasets of one series, one per segment so gaps stay gaps. Only the first is named,
+/// so the series shows up in the legend once - even with no points in the window.
+pub fn line_datasets<'a>(name: impl Into<Line<'a>>, color: Color, segments: &'a [Segment]) -> Vec<Dataset<'a>> {
+    let line = |data: &'a [(f64, f64)]| {
+        Dataset::default()
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(color))
+            .data(data)
+    };
+    let (first, rest) = segments.split_first().map_or((&[][..], &[][..]), |(f, r)| (f.as_slice(), r));
+    std::iter::once(line(first).name(name))
+        .chain(rest.iter().map(|segment| line(segment)))
+        .collect()
+}
@@ -2,6 +2,7 @@ use crate::error::{Result, TraceError};
 use crate::trace::app::App;
 
 use crate::trace::ui::panels::*;
+use crate::trace::ui::tabs::TabKind;
 use ratatui::backend::Backend;
 use ratatui::layout::{Constraint, Direction, Layout, Rect};
 use ratatui::style::{Color, Modifier, Style};
@@ -18,18 +19,22 @@ pub fn render<B: Backend>(t: &mut Terminal<B>, app: &App) -> Result<()> {
         let sub_areas = Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Length(4), Constraint::Min(5)].as_ref()) // Length(4) - we need so much space for the header, why?
-            .split(f.size()); // size? well it's big!
+            .split(f.area()); // size? well it's big!
 
         render_top(f, app, sub_areas[0]); // If we render top, we need to render bottom.
-        #[allow(clippy::single_match)]
-        match app.tabs.selection { // We are selecting tab, we need to know what tab!
-            0 => { // 0? What does it mean?
+        match app.tabs.current() { // We are selecting tab, we need to know what tab!
+            TabKind::CpuMemory => {
                 render_charts(f, app, sub_areas[1]); // Rendering chart, because charts are important!
             }
-            1 => { // GPU monitoring tab
+            TabKind::Gpu => { // GPU monitoring tab
                 render_gpu_panels(f, app, sub_areas[1]);
             }
-            _ => {} // Anything else is just not worth our time.
+            TabKind::Topology => { // Who is wired to whom, and who runs where
+                render_topology(f, app, sub_areas[1]);
+            }
+            TabKind::Output => { // What the application has to say
+                render_output(f, app, sub_areas[1]);
+            }
         };
     }) {
         Ok(_) => Ok(()), // It's ok! Or is it?
@@ -37,6 +42,21 @@ pub fn render<B: Backend>(t: &mut Terminal<B>, app: &App) -> Result<()> {
     }
 }
 
+/// Renders application output, with charts of its metrics on top once there are any.
+/// "What gets measured gets managed." - Peter Drucker, definitely while grepping logs.
+fn render_output(f: &mut Frame, app: &App, area: Rect) {
+    if app.datastreams.app_metrics().metrics.is_empty() {
+        output_panel(f, app, area); // Nothing measured yet, all space goes to the text.
+        return;
+    }
+    let sub_areas = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(35), Constraint::Percentage(65)].as_ref())
+        .split(area);
+    app_metrics_panel(f, app, sub_areas[0]); // Numbers first.
+    output_panel(f, app, sub_areas[1]); // Words second.
+}
+
 /// Renders the top part of the screen. As if the top was any more special than the bottom.
 /// "The only thing we have to fear is fear itself." - Franklin D. Roosevelt, probably while trying to render a UI.
 fn render_top(f: &mut Frame, app: &App, area: Rect) {
@@ -99,6 +119,25 @@ pub fn render_gpu_panels(f: &mut Frame, app: &App, area: Rect) {
         return;
     }
 
+    // Backend in trouble - say so above the (last good) readings
+    let area = if app.gpu_health.is_healthy() {
+        area
+    } else {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(area);
+        let hint = app.gpu_health.last_error().and_then(|e| e.hint());
+        let text = match hint {
+            Some(hint) => format!("{} ({})", app.gpu_health, hint),
+            None => app.gpu_health.to_string(),
+        };
+        let degraded = Paragraph::new(text)
+            .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD));
+        f.render_widget(degraded, chunks[0]);
+        chunks[1]
+    };
+
     // Split the area into multiple sections for different GPU metrics
     let main_chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -120,29 +159,51 @@ pub fn render_gpu_panels(f: &mut Frame, app: &App, area: Rect) {
         ])
         .split(main_chunks[1]);
 
-    // Left side: Memory and Utilization
+    // Left side: Memory, Utilization, Clocks with their throttle reasons, and PCIe/NVLink
     let left_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Percentage(50), // Memory
-            Constraint::Percentage(50), // Utilization
+            Constraint::Percentage(25), // Memory
+            Constraint::Percentage(20), // Utilization
+            Constraint::Percentage(30), // Clocks
+            Constraint::Percentage(25), // PCIe/NVLink
         ])
         .split(metrics_chunks[0]);
 
     gpu_memory_panel(f, app, &app.gpu_readings, left_chunks[0]);
     gpu_utilization_panel(f, app, &app.gpu_readings, left_chunks[1]);
+    gpu_clocks_panel(f, app, &app.gpu_readings, left_chunks[2]);
+    gpu_interconnect_panel(f, app, &app.gpu_readings, left_chunks[3]);
 
-    // Right side: Temperature, Power, and Processes
+    // Right side: Temperature, Power, Health, Media engines, and Processes
     let right_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(6),  // Temperature
             Constraint::Length(6),  // Power
+            Constraint::Length(9),  // Health
+            Constraint::Length(8),  // Media engines
             Constraint::Min(0),     // Processes
         ])
         .split(metrics_chunks[1]);
 
     gpu_temperature_panel(f, app, &app.gpu_readings, right_chunks[0]);
     gpu_power_panel(f, app, &app.gpu_readings, right_chunks[1]);
-    gpu_processes_panel(f, app, &app.gpu_readings, right_chunks[2]);
+    gpu_health_panel(f, app, &app.gpu_readings, right_chunks[2]);
+    gpu_media_panel(f, app, &app.gpu_readings, right_chunks[3]);
+    gpu_processes_panel(f, app, &app.gpu_readings, right_chunks[4]);
+}
+
+/// Renders the topology tab: the wiring of the GPUs on top, where their traced users run below.
+/// "Location, location, location." - Harold Samuel, who never had to cross a NUMA node.
+pub fn render_topology(f: &mut Frame, app: &App, area: Rect) {
+    // Matrix is as high as there are GPUs, plus borders, header and legend
+    let height = app.gpu_readings.gpus.len().max(1) as u16 + 3;
+    let sub_areas = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(height), Constraint::Min(0)].as_ref())
+        .split(area);
+
+    gpu_topology_panel(f, app, &app.gpu_readings, sub_areas[0]);
+    gpu_affinity_panel(f, app, sub_areas[1]);
 }
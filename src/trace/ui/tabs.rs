@@ -1,7 +1,17 @@
 use ratatui::text::Line;
 
+/// What is shown under the tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabKind {
+    CpuMemory,
+    Gpu,
+    Topology,
+    Output,
+}
+
 pub struct Tabs<'a> {
     pub titles: Vec<Line<'a>>,
+    pub kinds: Vec<TabKind>,
     pub selection: usize,
 }
 
@@ -17,4 +27,9 @@ impl Tabs<'_> {
             self.selection = self.titles.len() - 1;
         }
     }
+
+    /// Kind of the selected tab
+    pub fn current(&self) -> TabKind {
+        self.kinds[self.selection]
+    }
 }
@@ -1,32 +1,39 @@
 #[macro_use]
 extern crate log;
 
-use crate::args::Args;
-use crate::trace::{app::App, cmd::Cmd, event::Event, ui::renderer::render, Record};
-use crate::utils::create_file;
+use gpu_tracer::args::{Args, GpuBackendKind};
+use gpu_tracer::trace::collector::Collector;
+use gpu_tracer::trace::datastreams::Cgroup;
+use gpu_tracer::trace::datastreams::{
+    GpuBackend, GpuEccMonitor, GpuSimulator, GpuTopologyMonitor, IntelGpuTopMonitor, NvidiaSmiMonitor,
+    RawCapture, Retention, RocmSmiMonitor, Sample, StreamRegistry, SysfsDrmMonitor, Timestamp, ToolRunner,
+};
+use gpu_tracer::error::{GpuError, TraceError};
+use gpu_tracer::trace::summary::{RunSummary, SummaryBuilder};
+use gpu_tracer::trace::{app::App, cmd::Cmd, event::Event, ui::renderer::render, GpuCsvRecord, MetricRecord, Record};
+use gpu_tracer::launcher::{ChildProcess, LaunchSpec, SIGINT, SIGTERM};
+use gpu_tracer::utils::{create_file, setup_logger};
 use clap::Parser;
 use color_eyre::eyre::{eyre, Result};
 use csv::Writer;
-use itertools::Itertools;
 use ratatui::backend::CrosstermBackend;
 use ratatui::Terminal;
 use std::{
+    collections::BTreeSet,
     fs::File,
+    path::Path,
     io,
-    process::{Command, Stdio},
-    sync::mpsc,
-    thread, time,
+    sync::atomic::{AtomicBool, AtomicI32, Ordering},
+    sync::{mpsc, Arc, Mutex},
+    thread,
     time::Duration,
 };
-use sysinfo::{Pid, ProcessExt, System, SystemExt};
+use sysinfo::Pid;
 use termion::{event, input::TermRead, raw::IntoRawMode, screen::IntoAlternateScreen};
 use tokio::{signal, spawn};
-use utils::{check_in_current_dir, get_current_working_dir, setup_logger};
 
-mod args;
-mod error;
-mod trace;
-mod utils;
+/// How often the UI is redrawn - independent of how often samples are taken.
+const FRAME_INTERVAL: Duration = Duration::from_millis(200);
 
 /// Main function. Because every program needs one.
 /// "The journey of a thousand miles begins with a single step." - Lao Tzu, who probably never wrote multithreaded applications.
@@ -37,67 +44,58 @@ async fn main() -> Result<()> {
 
     debug!("Start"); // Of course, we're starting.
 
-    let mut kill = false; // Kill it with fire! Or, you know, a boolean.
+    // Before anything is launched - a GPU backend asked for by name may refuse to start.
+    let gpu = gpu_backend(&args)?;
+
+    let mut child: Option<ChildProcess> = None; // Kill it with fire! Or, you know, politely first.
     let id: i32; // Because every process needs an ID. Or an identity crisis.
+    let mut cgroup: Option<Cgroup> = None; // Whole container / job instead of single process.
 
-    if let Some(app) = args.application {
+    if let Some(spec) = LaunchSpec::from_args(&args)? {
         // What application?
-        let with_params = app.split(' ').collect_vec(); // Let's split the app name from params
-        let apt = app.as_str(); // And we need it as str? why?
-        let (app, params) = if let Some((a, p)) = with_params.split_first() {
-            // Split first
-            (a, p)
-        } else {
-            (&apt, [""].as_slice()) // nothing to split
-        };
-
-        let mut p = args.args.to_vec(); // get arguments
-        for d in params {
-            p.push(String::from(*d)); // add additional arguments if needed.
-        }
-
-        let (path, app) = check_in_current_dir(app)?; // Let's check app again
         info!(
-            "Application to be monitored is: {}, in dir {} , with params: {:?}",
-            app,
-            path,
-            p // Yeah, we have it all!
+            "Application to be monitored is: {}, in dir {}, with params: {:?}",
+            spec.program.display(),
+            spec.cwd.display(),
+            spec.args // Yeah, we have it all!
         );
+        if !spec.env.is_empty() {
+            info!("With environment: {:?}", spec.env);
+        }
 
-        let output_file = File::create(format!("{}.out", app))?; // Create output file
-        let error_file = File::create(format!("{}.err", app))?; // and error file
-
-        let cmd = Command::new(&path) // create command
-            .current_dir(get_current_working_dir()) // set current dir
-            .args(p) // and params
-            .stdin(Stdio::null()) // no input
-            .stdout(Stdio::from(output_file)) // save output to file
-            .stderr(Stdio::from(error_file)) // save error to file
-            .spawn() // spawn it !
-            .expect("Failed to run "); // if it is failed
-
-        kill = true; // we will kill it later
+        let cmd = spec.spawn()?; // spawn it !
         id = cmd.id() as i32; // get the id
+        child = Some(cmd); // we will terminate it later
     } else if let Some(pid) = &args.pid {
         // What about PID ?
         info!("Application to be monitored is: [PID] {:?}", pid); // just log
         id = *pid; // save pid
+    } else if let Some(path) = &args.cgroup {
+        // What about whole cgroup?
+        let group = Cgroup::new(&args.cgroup_root, path)?;
+        let pids = group.pids()?;
+        info!(
+            "Application to be monitored is: [cgroup] {} with PIDs {:?}",
+            group.dir().display(),
+            pids
+        );
+        id = *pids
+            .first()
+            .ok_or_else(|| eyre!("cgroup {} has no processes.", path))? as i32;
+        cgroup = Some(group);
     } else {
-        return Err(eyre!("Not sure what supposed to trace. Please provide application path or PID. [Use -h for help]".to_string()));
+        return Err(eyre!("Not sure what supposed to trace. Please provide application path, PID or cgroup. [Use -h for help]".to_string()));
         // well...
     }
 
     let refresh_millis = args.refresh;
     info!("Refresh rate: {} ms.", refresh_millis); // How fast do you want it to be?
 
-    let mut writer: Option<Writer<File>> = args
-        .output
-        .as_ref()
-        .map(|path| csv::Writer::from_writer(create_file(path).inner)); // CSV time
-    match writer {
+    let mut outputs = Outputs::new(&args, gpu.is_some()); // CSV time
+    match outputs.readings {
         Some(_) => info!(
             "Output readings persisted into \"{}\".",
-            args.output.unwrap()
+            args.output.as_deref().unwrap_or_default()
         ), // it will be saved
         None => info!("No output persistence."), // it will be lost
     }
@@ -105,31 +103,67 @@ async fn main() -> Result<()> {
     let pid: Pid = Pid::from(id);
     info!("Starting with PID::{}", pid); // start with pid
 
+    // Which signal asked us to stop - it will be forwarded to the application.
+    let received_signal = Arc::new(AtomicI32::new(0));
+
+    // Samples are taken on their own thread, so slow sysinfo/nvidia-smi never blocks the UI.
+    let stop_collector = Arc::new(AtomicBool::new(false));
+    let output_log = child.as_ref().and_then(|c| c.output_log());
+    let (tx, rx) = mpsc::channel();
+    let collector = Collector::new(pid, cgroup, gpu, output_log.clone(), &args.sysfs_root, &args.procfs_root);
+
     if args.noui {
-        let mut system = System::new_all();
+        let mut summary = SummaryBuilder::new();
+        let mut alerts = Alerts::new(); // no panel turns red here - the log has to do
 
         info!("Running in TXT mode.");
-        // TODO add Ctrl+C - kill child process !!
+        listen_for_signals(received_signal.clone(), None);
+        collector.spawn(Duration::from_millis(refresh_millis), tx, stop_collector.clone());
         loop {
-            thread::sleep(Duration::from_millis(refresh_millis));
-            system.refresh_process(pid);
-            let process = system.process(pid).unwrap();
-            let t = format!("{}", chrono::Utc::now().time());
-            let c = format!("{}", process.cpu_usage());
-            let m = format!("{}", process.memory() / 1024);
-            info!("CPU: {} [%],  memory: {} [kB]", c, m,);
-            if let Some(wtr) = &mut writer {
-                let r = Record::new(&t, &c, &m);
-                wtr.serialize(r).expect("Error serializing outputs to csv");
-                wtr.flush()?;
+            if received_signal.load(Ordering::SeqCst) != 0 {
+                break;
+            }
+            if let Some(status) = child.as_mut().map(|c| c.try_wait()).transpose()?.flatten() {
+                info!("Application finished: {}", status);
+                break;
             }
+            // Short waits, so signals and application exit are noticed quickly.
+            let sample = match rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(Event::Sample(sample)) => sample,
+                Ok(_) | Err(mpsc::RecvTimeoutError::Timeout) => continue,
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            };
+            info!("CPU: {} [%],  memory: {} [kB]", sample.readings.get_cpu(), sample.readings.get_mem());
+            if let Some(gpu) = &sample.gpu {
+                let (u, m) = (gpu.get_average_gpu_utilization(), gpu.get_total_memory_used());
+                info!("GPU: {} [%], memory: {} [MB]", u, m);
+            }
+            for (name, value) in &sample.metrics {
+                info!("Metric {}: {}", name, value);
+            }
+            alerts.poll(&sample);
+            outputs.write(&sample)?;
+            summary.record(&sample);
         }
+        finish_summary(summary.finish(), args.summary.as_deref());
     } else {
         info!("Running in TUI mode.");
 
         //Program
-        let mut app = App::new(5000, 50, pid, !args.autoscale, refresh_millis)?;
-        let (tx, rx) = mpsc::channel();
+        let retention = Retention::new(
+            Duration::from_millis(refresh_millis),
+            Duration::from_secs(args.history_minutes * 60),
+        );
+        let mut app = App::new(
+            retention,                                   // full readings, then rollups
+            Duration::from_millis(refresh_millis * 100), // charts show last 100 readings
+            collector.readings().clone(),
+            !args.autoscale,
+            refresh_millis,
+            collector.gpu_available(),
+            output_log,
+        )?;
+        app.selected_mig = args.get_mig_uuids();
         let input_tx = tx.clone();
         let ticker_tx = tx.clone();
 
@@ -138,17 +172,21 @@ async fn main() -> Result<()> {
             let stdin = io::stdin();
             for c in stdin.keys() {
                 let evt = c.unwrap();
-                input_tx.send(Event::Input(evt)).unwrap();
-                if evt == event::Key::Char('q') {
+                if input_tx.send(Event::Input(evt)).is_err() {
                     break;
                 }
             }
         });
 
+        debug!("Collector starting");
+        collector.spawn(Duration::from_millis(refresh_millis), tx.clone(), stop_collector.clone());
+
         debug!("Ticker starting");
         thread::spawn(move || loop {
-            ticker_tx.send(Event::Tick).unwrap();
-            thread::sleep(time::Duration::from_millis(refresh_millis));
+            if ticker_tx.send(Event::Tick).is_err() {
+                break;
+            }
+            thread::sleep(FRAME_INTERVAL);
         });
 
         let stdout = io::stdout().into_raw_mode()?;
@@ -160,14 +198,8 @@ async fn main() -> Result<()> {
         terminal.clear()?;
         terminal.hide_cursor()?;
 
-        // Setup Ctrl+C handler
-        let ctrl_c_tx = tx.clone();
-
-        spawn(async move {
-            if let Ok(()) = signal::ctrl_c().await {
-                ctrl_c_tx.send(Event::Quit).unwrap_or_default();
-            }
-        });
+        // Setup SIGINT/SIGTERM handler
+        listen_for_signals(received_signal.clone(), Some(tx.clone()));
 
         debug!("Into loop");
         loop {
@@ -175,7 +207,9 @@ async fn main() -> Result<()> {
             {
                 match evt {
                     Event::Input(key) => {
-                        if key == event::Key::Char('q') {
+                        if key == event::Key::Ctrl('c') {
+                            // Raw mode eats SIGINT, so Ctrl+C arrives as a key.
+                            received_signal.store(SIGINT, Ordering::SeqCst);
                             break;
                         }
                         if let Some(cmd) = app.input_handler(key) {
@@ -184,16 +218,11 @@ async fn main() -> Result<()> {
                             }
                         }
                     }
-                    Event::Tick => {
-                        app.update()?;
-                        if let Some(wtr) = &mut writer {
-                            let t = format!("{}", chrono::Utc::now().time());
-                            let c = format!("{}", app.datastreams.readings.get_cpu());
-                            let m = format!("{}", app.datastreams.readings.get_mem());
-                            let r = Record::new(&t, &c, &m);
-                            wtr.serialize(r).expect("Error serializing outputs to csv");
-                            wtr.flush()?;
-                        }
+                    Event::Tick => {} // just redraw
+                    Event::Sample(sample) => {
+                        app.apply(&sample);
+                        outputs.write(&sample)?;
+                        continue; // next frame tick draws it
                     }
                     Event::Quit => {
                         break;
@@ -207,25 +236,304 @@ async fn main() -> Result<()> {
         debug!("Back with cursor and original terminal");
         terminal.clear()?;
         terminal.show_cursor()?;
-
-        // TODO: Kill the monitored process if it's still running, need to rethink whole no UI stuff as well
-        // if let Ok(mut process) = sysinfo::System::new_all().processes().get(&pid.as_u32()) {
-        //     process.kill();
-        // }
+        drop(terminal); // summary goes to the normal screen
+        finish_summary(app.summary.finish(), args.summary.as_deref());
     }
-    if let Some(wtr) = &mut writer {
+    stop_collector.store(true, Ordering::SeqCst);
+    if let Some(wtr) = &mut outputs.readings {
         wtr.flush()?;
     }
     // in case of exit from application that was not terminated by user
-    if kill {
-        let _ = Command::new("kill")
-            .arg("-9")
-            .arg(format!("{}", id))
-            .stdin(Stdio::null())
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .output();
+    if let Some(mut child) = child {
+        let signal = match received_signal.load(Ordering::SeqCst) {
+            0 => SIGTERM, // we are leaving, so application should too
+            s => s,       // forward what we got
+        };
+        let status = child.terminate(signal, Duration::from_secs(args.kill_timeout))?;
+        info!("Application exited: {}", status);
+    }
+
+    Ok(())
+}
+
+/// Picks where GPU readings come from: the simulator or a replay when asked for, nvidia-smi when it works.
+/// A backend the user asked for by name that cannot start is an error, not a quiet run without GPUs.
+/// "If you can't make it, fake it." - every demo ever.
+fn gpu_backend(args: &Args) -> Result<Option<Box<dyn GpuBackend>>> {
+    if let Some(count) = args.simulate_gpus {
+        info!("Simulating {} GPU(s) with seed {}.", count, args.simulate_seed);
+        let step = Duration::from_millis(args.refresh);
+        return Ok(Some(Box::new(GpuSimulator::new(count, args.simulate_seed, step))));
+    }
+
+    let timeout = Duration::from_millis(args.gpu_timeout);
+    let capture = match (&args.capture_raw, &args.replay_raw) {
+        (Some(dir), None) => {
+            info!("Raw GPU tool output captured into {}.", dir);
+            Some(Arc::new(Mutex::new(RawCapture::create(Path::new(dir))?)))
+        }
+        _ => None,
+    };
+    let runner = |program: String| -> Result<ToolRunner> {
+        let runner = match &args.replay_raw {
+            Some(dir) => ToolRunner::replay(&program, Path::new(dir))?,
+            None => ToolRunner::new(&program, timeout),
+        };
+        Ok(match &capture {
+            Some(capture) => runner.capturing(capture.clone()),
+            None => runner,
+        })
+    };
+
+    // NVML, intel_gpu_top and sysfs are read live, there is nothing of them in a capture
+    let live = args.replay_raw.is_none();
+    // each backend opens into itself and why it cannot be used, if it cannot
+    type Probe = (Box<dyn GpuBackend>, Option<GpuError>);
+    type Open<'a> = &'a dyn Fn() -> Result<Probe>;
+    #[cfg(feature = "nvml")]
+    let nvml = || -> Result<Probe> {
+        if args.gpu_accounting {
+            warn!("NVML does not report accounted processes, --gpu-accounting needs --gpu-backend nvidia-smi");
+        }
+        let monitor = gpu_tracer::trace::datastreams::NvmlMonitor::new();
+        let error = (!monitor.is_available()).then(|| monitor.get_last_error().cloned()).flatten();
+        Ok((Box::new(monitor), error))
+    };
+    let nvidia_smi = || -> Result<Probe> {
+        let mut monitor = NvidiaSmiMonitor::with_runner(runner(NvidiaSmiMonitor::find_path())?);
+        let interval = (args.report_interval > 0).then(|| Duration::from_secs(args.report_interval));
+        monitor.set_report_interval(interval);
+        monitor.set_accounting(args.gpu_accounting);
+        let error = (!monitor.is_available()).then(|| monitor.get_last_error().cloned()).flatten();
+        Ok((Box::new(monitor), error))
+    };
+    let rocm_smi = || -> Result<Probe> {
+        let monitor = RocmSmiMonitor::with_runner(runner(RocmSmiMonitor::find_path())?);
+        let error = (!monitor.is_available()).then(|| monitor.get_last_error().cloned()).flatten();
+        Ok((Box::new(monitor), error))
+    };
+    let intel_gpu_top = || -> Result<Probe> {
+        let period = Duration::from_millis(args.refresh);
+        let monitor = IntelGpuTopMonitor::new(&IntelGpuTopMonitor::find_path(), period, timeout);
+        let error = (!monitor.is_available()).then(|| monitor.get_last_error().cloned()).flatten();
+        Ok((Box::new(monitor), error))
+    };
+    let sysfs = || -> Result<Probe> {
+        let monitor = SysfsDrmMonitor::new(&args.sysfs_root, &args.procfs_root);
+        let error = (!monitor.is_available()).then(|| monitor.get_last_error().cloned()).flatten();
+        Ok((Box::new(monitor), error))
+    };
+
+    let candidates: Vec<Open> = match args.gpu_backend {
+        GpuBackendKind::Auto => {
+            let mut candidates: Vec<Open> = Vec::new();
+            // only nvidia-smi reads the accounted processes
+            #[cfg(feature = "nvml")]
+            if live && !args.gpu_accounting {
+                candidates.push(&nvml);
+            }
+            candidates.push(&nvidia_smi);
+            candidates.push(&rocm_smi);
+            if live {
+                candidates.push(&intel_gpu_top);
+                candidates.push(&sysfs);
+            }
+            candidates
+        }
+        // nvidia-smi stands in where the library is missing
+        #[cfg(feature = "nvml")]
+        GpuBackendKind::Nvml => vec![&nvml, &nvidia_smi],
+        GpuBackendKind::NvidiaSmi => vec![&nvidia_smi],
+        GpuBackendKind::RocmSmi => vec![&rocm_smi],
+        GpuBackendKind::IntelGpuTop => vec![&intel_gpu_top],
+        GpuBackendKind::Sysfs => vec![&sysfs],
+    };
+    let mut last_error = None;
+    for open in candidates {
+        let (backend, error) = open()?;
+        match error {
+            None => {
+                info!("GPU backend: {}.", backend.name());
+                return Ok(Some(backend));
+            }
+            Some(e) => {
+                match e.hint() {
+                    Some(hint) => info!("GPU monitoring off: {} - {}", e, hint),
+                    None => info!("GPU monitoring off: {}", e),
+                }
+                last_error = Some(e);
+            }
+        }
     }
+    match (args.gpu_backend, last_error) {
+        (GpuBackendKind::Auto, _) | (_, None) => Ok(None),
+        (_, Some(e)) => Err(TraceError::Gpu(e).into()),
+    }
+}
+
+/// The CSV files a run writes next to `--output`, the same in TUI and TXT mode.
+struct Outputs {
+    /// CPU and memory: out.csv
+    readings: Option<Writer<File>>,
+    /// Application metrics: out.csv -> out.metrics.csv
+    metrics: Option<Writer<File>>,
+    /// GPU readings: out.csv -> out.gpu.csv
+    gpus: Option<Writer<File>>,
+    /// When the previous sample was taken, for the time spent throttled
+    last_sample: Option<Timestamp>,
+}
+
+impl Outputs {
+    fn new(args: &Args, gpu_available: bool) -> Self {
+        let next_to_output = |extension: &str, what: &str| {
+            args.output.as_ref().map(|path| {
+                let path = Path::new(path).with_extension(extension);
+                info!("{} persisted into {}.", what, path.display());
+                csv::Writer::from_writer(create_file(&path.to_string_lossy()).inner)
+            })
+        };
+        Outputs {
+            readings: args
+                .output
+                .as_ref()
+                .map(|path| csv::Writer::from_writer(create_file(path).inner)),
+            metrics: (!args.metric_regex.is_empty())
+                .then(|| next_to_output("metrics.csv", "Application metrics"))
+                .flatten(),
+            gpus: gpu_available.then(|| next_to_output("gpu.csv", "GPU readings")).flatten(),
+            last_sample: None,
+        }
+    }
+
+    /// Appends one sample to every CSV there is.
+    fn write(&mut self, sample: &Sample) -> Result<()> {
+        let c = format!("{}", sample.readings.get_cpu());
+        let m = format!("{}", sample.readings.get_mem());
+        write_record(&mut self.readings, sample.time, &c, &m)?;
+        write_metrics(&mut self.metrics, sample)?;
+        write_gpus(&mut self.gpus, sample, self.last_sample)?;
+        self.last_sample = Some(sample.time);
+        Ok(())
+    }
+}
+
+/// Streams that raise alerts, fed in TXT mode too - the TUI gets them with all the others.
+struct Alerts {
+    streams: StreamRegistry,
+    /// GPUs that already have their streams registered
+    gpus: BTreeSet<u32>,
+}
+
+impl Alerts {
+    fn new() -> Self {
+        let mut streams = StreamRegistry::new();
+        streams.register(Box::new(GpuTopologyMonitor::new())); // who runs far from their GPU
+        Alerts {
+            streams,
+            gpus: BTreeSet::new(),
+        }
+    }
+
+    /// Feeds one sample. First time a GPU shows up, its memory errors get watched.
+    fn poll(&mut self, sample: &Sample) {
+        for gpu in sample.gpu.iter().flat_map(|g| &g.gpus) {
+            if self.gpus.insert(gpu.index) {
+                self.streams.register(Box::new(GpuEccMonitor::new_for_gpu(gpu.index)));
+            }
+        }
+        self.streams.poll(sample);
+    }
+}
+
+/// Appends CPU and memory of one sample into the readings CSV.
+fn write_record(writer: &mut Option<Writer<File>>, time: Timestamp, cpu: &str, mem: &str) -> Result<()> {
+    if let Some(wtr) = writer {
+        wtr.serialize(Record::new(&time.to_rfc3339(), cpu, mem))
+            .expect("Error serializing outputs to csv");
+        wtr.flush()?;
+    }
+    Ok(())
+}
 
+/// Appends metrics of one sample into the metrics CSV, with GPU power and memory for context.
+/// "Not everything that counts can be counted." - Einstein, who never had a regex.
+fn write_metrics(writer: &mut Option<Writer<File>>, sample: &Sample) -> Result<()> {
+    let Some(wtr) = writer else {
+        return Ok(());
+    };
+    if sample.metrics.is_empty() {
+        return Ok(());
+    }
+    let timestamp = sample.time.to_rfc3339();
+    let gpu = sample.gpu.as_ref();
+    let power = gpu.and_then(|g| g.gpus.iter().filter_map(|i| i.power.draw).reduce(|a, b| a + b));
+    for (name, value) in &sample.metrics {
+        wtr.serialize(MetricRecord {
+            timestamp: timestamp.clone(),
+            sample: sample.index,
+            metric: name.clone(),
+            value: *value,
+            gpu_power_draw_watts: power,
+            gpu_memory_used_mb: gpu.map(|g| g.get_total_memory_used()),
+        })?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Appends one row per GPU of a sample into the GPU CSV. Time since the previous sample is
+/// counted as spent in the throttle reasons active now.
+fn write_gpus(writer: &mut Option<Writer<File>>, sample: &Sample, last_sample: Option<Timestamp>) -> Result<()> {
+    let (Some(wtr), Some(gpu)) = (writer, &sample.gpu) else {
+        return Ok(());
+    };
+    let secs = last_sample.map_or(0.0, |last| (sample.time - last).num_milliseconds() as f64 / 1000.0);
+    for info in &gpu.gpus {
+        let mut record = GpuCsvRecord::from_gpu_info(info, gpu.get_gpu_processes(info.index).len());
+        // when the sample was taken, like every other CSV - not the clock of the GPU tool
+        record.timestamp = sample.time.to_rfc3339();
+        if info.throttle_reasons.is_some() {
+            record.throttle_secs = secs;
+        }
+        wtr.serialize(record)?;
+    }
+    wtr.flush()?;
     Ok(())
 }
+
+/// Logs the run summary and writes it as JSON when asked to.
+fn finish_summary(summary: RunSummary, path: Option<&str>) {
+    summary.log();
+    if let Some(path) = path {
+        match summary.write_json(path) {
+            Ok(()) => info!("Run summary written into \"{}\".", path),
+            Err(e) => error!("Cannot write run summary into \"{}\": {}", path, e),
+        }
+    }
+}
+
+/// Listens for SIGINT/SIGTERM, remembers which one came (to forward it) and wakes up the main loop.
+fn listen_for_signals(received: Arc<AtomicI32>, tx: Option<mpsc::Sender<Event>>) {
+    spawn(async move {
+        #[cfg(unix)]
+        let signal = {
+            let mut term = signal::unix::signal(signal::unix::SignalKind::terminate())
+                .expect("Cannot register SIGTERM handler");
+            tokio::select! {
+                _ = signal::ctrl_c() => SIGINT,
+                _ = term.recv() => SIGTERM,
+            }
+        };
+        #[cfg(not(unix))]
+        let signal = {
+            let _ = signal::ctrl_c().await;
+            SIGINT
+        };
+
+        info!("Received signal {}, stopping.", signal);
+        received.store(signal, Ordering::SeqCst);
+        if let Some(tx) = tx {
+            tx.send(Event::Quit).unwrap_or_default();
+        }
+    });
+}
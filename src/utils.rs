@@ -1,10 +1,8 @@
-use crate::error::{Result, TraceError};
 use chrono::prelude::*;
 use env_logger::fmt::Formatter;
 use env_logger::{Builder, WriteStyle};
 use log::{Level, LevelFilter, Record};
 use std::io::Write;
-use std::process::{Command, Stdio};
 use std::{env, thread};
 use termion::color::{self, Fg};
 
@@ -19,63 +17,6 @@ pub fn get_current_working_dir() -> String {
     }
 }
 
-/// Checking if application is in current dir or is the full path.
-/// Returns full paths and short name of app.
-/// Error otherwise.
-/// Because who needs a simple true/false when you can have a whole Result type?
-/// "To be or not to be, that is the question." - Shakespeare, almost certainly while checking file paths.
-pub fn check_in_current_dir(app: &str) -> Result<(String, String)> {
-    let (full, short) = if app.contains(std::path::MAIN_SEPARATOR) {
-        (
-            app.to_string(),
-            app.split(std::path::MAIN_SEPARATOR)
-                .last()
-                .unwrap() // It is safe to unwrap, really?
-                .to_string(),
-        )
-    } else {
-        (
-            format!(
-                "{}{}{}",
-                get_current_working_dir(),
-                std::path::MAIN_SEPARATOR,
-                app
-            ),
-            app.to_string(),
-        )
-    };
-
-    let checker = if cfg!(target_os = "windows") {
-        "dir" // Because Windows can't do anything the same way as anyone else.
-    } else {
-        "ls"
-    };
-
-    let cmd = Command::new(checker)
-        .arg(&full)
-        .current_dir(get_current_working_dir())
-        .stdin(Stdio::null())
-        .stdout(Stdio::piped()) // We're capturing the output, because it's so useful for an application check.
-        .output();
-
-    match cmd {
-        Ok(out) => {
-            if out.status.code() == Some(0) {
-                Ok((full, short))
-            } else {
-                Err(TraceError::AppNotFound(format!(
-                    "Could not find application: {}.",
-                    short
-                )))
-            }
-        }
-        Err(e) => Err(TraceError::Unknown(format!(
-            "Wrong system utils - are you on windows? {:?}", // The eternal question.
-            e
-        ))),
-    }
-}
-
 /// Creates output file for tracing.
 /// "The only way to do great work is to love what you do." - Steve Jobs, probably while not writing code for this.
 pub fn create_file(filename: &str) -> tagger::Adaptor<std::fs::File> {